@@ -0,0 +1,50 @@
+// Search over a big list, the work behind every keystroke at the `/`
+// prompt. Run with `cargo bench`.
+
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use tuido::Todo;
+use tuido::matching::{SearchIndex, search};
+
+const WORDS: [&str; 12] = [
+    "review", "pull", "request", "call", "bank", "prepare", "slides", "update", "project",
+    "roadmap", "+work", "@home",
+];
+
+fn big_list(len: usize) -> Vec<Todo> {
+    (0..len)
+        .map(|i| {
+            let text: Vec<&str> = (0..5)
+                .map(|k| WORDS[(i * 7 + k * 5) % WORDS.len()])
+                .collect();
+            Todo::new(format!("{} #{}", text.join(" "), i))
+        })
+        .collect()
+}
+
+fn filter(c: &mut Criterion) {
+    let todos = big_list(10_000);
+
+    c.bench_function("search 10k, uncached", |b| {
+        b.iter(|| search(black_box(&todos), black_box("prj"), false))
+    });
+
+    // Typing: each search has a new query, the texts are already indexed
+    let mut index = SearchIndex::default();
+    let queries = ["p", "pr", "prj", "prjr"];
+    let mut next = 0;
+    c.bench_function("search 10k, indexed", |b| {
+        b.iter(|| {
+            next = (next + 1) % queries.len();
+            index.search(black_box(&todos), queries[next], false)
+        })
+    });
+
+    // Redrawing with the query and todos unchanged
+    let mut index = SearchIndex::default();
+    c.bench_function("search 10k, repeated", |b| {
+        b.iter(|| index.search(black_box(&todos), black_box("prj"), false))
+    });
+}
+
+criterion_group!(benches, filter);
+criterion_main!(benches);
@@ -0,0 +1,43 @@
+// Reads a todo.txt file and prints what tuido makes of each line.
+//
+//     cargo run --example parse -- todo.txt [query]
+
+use std::env;
+use std::fs;
+use std::process;
+
+use tuido::matching::match_todos;
+use tuido::validate::validate;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let Some(path) = args.next() else {
+        eprintln!("usage: parse <todo.txt> [query]");
+        process::exit(2);
+    };
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!("Error opening {}: {}", path, e);
+            process::exit(1);
+        }
+    };
+
+    let (todos, report) = validate(tuido::todotxt::parse(&contents));
+    println!("{}", report.summary(todos.len()));
+
+    let query = args.next().unwrap_or_default();
+    for i in match_todos(&todos, &query) {
+        let todo = &todos[i];
+        let status = if todo.completed { "x" } else { " " };
+        let priority = todo
+            .priority
+            .map(|p| format!("({}) ", p))
+            .unwrap_or_default();
+        let due = todo
+            .due
+            .map(|d| format!("  [due {}]", d))
+            .unwrap_or_default();
+        println!("[{}] {}{}{}", status, priority, todo.text, due);
+    }
+}
@@ -0,0 +1,7919 @@
+//! The application state behind the terminal UI: the list and everything
+//! done to it, the modes and their keys, and the commands. [`handle_key`]
+//! applies a key press and [`crate::ui::draw`] renders the result, so the
+//! binary is left with the terminal and the event loop.
+
+use crate::matching::{REGEX_PREFIX, SearchIndex, similarity};
+use crate::stats::{Counts, RECENT_DAYS, Stats, compute_stats};
+use crate::substitute::{Global, Substitute, is_delimiter};
+use crate::validate::validate;
+use crate::width::{display_width, truncate};
+use crate::{
+    Todo, bulkedit, csv, dedup, escalate, find_urls, html, import, lower_priority, markdown,
+    parse_due, parse_meta, parse_priority, raise_priority, split_meta, tag_kind, todotxt,
+};
+use chrono::{Local, NaiveDate};
+use crossterm::{
+    cursor,
+    event::{
+        KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
+};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    widgets::ListState,
+};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::{Arc, mpsc};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use unicode_segmentation::UnicodeSegmentation;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mode {
+    Normal,
+    Insert,
+    Command,
+    Visual,
+    Search,
+    NoteEdit,
+    Help,
+    Popup,
+    Merge,
+    Picker,
+    Confirm,
+    // `gx` found several URLs; a digit picks one
+    ChooseUrl,
+}
+
+// A destructive command waiting for y/n on the message line
+#[derive(Clone, Copy, PartialEq)]
+enum Confirm {
+    ClearCompleted,
+    Dedup,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CommandOutcome {
+    Done,
+    Failed,
+    Quit,
+}
+
+#[derive(Clone, PartialEq)]
+enum Action {
+    Toggle,
+    Delete,
+    // `dG`, `d3j` and the like, repeated with the same motion
+    DeleteTo(Motion),
+    Postpone,
+    Priority(PriorityChange),
+    // A todo typed in Insert mode, as typed
+    Add(String),
+    Paste {
+        above: bool,
+        reset: bool,
+        register: Option<char>,
+    },
+    // The text an edit left the todo with, as shown in the input
+    Edit(String),
+}
+
+impl Action {
+    // For the "repeated: delete ×3" message
+    fn name(&self) -> &'static str {
+        match self {
+            Action::Toggle => "toggle",
+            Action::Delete | Action::DeleteTo(_) => "delete",
+            Action::Postpone => "postpone",
+            Action::Priority(_) => "priority",
+            Action::Add(_) => "add",
+            Action::Paste { .. } => "paste",
+            Action::Edit(_) => "edit",
+        }
+    }
+}
+
+// `d` and `y` followed by a motion, e.g. `dG` or `y3j`
+#[derive(Clone, Copy, PartialEq)]
+enum Operator {
+    Delete,
+    Yank,
+}
+
+// An operator waiting for its motion, as typed so far: `d`, `d3`, `d3g`.
+// A count before the operator is only kept in `before` when the operator's
+// key used it up, as `3y` does by yanking three rows straight away.
+struct PendingOperator {
+    operator: Operator,
+    before: usize,
+    after: usize,
+    // The first g of gg
+    g: bool,
+}
+
+// How far an operator reaches from the cursor, in rows of the list as shown
+#[derive(Clone, Copy, PartialEq)]
+enum Motion {
+    Down(usize),
+    Up(usize),
+    // A row counting from 1, as `5G` and `5gg` go to; usize::MAX is the last
+    ToRow(usize),
+}
+
+// `+`, `-` and `:pri`
+#[derive(Clone, Copy, PartialEq)]
+enum PriorityChange {
+    Raise,
+    Lower,
+    Set(Option<char>),
+}
+
+// What `:g/pattern/...` does to each todo it matches
+enum GlobalAction {
+    // `d!` skips the check on deleting many todos
+    Delete { force: bool },
+    Toggle,
+    Priority(Option<char>),
+}
+
+impl GlobalAction {
+    fn parse(command: &str) -> Result<GlobalAction, String> {
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        match parts.as_slice() {
+            ["d" | "delete"] => Ok(GlobalAction::Delete { force: false }),
+            ["d!" | "delete!"] => Ok(GlobalAction::Delete { force: true }),
+            ["x" | "toggle"] => Ok(GlobalAction::Toggle),
+            ["pri", "-"] => Ok(GlobalAction::Priority(None)),
+            ["pri", p] if p.len() == 1 && p.as_bytes()[0].is_ascii_alphabetic() => Ok(
+                GlobalAction::Priority(p.to_ascii_uppercase().chars().next()),
+            ),
+            _ => Err("Usage: :g/pattern/d, :g/pattern/x or :g/pattern/pri A".to_string()),
+        }
+    }
+}
+
+// What `.` repeats: the action and how many rows it covered (or times it
+// pasted). Like vim, the repeat applies to the cursor's current row (and the
+// rows after it for a multi-row action), not to the todos the original
+// action touched.
+#[derive(Clone)]
+struct LastAction {
+    action: Action,
+    rows: usize,
+}
+
+// Normal-mode actions the config's [keys] table can rebind
+#[derive(Clone, Copy, PartialEq)]
+enum KeyAction {
+    Down,
+    Up,
+    Toggle,
+    Delete,
+    Postpone,
+    Insert,
+    Append,
+    Edit,
+    Command,
+    Visual,
+    Yank,
+    Paste,
+    PasteAbove,
+    Search,
+    Note,
+    Fold,
+    Undo,
+    Redo,
+    Help,
+    Repeat,
+    NextMatch,
+    PrevMatch,
+    RaisePriority,
+    LowerPriority,
+    MoveDown,
+    MoveUp,
+    Quit,
+}
+
+// Config name and default key for each action
+const KEY_ACTIONS: [(&str, KeyAction, &str); 27] = [
+    ("down", KeyAction::Down, "j"),
+    ("up", KeyAction::Up, "k"),
+    ("toggle", KeyAction::Toggle, "x"),
+    ("delete", KeyAction::Delete, "dd"),
+    ("postpone", KeyAction::Postpone, "T"),
+    ("insert", KeyAction::Insert, "i"),
+    ("append", KeyAction::Append, "A"),
+    ("edit", KeyAction::Edit, "e"),
+    ("command", KeyAction::Command, ":"),
+    ("visual", KeyAction::Visual, "v"),
+    ("yank", KeyAction::Yank, "y"),
+    ("paste", KeyAction::Paste, "p"),
+    ("paste_above", KeyAction::PasteAbove, "P"),
+    ("search", KeyAction::Search, "/"),
+    ("note", KeyAction::Note, "o"),
+    ("fold", KeyAction::Fold, "tab"),
+    ("undo", KeyAction::Undo, "u"),
+    ("redo", KeyAction::Redo, "ctrl+r"),
+    ("help", KeyAction::Help, "?"),
+    ("repeat", KeyAction::Repeat, "."),
+    ("next_match", KeyAction::NextMatch, "n"),
+    ("prev_match", KeyAction::PrevMatch, "N"),
+    ("raise_priority", KeyAction::RaisePriority, "+"),
+    ("lower_priority", KeyAction::LowerPriority, "-"),
+    ("move_down", KeyAction::MoveDown, "J"),
+    ("move_up", KeyAction::MoveUp, "K"),
+    ("quit", KeyAction::Quit, "q"),
+];
+
+// A key as written in the config: a character, a name such as `space` or
+// `tab`, `ctrl+<char>`, or a doubled character like `dd`
+#[derive(Clone, Copy, PartialEq)]
+struct KeyBinding {
+    code: KeyCode,
+    ctrl: bool,
+    double: bool,
+}
+
+impl KeyBinding {
+    fn parse(spec: &str) -> Option<KeyBinding> {
+        let (ctrl, name) = match spec.to_lowercase().strip_prefix("ctrl+") {
+            Some(_) => (true, &spec[5..]),
+            None => (false, spec),
+        };
+        let named = match name.to_lowercase().as_str() {
+            "space" => Some(KeyCode::Char(' ')),
+            "tab" => Some(KeyCode::Tab),
+            "enter" => Some(KeyCode::Enter),
+            "backspace" => Some(KeyCode::Backspace),
+            "delete" | "del" => Some(KeyCode::Delete),
+            "up" => Some(KeyCode::Up),
+            "down" => Some(KeyCode::Down),
+            "left" => Some(KeyCode::Left),
+            "right" => Some(KeyCode::Right),
+            _ => None,
+        };
+        let chars: Vec<char> = name.chars().collect();
+        let (code, double) = match (named, chars.as_slice()) {
+            (Some(code), _) => (code, false),
+            (None, [c]) => (KeyCode::Char(*c), false),
+            (None, [a, b]) if a == b && !ctrl => (KeyCode::Char(*a), true),
+            _ => return None,
+        };
+        // Ctrl+letter arrives lowercase
+        let code = match code {
+            KeyCode::Char(c) if ctrl => KeyCode::Char(c.to_ascii_lowercase()),
+            code => code,
+        };
+        Some(KeyBinding { code, ctrl, double })
+    }
+
+    // `pending` is the first half of a doubled key like `dd`, if typed
+    fn matches(&self, key: KeyEvent, pending: &str) -> bool {
+        key.code == self.code
+            && key.modifiers.contains(KeyModifiers::CONTROL) == self.ctrl
+            && (!self.double || self.starts_with(pending))
+    }
+
+    fn starts_with(&self, keys: &str) -> bool {
+        let mut keys = keys.chars();
+        matches!((keys.next(), keys.next()), (Some(c), None) if KeyCode::Char(c) == self.code)
+    }
+
+    fn describe(&self) -> String {
+        let key = match self.code {
+            KeyCode::Char(' ') => "space".to_string(),
+            KeyCode::Char(c) if self.double => format!("{}{}", c, c),
+            KeyCode::Char(c) => c.to_string(),
+            code => code.to_string().to_lowercase(),
+        };
+        if self.ctrl {
+            format!("ctrl+{}", key)
+        } else {
+            key
+        }
+    }
+}
+
+struct Keymap {
+    bindings: Vec<(KeyBinding, KeyAction)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap {
+            bindings: KEY_ACTIONS
+                .iter()
+                .filter_map(|&(_, action, key)| Some((KeyBinding::parse(key)?, action)))
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    fn action(&self, key: KeyEvent, pending: &str) -> Option<KeyAction> {
+        self.bindings
+            .iter()
+            .find(|(binding, _)| binding.matches(key, pending))
+            .map(|&(_, action)| action)
+    }
+
+    // The operator whose key is `c`: the first half of the delete binding
+    // (`dd`) or the yank key. A single-key delete can't wait for a motion.
+    fn operator(&self, c: char) -> Option<Operator> {
+        self.bindings.iter().find_map(|(binding, action)| {
+            if binding.ctrl || binding.code != KeyCode::Char(c) {
+                return None;
+            }
+            match action {
+                KeyAction::Delete if binding.double => Some(Operator::Delete),
+                KeyAction::Yank => Some(Operator::Yank),
+                _ => None,
+            }
+        })
+    }
+
+    // Whether `c` is the first half of a doubled binding like `dd`
+    fn starts_sequence(&self, c: char) -> bool {
+        self.bindings
+            .iter()
+            .any(|(binding, _)| binding.double && !binding.ctrl && binding.code == KeyCode::Char(c))
+    }
+
+    fn bind(&mut self, action: KeyAction, binding: KeyBinding) {
+        self.bindings.retain(|&(_, a)| a != action);
+        self.bindings.push((binding, action));
+    }
+}
+
+// Colors the config's [colors] table can change. RGB values are adapted to
+// the terminal when drawn.
+pub(crate) struct Theme {
+    pub(crate) priority_a: Color,
+    pub(crate) priority_b: Color,
+    pub(crate) priority_c: Color,
+    // D to Z
+    pub(crate) priority_other: Color,
+    pub(crate) completed: Color,
+    visual_bg: Color,
+    pub(crate) highlight_bg: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            priority_a: Color::Red,
+            priority_b: Color::Yellow,
+            priority_c: Color::Blue,
+            priority_other: Color::Gray,
+            completed: Color::DarkGray,
+            visual_bg: Color::Rgb(40, 60, 80),
+            highlight_bg: Color::Rgb(60, 60, 60),
+        }
+    }
+}
+
+impl Theme {
+    fn slot(&mut self, name: &str) -> Option<&mut Color> {
+        match name {
+            "priority_a" => Some(&mut self.priority_a),
+            "priority_b" => Some(&mut self.priority_b),
+            "priority_c" => Some(&mut self.priority_c),
+            "priority_other" => Some(&mut self.priority_other),
+            "completed" => Some(&mut self.completed),
+            "visual_bg" => Some(&mut self.visual_bg),
+            "highlight_bg" => Some(&mut self.highlight_bg),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ImportFormat {
+    GoogleTasks,
+    Reminders,
+    // Replaces the list rather than appending, as a migration path
+    TodoTxt,
+    Markdown,
+    Csv,
+}
+
+/// The formats `:export` and `tuido print` write.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    TodoTxt,
+    Markdown,
+    // Markdown with a heading per priority
+    MarkdownGrouped,
+    Json,
+    Csv,
+    Html,
+}
+
+impl ExportFormat {
+    /// Reads a format name as given to `tuido print --format`.
+    pub fn parse(name: &str) -> Option<ExportFormat> {
+        match name.to_lowercase().as_str() {
+            "todotxt" | "txt" => Some(ExportFormat::TodoTxt),
+            "md" | "markdown" => Some(ExportFormat::Markdown),
+            "md-grouped" => Some(ExportFormat::MarkdownGrouped),
+            "json" => Some(ExportFormat::Json),
+            "csv" => Some(ExportFormat::Csv),
+            "html" => Some(ExportFormat::Html),
+            _ => None,
+        }
+    }
+
+    // `:export` goes by the file's extension
+    fn from_file(file: &str, group: bool) -> Option<ExportFormat> {
+        let extension = Path::new(file).extension()?.to_str()?;
+        match extension {
+            "md" if group => Some(ExportFormat::MarkdownGrouped),
+            "txt" | "md" | "json" | "csv" | "html" => ExportFormat::parse(extension),
+            _ => None,
+        }
+    }
+}
+
+/// Writes `todos` to `out` in `format`.
+pub fn export(todos: &[Todo], format: ExportFormat, out: &mut impl Write) -> io::Result<()> {
+    match format {
+        ExportFormat::TodoTxt => App::export_todotxt(todos, out),
+        ExportFormat::Markdown => App::export_markdown(todos, out, false),
+        ExportFormat::MarkdownGrouped => App::export_markdown(todos, out, true),
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, todos)?;
+            writeln!(out)
+        }
+        ExportFormat::Csv => out.write_all(csv::to_string(todos).as_bytes()),
+        ExportFormat::Html => {
+            out.write_all(html::to_string(todos, Local::now().naive_local()).as_bytes())
+        }
+    }
+}
+
+// What :sort orders by. Todos without the key always go last, also when
+// the order is reversed with `!`.
+#[derive(Clone, PartialEq)]
+enum SortKey {
+    // Completed first, as :sort always has
+    Completion,
+    Priority,
+    Alpha,
+    Due,
+    Created,
+    // Most recently completed first
+    Completed,
+    Meta(String),
+}
+
+impl SortKey {
+    fn parse(field: &str) -> Option<SortKey> {
+        Some(match field {
+            "priority" => SortKey::Priority,
+            "alpha" => SortKey::Alpha,
+            "due" => SortKey::Due,
+            "created" => SortKey::Created,
+            "completed" => SortKey::Completed,
+            _ => SortKey::Meta(field.strip_prefix("meta:")?.to_string()),
+        })
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            SortKey::Completion => "completion status".to_string(),
+            SortKey::Priority => "priority".to_string(),
+            SortKey::Alpha => "text".to_string(),
+            SortKey::Due => "due date".to_string(),
+            SortKey::Created => "creation date".to_string(),
+            SortKey::Completed => "completion date".to_string(),
+            SortKey::Meta(key) => key.clone(),
+        }
+    }
+
+    fn compare(&self, a: &Todo, b: &Todo, reverse: bool) -> Ordering {
+        match self {
+            SortKey::Completion => flip(b.completed.cmp(&a.completed), reverse),
+            // Pending before completed within each priority
+            SortKey::Priority => compare_present(a.priority, b.priority, reverse, Ord::cmp)
+                .then(a.completed.cmp(&b.completed)),
+            SortKey::Alpha => flip(a.text.to_lowercase().cmp(&b.text.to_lowercase()), reverse),
+            SortKey::Due => compare_present(a.due, b.due, reverse, Ord::cmp),
+            SortKey::Created => compare_present(a.created, b.created, reverse, Ord::cmp),
+            SortKey::Completed => {
+                compare_present(a.completed_on, b.completed_on, !reverse, Ord::cmp)
+            }
+            SortKey::Meta(key) => {
+                compare_present(a.meta.get(key), b.meta.get(key), reverse, |a, b| {
+                    compare_meta(a, b)
+                })
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum DupeCheck {
+    Off,
+    Warn,
+    Strict,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Density {
+    Compact,
+    Comfortable,
+}
+
+// Everything `:set` changes, and the config's [options] table sets at
+// startup. OPTIONS lists the names they go by.
+#[derive(Clone, PartialEq)]
+pub struct Options {
+    // `:set nofiltersearch`: / highlights matches instead of hiding the rest
+    pub(crate) filter_search: bool,
+    // `:set regexsearch`: every query is a regex, not just `re:` ones
+    pub(crate) regex_search: bool,
+    pub(crate) density: Density,
+    pub(crate) dupe_check: DupeCheck,
+    pub(crate) hist_ignore_shell: bool,
+    pub(crate) esc_commits: bool,
+    pub(crate) show_meta: bool,
+    // `:set number` / `:set relativenumber`: a gutter of row numbers, or of
+    // distances from the cursor row (which keeps its own number)
+    pub(crate) number: bool,
+    pub(crate) relative_number: bool,
+    // `:set nomouse` turns mouse capture off, leaving the terminal's own
+    // text selection
+    pub mouse: bool,
+    pub(crate) compact_json: bool,
+    // Copies of the data file taken before each save; 0 turns them off
+    pub(crate) backup_count: usize,
+    // `:set escalate 14d`: stale pending todos move up a priority each period
+    pub(crate) escalate_after: Option<i64>,
+    pub(crate) color_support: ColorSupport,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            filter_search: true,
+            regex_search: false,
+            density: Density::Compact,
+            dupe_check: DupeCheck::Warn,
+            hist_ignore_shell: false,
+            esc_commits: false,
+            show_meta: true,
+            number: false,
+            relative_number: false,
+            mouse: true,
+            compact_json: false,
+            backup_count: DEFAULT_BACKUP_COUNT,
+            escalate_after: None,
+            color_support: ColorSupport::detect(),
+        }
+    }
+}
+
+// `:set` options: the name, a short name ("" for none), and whether it's
+// a flag (`:set number` / `:set nonumber`) rather than taking a value
+const OPTIONS: [(&str, &str, bool); 15] = [
+    ("backups", "", false),
+    ("colors", "", false),
+    ("compactjson", "", true),
+    ("dupecheck", "", false),
+    ("escalate", "", false),
+    ("esccommits", "", true),
+    ("filtersearch", "", true),
+    ("histignoreshell", "", true),
+    ("layout", "", false),
+    ("meta", "", true),
+    ("mouse", "", true),
+    ("number", "nu", true),
+    ("regexsearch", "", true),
+    ("relativenumber", "rnu", true),
+    ("truecolor", "", true),
+];
+
+impl Options {
+    // An option as `:set name?` shows it: `number`, `nonumber` or
+    // `layout=compact`
+    fn show(&self, name: &str) -> Option<String> {
+        let flag = |on: bool| {
+            Some(if on {
+                name.to_string()
+            } else {
+                format!("no{}", name)
+            })
+        };
+        let value = |value: &str| Some(format!("{}={}", name, value));
+        match name {
+            "backups" => value(&self.backup_count.to_string()),
+            "colors" => value(match self.color_support {
+                ColorSupport::TrueColor => "true",
+                ColorSupport::Indexed256 => "256",
+                ColorSupport::Basic16 => "16",
+                ColorSupport::NoColor => "none",
+            }),
+            "compactjson" => flag(self.compact_json),
+            "dupecheck" => value(match self.dupe_check {
+                DupeCheck::Off => "off",
+                DupeCheck::Warn => "warn",
+                DupeCheck::Strict => "strict",
+            }),
+            "escalate" => value(&match self.escalate_after {
+                Some(days) => format!("{}d", days),
+                None => "off".to_string(),
+            }),
+            "esccommits" => flag(self.esc_commits),
+            "filtersearch" => flag(self.filter_search),
+            "histignoreshell" => flag(self.hist_ignore_shell),
+            "layout" => value(match self.density {
+                Density::Compact => "compact",
+                Density::Comfortable => "comfortable",
+            }),
+            "meta" => flag(self.show_meta),
+            "mouse" => flag(self.mouse),
+            "number" => flag(self.number),
+            "regexsearch" => flag(self.regex_search),
+            "relativenumber" => flag(self.relative_number),
+            "truecolor" => flag(self.color_support == ColorSupport::TrueColor),
+            _ => None,
+        }
+    }
+}
+
+// The full name of a `:set` option, given it or its short name, and
+// whether it's a flag
+fn find_option(name: &str) -> Option<(&'static str, bool)> {
+    OPTIONS
+        .iter()
+        .find(|&&(full, short, _)| name == full || (!short.is_empty() && name == short))
+        .map(|&(full, _, flag)| (full, flag))
+}
+
+// What `:set` was given for an option
+#[derive(Clone, Copy)]
+enum OptionValue<'a> {
+    Flag(bool),
+    Text(&'a str),
+}
+
+const BULK_CONFIRM_THRESHOLD: usize = 20;
+
+// Near-duplicate detection ignores texts shorter than this, where a couple of
+// edits already make unrelated todos look alike.
+const DUPE_MIN_LEN: usize = 8;
+const DUPE_SIMILARITY: f64 = 0.85;
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum ColorSupport {
+    TrueColor,
+    Indexed256,
+    Basic16,
+    // NO_COLOR: styling is left to bold, reversed and the like
+    NoColor,
+}
+
+// xterm's default values for the 16 named colors. Black is left out so a
+// background mapped down from RGB never disappears into the terminal's own.
+const BASIC_COLORS: [(Color, (u8, u8, u8)); 15] = [
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+impl ColorSupport {
+    fn detect() -> ColorSupport {
+        if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            return ColorSupport::NoColor;
+        }
+        let colorterm = std::env::var("COLORTERM")
+            .unwrap_or_default()
+            .to_lowercase();
+        let term = std::env::var("TERM").unwrap_or_default().to_lowercase();
+        if colorterm == "truecolor" || colorterm == "24bit" || term.contains("direct") {
+            ColorSupport::TrueColor
+        } else if term.contains("256color") {
+            ColorSupport::Indexed256
+        } else {
+            ColorSupport::Basic16
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ColorSupport::TrueColor => "truecolor",
+            ColorSupport::Indexed256 => "256 colors",
+            ColorSupport::Basic16 => "16 colors",
+            ColorSupport::NoColor => "no color",
+        }
+    }
+
+    // Named and indexed colors pass through; RGB goes through `rgb`
+    pub(crate) fn adapt(self, color: Color) -> Color {
+        match color {
+            _ if self == ColorSupport::NoColor => Color::Reset,
+            Color::Rgb(r, g, b) => self.rgb(r, g, b),
+            color => color,
+        }
+    }
+
+    // The default visual and cursor-row backgrounds both come out DarkGray
+    // in 16 colors, which would hide a visual selection
+    pub(crate) fn visual_bg(self, theme: &Theme) -> Color {
+        if self == ColorSupport::Basic16 && theme.visual_bg == Theme::default().visual_bg {
+            Color::Blue
+        } else {
+            self.adapt(theme.visual_bg)
+        }
+    }
+
+    // Tint for todos just changed by a bulk operation; in 16 colors an amber
+    // tint would land on the cursor row's DarkGray
+    pub(crate) fn flash_style(self) -> Style {
+        match self {
+            ColorSupport::NoColor => Style::default().add_modifier(Modifier::UNDERLINED),
+            ColorSupport::Basic16 => Style::default().bg(Color::Magenta),
+            _ => Style::default().bg(self.rgb(90, 75, 20)),
+        }
+    }
+
+    // Every RGB color in the UI goes through here so it degrades to the
+    // nearest color the terminal can show.
+    pub(crate) fn rgb(self, r: u8, g: u8, b: u8) -> Color {
+        let distance = |(r2, g2, b2): (u8, u8, u8)| {
+            let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+            d(r, r2) + d(g, g2) + d(b, b2)
+        };
+        match self {
+            ColorSupport::TrueColor => Color::Rgb(r, g, b),
+            ColorSupport::NoColor => Color::Reset,
+            ColorSupport::Indexed256 => {
+                // 6x6x6 color cube (16-231) or the grayscale ramp (232-255)
+                const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+                let nearest = |v: u8| {
+                    (0..6)
+                        .min_by_key(|&i| (LEVELS[i] as i32 - v as i32).abs())
+                        .unwrap_or(0)
+                };
+                let (ri, gi, bi) = (nearest(r), nearest(g), nearest(b));
+                let cube = (LEVELS[ri], LEVELS[gi], LEVELS[bi]);
+                let avg = ((r as u16 + g as u16 + b as u16) / 3) as u8;
+                let gray_step = (avg.saturating_sub(8) / 10).min(23);
+                let gray_value = 8 + gray_step * 10;
+                if distance((gray_value, gray_value, gray_value)) < distance(cube) {
+                    Color::Indexed(232 + gray_step)
+                } else {
+                    Color::Indexed(16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8)
+                }
+            }
+            ColorSupport::Basic16 => BASIC_COLORS
+                .iter()
+                .min_by_key(|(_, rgb)| distance(*rgb))
+                .map(|(color, _)| *color)
+                .unwrap_or(Color::DarkGray),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum MergeChoice {
+    KeepMine,
+    TakeTheirs,
+    KeepBoth,
+}
+
+// Incoming todos sorted against the current list: `conflicts` pairs an
+// existing index with an incoming todo that has the same text but differs in
+// completion, priority or note.
+pub(crate) struct MergePlan {
+    new: Vec<Todo>,
+    pub(crate) conflicts: Vec<(usize, Todo)>,
+    unchanged: usize,
+}
+
+pub(crate) struct MergeState {
+    pub(crate) source: String,
+    pub(crate) plan: MergePlan,
+    pub(crate) choices: Vec<MergeChoice>,
+}
+
+// A file's state when tuido last read or wrote it. `hash` is None when the
+// file didn't exist yet.
+struct DiskState {
+    path: PathBuf,
+    hash: Option<u64>,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl DiskState {
+    fn new(path: &Path, contents: Option<&str>) -> DiskState {
+        let metadata = fs::metadata(path).ok();
+        DiskState {
+            path: path.to_path_buf(),
+            hash: contents.map(content_hash),
+            len: contents.map_or(0, |c| c.len() as u64),
+            modified: metadata.and_then(|m| m.modified().ok()),
+        }
+    }
+
+    // Something other than tuido wrote (or created) the file since. A
+    // matching size and mtime is trusted; otherwise the contents decide, so
+    // a plain `touch` doesn't count. A deleted file has nothing to lose.
+    fn changed(&self) -> bool {
+        let Ok(metadata) = fs::metadata(&self.path) else {
+            return false;
+        };
+        if self.hash.is_some()
+            && metadata.len() == self.len
+            && metadata.modified().ok() == self.modified
+        {
+            return false;
+        }
+        match fs::read_to_string(&self.path) {
+            Ok(contents) => self.hash != Some(content_hash(&contents)),
+            Err(_) => self.hash.is_some(),
+        }
+    }
+}
+
+// Position within a popup: the scroll offset of a text popup or the
+// selected entry of a picker. `max` and `page` come from the last render,
+// so movement is clamped to what's actually on screen.
+#[derive(Default)]
+pub(crate) struct ScrollState {
+    pub(crate) position: usize,
+    max: usize,
+    page: usize,
+}
+
+impl ScrollState {
+    pub(crate) fn set_bounds(&mut self, max: usize, page: usize) {
+        self.max = max;
+        self.page = page.max(1);
+        self.position = self.position.min(max);
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        self.position = self.position.saturating_add_signed(delta).min(self.max);
+    }
+
+    // Shared popup navigation: j/k, arrows, gg/G, Home/End, Ctrl+d/u,
+    // PageUp/PageDown and Space. Returns false for keys it doesn't handle.
+    fn navigate(&mut self, key: KeyEvent, pending: &str) -> bool {
+        let page = self.page as isize;
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('d') if ctrl => self.move_by((page / 2).max(1)),
+            KeyCode::Char('u') if ctrl => self.move_by(-(page / 2).max(1)),
+            KeyCode::Char('j') | KeyCode::Down => self.move_by(1),
+            KeyCode::Char('k') | KeyCode::Up => self.move_by(-1),
+            KeyCode::PageDown | KeyCode::Char(' ') => self.move_by(page),
+            KeyCode::PageUp => self.move_by(-page),
+            KeyCode::Char('g') if pending == "g" => self.position = 0,
+            // First half of gg
+            KeyCode::Char('g') => {}
+            KeyCode::Home => self.position = 0,
+            KeyCode::Char('G') | KeyCode::End => self.position = self.max,
+            _ => return false,
+        }
+        true
+    }
+
+    fn scroll_mouse(&mut self, kind: MouseEventKind) {
+        match kind {
+            MouseEventKind::ScrollDown => self.move_by(MOUSE_SCROLL_LINES),
+            MouseEventKind::ScrollUp => self.move_by(-MOUSE_SCROLL_LINES),
+            _ => {}
+        }
+    }
+}
+
+const MOUSE_SCROLL_LINES: isize = 3;
+
+// Two clicks on the same row closer together than this toggle it
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+// Where the last draw put the list's rows, so a click can find its todo
+#[derive(Default)]
+pub(crate) struct ListHits {
+    pub(crate) columns: Range<u16>,
+    pub(crate) checkbox: Range<u16>,
+    // The screen lines each visible row covers, and its index in
+    // `filtered_todos`
+    pub(crate) rows: Vec<(Range<u16>, usize)>,
+}
+
+impl ListHits {
+    // The row under the cell, and whether the cell is on its checkbox
+    fn row_at(&self, column: u16, line: u16) -> Option<(usize, bool)> {
+        if !self.columns.contains(&column) {
+            return None;
+        }
+        self.rows
+            .iter()
+            .find(|(lines, _)| lines.contains(&line))
+            .map(|&(_, row)| (row, self.checkbox.contains(&column)))
+    }
+}
+
+// A popup list of choices; `kind` says what picking an entry does
+pub(crate) struct Picker {
+    pub(crate) title: String,
+    pub(crate) items: Vec<String>,
+    pub(crate) scroll: ScrollState,
+    kind: PickerKind,
+}
+
+enum PickerKind {
+    // Entry i adopts candidates[i]; the final entry starts fresh
+    Migration(Vec<PathBuf>),
+}
+
+// Text with a cursor, shared by the note editor and the one-line inputs
+// (insert, command and search). `col` counts chars, not bytes, and may sit
+// one past the end of its line.
+pub(crate) struct TextBuffer {
+    lines: Vec<String>,
+    row: usize,
+    pub(crate) col: usize,
+}
+
+impl Default for TextBuffer {
+    fn default() -> TextBuffer {
+        TextBuffer::new("")
+    }
+}
+
+impl std::fmt::Display for TextBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.text())
+    }
+}
+
+// Char offsets in `line` where grapheme clusters start, plus its end. The
+// cursor only stops at these, so it never lands inside an emoji sequence or
+// between a letter and its combining accent.
+fn grapheme_stops(line: &str) -> Vec<usize> {
+    let mut stops = vec![0];
+    let mut offset = 0;
+    for grapheme in line.graphemes(true) {
+        offset += grapheme.chars().count();
+        stops.push(offset);
+    }
+    stops
+}
+
+// Char offsets where each word in `line` starts and ends, by Unicode word
+// boundaries; spaces and punctuation between words aren't words
+fn word_spans(line: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut offset = 0;
+    for segment in line.split_word_bounds() {
+        let len = segment.chars().count();
+        if segment.chars().any(char::is_alphanumeric) {
+            spans.push((offset, offset + len));
+        }
+        offset += len;
+    }
+    spans
+}
+
+impl TextBuffer {
+    fn new(text: &str) -> TextBuffer {
+        let lines: Vec<String> = text.split('\n').map(str::to_string).collect();
+        let row = lines.len() - 1;
+        let col = lines[row].chars().count();
+        TextBuffer { lines, row, col }
+    }
+
+    pub(crate) fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.lines.iter().all(String::is_empty)
+    }
+
+    fn clear(&mut self) {
+        *self = TextBuffer::default();
+    }
+
+    // Replaces the text, leaving the cursor at the end
+    fn set(&mut self, text: &str) {
+        *self = TextBuffer::new(text);
+    }
+
+    // The cursor's line up to the cursor, for placing the terminal cursor
+    pub(crate) fn before_cursor(&self) -> String {
+        self.lines[self.row].chars().take(self.col).collect()
+    }
+
+    fn line_len(&self, row: usize) -> usize {
+        self.lines[row].chars().count()
+    }
+
+    fn byte_index(&self) -> usize {
+        self.byte_at(self.col)
+    }
+
+    fn byte_at(&self, col: usize) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices().nth(col).map_or(line.len(), |(i, _)| i)
+    }
+
+    // Removes chars `from..to` of the cursor's line and leaves the cursor at
+    // `from`
+    fn delete_range(&mut self, from: usize, to: usize) {
+        let range = self.byte_at(from)..self.byte_at(to);
+        self.lines[self.row].replace_range(range, "");
+        self.col = from;
+    }
+
+    fn prev_stop(&self) -> usize {
+        grapheme_stops(&self.lines[self.row])
+            .into_iter()
+            .rev()
+            .find(|&stop| stop < self.col)
+            .unwrap_or(0)
+    }
+
+    fn next_stop(&self) -> usize {
+        grapheme_stops(&self.lines[self.row])
+            .into_iter()
+            .find(|&stop| stop > self.col)
+            .unwrap_or(self.col)
+    }
+
+    // Moves a cursor that landed inside a grapheme (after up/down) back to
+    // its start
+    fn snap(&mut self) {
+        self.col = grapheme_stops(&self.lines[self.row])
+            .into_iter()
+            .rev()
+            .find(|&stop| stop <= self.col)
+            .unwrap_or(0);
+    }
+
+    fn insert(&mut self, c: char) {
+        let at = self.byte_index();
+        self.lines[self.row].insert(at, c);
+        self.col += 1;
+    }
+
+    // Pasted text arrives in one piece. Tabs become spaces and other control
+    // characters are dropped; newlines start a new line only in a multi-line
+    // buffer such as a note, and are spaces anywhere else, except that a
+    // trailing one is dropped.
+    fn paste(&mut self, text: &str, multiline: bool) {
+        let text = if multiline {
+            text
+        } else {
+            text.trim_end_matches(['\r', '\n'])
+        };
+        for c in text.chars() {
+            match c {
+                '\n' if multiline => self.newline(),
+                '\n' | '\t' => self.insert(' '),
+                c if c.is_control() => {}
+                c => self.insert(c),
+            }
+        }
+    }
+
+    fn newline(&mut self) {
+        let at = self.byte_index();
+        let rest = self.lines[self.row].split_off(at);
+        self.row += 1;
+        self.lines.insert(self.row, rest);
+        self.col = 0;
+    }
+
+    fn backspace(&mut self) {
+        if self.col > 0 {
+            self.delete_range(self.prev_stop(), self.col);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_len(self.row);
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.col < self.line_len(self.row) {
+            self.delete_range(self.col, self.next_stop());
+        } else if self.row + 1 < self.lines.len() {
+            let line = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    fn left(&mut self) {
+        if self.col > 0 {
+            self.col = self.prev_stop();
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.line_len(self.row);
+        }
+    }
+
+    fn right(&mut self) {
+        if self.col < self.line_len(self.row) {
+            self.col = self.next_stop();
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    fn up(&mut self) {
+        if self.row > 0 {
+            self.row -= 1;
+            self.col = self.col.min(self.line_len(self.row));
+            self.snap();
+        }
+    }
+
+    fn down(&mut self) {
+        if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = self.col.min(self.line_len(self.row));
+            self.snap();
+        }
+    }
+
+    fn home(&mut self) {
+        self.col = 0;
+    }
+
+    fn end(&mut self) {
+        self.col = self.line_len(self.row);
+    }
+
+    // To the end of the next word, or the next line at the end of this one
+    fn word_right(&mut self) {
+        match word_spans(&self.lines[self.row])
+            .into_iter()
+            .find(|&(_, end)| end > self.col)
+        {
+            Some((_, end)) => self.col = end,
+            None if self.col < self.line_len(self.row) => self.end(),
+            None => self.right(),
+        }
+    }
+
+    // To the start of the previous word, or the previous line at the start
+    // of this one
+    fn word_left(&mut self) {
+        match word_spans(&self.lines[self.row])
+            .into_iter()
+            .rev()
+            .find(|&(start, _)| start < self.col)
+        {
+            Some((start, _)) => self.col = start,
+            None if self.col > 0 => self.home(),
+            None => self.left(),
+        }
+    }
+
+    // Deletes up to the end of the next word; at the end of a line, joins
+    // the next one
+    fn delete_word(&mut self) {
+        let (row, col) = (self.row, self.col);
+        self.word_right();
+        if self.row != row {
+            (self.row, self.col) = (row, col);
+            self.delete();
+            return;
+        }
+        let end = self.col;
+        self.delete_range(col, end);
+    }
+
+    // Ctrl+W: deletes back to the start of the previous word; at the start
+    // of a line, joins it to the one above
+    fn delete_word_back(&mut self) {
+        if self.col == 0 {
+            self.backspace();
+            return;
+        }
+        let end = self.col;
+        self.word_left();
+        let start = self.col;
+        self.delete_range(start, end);
+    }
+
+    // Ctrl+U: deletes from the start of the line to the cursor
+    fn delete_to_line_start(&mut self) {
+        let end = self.col;
+        self.delete_range(0, end);
+    }
+
+    // Hard-wraps every line at `width` chars and returns the visual rows
+    // plus the cursor's (row, column) among them.
+    pub(crate) fn wrapped(&self, width: usize) -> (Vec<String>, (usize, usize)) {
+        let width = width.max(1);
+        let mut rows = Vec::new();
+        let mut cursor = (0, 0);
+        for (i, line) in self.lines.iter().enumerate() {
+            let chars: Vec<char> = line.chars().collect();
+            if i == self.row {
+                // A cursor at the very end of a full row moves to the next one
+                cursor = (rows.len() + self.col / width, self.col % width);
+            }
+            if chars.is_empty() {
+                rows.push(String::new());
+            }
+            for chunk in chars.chunks(width) {
+                rows.push(chunk.iter().collect());
+            }
+            if i == self.row && cursor.0 >= rows.len() {
+                rows.push(String::new());
+            }
+        }
+        (rows, cursor)
+    }
+}
+
+// Read-only text in a scrollable popup, shared by help and shell output
+pub(crate) struct TextPopup {
+    pub(crate) title: String,
+    pub(crate) lines: Vec<String>,
+    // Indices into `lines` drawn in red
+    pub(crate) error_lines: Vec<usize>,
+    pub(crate) scroll: ScrollState,
+    pub(crate) hscroll: usize,
+}
+
+impl TextPopup {
+    fn new(title: &str, lines: Vec<String>) -> TextPopup {
+        TextPopup {
+            title: title.to_string(),
+            lines,
+            error_lines: Vec::new(),
+            scroll: ScrollState::default(),
+            hscroll: 0,
+        }
+    }
+}
+
+// A todo file found outside the active location, for the migration picker
+struct LegacyCandidate {
+    path: PathBuf,
+    count: usize,
+    modified: std::time::SystemTime,
+}
+
+// Newest first, and the longer list first among files modified together.
+// Files that tie on both keep the order the scan found them in.
+fn rank_candidates(candidates: &mut [LegacyCandidate]) {
+    candidates.sort_by(|a, b| {
+        b.modified
+            .cmp(&a.modified)
+            .then_with(|| b.count.cmp(&a.count))
+    });
+}
+
+// Candidate files larger than this aren't parsed, keeping the startup scan quick
+const MAX_CANDIDATE_SIZE: u64 = 5 * 1024 * 1024;
+
+// Saving a data file bigger than this suggests ways to shrink it
+const LARGE_FILE_WARNING: u64 = 10 * 1024 * 1024;
+
+// How often the active file is checked for changes made by someone else
+const DISK_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// Stands in for the file name of a scratch list read from stdin
+pub(crate) const STDIN_NAME: &str = "[stdin]";
+
+// A half-typed sequence like `d` or `g` is dropped after this long, as
+// with vim's 'timeoutlen'
+const PENDING_TIMEOUT: Duration = Duration::from_secs(1);
+
+// The start of a key sequence (`dd`, `gg`, `gx`, `zA`, `"+y`) waiting for
+// the rest of it
+pub(crate) struct PendingKey {
+    pub(crate) keys: String,
+    since: Instant,
+}
+
+// How long todos changed by a bulk operation stay tinted
+const FLASH_DURATION: Duration = Duration::from_millis(600);
+
+// Backups kept per data file unless changed with `:set backups N`
+const DEFAULT_BACKUP_COUNT: usize = 10;
+
+// Oldest command and search history entries are dropped past this
+const MAX_HISTORY: usize = 100;
+
+// States kept for undo and redo, counting the live list once something's
+// been undone
+const MAX_UNDO: usize = 100;
+
+// `:messages` keeps this many, dropping the oldest
+const MAX_MESSAGES: usize = 200;
+
+// Counts typed before a key stop growing here, so a run of digits can't
+// overflow
+const MAX_COUNT: usize = 99_999;
+
+/// How much a message matters: errors show in red and stay on the message
+/// line until the next key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MessageLevel {
+    Info,
+    Error,
+}
+
+// One line of `:messages`
+struct LoggedMessage {
+    at: chrono::NaiveDateTime,
+    level: MessageLevel,
+    text: String,
+}
+
+// Names the `:` prompt already knows, which an alias only replaces when
+// given with `!`
+const COMMAND_NAMES: &[&str] = &[
+    "alias",
+    "backups",
+    "checkhealth",
+    "clear",
+    "collapseall",
+    "d",
+    "dall",
+    "dedup",
+    "delete",
+    "done",
+    "e",
+    "edit",
+    "editall",
+    "expandall",
+    "export",
+    "file",
+    "filter",
+    "g",
+    "help",
+    "import",
+    "info",
+    "invert",
+    "m",
+    "merge",
+    "mes",
+    "messages",
+    "move",
+    "open",
+    "pri",
+    "q",
+    "quit",
+    "r",
+    "read",
+    "reg",
+    "registers",
+    "restore",
+    "retag",
+    "s",
+    "sav",
+    "saveas",
+    "selectall",
+    "set",
+    "sort",
+    "stats",
+    "toggle",
+    "toggleall",
+    "unalias",
+    "undone",
+    "v",
+    "w",
+    "wq",
+    "write",
+    "x",
+    "xall",
+];
+
+// Submitted commands or searches, oldest first, for Up/Down recall
+#[derive(Default)]
+struct History {
+    entries: Vec<String>,
+    // Position while recalling; None when typing fresh
+    index: Option<usize>,
+}
+
+impl History {
+    fn push(&mut self, entry: &str) {
+        self.index = None;
+        if entry.is_empty() || self.entries.last().map(String::as_str) == Some(entry) {
+            return;
+        }
+        self.entries.push(entry.to_string());
+        if self.entries.len() > MAX_HISTORY {
+            self.entries.remove(0);
+        }
+    }
+
+    // Steps to an older or newer entry and returns the text the input
+    // should show: empty after the newest, None when there's nowhere to go
+    fn recall(&mut self, older: bool) -> Option<String> {
+        let index = match (self.index, older) {
+            (Some(0), true) | (None, false) => return None,
+            (Some(i), true) => i - 1,
+            (None, true) => self.entries.len().checked_sub(1)?,
+            (Some(i), false) if i + 1 < self.entries.len() => i + 1,
+            (Some(_), false) => {
+                self.index = None;
+                return Some(String::new());
+            }
+        };
+        self.index = Some(index);
+        Some(self.entries[index].clone())
+    }
+}
+
+// How many deletes `"1` to `"9` keep
+const NUMBERED_REGISTERS: usize = 9;
+
+// What p pastes, for the session only. y and d fill the unnamed register,
+// which p uses unless `"` names another first: `"a` to `"z` hold todos
+// until replaced (`"A` to `"Z` append to them), `"0` the last yank and
+// `"1` to `"9` the last nine deletes, newest first.
+#[derive(Default)]
+struct Registers {
+    unnamed: Vec<Todo>,
+    named: BTreeMap<char, Vec<Todo>>,
+    yanked: Vec<Todo>,
+    deleted: VecDeque<Vec<Todo>>,
+    // The register the last store appended to, and its length before
+    appended: Option<(char, usize)>,
+}
+
+impl Registers {
+    fn is_name(c: char) -> bool {
+        c.is_ascii_alphanumeric()
+    }
+
+    // Why `name` can't be yanked or deleted into, if it can't
+    fn check_writable(name: Option<char>) -> Result<(), String> {
+        match name {
+            Some(c) if c.is_ascii_digit() => {
+                Err(format!("Register {} is filled by yanks and deletes", c))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Keeps yanked or deleted todos in `name`, or the numbered registers
+    // without one; like vim, a named delete leaves `"1` to `"9` alone
+    fn store(&mut self, name: Option<char>, todos: Vec<Todo>, deleted: bool) {
+        self.appended = None;
+        self.unnamed = match name {
+            Some(c) if c.is_ascii_uppercase() => {
+                let named = self.named.entry(c.to_ascii_lowercase()).or_default();
+                self.appended = Some((c, named.len()));
+                named.extend(todos);
+                named.clone()
+            }
+            Some(c) => {
+                self.named.insert(c, todos.clone());
+                todos
+            }
+            None if deleted => {
+                self.deleted.push_front(todos.clone());
+                self.deleted.truncate(NUMBERED_REGISTERS);
+                todos
+            }
+            None => {
+                self.yanked = todos.clone();
+                todos
+            }
+        };
+    }
+
+    // Takes back the last append to `name`, if that was the last store
+    fn undo_append(&mut self, name: char) {
+        if let Some((appended, len)) = self.appended.take()
+            && appended == name
+            && let Some(named) = self.named.get_mut(&name.to_ascii_lowercase())
+        {
+            named.truncate(len);
+        }
+    }
+
+    fn get(&self, name: Option<char>) -> &[Todo] {
+        let todos = match name {
+            None => Some(&self.unnamed),
+            Some('0') => Some(&self.yanked),
+            Some(c @ '1'..='9') => self.deleted.get(c as usize - '1' as usize),
+            Some(c) => self.named.get(&c.to_ascii_lowercase()),
+        };
+        todos.map_or(&[], Vec::as_slice)
+    }
+
+    // `:registers`: each non-empty register with its todos, one per line
+    fn lines(&self) -> Vec<String> {
+        let numbered = (0..=NUMBERED_REGISTERS as u8).map(|n| (b'0' + n) as char);
+        let names = std::iter::once(None)
+            .chain(numbered.map(Some))
+            .chain(self.named.keys().copied().map(Some));
+        let mut lines = Vec::new();
+        for name in names {
+            let label = format!("\"{}", name.unwrap_or('"'));
+            for (i, todo) in self.get(name).iter().enumerate() {
+                let label = if i == 0 { label.as_str() } else { "" };
+                lines.push(format!("{:<4}{}", label, todo.text));
+            }
+        }
+        lines
+    }
+}
+
+// The history file in the data directory
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct SavedHistory {
+    #[serde(default)]
+    commands: Vec<String>,
+    #[serde(default)]
+    searches: Vec<String>,
+}
+
+// Lists at least this long are searched on a background thread
+const ASYNC_SEARCH_THRESHOLD: usize = 5_000;
+
+struct SearchJob {
+    generation: u64,
+    query: String,
+    regex: bool,
+    todos: Arc<Vec<Todo>>,
+}
+
+struct SearchResult {
+    generation: u64,
+    matched: Result<Vec<(usize, Vec<usize>)>, String>,
+}
+
+// The highlighted char offsets when todo `index` is among `matches`
+pub(crate) fn find_match(matches: &[(usize, Vec<usize>)], index: usize) -> Option<&[usize]> {
+    matches
+        .binary_search_by_key(&index, |&(i, _)| i)
+        .ok()
+        .map(|found| matches[found].1.as_slice())
+}
+
+struct SearchWorker {
+    jobs: mpsc::Sender<SearchJob>,
+    results: mpsc::Receiver<SearchResult>,
+}
+
+impl SearchWorker {
+    fn spawn() -> SearchWorker {
+        let (jobs, job_rx) = mpsc::channel::<SearchJob>();
+        let (result_tx, results) = mpsc::channel();
+        thread::spawn(move || {
+            let mut index = SearchIndex::default();
+            while let Ok(mut job) = job_rx.recv() {
+                // Only the newest queued query is worth matching
+                while let Ok(newer) = job_rx.try_recv() {
+                    job = newer;
+                }
+                let matched = index.search(&job.todos, &job.query, job.regex);
+                let result = SearchResult {
+                    generation: job.generation,
+                    matched,
+                };
+                if result_tx.send(result).is_err() {
+                    break;
+                }
+            }
+        });
+        SearchWorker { jobs, results }
+    }
+}
+
+// A `:!cmd` or `:r !cmd` running in the background, so a slow command
+// doesn't freeze the screen
+pub(crate) struct ShellJob {
+    pub(crate) command: String,
+    // `:r !cmd` adds its output as todos rather than showing it
+    read: bool,
+    child: Child,
+    pipes: mpsc::Receiver<(Vec<u8>, Vec<u8>)>,
+    // stdout and stderr, once both pipes have closed
+    output: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl ShellJob {
+    fn spawn(command: &str, read: bool) -> io::Result<ShellJob> {
+        let mut child = spawn_shell(command, None)?;
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let (tx, pipes) = mpsc::channel();
+        thread::spawn(move || {
+            // Each pipe drains on its own thread, so a command that fills
+            // stderr while we wait on stdout can't stall
+            let errors = thread::spawn(move || {
+                let mut buf = Vec::new();
+                if let Some(mut stderr) = stderr {
+                    let _ = stderr.read_to_end(&mut buf);
+                }
+                buf
+            });
+            let mut out = Vec::new();
+            if let Some(mut stdout) = stdout {
+                let _ = stdout.read_to_end(&mut out);
+            }
+            let _ = tx.send((out, errors.join().unwrap_or_default()));
+        });
+        Ok(ShellJob {
+            command: command.to_string(),
+            read,
+            child,
+            pipes,
+            output: None,
+        })
+    }
+
+    // The exit status and output once the command has finished
+    fn finished(&mut self) -> Option<Output> {
+        if self.output.is_none() {
+            self.output = match self.pipes.try_recv() {
+                Ok(output) => Some(output),
+                Err(mpsc::TryRecvError::Disconnected) => Some(Default::default()),
+                Err(mpsc::TryRecvError::Empty) => None,
+            };
+        }
+        self.output.as_ref()?;
+        let status: ExitStatus = self.child.try_wait().ok()??;
+        let (stdout, stderr) = self.output.take()?;
+        Some(Output {
+            status,
+            stdout,
+            stderr,
+        })
+    }
+}
+
+// Quitting while a command runs stops it too
+impl Drop for ShellJob {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+struct AppSnapshot {
+    todos: Vec<Todo>,
+    selected_index: Option<usize>,
+}
+
+pub struct App {
+    pub todos: Vec<Todo>,
+    pub(crate) filtered_todos: Vec<usize>,
+    // Undo history: the state before each change, oldest first. While the
+    // list is live `history_index` is `history.len()`; the first undo pushes
+    // the live state too, so redo can get back to it, and from then on the
+    // index points at the snapshot on screen.
+    history: VecDeque<AppSnapshot>,
+    history_index: usize,
+    // Set while `3.` repeats a change, which is undone in one step
+    grouping_changes: bool,
+    pub(crate) list_state: ListState,
+    // The rows of the list on screen at the last draw, for paging and H/M/L
+    pub(crate) list_view: Range<usize>,
+    pub(crate) list_hits: ListHits,
+    // The time and row of the last click, to spot a double-click
+    last_click: Option<(Instant, usize)>,
+    pub mode: Mode,
+    pub(crate) input: TextBuffer,
+    pub(crate) command_input: TextBuffer,
+    pub message: String,
+    pub(crate) message_level: MessageLevel,
+    // An error shown since the last key: routine messages go to the log
+    // without replacing it
+    error_pinned: bool,
+    // What the message line has said, oldest first, for `:messages`
+    message_log: VecDeque<LoggedMessage>,
+    pub(crate) visual_start: Option<usize>,
+    pub(crate) search_query: TextBuffer,
+    // Todos matching the search (after the quick filters) in list order, each
+    // with the char offsets the list highlights. With `filter_search` off the
+    // list shows everything and n/N jump between these.
+    pub(crate) search_matches: Vec<(usize, Vec<usize>)>,
+    // Why the current query can't be used (a bad regex); the last good
+    // results stay up meanwhile
+    pub(crate) search_error: Option<String>,
+    // Ctrl+N overlay input; while open it takes keys ahead of the mode
+    pub(crate) quick_add: Option<TextBuffer>,
+    pub(crate) note_input: TextBuffer,
+    pub(crate) current_note_index: Option<usize>,
+    pub(crate) popup: Option<TextPopup>,
+    pub(crate) is_dirty: bool,
+    pub(crate) current_file: PathBuf,
+    // The list came from stdin (`--stdin`) and has no file until `:saveas`
+    pub(crate) scratch: bool,
+    file_unwritable: bool,
+    saved_snapshot: Option<Vec<Todo>>,
+    is_editing: bool,
+    repeat_count: usize,
+    registers: Registers,
+    // The register `"a` named for the next yank, delete or paste
+    register: Option<char>,
+    // The todo the last J/K moved, while no other change has happened since
+    move_streak: Option<usize>,
+    // Opened on the first "+y or "+p
+    system_clipboard: Option<arboard::Clipboard>,
+    last_action: Option<LastAction>,
+    pub options: Options,
+    confirmed_duplicate: Option<String>,
+    command_history: History,
+    search_history: History,
+    // Where both histories are kept between sessions; None keeps them in
+    // memory only
+    history_file: Option<PathBuf>,
+    // `:alias sp sort priority` and the config's [aliases]: a name for a
+    // whole command, with `%s` standing for whatever follows the name
+    aliases: BTreeMap<String, String>,
+    // The config file `:alias` saves to; None keeps aliases in memory only
+    config_path: Option<PathBuf>,
+    pub(crate) confirmed_shell: Option<String>,
+    pending_confirm: Option<Confirm>,
+    // Rows of the Visual selection `:` was pressed in, for `:'<,'>` commands
+    command_range: Option<(usize, usize)>,
+    pub(crate) priority_filter: Option<char>,
+    // `:filter key:value`; the value is matched case-insensitively
+    pub(crate) meta_filter: Option<(String, String)>,
+    // The active file as last read or written, so saving an unchanged list
+    // skips the write and changes made by someone else aren't clobbered
+    last_written: Option<DiskState>,
+    pub(crate) changed_on_disk: bool,
+    last_disk_check: Instant,
+    // Indices into `todos` touched by the last bulk operation, tinted until
+    // `flash_until` so changes to rows out of view don't go unnoticed
+    pub(crate) recently_changed: Vec<usize>,
+    flash_until: Option<Instant>,
+    // Where copies of the data file are written before each save and listed
+    // by :backups: tuido/backups in the data dir, or ~/.tuido_backups
+    backup_dir: PathBuf,
+    pub(crate) theme: Theme,
+    keymap: Keymap,
+    detected_color_support: ColorSupport,
+    pub(crate) merge: Option<MergeState>,
+    pub(crate) picker: Option<Picker>,
+    // Searches on the main thread; the worker keeps its own
+    search_index: SearchIndex,
+    search_worker: Option<SearchWorker>,
+    pub(crate) shell_job: Option<ShellJob>,
+    // Set by `:editall`; the event loop owns the terminal, so it hands the
+    // list to $EDITOR before the next draw
+    pub edit_all_pending: bool,
+    url_choices: Vec<String>,
+    // Keys typed so far of an unfinished sequence
+    pub(crate) pending: Option<PendingKey>,
+    // `qa` until `q`: the register and the keys typed since
+    recording: Option<(char, Vec<KeyEvent>)>,
+    macros: BTreeMap<char, Vec<KeyEvent>>,
+    // For `@@`
+    last_macro: Option<char>,
+    replaying: bool,
+    // Set by `fail` during a key, so a playing macro knows to stop
+    key_failed: bool,
+    // Set when a lone `q` times out; the event loop quits on it
+    pub quit_requested: bool,
+    search_generation: u64,
+    pub(crate) search_pending: bool,
+    pub(crate) today: NaiveDate,
+}
+
+impl App {
+    pub fn new(current_file: PathBuf) -> App {
+        let mut state = ListState::default();
+        state.select(Some(0));
+
+        // Don't save initial snapshot - that way it won't be dirty on startup
+        App {
+            todos: vec![],
+            filtered_todos: Vec::new(),
+            history: VecDeque::new(),
+            history_index: 0,
+            grouping_changes: false,
+            list_state: state,
+            list_view: 0..0,
+            list_hits: ListHits::default(),
+            last_click: None,
+            mode: Mode::Normal,
+            input: TextBuffer::default(),
+            command_input: TextBuffer::default(),
+            message: String::new(),
+            message_level: MessageLevel::Info,
+            error_pinned: false,
+            message_log: VecDeque::new(),
+            visual_start: None,
+            search_query: TextBuffer::default(),
+            search_matches: Vec::new(),
+            search_error: None,
+            quick_add: None,
+            note_input: TextBuffer::default(),
+            current_note_index: None,
+            popup: None,
+            is_dirty: false,
+            current_file,
+            scratch: false,
+            file_unwritable: false,
+            saved_snapshot: None,
+            is_editing: false,
+            repeat_count: 0,
+            registers: Registers::default(),
+            register: None,
+            recording: None,
+            macros: BTreeMap::new(),
+            last_macro: None,
+            replaying: false,
+            key_failed: false,
+            quit_requested: false,
+            move_streak: None,
+            system_clipboard: None,
+            last_action: None,
+            options: Options::default(),
+            confirmed_duplicate: None,
+            command_history: History::default(),
+            search_history: History::default(),
+            history_file: None,
+            aliases: BTreeMap::new(),
+            config_path: None,
+            confirmed_shell: None,
+            pending_confirm: None,
+            command_range: None,
+            priority_filter: None,
+            meta_filter: None,
+            last_written: None,
+            changed_on_disk: false,
+            last_disk_check: Instant::now(),
+            recently_changed: Vec::new(),
+            flash_until: None,
+            backup_dir: match dirs::data_dir() {
+                Some(data) => data.join("tuido").join("backups"),
+                None => dirs::home_dir()
+                    .unwrap_or_else(|| PathBuf::from("."))
+                    .join(".tuido_backups"),
+            },
+            theme: Theme::default(),
+            keymap: Keymap::default(),
+            detected_color_support: ColorSupport::detect(),
+            merge: None,
+            picker: None,
+            search_index: SearchIndex::default(),
+            search_worker: None,
+            shell_job: None,
+            edit_all_pending: false,
+            url_choices: Vec::new(),
+            pending: None,
+            search_generation: 0,
+            search_pending: false,
+            today: Local::now().date_naive(),
+        }
+    }
+
+    // The platform data dir ($XDG_DATA_HOME/tuido/todos.json on Linux,
+    // Application Support on macOS, AppData on Windows), or the old
+    // ~/.tuido.json where there isn't one
+    pub fn get_todo_file() -> PathBuf {
+        match dirs::data_dir() {
+            Some(data) => data.join("tuido").join("todos.json"),
+            None => Self::legacy_todo_file(),
+        }
+    }
+
+    // Next to the default data file, whichever file is being edited
+    pub fn history_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|data| data.join("tuido").join("history.json"))
+    }
+
+    // $XDG_CONFIG_HOME/tuido/config.toml on Linux
+    pub fn config_file() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("tuido").join("config.toml"))
+    }
+
+    // Where `:alias` saves new aliases, whether or not the file exists yet
+    pub fn use_config_file(&mut self, path: PathBuf) {
+        self.config_path = Some(path);
+    }
+
+    // Applies a config file's [colors], [keys], [aliases] and [options]
+    // tables and returns what couldn't be used; anything not mentioned keeps
+    // its default
+    pub fn apply_config(&mut self, contents: &str) -> Vec<String> {
+        let (entries, mut errors) = crate::config::parse(contents);
+        for entry in entries {
+            let problem = match entry.section.as_str() {
+                "options" => {
+                    let value = entry.value.to_lowercase();
+                    match find_option(&entry.key) {
+                        Some((name, true)) => match value.as_str() {
+                            "true" | "on" | "yes" => {
+                                self.set_option(name, OptionValue::Flag(true)).err()
+                            }
+                            "false" | "off" | "no" => {
+                                self.set_option(name, OptionValue::Flag(false)).err()
+                            }
+                            _ => Some(format!("{} is true or false", name)),
+                        },
+                        Some((name, false)) => {
+                            self.set_option(name, OptionValue::Text(&value)).err()
+                        }
+                        None => Some(unknown_option(&entry.key)),
+                    }
+                }
+                "colors" => match (self.theme.slot(&entry.key), entry.value.parse::<Color>()) {
+                    (Some(slot), Ok(color)) => {
+                        *slot = color;
+                        None
+                    }
+                    (Some(_), Err(_)) => Some(format!(
+                        "can't read color '{}' (use a name like red or #rrggbb)",
+                        entry.value
+                    )),
+                    (None, _) => Some(format!("unknown color '{}'", entry.key)),
+                },
+                "keys" => {
+                    let action = KEY_ACTIONS
+                        .iter()
+                        .find(|(name, _, _)| *name == entry.key)
+                        .map(|&(_, action, _)| action);
+                    match (action, KeyBinding::parse(&entry.value)) {
+                        (Some(action), Some(binding)) => {
+                            self.keymap.bind(action, binding);
+                            None
+                        }
+                        (Some(_), None) => Some(format!("can't read key '{}'", entry.value)),
+                        (None, _) => Some(format!("unknown action '{}'", entry.key)),
+                    }
+                }
+                // `sort! = "sort due"` replaces a built-in, as :alias! does
+                "aliases" => {
+                    let (name, force) = match entry.key.strip_suffix('!') {
+                        Some(name) => (name, true),
+                        None => (entry.key.as_str(), false),
+                    };
+                    match check_alias(name, force) {
+                        Ok(()) => {
+                            self.aliases
+                                .insert(name.to_lowercase(), entry.value.clone());
+                            None
+                        }
+                        Err(e) => Some(e),
+                    }
+                }
+                section => Some(format!("unknown section [{}]", section)),
+            };
+            if let Some(problem) = problem {
+                errors.push(format!("line {}: {}", entry.line, problem));
+            }
+        }
+
+        let name = |action: KeyAction| {
+            KEY_ACTIONS
+                .iter()
+                .find(|&&(_, a, _)| a == action)
+                .map_or("", |&(name, _, _)| name)
+        };
+        let bindings = &self.keymap.bindings;
+        for (i, (binding, action)) in bindings.iter().enumerate() {
+            if let Some((_, other)) = bindings[i + 1..].iter().find(|(b, _)| b == binding) {
+                errors.push(format!(
+                    "'{}' is bound to both {} and {}",
+                    binding.describe(),
+                    name(*action),
+                    name(*other)
+                ));
+            }
+        }
+        errors
+    }
+
+    pub fn legacy_todo_file() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(".tuido.json")
+    }
+
+    // Copies the old data file to `target` when only the old one exists,
+    // creating the directory first. Returns true when it copied; the old file
+    // is left where it was.
+    pub fn migrate_legacy_file(legacy: &Path, target: &Path) -> io::Result<bool> {
+        if target.exists() || !legacy.is_file() || legacy == target {
+            return Ok(false);
+        }
+        if let Some(dir) = target.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::copy(legacy, target)?;
+        Ok(true)
+    }
+
+    // Probe without clobbering: append to an existing file, or create and
+    // remove a missing one.
+    fn is_writable(path: &Path) -> bool {
+        if path.exists() {
+            OpenOptions::new().append(true).open(path).is_ok()
+        } else {
+            let created = OpenOptions::new().write(true).create_new(true).open(path);
+            created.is_ok() && fs::remove_file(path).is_ok()
+        }
+    }
+
+    // Shallow scan of the home dir, the data dir and the working directory for
+    // other tuido JSON files, newest first, then by size of the list.
+    fn find_legacy_candidates(&self) -> Vec<LegacyCandidate> {
+        let mut dirs_to_scan = Vec::new();
+        if let Some(home) = dirs::home_dir() {
+            dirs_to_scan.push(home);
+        }
+        if let Some(data) = dirs::data_dir() {
+            dirs_to_scan.push(data.join("tuido"));
+            dirs_to_scan.push(data);
+        }
+        if let Ok(cwd) = std::env::current_dir() {
+            dirs_to_scan.push(cwd);
+        }
+
+        let mut seen = Vec::new();
+        let mut candidates = Vec::new();
+        for dir in dirs_to_scan {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let name = entry.file_name().to_string_lossy().to_lowercase();
+                if !name.contains("tuido") || !name.ends_with(".json") {
+                    continue;
+                }
+                let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+                let current = fs::canonicalize(&self.current_file).ok();
+                if seen.contains(&canonical) || current.as_ref() == Some(&canonical) {
+                    continue;
+                }
+                seen.push(canonical);
+
+                let Ok(meta) = entry.metadata() else {
+                    continue;
+                };
+                if !meta.is_file() || meta.len() > MAX_CANDIDATE_SIZE {
+                    continue;
+                }
+                let count = fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|c| serde_json::from_str::<Vec<Todo>>(&c).ok())
+                    .map(|todos| todos.len());
+                if let Some(count) = count
+                    && count > 0
+                {
+                    candidates.push(LegacyCandidate {
+                        path,
+                        count,
+                        modified: meta.modified().unwrap_or(std::time::UNIX_EPOCH),
+                    });
+                }
+            }
+        }
+        rank_candidates(&mut candidates);
+        candidates
+    }
+
+    // Offered at startup when the active file is missing or empty
+    pub fn offer_migration(&mut self) {
+        if !self.todos.is_empty() {
+            return;
+        }
+        let candidates = self.find_legacy_candidates();
+        if candidates.is_empty() {
+            return;
+        }
+
+        let mut items: Vec<String> = candidates
+            .iter()
+            .map(|c| {
+                let modified: chrono::DateTime<Local> = c.modified.into();
+                format!(
+                    "{}  ({} todos, modified {})",
+                    c.path.display(),
+                    c.count,
+                    modified.format("%Y-%m-%d %H:%M")
+                )
+            })
+            .collect();
+        items.push("Start fresh".to_string());
+        self.picker = Some(Picker {
+            title: format!(" No todos in {} — adopt one? ", self.current_file.display()),
+            items,
+            scroll: ScrollState::default(),
+            kind: PickerKind::Migration(candidates.into_iter().map(|c| c.path).collect()),
+        });
+        self.mode = Mode::Picker;
+    }
+
+    fn pick(&mut self) {
+        let Some(picker) = self.picker.take() else {
+            return;
+        };
+        self.mode = Mode::Normal;
+        match picker.kind {
+            PickerKind::Migration(candidates) => match candidates.get(picker.scroll.position) {
+                Some(source) => self.adopt_file(source),
+                None => self.notify(MessageLevel::Info, "Starting fresh"),
+            },
+        }
+    }
+
+    fn dismiss_picker(&mut self) {
+        if let Some(picker) = self.picker.take() {
+            match picker.kind {
+                PickerKind::Migration(_) => self.notify(MessageLevel::Info, "Starting fresh"),
+            }
+        }
+        self.mode = Mode::Normal;
+    }
+
+    // Copies a found file to the active location and loads it. If the copy
+    // fails the list is still loaded so it can be saved elsewhere.
+    fn adopt_file(&mut self, source: &Path) {
+        match fs::copy(source, &self.current_file) {
+            Ok(_) => {
+                self.load_todos();
+                self.notify(
+                    MessageLevel::Info,
+                    format!(
+                        "Adopted {} into {}",
+                        source.display(),
+                        self.current_file.display()
+                    ),
+                );
+            }
+            Err(e) => {
+                let loaded = fs::read_to_string(source)
+                    .ok()
+                    .and_then(|c| serde_json::from_str::<Vec<Todo>>(&c).ok());
+                if let Some(todos) = loaded {
+                    let (todos, _) = validate(todos);
+                    self.save_snapshot();
+                    self.todos = todos;
+                    self.filter_todos();
+                }
+                self.notify(
+                    MessageLevel::Error,
+                    format!(
+                        "Couldn't copy to {} ({}); loaded {} unsaved",
+                        self.current_file.display(),
+                        e,
+                        source.display()
+                    ),
+                );
+            }
+        }
+    }
+
+    pub fn check_writable(&mut self) {
+        if !Self::is_writable(&self.current_file) {
+            self.mark_unwritable("not writable");
+        }
+    }
+
+    fn mark_unwritable(&mut self, reason: &str) {
+        self.file_unwritable = true;
+        let mut alternatives = Vec::new();
+        if let Some(data_dir) = dirs::data_dir() {
+            alternatives.push(data_dir.join("tuido.json"));
+        }
+        alternatives.push(std::env::temp_dir().join("tuido.json"));
+        let alternatives: Vec<String> = alternatives
+            .iter()
+            .map(|p| format!(":saveas {}", p.display()))
+            .collect();
+        self.notify(
+            MessageLevel::Error,
+            format!(
+                "Can't write {} ({}). Try {} or restart with --file <path>",
+                self.current_file.display(),
+                reason,
+                alternatives.join(" or ")
+            ),
+        );
+    }
+
+    fn unsaved_changes_message(&self) -> String {
+        if self.file_unwritable {
+            format!(
+                "Error: unsaved changes and {} is not writable. Use :saveas <path> or :q! to quit",
+                self.current_file.display()
+            )
+        } else {
+            "Error: unsaved changes. Use :q! to quit without saving".to_string()
+        }
+    }
+
+    fn command_arg(input: &str) -> Option<String> {
+        input
+            .trim()
+            .split_once(char::is_whitespace)
+            .map(|(_, arg)| arg.trim().to_string())
+            .filter(|arg| !arg.is_empty())
+    }
+
+    // `.txt` files are todo.txt, anything else tuido's JSON
+    fn parse_todo_file(path: &Path, contents: &str) -> Result<Vec<Todo>, String> {
+        if path.extension().is_some_and(|ext| ext == "txt") {
+            Ok(todotxt::parse(contents))
+        } else {
+            serde_json::from_str::<Vec<Todo>>(contents)
+                .map_err(|_| format!("Invalid file format in {}", path.display()))
+        }
+    }
+
+    /// Reads a todo file the way it's loaded at startup, repairs included.
+    /// A file that doesn't exist is an empty list.
+    pub fn read_todo_file(path: &Path) -> Result<Vec<Todo>, String> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Error reading {}: {}", path.display(), e)),
+        };
+        let (todos, _) = validate(Self::parse_todo_file(path, &contents)?);
+        Ok(todos)
+    }
+
+    pub fn load_todos(&mut self) {
+        let file_path = self.current_file.clone();
+        if let Ok(contents) = fs::read_to_string(&file_path)
+            && let Ok(todos) = Self::parse_todo_file(&file_path, &contents)
+        {
+            self.last_written = Some(DiskState::new(&file_path, Some(&contents)));
+            // Compare against what's on disk so repairs count as unsaved
+            let (valid, report) = validate(todos.clone());
+            self.todos = valid;
+            self.filtered_todos = (0..self.todos.len()).collect();
+            if !self.todos.is_empty() {
+                self.list_state.select(Some(0));
+            } else {
+                self.list_state.select(None);
+            }
+            self.notify(MessageLevel::Info, report.summary(self.todos.len()));
+            self.is_dirty = false;
+            self.saved_snapshot = Some(todos);
+            // Save initial snapshot for undo history
+            self.save_snapshot();
+        } else {
+            // No file, so fresh start - this is clean
+            if !file_path.exists() {
+                self.last_written = Some(DiskState::new(&file_path, None));
+            }
+            self.filtered_todos = vec![];
+            self.is_dirty = false;
+            self.saved_snapshot = Some(vec![]);
+        }
+    }
+
+    /// Starts a scratch list with no file behind it, as `--stdin` does.
+    /// `:w` then needs a file name, and quitting doesn't warn about unsaved
+    /// changes since the list is meant to leave some other way.
+    pub fn load_scratch(&mut self, todos: Vec<Todo>) {
+        self.scratch = true;
+        self.current_file = PathBuf::new();
+        self.last_written = None;
+        self.filtered_todos = (0..todos.len()).collect();
+        self.list_state
+            .select(if todos.is_empty() { None } else { Some(0) });
+        self.notify(
+            MessageLevel::Info,
+            format!(
+                "Read {} todos from stdin (:w <file> to keep them)",
+                todos.len()
+            ),
+        );
+        self.saved_snapshot = Some(todos.clone());
+        self.todos = todos;
+        self.is_dirty = false;
+        self.save_snapshot();
+    }
+
+    fn no_file_message() -> String {
+        "Error: no file name for a list read from stdin (use :w <file> or :saveas <file>)"
+            .to_string()
+    }
+
+    // A todo.txt file opened with :open is written back as todo.txt
+    fn serialize_todos(&self, path: &Path) -> io::Result<String> {
+        if path.extension().is_some_and(|ext| ext == "txt") {
+            Ok(todotxt::to_string(&self.todos, Local::now().date_naive()))
+        } else if self.options.compact_json {
+            Ok(serde_json::to_string(&self.todos)?)
+        } else {
+            Ok(serde_json::to_string_pretty(&self.todos)?)
+        }
+    }
+
+    // True when `path` still holds exactly `contents` as last read or written
+    fn unchanged_on_disk(&self, path: &Path, contents: &str) -> bool {
+        self.last_written
+            .as_ref()
+            .is_some_and(|state| state.path == path && state.hash == Some(content_hash(contents)))
+            && fs::metadata(path).is_ok_and(|m| m.len() == contents.len() as u64)
+    }
+
+    // True when the active file was changed by something else since tuido
+    // last read or wrote it
+    fn active_file_changed(&self) -> bool {
+        self.last_written
+            .as_ref()
+            .is_some_and(|state| state.path == self.current_file && state.changed())
+    }
+
+    fn flash(&mut self, changed: Vec<usize>) {
+        self.flash_until = (!changed.is_empty()).then(|| Instant::now() + FLASH_DURATION);
+        self.recently_changed = changed;
+    }
+
+    // Called every tick; drops a sequence left half-typed for
+    // PENDING_TIMEOUT, returning true when the status line needs a redraw
+    pub fn expire_pending(&mut self, now: Instant) -> bool {
+        let Some(pending) = self
+            .pending
+            .take_if(|pending| now.duration_since(pending.since) >= PENDING_TIMEOUT)
+        else {
+            return false;
+        };
+        self.register = None;
+        // A `q` no register followed is the quit key after all
+        if pending.keys == "q" && self.mode == Mode::Normal && self.q_quits() {
+            self.quit_requested = self.run_key_action(KeyAction::Quit);
+        }
+        true
+    }
+
+    // Whether `q` is still bound to quit, so a `q` that doesn't start a
+    // recording quits
+    fn q_quits(&self) -> bool {
+        let q = KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE);
+        self.keymap.action(q, "") == Some(KeyAction::Quit)
+    }
+
+    // The register a macro is being recorded into, for the status line
+    pub(crate) fn recording(&self) -> Option<char> {
+        self.recording.as_ref().map(|(name, _)| *name)
+    }
+
+    // `q` after `qa`: the keys typed in between become macro a
+    fn stop_recording(&mut self) {
+        let Some((name, mut keys)) = self.recording.take() else {
+            return;
+        };
+        // The `q` that stopped it
+        keys.pop();
+        self.notify(
+            MessageLevel::Info,
+            match keys.len() {
+                1 => format!("Recorded @{} (1 key)", name),
+                len => format!("Recorded @{} ({} keys)", name, len),
+            },
+        );
+        if keys.is_empty() {
+            self.macros.remove(&name);
+        } else {
+            self.macros.insert(name, keys);
+        }
+    }
+
+    // A count and the keys of an unfinished sequence, shown at the right
+    // of the status line like vim's 'showcmd'
+    pub(crate) fn pending_keys(&self) -> String {
+        let count = match self.repeat_count {
+            0 => String::new(),
+            count => count.to_string(),
+        };
+        let register = match self.register {
+            Some(name) => format!("\"{}", name),
+            None => String::new(),
+        };
+        let keys = self.pending.as_ref().map_or("", |pending| &pending.keys);
+        format!("{}{}{}", register, count, keys)
+    }
+
+    // The sequence `key` starts or carries on, given the mode it left the
+    // app in; a key that finishes or breaks a sequence starts nothing, so
+    // `ddd` deletes once and waits for one more `d`
+    fn pending_after(&self, pending: &str, key: KeyEvent, count: usize) -> Option<String> {
+        let KeyCode::Char(c) = key.code else {
+            return None;
+        };
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            return None;
+        }
+        // After an operator, a count and the first g of gg wait for the motion
+        if self.mode == Mode::Normal
+            && let Some(op) = self.parse_operator(pending)
+        {
+            return match c {
+                '0'..='9' if !op.g && counts_after(pending, key) => {
+                    Some(format!("{}{}", pending, c))
+                }
+                'g' if !op.g => Some(format!("{}g", pending)),
+                _ => None,
+            };
+        }
+        let starts = match self.mode {
+            Mode::Normal => {
+                matches!(c, 'g' | 'z' | '"' | '@')
+                    || (c == 'q' && self.recording.is_none() && !self.replaying)
+                    || self.keymap.starts_sequence(c)
+                    || self.keymap.operator(c).is_some()
+            }
+            Mode::Visual => matches!(c, 'g' | 'z' | '"'),
+            Mode::Picker | Mode::Help | Mode::Popup => c == 'g',
+            _ => false,
+        };
+        match (pending, c) {
+            ("\"", c)
+                if (c == '+' || Registers::is_name(c))
+                    && matches!(self.mode, Mode::Normal | Mode::Visual) =>
+            {
+                Some(format!("\"{}", c))
+            }
+            // The count `3y` used up stays with it, for `3yj`
+            ("", c) if starts && count > 0 && self.repeat_count == 0 => {
+                Some(format!("{}{}", count, c))
+            }
+            ("", c) if starts => Some(c.to_string()),
+            _ => None,
+        }
+    }
+
+    // The operator starting `pending`, if it's one waiting for a motion
+    fn parse_operator(&self, pending: &str) -> Option<PendingOperator> {
+        let rest = pending.trim_start_matches(|c: char| c.is_ascii_digit());
+        let before = &pending[..pending.len() - rest.len()];
+        let mut chars = rest.chars();
+        let operator = self.keymap.operator(chars.next()?)?;
+        let rest = chars.as_str();
+        let (after, g) = match rest.strip_suffix('g') {
+            Some(digits) => (digits, true),
+            None => (rest, false),
+        };
+        if !after.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        Some(PendingOperator {
+            operator,
+            before: before.parse().unwrap_or(0),
+            after: after.parse().unwrap_or(0),
+            g,
+        })
+    }
+
+    // A key typed after an operator: its count, the first g of gg, or the
+    // motion that says which rows it covers. Counts typed before and after
+    // the operator multiply, as in vim. Returns None for those, and for
+    // any other key the rest of `pending` it goes on with: the operator is
+    // dropped but what followed it still counts, so `yp` yanks and then
+    // pastes and `y3p` pastes three times.
+    fn operator_key<'a>(&mut self, pending: &'a str, key: KeyEvent) -> Option<&'a str> {
+        let Some(op) = self.parse_operator(pending) else {
+            return Some(pending);
+        };
+        let counts = [self.repeat_count, op.before, op.after];
+        let given = counts.iter().any(|&count| count > 0);
+        let rows: usize = counts.iter().map(|&count| count.max(1)).product();
+        let motion = match (key.code, self.keymap.action(key, "")) {
+            _ if key.modifiers.contains(KeyModifiers::CONTROL) => None,
+            (KeyCode::Char('0'..='9'), _) if !op.g && counts_after(pending, key) => return None,
+            (KeyCode::Char('g'), _) if !op.g => return None,
+            (KeyCode::Char('g'), _) => Some(Motion::ToRow(if given { rows } else { 1 })),
+            (KeyCode::Char('G'), _) if !op.g => {
+                Some(Motion::ToRow(if given { rows } else { usize::MAX }))
+            }
+            (KeyCode::Down, _) | (_, Some(KeyAction::Down)) if !op.g => Some(Motion::Down(rows)),
+            (KeyCode::Up, _) | (_, Some(KeyAction::Up)) if !op.g => Some(Motion::Up(rows)),
+            // `dd` and `yy` take the cursor row, `3dd` and `d3d` three rows
+            (KeyCode::Char(c), _) if !op.g && self.keymap.operator(c) == Some(op.operator) => {
+                Some(Motion::Down(rows - 1))
+            }
+            _ => None,
+        };
+        let Some(motion) = motion else {
+            if op.after > 0 {
+                self.repeat_count = op.after;
+            }
+            return Some(if op.g { "g" } else { "" });
+        };
+        self.repeat_count = 0;
+        self.operate(op.operator, motion);
+        None
+    }
+
+    // Applies `operator` from the cursor row to where `motion` lands. The
+    // rows are selected visually first, so they map to todos the same way
+    // a Visual `d` or `y` does; deletes are one undo step and go to the
+    // clipboard like `dd`.
+    fn operate(&mut self, operator: Operator, motion: Motion) {
+        let Some(cursor) = self.list_state.selected() else {
+            self.fail(self.no_selection_message());
+            return;
+        };
+        let last_row = self.filtered_todos.len().saturating_sub(1);
+        let target = match motion {
+            Motion::Down(rows) => cursor.saturating_add(rows).min(last_row),
+            Motion::Up(rows) => cursor.saturating_sub(rows),
+            Motion::ToRow(row) => row.saturating_sub(1).min(last_row),
+        };
+        self.mode = Mode::Visual;
+        self.visual_start = Some(cursor);
+        self.list_state.select(Some(target));
+        match operator {
+            Operator::Delete => {
+                self.delete_todo();
+                self.last_action = Some(LastAction {
+                    action: Action::DeleteTo(motion),
+                    rows: 1,
+                });
+            }
+            // Like vim, the cursor ends up at the top of the yanked rows
+            Operator::Yank => {
+                // The y of `"Ayj` already appended the cursor row on its own
+                if let Some(name) = self.register.filter(char::is_ascii_uppercase) {
+                    self.registers.undo_append(name);
+                }
+                self.yank_todo();
+                self.list_state.select(Some(cursor.min(target)));
+            }
+        }
+    }
+
+    // Called every tick; returns true when a flash just ended and the tint
+    // needs redrawing away
+    pub fn expire_flash(&mut self, now: Instant) -> bool {
+        if self.flash_until.is_some_and(|until| now >= until) {
+            self.flash_until = None;
+            self.recently_changed.clear();
+            return true;
+        }
+        false
+    }
+
+    // Called every tick; re-checks the active file every DISK_POLL_INTERVAL.
+    // Returns true when the status line needs a redraw.
+    pub fn poll_disk(&mut self, now: Instant) -> bool {
+        if now.duration_since(self.last_disk_check) < DISK_POLL_INTERVAL {
+            return false;
+        }
+        self.last_disk_check = now;
+        let changed = self.active_file_changed();
+        std::mem::replace(&mut self.changed_on_disk, changed) != changed
+    }
+
+    fn save_todos(&mut self) -> io::Result<()> {
+        self.write_todos(false)
+    }
+
+    // Refuses to overwrite changes made by someone else unless forced (`:w!`)
+    fn write_todos(&mut self, force: bool) -> io::Result<()> {
+        if self.scratch {
+            self.notify(MessageLevel::Error, Self::no_file_message());
+            return Err(io::Error::other("no file name"));
+        }
+        if !force && self.active_file_changed() {
+            self.changed_on_disk = true;
+            self.notify(
+                MessageLevel::Error,
+                format!(
+                    "{} changed on disk since it was read (:w! to overwrite, :e! to reload)",
+                    self.current_file.display()
+                ),
+            );
+            return Err(io::Error::other("changed on disk"));
+        }
+        let contents = self.serialize_todos(&self.current_file)?;
+        let unchanged = self.unchanged_on_disk(&self.current_file, &contents);
+        let backup_error = if unchanged {
+            None
+        } else {
+            self.backup_current_file().err()
+        };
+        if !unchanged && let Err(e) = write_atomic(&self.current_file, &contents) {
+            self.mark_unwritable(&e.to_string());
+            return Err(e);
+        }
+        self.last_written = Some(DiskState::new(&self.current_file, Some(&contents)));
+        self.changed_on_disk = false;
+        self.file_unwritable = false;
+        self.is_dirty = false;
+        self.saved_snapshot = Some(self.todos.clone());
+        let size = contents.len() as u64;
+        let saved = if size > LARGE_FILE_WARNING {
+            format!(
+                "Saved to {} — data file is {}; consider :clear{}",
+                self.current_file.display(),
+                format_size(size),
+                if self.options.compact_json {
+                    ""
+                } else {
+                    " or :set compactjson"
+                }
+            )
+        } else if unchanged {
+            format!("Saved to {} (unchanged)", self.current_file.display())
+        } else {
+            format!("Saved to {}", self.current_file.display())
+        };
+        match backup_error {
+            Some(e) => self.notify(
+                MessageLevel::Error,
+                format!("{} (backup failed: {})", saved, e),
+            ),
+            None => self.notify(MessageLevel::Info, saved),
+        }
+        Ok(())
+    }
+
+    // Backups are named after the data file, e.g. todos.json becomes
+    // todos-20240131-093000.json, so the names sort oldest first
+    fn backup_prefix(&self) -> String {
+        let stem = self
+            .current_file
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy();
+        format!("{}-", stem.trim_start_matches('.'))
+    }
+
+    // Backup file names for the active file, newest first
+    fn list_backups(&self) -> Vec<String> {
+        let prefix = self.backup_prefix();
+        let mut names: Vec<String> = fs::read_dir(&self.backup_dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| {
+                name.strip_prefix(&prefix)
+                    .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+            })
+            .collect();
+        names.sort_unstable_by(|a, b| b.cmp(a));
+        names
+    }
+
+    // Copies the data file into the backup dir before it's overwritten, then
+    // prunes all but the newest `backup_count` backups
+    fn backup_current_file(&self) -> io::Result<()> {
+        if self.options.backup_count == 0 || !self.current_file.is_file() {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.backup_dir)?;
+        let extension = self
+            .current_file
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy()))
+            .unwrap_or_default();
+        let name = format!(
+            "{}{}{}",
+            self.backup_prefix(),
+            Local::now().format("%Y%m%d-%H%M%S"),
+            extension
+        );
+        fs::copy(&self.current_file, self.backup_dir.join(name))?;
+        for old in self.list_backups().iter().skip(self.options.backup_count) {
+            fs::remove_file(self.backup_dir.join(old))?;
+        }
+        Ok(())
+    }
+
+    // Replaces the list with a backup's contents as one undoable change. The
+    // active file stays the same, so `:w` writes the restored list back.
+    fn restore_backup(&mut self, name: &str) -> Result<usize, String> {
+        let path = if Path::new(name).components().count() > 1 {
+            PathBuf::from(name)
+        } else {
+            self.backup_dir.join(name)
+        };
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Error reading backup {}: {} (see :backups)", name, e))?;
+        let (todos, _) = validate(Self::parse_todo_file(&path, &contents)?);
+        self.save_snapshot();
+        self.todos = todos;
+        self.filter_todos();
+        Ok(self.todos.len())
+    }
+
+    // `~` and `~/...` in command arguments refer to the home directory
+    fn expand_path(arg: &str) -> PathBuf {
+        match (arg.strip_prefix('~'), dirs::home_dir()) {
+            (Some(""), Some(home)) => home,
+            (Some(rest), Some(home)) if rest.starts_with(['/', std::path::MAIN_SEPARATOR]) => {
+                home.join(&rest[1..])
+            }
+            _ => PathBuf::from(arg),
+        }
+    }
+
+    // Like vim, writing to some other file that already exists needs `!`
+    fn check_overwrite(&mut self, path: &Path, force: bool) -> Result<(), ()> {
+        if force || !path.exists() || path == self.current_file {
+            return Ok(());
+        }
+        self.notify(
+            MessageLevel::Error,
+            format!("{} exists (add ! to overwrite)", path.display()),
+        );
+        Err(())
+    }
+
+    // `:w <path>` writes a copy and leaves the active file, dirty flag and
+    // change tracking alone
+    fn write_copy(&mut self, path: &Path) -> io::Result<()> {
+        if path == self.current_file {
+            return self.save_todos();
+        }
+        let result = self
+            .serialize_todos(path)
+            .and_then(|contents| write_atomic(path, &contents));
+        match &result {
+            Ok(()) => self.notify(
+                MessageLevel::Info,
+                format!(
+                    "Wrote copy to {} — still editing {}",
+                    path.display(),
+                    self.current_file.display()
+                ),
+            ),
+            Err(e) => self.notify(
+                MessageLevel::Error,
+                format!("Error writing {}: {}", path.display(), e),
+            ),
+        }
+        result
+    }
+
+    // `:saveas <path>` saves and makes the path the active file, so later
+    // `:w` and the quit guard work against it.
+    fn save_todos_as(&mut self, file_path: &Path) -> io::Result<()> {
+        let previous = std::mem::replace(&mut self.current_file, file_path.to_path_buf());
+        let was_unwritable = self.file_unwritable;
+        let was_scratch = std::mem::take(&mut self.scratch);
+        match self.save_todos() {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                self.current_file = previous;
+                self.file_unwritable = was_unwritable;
+                self.scratch = was_scratch;
+                self.notify(
+                    MessageLevel::Error,
+                    format!("Error saving to {}: {}", file_path.display(), e),
+                );
+                Err(e)
+            }
+        }
+    }
+
+    // Replaces the list with another file's contents and makes that file the
+    // active one. Refuses to drop unsaved changes unless forced.
+    fn open_file(&mut self, file_path: &str, force: bool) -> Result<(), String> {
+        if self.is_dirty && !force {
+            return Err(format!(
+                "Unsaved changes in {} (:w to save, or :open! to discard)",
+                self.current_file.display()
+            ));
+        }
+        let contents = fs::read_to_string(file_path)
+            .map_err(|e| format!("Error opening {}: {} (file not found?)", file_path, e))?;
+        let raw = Self::parse_todo_file(Path::new(file_path), &contents)?;
+        self.last_written = Some(DiskState::new(Path::new(file_path), Some(&contents)));
+        self.changed_on_disk = false;
+        let (todos, report) = validate(raw.clone());
+
+        self.save_snapshot();
+        self.current_file = PathBuf::from(file_path);
+        self.scratch = false;
+        self.file_unwritable = false;
+        self.todos = todos;
+        self.saved_snapshot = Some(raw);
+        self.update_dirty_status();
+        self.filter_todos();
+        self.notify(
+            MessageLevel::Info,
+            format!("{} from {}", report.summary(self.todos.len()), file_path),
+        );
+        Ok(())
+    }
+
+    // `:read` inserts another file's todos below the cursor, like vim's `:r`,
+    // leaving the existing todos and the active file as they are
+    fn read_file(&mut self, file_path: &str) -> Result<usize, String> {
+        let path = Path::new(file_path);
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Error reading {}: {} (file not found?)", file_path, e))?;
+        let raw = if path
+            .extension()
+            .is_some_and(|ext| ext == "md" || ext == "markdown")
+        {
+            markdown::parse(&contents)
+        } else {
+            Self::parse_todo_file(path, &contents)?
+        };
+        let (todos, _) = validate(raw);
+        if todos.is_empty() {
+            return Err(format!("No todos in {}", file_path));
+        }
+        Ok(self.insert_below(todos))
+    }
+
+    fn start_shell(&mut self, command: &str, read: bool) -> Result<(), String> {
+        if let Some(job) = &self.shell_job {
+            return Err(format!("Still running: {} (Ctrl+C to cancel)", job.command));
+        }
+        let job = ShellJob::spawn(command, read).map_err(|e| format!("Error: {}", e))?;
+        self.shell_job = Some(job);
+        self.message.clear();
+        Ok(())
+    }
+
+    // Ctrl+C while a command runs
+    fn cancel_shell(&mut self) {
+        if let Some(mut job) = self.shell_job.take() {
+            let _ = job.child.kill();
+            let _ = job.child.wait();
+            self.notify(MessageLevel::Info, format!("Cancelled !{}", job.command));
+        }
+    }
+
+    // Returns true when a command finished and the screen needs a redraw
+    pub fn poll_shell(&mut self) -> bool {
+        let Some(output) = self.shell_job.as_mut().and_then(ShellJob::finished) else {
+            return false;
+        };
+        let Some(job) = self.shell_job.take() else {
+            return false;
+        };
+        if job.read {
+            match self.read_command_output(&job.command, &output) {
+                Ok(count) => self.notify(
+                    MessageLevel::Info,
+                    format!("Added {} todos from !{}", count, job.command),
+                ),
+                Err(e) => self.notify(MessageLevel::Error, e),
+            }
+        } else {
+            self.show_shell_output(&job.command, &output);
+        }
+        true
+    }
+
+    fn show_shell_output(&mut self, command: &str, output: &Output) {
+        let text = shell_output_text(output);
+        let status = exit_status(output);
+        // More than a line of output gets a scrollable popup
+        if text.contains('\n') && self.mode == Mode::Normal {
+            let title = match &status {
+                Some(status) => format!(" :!{} ({}) ", command, status),
+                None => format!(" :!{} ", command),
+            };
+            self.show_popup(&title, &text);
+        }
+        let last_line = text.lines().last().unwrap_or("");
+        // A command that failed says so in red
+        let level = match status {
+            Some(_) => MessageLevel::Error,
+            None => MessageLevel::Info,
+        };
+        self.notify(
+            level,
+            match status {
+                Some(status) if last_line.is_empty() => format!("> {}, no output", status),
+                Some(status) => format!("> {} ({})", last_line, status),
+                None if last_line.is_empty() => "> done, no output".to_string(),
+                None => format!("> {}", last_line),
+            },
+        );
+    }
+
+    // `:r !cmd` adds a todo per line the command printed, below the cursor
+    fn read_command_output(&mut self, command: &str, output: &Output) -> Result<usize, String> {
+        if let Some(status) = exit_status(output) {
+            return Err(format!("!{} failed ({}); no todos added", command, status));
+        }
+        let todos = self.parse_lines(&String::from_utf8_lossy(&output.stdout));
+        if todos.is_empty() {
+            return Err(format!("!{} printed nothing; no todos added", command));
+        }
+        Ok(self.insert_below(todos))
+    }
+
+    // `:'<,'>!cmd` and `:%!cmd` filter todos through a command, like vim:
+    // the selection (or everything the search shows) goes to its stdin, one
+    // todo per line as it's typed, and the lines it prints replace them
+    fn filter_through(&mut self, command: &str) -> Result<String, String> {
+        let targets = self.command_todos();
+        if targets.is_empty() {
+            return Err(self.empty_list_hint());
+        }
+        let input: String = targets
+            .iter()
+            .map(|&i| self.todos[i].input_text() + "\n")
+            .collect();
+        let output = run_shell(command, Some(input)).map_err(|e| format!("Error: {}", e))?;
+        if let Some(status) = exit_status(&output) {
+            return Err(format!("!{} failed ({}); nothing changed", command, status));
+        }
+
+        // A line that comes back as it went in keeps its todo, note and all
+        let mut originals: Vec<Option<Todo>> = targets
+            .iter()
+            .map(|&i| Some(self.todos[i].clone()))
+            .collect();
+        let replacements: Vec<Todo> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                originals
+                    .iter_mut()
+                    .find(|todo| todo.as_ref().is_some_and(|t| t.input_text() == line))
+                    .and_then(Option::take)
+                    .unwrap_or_else(|| self.todo_from_input(line))
+            })
+            .collect();
+        if replacements.is_empty() {
+            return Err(format!(
+                "!{} printed nothing; the todos were left as they are",
+                command
+            ));
+        }
+
+        self.save_snapshot();
+        let mut removed = targets;
+        removed.sort_unstable();
+        let insert_pos = removed[0];
+        for &i in removed.iter().rev() {
+            self.todos.remove(i);
+        }
+        let count = replacements.len();
+        self.todos.splice(insert_pos..insert_pos, replacements);
+        self.filter_todos();
+        self.select_todo(insert_pos);
+        self.flash((insert_pos..insert_pos + count).collect());
+        Ok(format!(
+            "Replaced {} todos with {} from !{}",
+            removed.len(),
+            count,
+            command
+        ))
+    }
+
+    // Inserts todos below the cursor (at the end without one) as one
+    // change, and returns how many
+    fn insert_below(&mut self, todos: Vec<Todo>) -> usize {
+        self.save_snapshot();
+        let insert_pos = self.paste_position(false);
+        let count = todos.len();
+        self.todos.splice(insert_pos..insert_pos, todos);
+        self.filter_todos();
+        self.select_todo(insert_pos);
+        self.flash((insert_pos..insert_pos + count).collect());
+        count
+    }
+
+    // `:e` re-reads the active file as one undoable change; `:e!` also
+    // discards unsaved changes
+    fn reload_file(&mut self, force: bool) -> Result<usize, String> {
+        if self.scratch {
+            return Err(Self::no_file_message());
+        }
+        if self.is_dirty && !force {
+            return Err(format!(
+                "Unsaved changes in {} (:w to save, or :e! to discard them and reload)",
+                self.current_file.display()
+            ));
+        }
+        let path = self.current_file.clone();
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+        let raw = Self::parse_todo_file(&path, &contents)?;
+        let (todos, _) = validate(raw.clone());
+        self.save_snapshot();
+        self.todos = todos;
+        self.saved_snapshot = Some(raw);
+        self.last_written = Some(DiskState::new(&path, Some(&contents)));
+        self.changed_on_disk = false;
+        self.filter_todos();
+        self.update_dirty_status();
+        Ok(self.todos.len())
+    }
+
+    fn export_todotxt(todos: &[Todo], out: &mut impl Write) -> io::Result<()> {
+        let today = Local::now().date_naive();
+        out.write_all(todotxt::to_string(todos, today).as_bytes())
+    }
+
+    // Appends todos from another app's export (a todo.txt import replaces the
+    // list instead) and returns (imported, skipped, row errors).
+    fn import_tasks(
+        &mut self,
+        file_path: &str,
+        format: ImportFormat,
+    ) -> Result<(usize, usize, Vec<String>), String> {
+        // Read leniently so one badly encoded line doesn't sink the file
+        let bytes =
+            fs::read(file_path).map_err(|e| format!("Error opening {}: {}", file_path, e))?;
+        let contents = String::from_utf8_lossy(&bytes);
+        let mut errors = Vec::new();
+        let (todos, skipped) = match format {
+            ImportFormat::Csv => {
+                let (todos, row_errors) = csv::parse_todos(&contents);
+                errors = row_errors;
+                (todos, errors.len())
+            }
+            ImportFormat::GoogleTasks => import::google_tasks(&contents)?,
+            ImportFormat::Reminders => import::reminders(&contents),
+            ImportFormat::TodoTxt => (todotxt::parse(&contents), 0),
+            ImportFormat::Markdown => (markdown::parse(&contents), 0),
+        };
+        let (todos, report) = validate(todos);
+        let skipped = skipped + report.skipped;
+        let imported = todos.len();
+        if format == ImportFormat::TodoTxt {
+            self.save_snapshot();
+            self.todos = todos;
+            self.filter_todos();
+        } else if imported > 0 {
+            self.save_snapshot();
+            let start = self.todos.len();
+            self.todos.extend(todos);
+            self.filter_todos();
+            self.flash((start..self.todos.len()).collect());
+        }
+        Ok((imported, skipped, errors))
+    }
+
+    // Rewrites a whole-word tag across every todo and returns how many todos
+    // changed. Without `force`, changes above BULK_CONFIRM_THRESHOLD are only
+    // counted.
+    fn retag(&mut self, old: &str, new: &str, force: bool) -> Result<usize, String> {
+        if tag_kind(old).is_none() || tag_kind(old) != tag_kind(new) {
+            return Err("Usage: :retag +old +new or :retag @old @new".to_string());
+        }
+
+        let touched: Vec<usize> = self
+            .todos
+            .iter()
+            .enumerate()
+            .filter(|(_, todo)| todo.text.split(' ').any(|word| word == old))
+            .map(|(i, _)| i)
+            .collect();
+
+        if touched.len() > BULK_CONFIRM_THRESHOLD && !force {
+            return Err(format!(
+                "{} todos would change; use :retag! {} {} to confirm",
+                touched.len(),
+                old,
+                new
+            ));
+        }
+
+        if !touched.is_empty() {
+            self.save_snapshot();
+            for &i in &touched {
+                let text = &self.todos[i].text;
+                self.todos[i].text = text
+                    .split(' ')
+                    .map(|word| if word == old { new } else { word })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+            }
+            self.filter_todos();
+        }
+        let count = touched.len();
+        self.flash(touched);
+        Ok(count)
+    }
+
+    // Todos match by trimmed text; the first unclaimed existing todo with the
+    // same text is paired with each incoming one.
+    fn plan_merge(existing: &[Todo], incoming: Vec<Todo>) -> MergePlan {
+        let mut plan = MergePlan {
+            new: Vec::new(),
+            conflicts: Vec::new(),
+            unchanged: 0,
+        };
+        let mut claimed = vec![false; existing.len()];
+
+        for todo in incoming {
+            let found = existing
+                .iter()
+                .enumerate()
+                .position(|(i, e)| !claimed[i] && e.text.trim() == todo.text.trim());
+            match found {
+                Some(i) => {
+                    claimed[i] = true;
+                    let mine = &existing[i];
+                    if mine.completed == todo.completed
+                        && mine.priority == todo.priority
+                        && mine.note == todo.note
+                    {
+                        plan.unchanged += 1;
+                    } else {
+                        plan.conflicts.push((i, todo));
+                    }
+                }
+                None => plan.new.push(todo),
+            }
+        }
+        plan
+    }
+
+    fn start_merge(&mut self, file_path: &str) -> Result<(), String> {
+        let contents = fs::read_to_string(file_path)
+            .map_err(|e| format!("Error opening {}: {}", file_path, e))?;
+        let incoming = Self::parse_todo_file(Path::new(file_path), &contents)?;
+        let (incoming, _) = validate(incoming);
+        let plan = Self::plan_merge(&self.todos, incoming);
+
+        self.merge = Some(MergeState {
+            source: file_path.to_string(),
+            plan,
+            choices: Vec::new(),
+        });
+        if self
+            .merge
+            .as_ref()
+            .is_some_and(|m| m.plan.conflicts.is_empty())
+        {
+            self.finish_merge();
+        } else {
+            self.mode = Mode::Merge;
+        }
+        Ok(())
+    }
+
+    fn choose_merge(&mut self, choice: MergeChoice) {
+        let Some(merge) = &mut self.merge else {
+            return;
+        };
+        merge.choices.push(choice);
+        if merge.choices.len() == merge.plan.conflicts.len() {
+            self.finish_merge();
+        }
+    }
+
+    fn cancel_merge(&mut self) {
+        self.merge = None;
+        self.mode = Mode::Normal;
+        self.notify(MessageLevel::Info, "Merge cancelled, nothing changed");
+    }
+
+    // All decisions land in a single snapshot
+    fn finish_merge(&mut self) {
+        self.mode = Mode::Normal;
+        let Some(merge) = self.merge.take() else {
+            return;
+        };
+        let MergePlan {
+            new,
+            conflicts,
+            unchanged,
+        } = merge.plan;
+        let added = new.len();
+        let mut taken = 0;
+        let mut both = 0;
+
+        if added > 0 || merge.choices.iter().any(|c| *c != MergeChoice::KeepMine) {
+            self.save_snapshot();
+            let mut changed = Vec::new();
+            let start = self.todos.len();
+            for ((i, theirs), choice) in conflicts.into_iter().zip(&merge.choices) {
+                match choice {
+                    MergeChoice::KeepMine => {}
+                    MergeChoice::TakeTheirs => {
+                        self.todos[i] = theirs;
+                        changed.push(i);
+                        taken += 1;
+                    }
+                    MergeChoice::KeepBoth => {
+                        self.todos.push(theirs);
+                        both += 1;
+                    }
+                }
+            }
+            self.todos.extend(new);
+            self.filter_todos();
+            changed.extend(start..self.todos.len());
+            self.flash(changed);
+        }
+
+        let mut summary = format!(
+            "Merged {}: {} added, {} already present",
+            merge.source, added, unchanged
+        );
+        if !merge.choices.is_empty() {
+            summary.push_str(&format!(
+                ", {} conflicts ({} taken, {} kept both)",
+                merge.choices.len(),
+                taken,
+                both
+            ));
+        }
+        self.notify(MessageLevel::Info, summary);
+    }
+
+    fn export_markdown(todos: &[Todo], out: &mut impl Write, group: bool) -> io::Result<()> {
+        let contents = if group {
+            markdown::to_string_grouped(todos)
+        } else {
+            markdown::to_string(todos)
+        };
+        out.write_all(contents.as_bytes())
+    }
+
+    fn save_snapshot(&mut self) {
+        // The next change can move todos around under the flashed indices
+        self.flash(Vec::new());
+        self.move_streak = None;
+        if self.grouping_changes {
+            return;
+        }
+
+        // Truncate forward history if we're not at the end
+        if self.history_index < self.history.len() {
+            self.history.truncate(self.history_index);
+        }
+
+        self.coalesce_history();
+
+        self.push_history();
+        self.history_index = self.history.len();
+        self.update_dirty_status();
+    }
+
+    // Adds the list as it is now to the history, dropping the oldest state
+    // past MAX_UNDO. `history_index` keeps pointing at the same state.
+    fn push_history(&mut self) {
+        self.history.push_back(self.snapshot());
+        if self.history.len() > MAX_UNDO {
+            self.history.pop_front();
+            self.history_index = self.history_index.saturating_sub(1);
+        }
+    }
+
+    // Snapshots are taken before each change, so the head of the history is
+    // the state the most recent change started from. Drop it when that change
+    // turned out to be a no-op, and drop the last two when they were a change
+    // to a single todo followed by its exact reversal (toggle/untoggle,
+    // edit-then-revert), so undo never steps through states equal to the
+    // current one.
+    fn coalesce_history(&mut self) {
+        if self.history_index != self.history.len() {
+            return;
+        }
+
+        if self
+            .history
+            .back()
+            .is_some_and(|head| head.todos == self.todos)
+        {
+            self.history.pop_back();
+        }
+
+        let len = self.history.len();
+        if len >= 2 && self.history[len - 2].todos == self.todos {
+            let head = &self.history[len - 1].todos;
+            let changed = head.len() == self.todos.len()
+                && head.iter().zip(&self.todos).filter(|(a, b)| a != b).count() == 1;
+            if changed {
+                self.history.truncate(len - 2);
+            }
+        }
+
+        self.history_index = self.history.len();
+    }
+
+    /// The index into `todos` of the todo under the cursor. The cursor moves
+    /// over the filtered list, so this is the one to act on.
+    pub fn selected_todo_index(&self) -> Option<usize> {
+        self.list_state
+            .selected()
+            .and_then(|row| self.filtered_todos.get(row).copied())
+    }
+
+    pub fn update_dirty_status(&mut self) {
+        if let Some(ref saved) = self.saved_snapshot {
+            self.is_dirty = saved != &self.todos;
+        } else {
+            self.is_dirty = true;
+        }
+    }
+
+    fn snapshot(&self) -> AppSnapshot {
+        AppSnapshot {
+            todos: self.todos.clone(),
+            selected_index: self.list_state.selected(),
+        }
+    }
+
+    fn restore_snapshot(&mut self, index: usize) {
+        if let Some(snapshot) = self.history.get(index) {
+            self.todos = snapshot.todos.clone();
+            self.list_state.select(snapshot.selected_index);
+            self.history_index = index;
+            self.filter_todos();
+            self.update_dirty_status();
+        }
+    }
+
+    /// Steps back to the list as it was before the last change.
+    pub fn undo(&mut self) {
+        self.move_streak = None;
+        self.coalesce_history();
+        if self.history_index == 0 {
+            self.notify(MessageLevel::Error, "Nothing to undo");
+            return;
+        }
+        if self.history_index == self.history.len() {
+            // Leaving the live list: keep it as the state redo returns to
+            self.push_history();
+        }
+        self.restore_snapshot(self.history_index - 1);
+        self.notify(MessageLevel::Info, "Undo: reverted to previous state");
+    }
+
+    /// Reapplies a change taken back by [`App::undo`].
+    pub fn redo(&mut self) {
+        self.move_streak = None;
+        if self.history_index + 1 < self.history.len() {
+            self.restore_snapshot(self.history_index + 1);
+            self.notify(MessageLevel::Info, "Redo: reapplied change");
+        } else {
+            self.notify(MessageLevel::Error, "Nothing to redo");
+        }
+    }
+
+    fn filter_todos(&mut self) {
+        // Anything computed here supersedes a search still running
+        self.search_generation += 1;
+        self.search_pending = false;
+        let matched = self.search_index.search(
+            &self.todos,
+            &self.search_query.text(),
+            self.options.regex_search,
+        );
+        self.apply_search(matched);
+    }
+
+    // Search keystrokes on big lists are matched on a worker thread against a
+    // snapshot of the todos; the result is applied by poll_search.
+    fn request_search(&mut self) {
+        if self.todos.len() < ASYNC_SEARCH_THRESHOLD {
+            self.filter_todos();
+            return;
+        }
+
+        self.search_generation += 1;
+        self.search_pending = true;
+        let worker = self.search_worker.get_or_insert_with(SearchWorker::spawn);
+        let job = SearchJob {
+            generation: self.search_generation,
+            query: self.search_query.text(),
+            regex: self.options.regex_search,
+            todos: Arc::new(self.todos.clone()),
+        };
+        if worker.jobs.send(job).is_err() {
+            // Worker is gone; fall back to matching here
+            self.search_worker = None;
+            self.filter_todos();
+        }
+    }
+
+    // Called every tick with the current local date, re-read each time so a
+    // timezone change is picked up too. Returns true when the day changed
+    // and due highlighting needs a redraw.
+    pub fn check_rollover(&mut self, now: NaiveDate) -> bool {
+        if now == self.today {
+            return false;
+        }
+        self.today = now;
+        let escalated = self.escalate_stale();
+        let due_today = self
+            .todos
+            .iter()
+            .filter(|t| !t.completed && t.due == Some(now))
+            .count();
+        if self.mode == Mode::Normal {
+            let mut news = Vec::new();
+            if due_today > 0 {
+                news.push(format!(
+                    "{} {} due today",
+                    due_today,
+                    if due_today == 1 { "item" } else { "items" }
+                ));
+            }
+            if escalated > 0 {
+                news.push(Self::escalated_message(escalated));
+            }
+            if !news.is_empty() {
+                self.notify(MessageLevel::Info, format!("New day — {}", news.join(", ")));
+            }
+        }
+        true
+    }
+
+    // Applies `:set escalate` as one undoable change; returns how many todos
+    // moved up a priority
+    fn escalate_stale(&mut self) -> usize {
+        let Some(period) = self.options.escalate_after else {
+            return 0;
+        };
+        if !self
+            .todos
+            .iter()
+            .any(|todo| escalate::is_stale(todo, self.today, period))
+        {
+            return 0;
+        }
+        self.save_snapshot();
+        let stale: Vec<usize> = (0..self.todos.len())
+            .filter(|&i| escalate::is_stale(&self.todos[i], self.today, period))
+            .collect();
+        let count = escalate::escalate(&mut self.todos, self.today, period);
+        self.filter_todos();
+        self.flash(stale);
+        count
+    }
+
+    fn escalated_message(count: usize) -> String {
+        format!(
+            "escalated {} stale {} (u to undo)",
+            count,
+            if count == 1 { "todo" } else { "todos" }
+        )
+    }
+
+    // Returns true when a fresh result was applied and the screen needs a redraw
+    pub fn poll_search(&mut self) -> bool {
+        let Some(worker) = &self.search_worker else {
+            return false;
+        };
+        let mut latest = None;
+        while let Ok(result) = worker.results.try_recv() {
+            if result.generation == self.search_generation {
+                latest = Some(result.matched);
+            }
+        }
+        match latest {
+            Some(matched) if self.search_pending => {
+                self.search_pending = false;
+                self.apply_search(matched);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn apply_search(&mut self, matched: Result<Vec<(usize, Vec<usize>)>, String>) {
+        match matched {
+            Ok(matched) => {
+                self.search_error = None;
+                self.apply_filter(matched);
+            }
+            Err(e) => self.search_error = Some(e),
+        }
+    }
+
+    fn apply_filter(&mut self, mut matched: Vec<(usize, Vec<usize>)>) {
+        // Quick filter (zA/zB/zC) and :filter layer on top of the search
+        let todos = &self.todos;
+        let keep = |&i: &usize| {
+            self.priority_filter
+                .is_none_or(|p| !todos[i].completed && todos[i].priority == Some(p))
+                && self.meta_filter.as_ref().is_none_or(|(key, value)| {
+                    todos[i]
+                        .meta
+                        .get(key)
+                        .is_some_and(|v| v.eq_ignore_ascii_case(value))
+                })
+        };
+        matched.retain(|(i, _)| keep(i));
+        // A filtering search lists the best matches first
+        self.filtered_todos = if self.options.filter_search {
+            matched.iter().map(|&(i, _)| i).collect()
+        } else {
+            (0..todos.len()).filter(keep).collect()
+        };
+        let best = matched.first().map(|&(i, _)| i);
+        matched.sort_unstable_by_key(|&(i, _)| i);
+        self.search_matches = matched;
+
+        // While typing a search the cursor follows the best match
+        if self.mode == Mode::Search
+            && !self.search_query.is_empty()
+            && let Some(row) = best.and_then(|i| self.filtered_todos.iter().position(|&t| t == i))
+        {
+            self.list_state.select(Some(row));
+        }
+
+        // Adjust selection if out of bounds
+        if let Some(selected) = self.list_state.selected()
+            && selected >= self.filtered_todos.len()
+        {
+            if !self.filtered_todos.is_empty() {
+                self.list_state.select(Some(self.filtered_todos.len() - 1));
+            } else {
+                self.list_state.select(None);
+            }
+        }
+
+        // Matches came back (e.g. the query was cleared) after the selection
+        // was dropped for an empty result
+        if self.list_state.selected().is_none() && !self.filtered_todos.is_empty() {
+            self.list_state.select(Some(0));
+        }
+    }
+
+    // Shown when the list is empty, and when a key needs a selected todo
+    pub(crate) fn empty_list_hint(&self) -> String {
+        if self.todos.is_empty() {
+            "No todos yet — press i to add one".to_string()
+        } else if let Some((key, value)) = &self.meta_filter {
+            format!("No todos with {}:{} — :filter to clear", key, value)
+        } else if let Some(priority) = self.priority_filter {
+            if self.search_query.is_empty() {
+                format!(
+                    "No open ({}) todos — press za to clear the filter",
+                    priority
+                )
+            } else {
+                format!(
+                    "No open ({}) matches for '{}' — press za or Esc to widen",
+                    priority, self.search_query
+                )
+            }
+        } else {
+            format!(
+                "No matches for '{}' — press Esc to clear search",
+                self.search_query
+            )
+        }
+    }
+
+    fn set_priority_filter(&mut self, priority: Option<char>) {
+        self.priority_filter = priority;
+        self.filter_todos();
+        self.notify(
+            MessageLevel::Info,
+            match priority {
+                Some(p) => format!("Showing open ({}) todos", p),
+                None => "Priority filter cleared".to_string(),
+            },
+        );
+    }
+
+    // An error the user should notice. It also stops a playing macro, as a
+    // beep does in vim.
+    fn fail(&mut self, message: String) {
+        self.notify(MessageLevel::Error, message);
+        self.key_failed = true;
+    }
+
+    /// Shows `text` on the message line and keeps it for `:messages`. An
+    /// error stays up until the next key, whatever routine messages come
+    /// after it.
+    pub fn notify(&mut self, level: MessageLevel, text: impl Into<String>) {
+        let text = text.into();
+        if !text.is_empty() {
+            if self.message_log.len() == MAX_MESSAGES {
+                self.message_log.pop_front();
+            }
+            self.message_log.push_back(LoggedMessage {
+                at: Local::now().naive_local(),
+                level,
+                text: text.clone(),
+            });
+        }
+        if level == MessageLevel::Info && self.error_pinned {
+            return;
+        }
+        self.error_pinned = level == MessageLevel::Error;
+        self.message = text;
+        self.message_level = level;
+    }
+
+    // :messages, newest at the bottom and scrolled to, errors in red
+    fn show_messages(&mut self) {
+        if self.message_log.is_empty() {
+            self.notify(MessageLevel::Info, "No messages yet");
+            return;
+        }
+        let lines = self
+            .message_log
+            .iter()
+            .map(|logged| {
+                format!(
+                    "{}  {}",
+                    logged.at.format("%H:%M:%S"),
+                    logged.text.replace('\n', " ")
+                )
+            })
+            .collect();
+        let mut popup = TextPopup::new(" Messages ", lines);
+        popup.error_lines = self
+            .message_log
+            .iter()
+            .enumerate()
+            .filter(|(_, logged)| logged.level == MessageLevel::Error)
+            .map(|(i, _)| i)
+            .collect();
+        popup.scroll.position = usize::MAX;
+        self.popup = Some(popup);
+        self.mode = Mode::Popup;
+    }
+
+    fn push_count_digit(&mut self, digit: char) {
+        let digit = digit as usize - '0' as usize;
+        self.repeat_count = self
+            .repeat_count
+            .saturating_mul(10)
+            .saturating_add(digit)
+            .min(MAX_COUNT);
+    }
+
+    fn no_selection_message(&self) -> String {
+        if self.filtered_todos.is_empty() {
+            self.empty_list_hint()
+        } else {
+            "No todo selected".to_string()
+        }
+    }
+
+    fn clear_search(&mut self) {
+        self.search_query.clear();
+        self.filter_todos();
+    }
+
+    // n/N: moves to the next or previous row whose todo matches the search,
+    // wrapping around. `skip_current` is false for the jump made when a
+    // search is confirmed, so a match under the cursor stays put.
+    fn search_next(&mut self, forward: bool, skip_current: bool) {
+        if self.search_query.is_empty() {
+            self.notify(MessageLevel::Info, "No search — press / to search");
+            return;
+        }
+        if self.search_pending {
+            self.filter_todos();
+        }
+        let rows = self.filtered_todos.len();
+        if rows == 0 || self.search_matches.is_empty() {
+            self.fail(format!("No matches for '{}'", self.search_query));
+            return;
+        }
+        let cursor = self.list_state.selected().unwrap_or(0);
+        let first = if skip_current { 1 } else { 0 };
+        for step in first..first + rows {
+            let row = if forward {
+                (cursor + step) % rows
+            } else {
+                (cursor + rows - step % rows) % rows
+            };
+            if find_match(&self.search_matches, self.filtered_todos[row]).is_some() {
+                self.list_state.select(Some(row));
+                let wrapped = if forward { row < cursor } else { row > cursor };
+                self.notify(
+                    MessageLevel::Info,
+                    if wrapped && forward {
+                        "search hit BOTTOM, continuing at TOP".to_string()
+                    } else if wrapped {
+                        "search hit TOP, continuing at BOTTOM".to_string()
+                    } else {
+                        format!("/{}", self.search_query)
+                    },
+                );
+                return;
+            }
+        }
+    }
+
+    // :set lists the options changed from their defaults and :set all every
+    // one; :set number, :set nonumber, :set number!, :set layout=comfortable
+    // (or :set layout comfortable) change one and :set layout? shows it.
+    // :set! also saves the change to the config file.
+    fn set_command(&mut self, args: &str, save: bool) -> Result<(), String> {
+        let args = args.trim().to_lowercase();
+        if args.is_empty() || args == "all" {
+            if save {
+                return Err(
+                    "Usage: :set! number, :set! nonumber or :set! layout=compact".to_string(),
+                );
+            }
+            self.show_options(args == "all");
+            return Ok(());
+        }
+        let (name, value) = match args.split_once(['=', ' ']) {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (args.as_str(), None),
+        };
+        let query = |app: &mut App, name: &str| {
+            app.notify(
+                MessageLevel::Info,
+                app.options.show(name).unwrap_or_default(),
+            );
+            Ok(())
+        };
+        let (name, value) = match (find_option(name), value) {
+            (Some((name, true)), None) => (name, OptionValue::Flag(true)),
+            (Some((name, true)), Some(_)) => {
+                return Err(format!(
+                    "{} is on or off: :set {} or :set no{}",
+                    name, name, name
+                ));
+            }
+            (Some((name, false)), Some(value)) if !value.is_empty() => {
+                (name, OptionValue::Text(value))
+            }
+            (Some((name, false)), _) => return query(self, name),
+            (None, None) => {
+                if let Some((name, _)) = name.strip_suffix('?').and_then(find_option) {
+                    return query(self, name);
+                }
+                let off = name.strip_prefix("no").and_then(find_option);
+                // `:set number!` flips it
+                let flip = name.strip_suffix('!').and_then(find_option);
+                match (off, flip) {
+                    (Some((name, true)), _) => (name, OptionValue::Flag(false)),
+                    (_, Some((name, true))) => {
+                        let on = self.options.show(name).as_deref() == Some(name);
+                        (name, OptionValue::Flag(!on))
+                    }
+                    (Some((name, false)), _) | (_, Some((name, false))) => {
+                        return Err(format!("{} takes a value: :set {}=...", name, name));
+                    }
+                    (None, None) => return Err(unknown_option(name)),
+                }
+            }
+            (None, Some(_)) => return Err(unknown_option(name)),
+        };
+        let said = self.set_option(name, value)?;
+        if !save {
+            self.notify(MessageLevel::Info, said);
+            return Ok(());
+        }
+        let shown = self.options.show(name).unwrap_or_default();
+        let saved = match value {
+            OptionValue::Flag(_) => (shown == name).to_string(),
+            OptionValue::Text(_) => shown
+                .split_once('=')
+                .map_or(shown.clone(), |(_, v)| v.to_string()),
+        };
+        match self
+            .update_config(|config| crate::config::set_value(config, "options", name, Some(&saved)))
+        {
+            Ok(()) => self.notify(MessageLevel::Info, format!("{} (saved)", said)),
+            Err(e) => self.notify(
+                MessageLevel::Error,
+                format!("{}, but it couldn't be saved: {}", said, e),
+            ),
+        }
+        Ok(())
+    }
+
+    // Changes one option, with whatever else that takes, and returns what
+    // to say about it
+    fn set_option(&mut self, name: &str, value: OptionValue) -> Result<String, String> {
+        let said = match (name, value) {
+            ("number", OptionValue::Flag(on)) => {
+                self.options.number = on;
+                if on {
+                    "Showing row numbers"
+                } else {
+                    "Hiding row numbers"
+                }
+                .to_string()
+            }
+            ("relativenumber", OptionValue::Flag(on)) => {
+                self.options.relative_number = on;
+                if on {
+                    "Showing distances from the cursor row"
+                } else if self.options.number {
+                    "Showing row numbers"
+                } else {
+                    "Hiding row numbers"
+                }
+                .to_string()
+            }
+            ("mouse", OptionValue::Flag(on)) => {
+                self.options.mouse = on;
+                if on { "Mouse on" } else { "Mouse off" }.to_string()
+            }
+            ("meta", OptionValue::Flag(on)) => {
+                self.options.show_meta = on;
+                if on {
+                    "Showing key:value fields"
+                } else {
+                    "Hiding key:value fields"
+                }
+                .to_string()
+            }
+            ("layout", OptionValue::Text(value)) => {
+                self.options.density = match value {
+                    "compact" => Density::Compact,
+                    "comfortable" => Density::Comfortable,
+                    _ => return Err("Usage: :set layout compact|comfortable".to_string()),
+                };
+                format!("Layout: {}", value)
+            }
+            ("dupecheck", OptionValue::Text(value)) => {
+                self.options.dupe_check = match value {
+                    "off" => DupeCheck::Off,
+                    "warn" => DupeCheck::Warn,
+                    "strict" => DupeCheck::Strict,
+                    _ => return Err("Usage: :set dupecheck off|warn|strict".to_string()),
+                };
+                format!("Duplicate check: {}", value)
+            }
+            ("histignoreshell", OptionValue::Flag(on)) => {
+                self.options.hist_ignore_shell = on;
+                if on {
+                    self.command_history
+                        .entries
+                        .retain(|c| !is_shell_command(c));
+                    self.save_history();
+                }
+                if on {
+                    "Shell commands are no longer kept in history"
+                } else {
+                    "Shell commands are kept in history"
+                }
+                .to_string()
+            }
+            ("regexsearch", OptionValue::Flag(on)) => {
+                self.options.regex_search = on;
+                self.filter_todos();
+                if on {
+                    "Searches are regular expressions".to_string()
+                } else {
+                    format!(
+                        "Searches are fuzzy; start one with {} for a regex",
+                        REGEX_PREFIX
+                    )
+                }
+            }
+            ("filtersearch", OptionValue::Flag(on)) => self.set_filter_search(on),
+            ("esccommits", OptionValue::Flag(on)) => {
+                self.options.esc_commits = on;
+                if on {
+                    "Esc in Insert mode now saves the input"
+                } else {
+                    "Esc in Insert mode now cancels"
+                }
+                .to_string()
+            }
+            ("colors", OptionValue::Text(value)) => {
+                self.options.color_support = match value {
+                    "16" => ColorSupport::Basic16,
+                    "256" => ColorSupport::Indexed256,
+                    "true" | "truecolor" | "24bit" => ColorSupport::TrueColor,
+                    "none" => ColorSupport::NoColor,
+                    "auto" => self.detected_color_support,
+                    _ => return Err("Usage: :set colors 16|256|true|none|auto".to_string()),
+                };
+                format!("Colors: {}", self.options.color_support.name())
+            }
+            // Off goes back to what was detected, or 256 colors when
+            // detection is what claimed truecolor
+            ("truecolor", OptionValue::Flag(on)) => {
+                self.options.color_support = match self.detected_color_support {
+                    _ if on => ColorSupport::TrueColor,
+                    ColorSupport::TrueColor => ColorSupport::Indexed256,
+                    detected => detected,
+                };
+                format!("Colors: {}", self.options.color_support.name())
+            }
+            ("backups", OptionValue::Text(value)) => {
+                let count = value
+                    .parse::<usize>()
+                    .map_err(|_| "Usage: :set backups <count> (0 turns them off)".to_string())?;
+                self.options.backup_count = count;
+                if count == 0 {
+                    "Backups: off".to_string()
+                } else {
+                    format!("Backups: keeping the newest {}", count)
+                }
+            }
+            ("escalate", OptionValue::Text(value)) => {
+                if matches!(value, "off" | "0") {
+                    self.options.escalate_after = None;
+                    "Escalation: off".to_string()
+                } else if let Some(days) = escalate::parse_period(value) {
+                    self.options.escalate_after = Some(days);
+                    let count = self.escalate_stale();
+                    format!(
+                        "Escalation: every {} days{}",
+                        days,
+                        if count > 0 {
+                            format!(" — {}", Self::escalated_message(count))
+                        } else {
+                            String::new()
+                        }
+                    )
+                } else {
+                    return Err("Usage: :set escalate <days>d|<weeks>w|off".to_string());
+                }
+            }
+            ("compactjson", OptionValue::Flag(on)) => {
+                self.options.compact_json = on;
+                if on {
+                    "Saving minified JSON"
+                } else {
+                    "Saving pretty-printed JSON"
+                }
+                .to_string()
+            }
+            _ => return Err(unknown_option(name)),
+        };
+        Ok(said)
+    }
+
+    // :set and :set all. Colors count as default when they're what the
+    // terminal was detected to support.
+    fn show_options(&mut self, all: bool) {
+        let defaults = Options {
+            color_support: self.detected_color_support,
+            ..Options::default()
+        };
+        let changed: Vec<String> = OPTIONS
+            .iter()
+            // truecolor is only another way to say colors=true
+            .filter(|&&(name, _, _)| all || name != "truecolor")
+            .filter_map(|&(name, _, _)| {
+                let shown = self.options.show(name)?;
+                (all || defaults.show(name).as_ref() != Some(&shown)).then_some(shown)
+            })
+            .collect();
+        if changed.is_empty() {
+            self.notify(
+                MessageLevel::Info,
+                "Every option is at its default (:set all lists them)",
+            );
+            return;
+        }
+        let count = changed.len();
+        let mut lines: Vec<String> = changed
+            .into_iter()
+            .map(|line| format!("  {}", line))
+            .collect();
+        lines.push(String::new());
+        lines.push(
+            ":set name, :set noname or :set name=value changes one; :set! saves it".to_string(),
+        );
+        self.show_popup(
+            if all { "All options" } else { "Options" },
+            &lines.join("\n"),
+        );
+        self.notify(
+            MessageLevel::Info,
+            if all {
+                format!("{} options", count)
+            } else {
+                format!("{} options changed", count)
+            },
+        );
+    }
+
+    // Switches between a filtering search and one that only highlights,
+    // keeping the cursor on the same todo
+    fn set_filter_search(&mut self, on: bool) -> String {
+        let selected = self
+            .list_state
+            .selected()
+            .and_then(|row| self.filtered_todos.get(row).copied());
+        self.options.filter_search = on;
+        self.filter_todos();
+        if let Some(row) = selected.and_then(|i| self.filtered_todos.iter().position(|&t| t == i)) {
+            self.list_state.select(Some(row));
+        }
+        if on {
+            "/ now filters the list".to_string()
+        } else {
+            "/ now highlights matches; n/N jump between them".to_string()
+        }
+    }
+
+    fn get_selected_indices(&self) -> Vec<usize> {
+        match self.mode {
+            Mode::Visual if self.visual_start.is_some() => {
+                if let Some(start) = self.visual_start {
+                    if let Some(current) = self.list_state.selected() {
+                        let start_idx = start.min(current);
+                        let end_idx = start.max(current);
+                        (start_idx..=end_idx).collect()
+                    } else {
+                        vec![start]
+                    }
+                } else {
+                    vec![]
+                }
+            }
+            Mode::Normal => {
+                if let Some(idx) = self.list_state.selected() {
+                    vec![idx]
+                } else {
+                    vec![]
+                }
+            }
+            _ => vec![],
+        }
+    }
+
+    // v and V
+    fn start_visual(&mut self) {
+        // Sync visual_start with current selection to avoid stale indices
+        self.visual_start = self.list_state.selected();
+        self.mode = Mode::Visual;
+    }
+
+    // Ctrl+A or :selectall: a visual range over every todo shown
+    fn select_all(&mut self) {
+        if self.filtered_todos.is_empty() {
+            self.notify(MessageLevel::Info, self.empty_list_hint());
+            return;
+        }
+        self.visual_start = Some(0);
+        self.list_state.select(Some(self.filtered_todos.len() - 1));
+        self.mode = Mode::Visual;
+        self.notify(
+            MessageLevel::Info,
+            format!("{} todos selected", self.filtered_todos.len()),
+        );
+    }
+
+    // Moves the cursor end of a visual range, stopping at the top and
+    // bottom rather than wrapping around like j/k in Normal mode
+    fn extend_visual(&mut self, offset: isize) {
+        let Some(last) = self.filtered_todos.len().checked_sub(1) else {
+            return;
+        };
+        let row = self.list_state.selected().unwrap_or(0);
+        self.list_state
+            .select(Some(row.saturating_add_signed(offset).min(last)));
+    }
+
+    // o in Visual mode: the cursor jumps to the other end of the range, so
+    // the range can be grown or shrunk from that side
+    fn swap_visual_ends(&mut self) {
+        if let (Some(start), Some(end)) = (self.visual_start, self.list_state.selected()) {
+            self.visual_start = Some(end);
+            self.list_state.select(Some(start));
+        }
+    }
+
+    // `5G`, `5gg` and `:5`: row n of the list as shown, counting from 1
+    // and stopping at the last
+    fn jump_to_row(&mut self, n: usize) {
+        if !self.filtered_todos.is_empty() {
+            let row = n.saturating_sub(1).min(self.filtered_todos.len() - 1);
+            self.list_state.select(Some(row));
+        }
+    }
+
+    // Ctrl+d/Ctrl+u (half a page, `page / 2`) and Ctrl+f/Ctrl+b (`page`):
+    // the view and the cursor move together, so the cursor keeps its place
+    // on screen until the view reaches an end of the list
+    fn scroll_list(&mut self, pages: isize, half: bool) {
+        if self.filtered_todos.is_empty() {
+            return;
+        }
+        let page = self.list_view.len().max(1);
+        let rows = pages * if half { (page / 2).max(1) } else { page } as isize;
+        let last = self.filtered_todos.len() - 1;
+        let selected = self.list_state.selected().unwrap_or(0);
+        let offset = self.list_state.offset();
+        self.list_state
+            .select(Some(selected.saturating_add_signed(rows).min(last)));
+        *self.list_state.offset_mut() = offset
+            .saturating_add_signed(rows)
+            .min(self.filtered_todos.len().saturating_sub(page));
+    }
+
+    // H, M and L: the row `from_top` rows into the view, counting from 0,
+    // kept inside both the view and the list
+    fn select_in_view(&mut self, from_top: usize) {
+        if self.filtered_todos.is_empty() {
+            return;
+        }
+        let last = (self.filtered_todos.len() - 1).min(self.list_view.end.saturating_sub(1));
+        let row = (self.list_view.start + from_top).min(last);
+        self.list_state.select(Some(row));
+    }
+
+    // `zz`: scrolls so the cursor row is in the middle of the view
+    fn center_view(&mut self) {
+        if let Some(selected) = self.list_state.selected() {
+            *self.list_state.offset_mut() = selected.saturating_sub(self.list_view.len() / 2);
+        }
+    }
+
+    // The mouse wheel: the view moves by `rows` and the selection stays
+    // in it
+    fn scroll_view(&mut self, rows: isize) {
+        if self.filtered_todos.is_empty() {
+            return;
+        }
+        let view = self.list_view.len().max(1);
+        let offset = self
+            .list_state
+            .offset()
+            .saturating_add_signed(rows)
+            .min(self.filtered_todos.len().saturating_sub(view));
+        let selected = self.list_state.selected().unwrap_or(0);
+        self.list_state
+            .select(Some(selected.clamp(offset, offset + view - 1)));
+        *self.list_state.offset_mut() = offset;
+    }
+
+    // A click selects `row`; `toggle` (a middle click or a click on the
+    // checkbox) or a second click soon after on the same row toggles it.
+    // Toggling leaves Visual mode so only the clicked todo changes.
+    fn click_row(&mut self, row: usize, toggle: bool, now: Instant) {
+        let double = self
+            .last_click
+            .is_some_and(|(at, last)| last == row && now.duration_since(at) < DOUBLE_CLICK);
+        self.last_click = Some((now, row));
+        self.list_state.select(Some(row));
+        if toggle || double {
+            if self.mode == Mode::Visual {
+                self.mode = Mode::Normal;
+                self.visual_start = None;
+            }
+            self.toggle_todo();
+            // A third click starts a new double-click
+            self.last_click = None;
+        }
+    }
+
+    // The keys is_view_motion accepts, in Normal or Visual mode
+    fn view_motion(&mut self, key: KeyEvent, count: usize) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let view = self.list_view.len().max(1);
+        match key.code {
+            KeyCode::Char('d') if ctrl => self.scroll_list(1, true),
+            KeyCode::Char('u') if ctrl => self.scroll_list(-1, true),
+            KeyCode::Char('f') if ctrl => self.scroll_list(1, false),
+            KeyCode::Char('b') if ctrl => self.scroll_list(-1, false),
+            KeyCode::PageDown => self.scroll_list(1, false),
+            KeyCode::PageUp => self.scroll_list(-1, false),
+            // 3H is the third row from the top, 3L the third from the bottom
+            KeyCode::Char('H') => self.select_in_view(count.max(1) - 1),
+            KeyCode::Char('M') => self.select_in_view((view - 1) / 2),
+            KeyCode::Char('L') => self.select_in_view(view.saturating_sub(count.max(1))),
+            KeyCode::Char('z') => self.center_view(),
+            _ => {}
+        }
+    }
+
+    fn next(&mut self) {
+        if self.filtered_todos.is_empty() {
+            return;
+        }
+        let i = match self.list_state.selected() {
+            Some(i) if i < self.filtered_todos.len() => {
+                if i >= self.filtered_todos.len() - 1 {
+                    // A macro stops at the end of the list rather than
+                    // starting over
+                    if self.replaying {
+                        self.fail("End of the list".to_string());
+                        return;
+                    }
+                    0
+                } else {
+                    i + 1
+                }
+            }
+            _ => 0,
+        };
+        if i < self.filtered_todos.len() {
+            self.list_state.select(Some(i));
+        }
+    }
+
+    fn previous(&mut self) {
+        if self.filtered_todos.is_empty() {
+            return;
+        }
+        let i = match self.list_state.selected() {
+            Some(i) if i < self.filtered_todos.len() => {
+                if i == 0 {
+                    if self.replaying {
+                        self.fail("Top of the list".to_string());
+                        return;
+                    }
+                    self.filtered_todos.len() - 1
+                } else {
+                    i - 1
+                }
+            }
+            _ => 0,
+        };
+        if i < self.filtered_todos.len() {
+            self.list_state.select(Some(i));
+        }
+    }
+
+    /// Toggles completion of the todo under the cursor, or of every todo in
+    /// the visual selection.
+    pub fn toggle_todo(&mut self) {
+        let indices = self.get_selected_indices();
+        if indices.is_empty() {
+            self.fail(self.no_selection_message());
+            return;
+        }
+
+        let count = indices.len();
+
+        self.save_snapshot();
+
+        for &idx in &indices {
+            if let Some(i) = self.filtered_todos.get(idx)
+                && *i < self.todos.len()
+            {
+                self.todos[*i].toggle(self.today);
+            }
+        }
+
+        self.notify(
+            MessageLevel::Info,
+            if count == 1 {
+                "TODO toggled".to_string()
+            } else {
+                format!("{} todos toggled", count)
+            },
+        );
+
+        // Track last action for repeat
+        self.last_action = Some(LastAction {
+            action: Action::Toggle,
+            rows: count,
+        });
+
+        if self.mode == Mode::Visual {
+            self.mode = Mode::Normal;
+            self.visual_start = None;
+        }
+    }
+
+    // Raises, lowers or sets the priority of the selected todos as one undo
+    // step
+    fn change_priority(&mut self, change: PriorityChange) {
+        let todos: Vec<usize> = if self.command_range.is_some() {
+            self.command_todos()
+        } else {
+            self.get_selected_indices()
+                .iter()
+                .filter_map(|&idx| self.filtered_todos.get(idx).copied())
+                .collect()
+        };
+        if todos.is_empty() {
+            self.fail(self.no_selection_message());
+            return;
+        }
+
+        self.save_snapshot();
+        for &i in &todos {
+            let todo = &mut self.todos[i];
+            todo.priority = match change {
+                PriorityChange::Raise => raise_priority(todo.priority),
+                PriorityChange::Lower => lower_priority(todo.priority),
+                PriorityChange::Set(priority) => priority,
+            };
+        }
+
+        self.notify(
+            MessageLevel::Info,
+            match (todos.as_slice(), change) {
+                ([i], _) => match self.todos[*i].priority {
+                    Some(priority) => format!("Priority ({})", priority),
+                    None => "No priority".to_string(),
+                },
+                (_, PriorityChange::Raise) => format!("Raised priority of {} todos", todos.len()),
+                (_, PriorityChange::Lower) => format!("Lowered priority of {} todos", todos.len()),
+                (_, PriorityChange::Set(Some(priority))) => {
+                    format!("Set {} todos to ({})", todos.len(), priority)
+                }
+                (_, PriorityChange::Set(None)) => {
+                    format!("Cleared priority of {} todos", todos.len())
+                }
+            },
+        );
+        self.last_action = Some(LastAction {
+            action: Action::Priority(change),
+            rows: todos.len(),
+        });
+
+        // A quick filter on a priority may now hide some of them
+        self.filter_todos();
+        if self.mode == Mode::Visual {
+            self.mode = Mode::Normal;
+            self.visual_start = None;
+        }
+    }
+
+    // Moves the selected todos (the visual range, or `count` from the cursor)
+    // below the last pending todo, so they land above any completed ones at
+    // the end. The cursor stays put and so lands on the next item.
+    fn postpone_todos(&mut self, count: usize) {
+        let selected: Vec<usize> = match self.mode {
+            Mode::Visual => self.get_selected_indices(),
+            _ => match self.list_state.selected() {
+                Some(idx) => (idx..(idx + count).min(self.filtered_todos.len())).collect(),
+                None => vec![],
+            },
+        };
+        let mut moved: Vec<usize> = selected
+            .iter()
+            .filter_map(|&idx| self.filtered_todos.get(idx).copied())
+            .collect();
+        if moved.is_empty() {
+            self.fail(self.no_selection_message());
+            return;
+        }
+        moved.sort_unstable();
+
+        self.save_snapshot();
+        let mut punted = Vec::with_capacity(moved.len());
+        for &i in moved.iter().rev() {
+            punted.push(self.todos.remove(i));
+        }
+        punted.reverse();
+        let at = self
+            .todos
+            .iter()
+            .rposition(|t| !t.completed)
+            .map_or(0, |i| i + 1);
+        self.todos.splice(at..at, punted);
+
+        let first = selected.iter().min().copied().unwrap_or(0);
+        self.filter_todos();
+        if !self.filtered_todos.is_empty() {
+            self.list_state
+                .select(Some(first.min(self.filtered_todos.len() - 1)));
+        }
+        self.notify(
+            MessageLevel::Info,
+            if moved.len() == 1 {
+                "TODO postponed".to_string()
+            } else {
+                format!("{} todos postponed", moved.len())
+            },
+        );
+        self.last_action = Some(LastAction {
+            action: Action::Postpone,
+            rows: moved.len(),
+        });
+
+        if self.mode == Mode::Visual {
+            self.mode = Mode::Normal;
+            self.visual_start = None;
+        }
+    }
+
+    // J/K: moves the selected todo `offset` rows down (or up, if negative).
+    // Holding the key down is one undo step, as long as it's the same todo
+    // and nothing else changed in between.
+    fn step_selected(&mut self, offset: isize) {
+        let Some(row) = self.list_state.selected() else {
+            self.fail(self.no_selection_message());
+            return;
+        };
+        let continuing =
+            self.move_streak.is_some() && self.move_streak == self.filtered_todos.get(row).copied();
+        self.move_selected(row.saturating_add_signed(offset), continuing);
+    }
+
+    // Moves the selected todo to `target` in the visible list. Under a
+    // filter it takes the place of the todo currently shown there, and the
+    // todos in between shift along those same places, so hidden todos never
+    // move. A filtering search lists by rank rather than position, so it
+    // has to be cleared first.
+    fn move_selected(&mut self, target: usize, continuing: bool) {
+        let Some(row) = self
+            .list_state
+            .selected()
+            .filter(|&row| row < self.filtered_todos.len())
+        else {
+            self.fail(self.no_selection_message());
+            return;
+        };
+        if self.options.filter_search && !self.search_query.is_empty() {
+            self.notify(
+                MessageLevel::Error,
+                "Clear the search (Esc) to reorder todos",
+            );
+            return;
+        }
+        let requested = target;
+        let target = target.min(self.filtered_todos.len() - 1);
+        if target == row {
+            self.notify(
+                MessageLevel::Info,
+                if requested <= row {
+                    "Already at the top".to_string()
+                } else {
+                    "Already at the bottom".to_string()
+                },
+            );
+            return;
+        }
+
+        if !continuing {
+            self.save_snapshot();
+        }
+        let rows = &self.filtered_todos;
+        if target > row {
+            for r in row..target {
+                self.todos.swap(rows[r], rows[r + 1]);
+            }
+        } else {
+            for r in (target..row).rev() {
+                self.todos.swap(rows[r], rows[r + 1]);
+            }
+        }
+        self.move_streak = Some(self.filtered_todos[target]);
+        self.filter_todos();
+        self.list_state.select(Some(target));
+        self.notify(
+            MessageLevel::Info,
+            format!("Moved to position {}", target + 1),
+        );
+    }
+
+    /// Deletes the todo under the cursor (or the visual selection) into the
+    /// unnamed register and `"1`, or the register named with `"`.
+    pub fn delete_todo(&mut self) {
+        let indices = self.get_selected_indices();
+        if indices.is_empty() {
+            self.fail(self.no_selection_message());
+            return;
+        }
+
+        if let Err(e) = Registers::check_writable(self.register) {
+            self.fail(e);
+            return;
+        }
+        if self.register == Some('+') {
+            self.fail("\"+ works with y and p only".to_string());
+            return;
+        }
+
+        // Copy selected todos to the registers before deleting
+        let deleted = indices
+            .iter()
+            .filter_map(|&idx| self.filtered_todos.get(idx))
+            .filter_map(|&i| self.todos.get(i).cloned())
+            .collect();
+        self.registers.store(self.register, deleted, true);
+
+        self.save_snapshot();
+
+        // Collect actual indices in reverse order
+        let mut to_delete: Vec<usize> = indices
+            .iter()
+            .filter_map(|&idx| self.filtered_todos.get(idx).copied())
+            .collect();
+        to_delete.sort();
+        to_delete.reverse();
+
+        // Delete in reverse order to maintain indices
+        for idx in to_delete {
+            if idx < self.todos.len() {
+                self.todos.remove(idx);
+            }
+        }
+
+        // Update filtered_todos
+        self.filter_todos();
+
+        // Adjust selection with better clamping for empty/shrinking lists
+        if self.todos.is_empty() {
+            // Completely empty list
+            self.list_state.select(None);
+        } else if self.filtered_todos.is_empty() {
+            // All todos filtered out but some still exist
+            self.list_state.select(None);
+        } else {
+            // Calculate safe selection index
+            let first_deleted_idx = if let Some(&first_idx) = indices.first() {
+                first_idx
+            } else {
+                0
+            };
+
+            // Clamp to valid range and prefer staying at same visual position
+            let max_idx = self.filtered_todos.len().saturating_sub(1);
+            let new_idx = first_deleted_idx.min(max_idx);
+
+            if let Some(selected_idx) = new_idx.checked_sub(0) {
+                if selected_idx < self.filtered_todos.len() {
+                    self.list_state.select(Some(selected_idx));
+                } else {
+                    self.list_state.select(Some(max_idx));
+                }
+            } else {
+                self.list_state.select(Some(0));
+            }
+        }
+
+        self.notify(
+            MessageLevel::Info,
+            if indices.len() == 1 {
+                "TODO deleted".to_string()
+            } else {
+                format!("{} todos deleted", indices.len())
+            },
+        );
+
+        // Track last action for repeat
+        self.last_action = Some(LastAction {
+            action: Action::Delete,
+            rows: indices.len(),
+        });
+
+        if self.mode == Mode::Visual {
+            self.mode = Mode::Normal;
+            self.visual_start = None;
+        }
+    }
+
+    // Runs a Normal-mode action looked up in the keymap. Returns true when
+    // tuido should quit.
+    fn run_key_action(&mut self, action: KeyAction) -> bool {
+        let typed_count = self.repeat_count;
+        let count = std::mem::take(&mut self.repeat_count).max(1);
+        match action {
+            KeyAction::Quit => {
+                if !self.is_dirty || self.scratch {
+                    return true;
+                }
+                self.notify(MessageLevel::Error, self.unsaved_changes_message());
+            }
+            KeyAction::Down => (0..count).for_each(|_| self.next()),
+            KeyAction::Up => (0..count).for_each(|_| self.previous()),
+            KeyAction::Toggle => self.toggle_rows(count),
+            KeyAction::Delete => self.delete_rows(count),
+            KeyAction::Postpone => self.postpone_todos(count),
+            KeyAction::Insert | KeyAction::Append => {
+                self.mode = Mode::Insert;
+                self.input.clear();
+                self.message.clear();
+                self.is_editing = false;
+            }
+            KeyAction::Edit => self.edit_todo(),
+            KeyAction::Command => self.start_command(),
+            KeyAction::Visual => self.start_visual(),
+            KeyAction::Yank => self.yank_rows(count),
+            KeyAction::Paste => self.paste_todos(false, false, count),
+            KeyAction::PasteAbove => self.paste_todos(true, false, count),
+            KeyAction::Search => self.start_search(),
+            KeyAction::Note => self.open_note_editor(),
+            KeyAction::Fold => self.toggle_note_expanded(),
+            KeyAction::Undo => self.undo(),
+            KeyAction::Redo => self.redo(),
+            KeyAction::Help => self.show_help(),
+            KeyAction::Repeat => self.repeat_last_action(typed_count),
+            KeyAction::NextMatch => (0..count).for_each(|_| self.search_next(true, true)),
+            KeyAction::PrevMatch => (0..count).for_each(|_| self.search_next(false, true)),
+            KeyAction::RaisePriority => self.priority_rows(PriorityChange::Raise, count),
+            KeyAction::LowerPriority => self.priority_rows(PriorityChange::Lower, count),
+            KeyAction::MoveDown => self.step_selected(count as isize),
+            KeyAction::MoveUp => self.step_selected(-(count as isize)),
+        }
+        false
+    }
+
+    // `.` in Normal mode. A multi-row action repeats on the same number of
+    // rows starting at the cursor, as if that range were selected visually.
+    // A count replaces the original one, as in vim: `3.` after `x` toggles
+    // three rows, after `p` pastes three times and after adding a todo adds
+    // it three more times. Either way it's one undo step.
+    fn repeat_last_action(&mut self, count: usize) {
+        let Some(last) = self.last_action.clone() else {
+            self.fail("Nothing to repeat".to_string());
+            return;
+        };
+        if self.mode != Mode::Normal {
+            self.fail("Repeat works from Normal mode".to_string());
+            return;
+        }
+        let on_rows = !matches!(last.action, Action::Add(_) | Action::Paste { .. });
+        if on_rows && self.list_state.selected().is_none() {
+            self.fail(self.no_selection_message());
+            return;
+        }
+        let times = if count > 0 { count } else { last.rows };
+
+        self.save_snapshot();
+        self.grouping_changes = true;
+        match &last.action {
+            Action::Postpone => self.postpone_todos(times),
+            Action::Toggle => self.toggle_rows(times),
+            Action::Delete => self.delete_rows(times),
+            Action::Priority(change) => self.priority_rows(*change, times),
+            Action::Edit(text) => self.replace_rows(text, times),
+            Action::Paste {
+                above,
+                reset,
+                register,
+            } => {
+                // `"ap` repeats from register a, unless `"b.` names another
+                self.register = self.register.or(*register);
+                self.paste_todos(*above, *reset, times)
+            }
+            // A shrinking list may run out of rows before the count does
+            Action::DeleteTo(motion) => {
+                for _ in 0..times {
+                    if self.list_state.selected().is_none() {
+                        break;
+                    }
+                    self.operate(Operator::Delete, *motion);
+                }
+            }
+            Action::Add(text) => {
+                for _ in 0..times {
+                    // Repeating an add asks for the duplicate
+                    self.confirmed_duplicate = Some(text.clone());
+                    self.input.set(text);
+                    self.add_todo();
+                }
+                self.input.clear();
+            }
+        }
+        self.grouping_changes = false;
+        // The repeat remembers its own count, for the next `.`
+        if let Some(repeated) = &mut self.last_action {
+            repeated.rows = times;
+        }
+
+        // Nothing changed: keep the message saying why
+        if self
+            .history
+            .back()
+            .is_some_and(|head| head.todos == self.todos)
+        {
+            return;
+        }
+        self.notify(
+            MessageLevel::Info,
+            match times {
+                1 => format!("repeated: {}", last.action.name()),
+                times => format!("repeated: {} ×{}", last.action.name(), times),
+            },
+        );
+    }
+
+    // Repeating an edit: the rows from the cursor get the edited todo's
+    // text, priority, due date and fields
+    fn replace_rows(&mut self, text: &str, count: usize) {
+        let cursor = self.list_state.selected();
+        if let Some(cursor) = cursor {
+            self.select_rows_from(cursor, count);
+        }
+        let todos: Vec<usize> = self
+            .get_selected_indices()
+            .iter()
+            .filter_map(|&idx| self.filtered_todos.get(idx).copied())
+            .collect();
+        self.save_snapshot();
+        for &i in &todos {
+            self.todos[i].apply_input(text);
+        }
+        self.mode = Mode::Normal;
+        self.visual_start = None;
+        self.list_state.select(cursor);
+        self.filter_todos();
+        self.notify(MessageLevel::Info, format!("{} todos updated", todos.len()));
+    }
+
+    // 3x, 3dd and 3y act on the cursor row and the ones after it, as if they
+    // were selected visually; past the end of the list they stop at the last
+    // row. The cursor stays where it was, except after a delete.
+    fn toggle_rows(&mut self, count: usize) {
+        let cursor = self.list_state.selected();
+        if let Some(cursor) = cursor {
+            self.select_rows_from(cursor, count);
+        }
+        self.toggle_todo();
+        self.list_state.select(cursor);
+    }
+
+    fn priority_rows(&mut self, change: PriorityChange, count: usize) {
+        let cursor = self.list_state.selected();
+        if let Some(cursor) = cursor {
+            self.select_rows_from(cursor, count);
+        }
+        self.change_priority(change);
+        self.list_state.select(cursor);
+    }
+
+    fn delete_rows(&mut self, count: usize) {
+        if let Some(cursor) = self.list_state.selected() {
+            self.select_rows_from(cursor, count);
+        }
+        self.delete_todo();
+    }
+
+    fn yank_rows(&mut self, count: usize) {
+        let cursor = self.list_state.selected();
+        if let Some(cursor) = cursor {
+            self.select_rows_from(cursor, count);
+        }
+        self.yank_todo();
+        self.list_state.select(cursor);
+    }
+
+    // Selects `rows` rows from `cursor` as a visual range; a single row
+    // stays a plain Normal-mode selection
+    fn select_rows_from(&mut self, cursor: usize, rows: usize) {
+        let last_row = self.filtered_todos.len().saturating_sub(1);
+        let end = (cursor + rows.saturating_sub(1)).min(last_row);
+        if end > cursor {
+            self.mode = Mode::Visual;
+            self.visual_start = Some(cursor);
+            self.list_state.select(Some(end));
+        }
+    }
+
+    // The todos a bulk command works on: the Visual selection it was typed
+    // from (`:'<,'>`), otherwise everything the current search shows
+    fn command_todos(&self) -> Vec<usize> {
+        match self.command_range {
+            Some((start, end)) => self
+                .filtered_todos
+                .get(start..=end)
+                .map(<[usize]>::to_vec)
+                .unwrap_or_default(),
+            None => self.filtered_todos.clone(),
+        }
+    }
+
+    // The todos a ranged command such as :1,5d works on; without a range,
+    // the one under the cursor
+    fn range_todos(&self) -> Vec<usize> {
+        if self.command_range.is_some() {
+            self.command_todos()
+        } else {
+            self.selected_todo_index().into_iter().collect()
+        }
+    }
+
+    // `:1,5d`, `:.,$x`, `:'<,'>pri A`: rows of the list as shown, counting
+    // from 1, become the command's range, the way a Visual selection does.
+    // Rows past either end are clamped to it, with a note in the message.
+    fn ranged_command(
+        &mut self,
+        first: Address,
+        last: Option<Address>,
+        command: &str,
+    ) -> CommandOutcome {
+        let command = spaced_move(command.trim());
+        if !takes_range(&command) {
+            self.fail(format!("No range allowed: :{}", command));
+            return CommandOutcome::Failed;
+        }
+        if self.filtered_todos.is_empty() {
+            self.fail(self.empty_list_hint());
+            return CommandOutcome::Failed;
+        }
+        let mut clamped = false;
+        let rows = self.address_row(first, &mut clamped).and_then(|start| {
+            let end = match last {
+                Some(last) => self.address_row(last, &mut clamped)?,
+                None => start,
+            };
+            Ok((start.min(end), start.max(end)))
+        });
+        let (start, end) = match rows {
+            Ok(rows) => rows,
+            Err(e) => {
+                self.fail(e);
+                return CommandOutcome::Failed;
+            }
+        };
+        if start == 0 {
+            clamped = true;
+        }
+        let start = start.max(1);
+        let end = end.max(1);
+
+        self.command_range = Some((start - 1, end - 1));
+        let outcome = self.run_command(&command);
+        self.command_range = None;
+        if clamped && outcome == CommandOutcome::Done {
+            self.notify(
+                MessageLevel::Info,
+                format!("{} (range clamped to {},{})", self.message, start, end),
+            );
+        }
+        outcome
+    }
+
+    // The row, counting from 1, that an address points to in the list as
+    // shown. One past the last row is clamped to it and sets `clamped`;
+    // `0` is left for the caller, since `:m0` means the top.
+    fn address_row(&self, address: Address, clamped: &mut bool) -> Result<usize, String> {
+        let len = self.filtered_todos.len();
+        let row = match address {
+            Address::Row(row) => row,
+            Address::Cursor => match self.list_state.selected() {
+                Some(row) => row + 1,
+                None => return Err(self.no_selection_message()),
+            },
+            Address::Last => len,
+            Address::VisualStart | Address::VisualEnd => match self.command_range {
+                Some((start, _)) if matches!(address, Address::VisualStart) => start + 1,
+                Some((_, end)) => end + 1,
+                None => return Err("'< and '> need : from Visual mode".to_string()),
+            },
+        };
+        if row > len {
+            *clamped = true;
+        }
+        Ok(row.min(len))
+    }
+
+    // :3m0 and :'<,'>m$: moves the range (or the todo under the cursor)
+    // below row `dest` of the list as shown, 0 being the top, as vim's
+    // :move does. Like J and K, the todos only trade the places the list
+    // shows, so hidden todos never move.
+    fn move_rows(&mut self, dest: &str) -> Result<String, String> {
+        let usage = "Usage: :m <row>, :m0 (top) or :m$ (bottom)";
+        let dest = match split_address(dest) {
+            Some((dest, rest)) if rest.trim().is_empty() => dest,
+            _ => return Err(usage.to_string()),
+        };
+        let (start, end) = match (self.command_range, self.list_state.selected()) {
+            (Some(range), _) => range,
+            (None, Some(row)) if row < self.filtered_todos.len() => (row, row),
+            _ => return Err(self.no_selection_message()),
+        };
+        if self.options.filter_search && !self.search_query.is_empty() {
+            return Err("Clear the search (Esc) to reorder todos".to_string());
+        }
+        let mut clamped = false;
+        let dest = self.address_row(dest, &mut clamped)?;
+        if dest > start && dest <= end {
+            return Err("Can't move a range below a row of its own".to_string());
+        }
+
+        let count = end - start + 1;
+        let mut order: Vec<usize> = (0..self.filtered_todos.len()).collect();
+        let moved: Vec<usize> = order.drain(start..=end).collect();
+        let at = if dest > end { dest - count } else { dest };
+        if at == start {
+            return Ok("Already there".to_string());
+        }
+        order.splice(at..at, moved);
+
+        self.save_snapshot();
+        let slots = self.filtered_todos.clone();
+        let before: Vec<Todo> = slots.iter().map(|&i| self.todos[i].clone()).collect();
+        for (slot, &from) in slots.iter().zip(&order) {
+            self.todos[*slot] = before[from].clone();
+        }
+        self.filter_todos();
+        self.list_state.select(Some(at + count - 1));
+        self.flash(slots[at..at + count].to_vec());
+        Ok(match count {
+            1 => format!("Moved to position {}", at + 1),
+            count => format!("Moved {} todos to rows {}-{}", count, at + 1, at + count),
+        })
+    }
+
+    // :s/a/b/ changes the todo under the cursor, :'<,'>s/a/b/ the Visual
+    // selection and :%s/a/b/ everything the search shows. Todos are changed
+    // as typed, like `e` edits them, and all in one undo step; one that
+    // would be left blank is left alone.
+    fn substitute(&mut self, whole: bool, spec: &str) -> CommandOutcome {
+        let substitute = match Substitute::parse(spec) {
+            Ok(substitute) => substitute,
+            Err(e) => {
+                self.fail(e);
+                return CommandOutcome::Failed;
+            }
+        };
+        if whole {
+            self.command_range = None;
+        }
+        let targets = if whole || self.command_range.is_some() {
+            self.command_todos()
+        } else {
+            self.selected_todo_index().into_iter().collect()
+        };
+        if targets.is_empty() {
+            self.fail(self.no_selection_message());
+            return CommandOutcome::Failed;
+        }
+
+        let mut changes = Vec::new();
+        for &i in &targets {
+            let Some((input, count)) = substitute.apply(&self.todos[i].input_text()) else {
+                continue;
+            };
+            let mut todo = self.todos[i].clone();
+            todo.apply_input(&input);
+            if !todo.text.trim().is_empty() {
+                changes.push((i, todo, count));
+            }
+        }
+        if changes.is_empty() {
+            self.fail(format!("Pattern not found: {}", substitute.pattern()));
+            return CommandOutcome::Failed;
+        }
+
+        self.save_snapshot();
+        let selected = self.selected_todo_index();
+        let substitutions: usize = changes.iter().map(|(_, _, count)| count).sum();
+        let changed: Vec<usize> = changes.iter().map(|&(i, _, _)| i).collect();
+        for (i, todo, _) in changes {
+            self.todos[i] = todo;
+        }
+        self.filter_todos();
+        if let Some(selected) = selected {
+            self.select_todo(selected);
+        }
+        self.notify(
+            MessageLevel::Info,
+            format!(
+                "{} substitution{} on {} todo{}",
+                substitutions,
+                if substitutions == 1 { "" } else { "s" },
+                changed.len(),
+                if changed.len() == 1 { "" } else { "s" },
+            ),
+        );
+        self.flash(changed);
+        CommandOutcome::Done
+    }
+
+    // :g/re/d deletes, :g/re/x toggles and :g/re/pri A sets the priority of
+    // every todo the search shows (or the Visual selection, from `:'<,'>`)
+    // that matches; :v/re/... (or :g!) does the same to the ones that don't.
+    // Todos are matched as `e` shows them, tags and fields included.
+    fn global(&mut self, whole: bool, invert: bool, spec: &str) -> CommandOutcome {
+        let parsed = Global::parse(spec).and_then(|global| {
+            let action = GlobalAction::parse(global.command())?;
+            Ok((global, action))
+        });
+        let (global, action) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                self.fail(e);
+                return CommandOutcome::Failed;
+            }
+        };
+        if whole {
+            self.command_range = None;
+        }
+        let targets: Vec<usize> = self
+            .command_todos()
+            .into_iter()
+            .filter(|&i| global.is_match(&self.todos[i].input_text()) != invert)
+            .collect();
+        if targets.is_empty() {
+            self.fail(if invert {
+                format!("Every todo matches: {}", global.pattern())
+            } else {
+                format!("Pattern not found: {}", global.pattern())
+            });
+            return CommandOutcome::Failed;
+        }
+
+        let count = targets.len();
+        let todos = if count == 1 { "todo" } else { "todos" };
+        match action {
+            GlobalAction::Delete { force } => {
+                if count > BULK_CONFIRM_THRESHOLD && !force {
+                    self.fail(format!(
+                        "{} todos would be deleted; add ! to confirm (d!)",
+                        count
+                    ));
+                    return CommandOutcome::Failed;
+                }
+                self.delete_todos(targets);
+            }
+            GlobalAction::Toggle => {
+                self.save_snapshot();
+                for &i in &targets {
+                    self.todos[i].toggle(self.today);
+                }
+                self.notify(MessageLevel::Info, format!("{} {} toggled", count, todos));
+                self.filter_todos();
+                self.flash(targets);
+            }
+            GlobalAction::Priority(priority) => {
+                self.save_snapshot();
+                for &i in &targets {
+                    self.todos[i].priority = priority;
+                }
+                self.notify(
+                    MessageLevel::Info,
+                    match priority {
+                        Some(priority) => format!("Set {} {} to ({})", count, todos, priority),
+                        None => format!("Cleared priority of {} {}", count, todos),
+                    },
+                );
+                self.filter_todos();
+                self.flash(targets);
+            }
+        }
+        CommandOutcome::Done
+    }
+
+    // :xall or :invert flips every todo the current search shows
+    fn toggle_all_filtered(&mut self) {
+        let targets = self.command_todos();
+        if targets.is_empty() {
+            self.notify(MessageLevel::Info, self.empty_list_hint());
+            return;
+        }
+        self.save_snapshot();
+        for &i in &targets {
+            self.todos[i].toggle(self.today);
+        }
+        self.notify(
+            MessageLevel::Info,
+            format!("{} todos toggled", targets.len()),
+        );
+        self.flash(targets);
+    }
+
+    // :done all and :undone all complete or reopen every todo the current
+    // search shows. Only the ones that change are counted and flashed.
+    fn set_all_filtered(&mut self, completed: bool) {
+        let targets = self.command_todos();
+        if targets.is_empty() {
+            self.notify(MessageLevel::Info, self.empty_list_hint());
+            return;
+        }
+        let changed: Vec<usize> = targets
+            .iter()
+            .copied()
+            .filter(|&i| self.todos[i].completed != completed)
+            .collect();
+        let state = if completed { "done" } else { "pending" };
+        if changed.is_empty() {
+            self.notify(
+                MessageLevel::Info,
+                format!("All {} todos are already {}", targets.len(), state),
+            );
+            return;
+        }
+        self.save_snapshot();
+        for &i in &changed {
+            self.todos[i].set_completed(completed, self.today);
+        }
+        self.notify(
+            MessageLevel::Info,
+            format!("{} todos marked {}", changed.len(), state),
+        );
+        self.flash(changed);
+    }
+
+    // :toggleall works like a select-all checkbox: if anything shown is still
+    // pending, everything shown is completed, otherwise it's all reopened
+    fn toggle_all_checkbox(&mut self) {
+        let any_pending = self
+            .command_todos()
+            .iter()
+            .any(|&i| !self.todos[i].completed);
+        self.set_all_filtered(any_pending);
+    }
+
+    // Stable, so todos that compare equal keep their order, and the cursor
+    // stays on the todo it was on
+    fn sort_todos(&mut self, key: SortKey, reverse: bool) {
+        let selected = self
+            .list_state
+            .selected()
+            .and_then(|row| self.filtered_todos.get(row).copied());
+        self.save_snapshot();
+        let mut order: Vec<usize> = (0..self.todos.len()).collect();
+        order.sort_by(|&a, &b| key.compare(&self.todos[a], &self.todos[b], reverse));
+        let mut todos: Vec<Option<Todo>> = std::mem::take(&mut self.todos)
+            .into_iter()
+            .map(Some)
+            .collect();
+        self.todos = order.iter().filter_map(|&i| todos[i].take()).collect();
+        self.filter_todos();
+        if let Some(row) = selected
+            .and_then(|old| order.iter().position(|&i| i == old))
+            .and_then(|new| self.filtered_todos.iter().position(|&i| i == new))
+        {
+            self.list_state.select(Some(row));
+        }
+        self.notify(
+            MessageLevel::Info,
+            format!(
+                "Sorted by {}{}",
+                key.describe(),
+                if reverse { ", reversed" } else { "" }
+            ),
+        );
+    }
+
+    // :dall deletes every todo the current search shows, keeping them in the
+    // clipboard. Above BULK_CONFIRM_THRESHOLD it needs `force` (:dall!).
+    fn delete_all_filtered(&mut self, force: bool) {
+        let to_delete = self.command_todos();
+        let count = to_delete.len();
+        if count == 0 {
+            self.notify(MessageLevel::Info, self.empty_list_hint());
+            return;
+        }
+        if count > BULK_CONFIRM_THRESHOLD && !force {
+            self.notify(
+                MessageLevel::Error,
+                format!("{} todos would be deleted; use :dall! to confirm", count),
+            );
+            return;
+        }
+
+        self.delete_todos(to_delete);
+    }
+
+    // Deletes the todos at `to_delete` into the registers, as one undo step.
+    // The cursor stays on the same row, or the last one left.
+    fn delete_todos(&mut self, mut to_delete: Vec<usize>) {
+        self.save_snapshot();
+        let selected = self.list_state.selected();
+        let deleted = to_delete.iter().map(|&i| self.todos[i].clone()).collect();
+        self.registers.store(None, deleted, true);
+        to_delete.sort_unstable();
+        for &i in to_delete.iter().rev() {
+            self.todos.remove(i);
+        }
+        self.filter_todos();
+        self.list_state.select(if self.filtered_todos.is_empty() {
+            None
+        } else {
+            Some(selected.unwrap_or(0).min(self.filtered_todos.len() - 1))
+        });
+        self.notify(
+            MessageLevel::Info,
+            match to_delete.len() {
+                1 => "1 todo deleted".to_string(),
+                count => format!("{} todos deleted", count),
+            },
+        );
+    }
+
+    fn add_todo(&mut self) {
+        let text = self.input.text();
+        if !self.append_todo(&text) {
+            return;
+        }
+        self.last_action = Some(LastAction {
+            action: Action::Add(text),
+            rows: 1,
+        });
+        self.input.clear();
+        if !self.filtered_todos.is_empty() {
+            self.list_state.select(Some(self.filtered_todos.len() - 1));
+        }
+    }
+
+    // Parses and appends a todo without moving the selection. Returns false
+    // when nothing was added (empty input, or a strict-mode duplicate waiting
+    // for a second Enter).
+    fn append_todo(&mut self, input: &str) -> bool {
+        if input.trim().is_empty() {
+            self.notify(MessageLevel::Info, "Empty todo not added");
+            return false;
+        }
+
+        let (priority, text) = parse_priority(input);
+        let (due, text) = parse_due(&text);
+        let (meta, text) = parse_meta(&text);
+
+        let similar = match self.options.dupe_check {
+            DupeCheck::Off => None,
+            _ => self.find_similar(&text),
+        };
+        if let Some(existing) = similar {
+            let warning = format!(
+                "Similar to existing: '{}' (#{})",
+                self.todos[existing].text,
+                existing + 1
+            );
+            // Strict mode holds the input until Enter is pressed again
+            if self.options.dupe_check == DupeCheck::Strict
+                && self.confirmed_duplicate.as_deref() != Some(input)
+            {
+                self.confirmed_duplicate = Some(input.to_string());
+                self.notify(
+                    MessageLevel::Info,
+                    format!("{} — press Enter again to add", warning),
+                );
+                return false;
+            }
+            self.notify(MessageLevel::Info, warning);
+        } else {
+            self.notify(MessageLevel::Info, "TODO added");
+        }
+        self.confirmed_duplicate = None;
+
+        self.save_snapshot();
+
+        let todo = Todo {
+            text,
+            priority,
+            due,
+            meta,
+            created: Some(self.today),
+            ..Default::default()
+        };
+
+        self.todos.push(todo);
+        self.filter_todos();
+        true
+    }
+
+    // A bar while typing into an input, a block otherwise
+    pub fn cursor_style(&self) -> cursor::SetCursorStyle {
+        let typing = matches!(
+            self.mode,
+            Mode::Insert | Mode::Command | Mode::Search | Mode::NoteEdit
+        );
+        if typing || self.quick_add.is_some() {
+            cursor::SetCursorStyle::SteadyBar
+        } else {
+            cursor::SetCursorStyle::SteadyBlock
+        }
+    }
+
+    // Ctrl+N in Normal, Visual or Search mode. Appending leaves the indices
+    // of everything already listed alone, so the selection, visual range and
+    // search carry on where they were.
+    fn open_quick_add(&mut self) {
+        self.quick_add = Some(TextBuffer::default());
+    }
+
+    fn submit_quick_add(&mut self) {
+        let Some(input) = self.quick_add.take() else {
+            return;
+        };
+        let text = input.text();
+        if !self.append_todo(&text) && !text.trim().is_empty() {
+            self.quick_add = Some(input);
+        }
+    }
+
+    // Closest pending todo whose normalized Levenshtein similarity reaches
+    // DUPE_SIMILARITY. Lengths are in graphemes, like the edit distance, and
+    // the length check skips pairs that can't reach it.
+    fn find_similar(&self, text: &str) -> Option<usize> {
+        let len = text.trim().graphemes(true).count();
+        if len < DUPE_MIN_LEN {
+            return None;
+        }
+
+        let mut best: Option<(usize, f64)> = None;
+        for (i, todo) in self.todos.iter().enumerate() {
+            if todo.completed {
+                continue;
+            }
+            let other_len = todo.text.trim().graphemes(true).count();
+            let longest = len.max(other_len) as f64;
+            if 1.0 - (len.abs_diff(other_len) as f64) / longest < DUPE_SIMILARITY {
+                continue;
+            }
+            let similarity = similarity(text, &todo.text);
+            if similarity >= DUPE_SIMILARITY && best.is_none_or(|(_, s)| similarity > s) {
+                best = Some((i, similarity));
+            }
+        }
+        best.map(|(i, _)| i)
+    }
+
+    // Esc in Insert mode throws the input away; the todo being edited is only
+    // written on Enter, so its original text is untouched.
+    fn cancel_insert(&mut self) {
+        self.notify(
+            MessageLevel::Info,
+            if self.is_editing {
+                "Edit cancelled".to_string()
+            } else {
+                "Insert cancelled".to_string()
+            },
+        );
+        self.mode = Mode::Normal;
+        self.input.clear();
+        self.is_editing = false;
+        self.confirmed_duplicate = None;
+    }
+
+    fn open_note_editor(&mut self) {
+        if let Some(idx) = self.list_state.selected()
+            && let Some(&todo_idx) = self.filtered_todos.get(idx)
+            && todo_idx < self.todos.len()
+        {
+            self.current_note_index = Some(todo_idx);
+            self.note_input = TextBuffer::new(self.todos[todo_idx].note.as_deref().unwrap_or(""));
+            self.mode = Mode::NoteEdit;
+        } else {
+            self.fail(self.no_selection_message());
+        }
+    }
+
+    // Expansion is display state only, so no snapshot is taken
+    fn toggle_note_expanded(&mut self) {
+        let Some(todo_idx) = self
+            .list_state
+            .selected()
+            .and_then(|idx| self.filtered_todos.get(idx).copied())
+        else {
+            self.fail(self.no_selection_message());
+            return;
+        };
+        let todo = &mut self.todos[todo_idx];
+        if todo.note.is_some() {
+            todo.note_expanded = !todo.note_expanded;
+        } else {
+            self.notify(MessageLevel::Info, "No note (press o to add one)");
+        }
+    }
+
+    fn set_all_notes_expanded(&mut self, expanded: bool) {
+        let mut count = 0;
+        for todo in self.todos.iter_mut().filter(|t| t.note.is_some()) {
+            todo.note_expanded = expanded;
+            count += 1;
+        }
+        self.notify(
+            MessageLevel::Info,
+            format!(
+                "{} {} notes",
+                if expanded { "Expanded" } else { "Collapsed" },
+                count
+            ),
+        );
+    }
+
+    fn save_note(&mut self) {
+        if let Some(todo_idx) = self.current_note_index
+            && todo_idx < self.todos.len()
+        {
+            self.save_snapshot();
+            let note = self.note_input.text();
+            if note.trim().is_empty() {
+                self.todos[todo_idx].note = None;
+            } else {
+                self.todos[todo_idx].note = Some(note);
+            }
+            self.notify(MessageLevel::Info, "Note saved");
+        }
+        self.mode = Mode::Normal;
+        self.note_input = TextBuffer::default();
+        self.current_note_index = None;
+    }
+
+    fn cancel_note(&mut self) {
+        self.mode = Mode::Normal;
+        self.note_input = TextBuffer::default();
+        self.current_note_index = None;
+    }
+
+    fn start_command(&mut self) {
+        self.mode = Mode::Command;
+        self.command_input.clear();
+        self.command_history.index = None;
+        self.confirmed_shell = None;
+        self.command_range = None;
+    }
+
+    // `:` in Visual mode: bulk commands such as :xall, :dall and :export
+    // then work on the selection instead of the whole search
+    fn start_range_command(&mut self) {
+        let rows = self.get_selected_indices();
+        self.visual_start = None;
+        self.start_command();
+        self.command_range = rows.first().zip(rows.last()).map(|(&a, &b)| (a, b));
+    }
+
+    pub(crate) fn command_prompt(&self) -> &'static str {
+        if self.command_range.is_some() {
+            ":'<,'>"
+        } else {
+            ":"
+        }
+    }
+
+    fn recall_command(&mut self, older: bool) {
+        if let Some(text) = self.command_history.recall(older) {
+            self.command_input.set(&text);
+            self.confirmed_shell = None;
+        }
+    }
+
+    fn start_search(&mut self) {
+        self.mode = Mode::Search;
+        self.search_query.clear();
+        self.search_history.index = None;
+    }
+
+    fn recall_search(&mut self, older: bool) {
+        if let Some(text) = self.search_history.recall(older) {
+            self.search_query.set(&text);
+            self.request_search();
+        }
+    }
+
+    // Enter in Search mode. An empty query repeats the last search.
+    fn submit_search(&mut self) {
+        self.mode = Mode::Normal;
+        if self.search_query.is_empty()
+            && let Some(last) = self.search_history.entries.last()
+        {
+            self.search_query.set(&last.clone());
+            self.filter_todos();
+        }
+        let query = self.search_query.text();
+        self.search_history.push(&query);
+        self.save_history();
+        if !self.options.filter_search && !query.is_empty() {
+            self.search_next(true, false);
+        }
+    }
+
+    pub fn load_history(&mut self, path: PathBuf) {
+        if let Ok(contents) = fs::read_to_string(&path)
+            && let Ok(saved) = serde_json::from_str::<SavedHistory>(&contents)
+        {
+            self.command_history.entries = saved.commands;
+            self.search_history.entries = saved.searches;
+        }
+        self.history_file = Some(path);
+    }
+
+    // Written after every submitted command or search, so history survives
+    // however tuido exits. A failure only costs the history, so it's ignored.
+    fn save_history(&self) {
+        let Some(path) = &self.history_file else {
+            return;
+        };
+        let saved = SavedHistory {
+            commands: self.command_history.entries.clone(),
+            searches: self.search_history.entries.clone(),
+        };
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Ok(json) = serde_json::to_string_pretty(&saved) {
+            let _ = fs::write(path, json);
+        }
+    }
+
+    // A shell command recalled from history is only run after a second Enter
+    pub(crate) fn recalled_shell_command(&self) -> bool {
+        self.command_history.index.is_some() && is_shell_command(&self.command_input.text())
+    }
+
+    // Enter in Command mode. Stays in Command mode while a recalled shell
+    // command waits for confirmation.
+    fn submit_command(&mut self) -> CommandOutcome {
+        if self.recalled_shell_command()
+            && self.confirmed_shell.as_deref() != Some(self.command_input.text().as_str())
+        {
+            self.confirmed_shell = Some(self.command_input.text());
+            return CommandOutcome::Done;
+        }
+        let input = std::mem::take(&mut self.command_input).text();
+        self.mode = Mode::Normal;
+        self.confirmed_shell = None;
+
+        let entry = input.trim();
+        if self.options.hist_ignore_shell && is_shell_command(entry) {
+            self.command_history.index = None;
+        } else {
+            self.command_history.push(entry);
+            self.save_history();
+        }
+        let outcome = self.execute_command(&input);
+        self.command_range = None;
+        outcome
+    }
+
+    /// Runs one command-line command (without the leading `:`). Shared by
+    /// Command mode and `-c` startup commands.
+    pub fn execute_command(&mut self, input: &str) -> CommandOutcome {
+        self.error_pinned = false;
+        match self.expand_alias(input) {
+            Some(command) => self.run_command(&command),
+            None => self.run_command(input),
+        }
+    }
+
+    // The command an alias stands for, with `%s` replaced by what was typed
+    // after the name, or that appended when there's no `%s`. Expanded once:
+    // an alias naming another alias, or itself, runs the built-in.
+    fn expand_alias(&self, input: &str) -> Option<String> {
+        let input = input.trim();
+        let (name, args) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        let command = self.aliases.get(&name.to_lowercase())?;
+        let args = args.trim();
+        Some(if command.contains("%s") {
+            command.replace("%s", args)
+        } else if args.is_empty() {
+            command.clone()
+        } else {
+            format!("{} {}", command, args)
+        })
+    }
+
+    fn run_command(&mut self, input: &str) -> CommandOutcome {
+        // Snapshots are taken before a change, so catch up with the last one
+        // before anything checks for unsaved changes
+        self.update_dirty_status();
+        if let Some((first, last, command)) = split_range(input)
+            && !command.trim().is_empty()
+        {
+            return self.ranged_command(first, last, command);
+        }
+        if let Some((whole, spec)) = pattern_command(input, "s") {
+            return self.substitute(whole, spec);
+        }
+        if let Some((whole, spec)) =
+            pattern_command(input, "v").or_else(|| pattern_command(input, "g!"))
+        {
+            return self.global(whole, true, spec);
+        }
+        if let Some((whole, spec)) = pattern_command(input, "g") {
+            return self.global(whole, false, spec);
+        }
+        let cmd = input.trim().to_lowercase();
+        let parts: Vec<&str> = cmd.split_whitespace().collect();
+
+        match parts.as_slice() {
+            [] => {}
+            ["q" | "quit"] => {
+                if self.is_dirty && !self.scratch {
+                    self.notify(MessageLevel::Error, self.unsaved_changes_message());
+                    return CommandOutcome::Failed;
+                } else {
+                    return CommandOutcome::Quit;
+                }
+            }
+            ["q!"] => return CommandOutcome::Quit,
+            ["w"] => {
+                if self.save_todos().is_err() {
+                    return CommandOutcome::Failed;
+                }
+            }
+            ["w!"] => {
+                if self.write_todos(true).is_err() {
+                    return CommandOutcome::Failed;
+                }
+            }
+            ["e" | "e!" | "edit" | "edit!"] => match self.reload_file(cmd.ends_with('!')) {
+                Ok(count) => {
+                    self.notify(
+                        MessageLevel::Info,
+                        format!(
+                            "Reloaded {} todos from {} (u to undo)",
+                            count,
+                            self.current_file.display()
+                        ),
+                    );
+                }
+                Err(e) => {
+                    self.notify(MessageLevel::Error, e);
+                    return CommandOutcome::Failed;
+                }
+            },
+            ["w" | "w!" | "write" | "write!", _, ..] => {
+                let path = Self::expand_path(&Self::command_arg(input).unwrap_or_default());
+                if self
+                    .check_overwrite(&path, parts[0].ends_with('!'))
+                    .is_err()
+                    || self.write_copy(&path).is_err()
+                {
+                    return CommandOutcome::Failed;
+                }
+            }
+            ["saveas" | "saveas!" | "sav" | "sav!", _, ..] => {
+                let path = Self::expand_path(&Self::command_arg(input).unwrap_or_default());
+                if self
+                    .check_overwrite(&path, parts[0].ends_with('!'))
+                    .is_err()
+                    || self.save_todos_as(&path).is_err()
+                {
+                    return CommandOutcome::Failed;
+                }
+            }
+            ["saveas" | "sav"] => {
+                self.notify(MessageLevel::Error, "Usage: :saveas <file>");
+                return CommandOutcome::Failed;
+            }
+            ["wq" | "wq!"] => {
+                if self.write_todos(cmd.ends_with('!')).is_ok() {
+                    return CommandOutcome::Quit;
+                }
+                return CommandOutcome::Failed;
+            }
+            ["clear" | "clear!"] => {
+                let count = self.todos.iter().filter(|t| t.completed).count();
+                if count == 0 {
+                    self.notify(MessageLevel::Info, "No completed todos to remove");
+                } else if cmd.ends_with('!') {
+                    self.clear_completed();
+                } else {
+                    self.ask_confirm(
+                        Confirm::ClearCompleted,
+                        format!("Remove {} completed todos? (y/n)", count),
+                    );
+                }
+            }
+            ["dedup" | "dedup!"] => {
+                let mut todos = self.todos.clone();
+                dedup::dedup(&mut todos);
+                let count = self.todos.len() - todos.len();
+                if count == 0 {
+                    self.notify(MessageLevel::Info, "No duplicates found");
+                } else if cmd.ends_with('!') {
+                    self.dedup_todos();
+                } else {
+                    self.ask_confirm(
+                        Confirm::Dedup,
+                        format!("Remove {} duplicates? (y/n)", count),
+                    );
+                }
+            }
+            ["sort" | "sort!"] => self.sort_todos(SortKey::Completion, cmd.ends_with('!')),
+            ["sort", field] => {
+                let (field, reverse) = match field.strip_suffix('!') {
+                    Some(field) => (field, true),
+                    None => (*field, false),
+                };
+                let Some(key) = SortKey::parse(field) else {
+                    self.notify(MessageLevel::Error, format!(
+                        "Unknown sort '{}' (alpha, due, priority, created, completed, meta:<key>)",
+                        field
+                    ));
+                    return CommandOutcome::Failed;
+                };
+                self.sort_todos(key, reverse);
+            }
+            ["filter"] => {
+                self.meta_filter = None;
+                self.filter_todos();
+                self.notify(MessageLevel::Info, "Filter cleared");
+            }
+            ["filter", _] => {
+                let field = Self::command_arg(input).unwrap_or_default();
+                match split_meta(&field) {
+                    Some((key, value)) => {
+                        self.meta_filter = Some((key.to_string(), value.to_string()));
+                        self.filter_todos();
+                        self.notify(MessageLevel::Info, format!("Showing todos with {}", field));
+                    }
+                    None => {
+                        self.notify(
+                            MessageLevel::Error,
+                            "Usage: :filter key:value (or :filter to clear)",
+                        );
+                        return CommandOutcome::Failed;
+                    }
+                }
+            }
+            ["set" | "set!", ..] => {
+                let args = Self::command_arg(input).unwrap_or_default();
+                if let Err(e) = self.set_command(&args, cmd.starts_with("set!")) {
+                    self.fail(e);
+                    return CommandOutcome::Failed;
+                }
+            }
+            ["info"] => match self.selected_todo_index() {
+                Some(i) => self.notify(MessageLevel::Info, date_info(&self.todos[i], self.today)),
+                None => {
+                    self.fail(self.no_selection_message());
+                    return CommandOutcome::Failed;
+                }
+            },
+            [first, ..]
+                if first.starts_with("%!")
+                    || (first.starts_with('!') && self.command_range.is_some()) =>
+            {
+                if first.starts_with('%') {
+                    self.command_range = None;
+                }
+                // Taken from the original input so the command keeps its case
+                let command = input.trim().trim_start_matches('%')[1..].trim();
+                let result = if command.is_empty() {
+                    Err("Usage: :%!cmd, or :'<,'>!cmd from Visual mode".to_string())
+                } else {
+                    self.filter_through(command)
+                };
+                match result {
+                    Ok(message) => self.notify(MessageLevel::Info, message),
+                    Err(e) => {
+                        self.notify(MessageLevel::Error, e);
+                        return CommandOutcome::Failed;
+                    }
+                }
+            }
+            [first, ..] if first.starts_with('!') => {
+                // Taken from the original input so the command keeps its case
+                let cmd_str = input.trim().trim_start_matches('!').trim().to_string();
+                if let Err(e) = self.start_shell(&cmd_str, false) {
+                    self.notify(MessageLevel::Error, e);
+                    return CommandOutcome::Failed;
+                }
+            }
+            ["write"] => {
+                // Fallback: save to the active file if no filename given
+                if self.save_todos().is_err() {
+                    return CommandOutcome::Failed;
+                }
+            }
+            ["open" | "open!", _, ..] => {
+                let force = cmd.starts_with("open!");
+                let file = Self::command_arg(input).unwrap_or_default();
+                if let Err(e) = self.open_file(&file, force) {
+                    self.notify(MessageLevel::Error, e);
+                    return CommandOutcome::Failed;
+                }
+            }
+            ["file"] => {
+                self.notify(
+                    MessageLevel::Info,
+                    format!(
+                        "\"{}\" {} todos{}",
+                        if self.scratch {
+                            STDIN_NAME.to_string()
+                        } else {
+                            self.current_file.display().to_string()
+                        },
+                        self.todos.len(),
+                        if self.is_dirty { " [+]" } else { "" }
+                    ),
+                );
+            }
+            ["expandall"] => self.set_all_notes_expanded(true),
+            ["collapseall"] => self.set_all_notes_expanded(false),
+            ["xall" | "invert"] => self.toggle_all_filtered(),
+            ["toggleall"] => self.toggle_all_checkbox(),
+            ["done", "all"] => self.set_all_filtered(true),
+            ["undone", "all"] => self.set_all_filtered(false),
+            ["selectall"] => self.select_all(),
+            ["dall"] => self.delete_all_filtered(false),
+            ["dall!"] => self.delete_all_filtered(true),
+            ["yank+"] => self.yank_to_system(),
+            ["d" | "delete"] => {
+                let targets = self.range_todos();
+                if targets.is_empty() {
+                    self.fail(self.no_selection_message());
+                    return CommandOutcome::Failed;
+                }
+                self.delete_todos(targets);
+            }
+            // A bare :x is vim's write-and-quit, so it needs a range
+            ["x"] | ["toggle"] if cmd == "toggle" || self.command_range.is_some() => {
+                let targets = self.range_todos();
+                if targets.is_empty() {
+                    self.fail(self.no_selection_message());
+                    return CommandOutcome::Failed;
+                }
+                self.save_snapshot();
+                for &i in &targets {
+                    self.todos[i].toggle(self.today);
+                }
+                self.notify(
+                    MessageLevel::Info,
+                    match targets.len() {
+                        1 => "TODO toggled".to_string(),
+                        count => format!("{} todos toggled", count),
+                    },
+                );
+                self.filter_todos();
+                self.flash(targets);
+            }
+            [name, dest] if *name == "m" || (*name == "move" && self.command_range.is_some()) => {
+                match self.move_rows(dest) {
+                    Ok(message) => self.notify(MessageLevel::Info, message),
+                    Err(e) => {
+                        self.fail(e);
+                        return CommandOutcome::Failed;
+                    }
+                }
+            }
+            ["pri", priority] => match *priority {
+                "-" => self.change_priority(PriorityChange::Set(None)),
+                p if p.len() == 1 && p.as_bytes()[0].is_ascii_lowercase() => {
+                    let priority = p.to_ascii_uppercase().chars().next();
+                    self.change_priority(PriorityChange::Set(priority));
+                }
+                _ => {
+                    self.notify(MessageLevel::Error, "Usage: :pri A-Z, or :pri - to clear");
+                    return CommandOutcome::Failed;
+                }
+            },
+            // `:5` goes to the fifth row, `:$` to the last
+            ["$"] => self.jump_to_row(usize::MAX),
+            [row] if row.bytes().all(|b| b.is_ascii_digit()) => {
+                self.jump_to_row(row.parse().unwrap_or(usize::MAX))
+            }
+            ["move", position] => match *position {
+                "$" => self.move_selected(usize::MAX, false),
+                n => match n.parse::<usize>() {
+                    Ok(n) => self.move_selected(n.saturating_sub(1), false),
+                    Err(_) => {
+                        self.notify(
+                            MessageLevel::Error,
+                            "Usage: :move <n>, :move 0 (top) or :move $ (bottom)",
+                        );
+                        return CommandOutcome::Failed;
+                    }
+                },
+            },
+            ["paste+"] => self.paste_from_system(),
+            ["retag" | "retag!", ..] => {
+                let args: Vec<String> = input.split_whitespace().map(str::to_string).collect();
+                let force = args[0].ends_with('!');
+                let result = match args.as_slice() {
+                    [_, old, new] => self
+                        .retag(old, new, force)
+                        .map(|count| format!("Retagged {} -> {} in {} todos", old, new, count)),
+                    _ => Err("Usage: :retag +old +new or :retag @old @new".to_string()),
+                };
+                match result {
+                    Ok(message) => self.notify(MessageLevel::Info, message),
+                    Err(e) => {
+                        self.notify(MessageLevel::Error, e);
+                        return CommandOutcome::Failed;
+                    }
+                }
+            }
+            ["import", ..] => {
+                let args = Self::command_arg(input).unwrap_or_default();
+                let mut format = None;
+                let mut path = Vec::new();
+                let mut tokens = args.split_whitespace();
+                while let Some(token) = tokens.next() {
+                    if token == "--format" {
+                        format = tokens.next().map(|f| f.to_lowercase());
+                    } else {
+                        path.push(token);
+                    }
+                }
+                let path = path.join(" ");
+                let format = match format.as_deref() {
+                    Some("gtasks") => Some(ImportFormat::GoogleTasks),
+                    Some("reminders") => Some(ImportFormat::Reminders),
+                    Some("todotxt") => Some(ImportFormat::TodoTxt),
+                    Some("markdown" | "md") => Some(ImportFormat::Markdown),
+                    Some("csv") => Some(ImportFormat::Csv),
+                    None if path.ends_with(".csv") => Some(ImportFormat::Csv),
+                    None if path.ends_with(".txt") => Some(ImportFormat::TodoTxt),
+                    None if path.ends_with(".md") || path.ends_with(".markdown") => {
+                        Some(ImportFormat::Markdown)
+                    }
+                    _ => None,
+                };
+                match format {
+                    Some(format) if !path.is_empty() => match self.import_tasks(&path, format) {
+                        Ok((imported, _, errors)) if !errors.is_empty() => {
+                            self.notify(
+                                MessageLevel::Info,
+                                format!(
+                                    "Imported {} todos from {}; skipped {} ({}{})",
+                                    imported,
+                                    path,
+                                    errors.len(),
+                                    errors[0],
+                                    if errors.len() > 1 { ", …" } else { "" }
+                                ),
+                            );
+                        }
+                        Ok((imported, _, _)) if format == ImportFormat::TodoTxt => {
+                            self.notify(
+                                MessageLevel::Info,
+                                format!(
+                                    "Replaced the list with {} todos from {} (u to undo)",
+                                    imported, path
+                                ),
+                            );
+                        }
+                        Ok((imported, _, _)) if format == ImportFormat::Markdown => {
+                            let completed = self.todos[self.todos.len() - imported..]
+                                .iter()
+                                .filter(|t| t.completed)
+                                .count();
+                            self.notify(
+                                MessageLevel::Info,
+                                format!(
+                                    "Imported {} items ({} completed) from {}",
+                                    imported, completed, path
+                                ),
+                            );
+                        }
+                        Ok((imported, skipped, _)) => {
+                            self.notify(
+                                MessageLevel::Info,
+                                format!(
+                                    "Imported {} todos from {} ({} skipped)",
+                                    imported, path, skipped
+                                ),
+                            );
+                        }
+                        Err(e) => {
+                            self.notify(MessageLevel::Error, e);
+                            return CommandOutcome::Failed;
+                        }
+                    },
+                    _ => {
+                        self.notify(
+                            MessageLevel::Error,
+                            "Usage: :import <file> [--format gtasks|reminders|todotxt|markdown|csv]",
+                        );
+                        return CommandOutcome::Failed;
+                    }
+                }
+            }
+            ["merge", _, ..] => {
+                let file = Self::command_arg(input).unwrap_or_default();
+                if let Err(e) = self.start_merge(&file) {
+                    self.notify(MessageLevel::Error, e);
+                    return CommandOutcome::Failed;
+                }
+            }
+            ["merge"] => {
+                self.notify(MessageLevel::Error, "Usage: :merge <file>");
+                return CommandOutcome::Failed;
+            }
+            ["open" | "open!"] => {
+                self.notify(
+                    MessageLevel::Error,
+                    "Usage: :open <filename> (use quotes for spaces)",
+                );
+                return CommandOutcome::Failed;
+            }
+            ["read" | "r", first, ..] if first.starts_with('!') => {
+                let arg = Self::command_arg(input).unwrap_or_default();
+                let command = arg.trim_start_matches('!').trim();
+                if command.is_empty() {
+                    self.notify(MessageLevel::Error, "Usage: :r !cmd");
+                    return CommandOutcome::Failed;
+                }
+                if let Err(e) = self.start_shell(command, true) {
+                    self.notify(MessageLevel::Error, e);
+                    return CommandOutcome::Failed;
+                }
+            }
+            ["read" | "r", _, ..] => {
+                let file = Self::command_arg(input).unwrap_or_default();
+                match self.read_file(&file) {
+                    Ok(count) => self.notify(
+                        MessageLevel::Info,
+                        format!("Read {} todos from {}", count, file),
+                    ),
+                    Err(e) => {
+                        self.notify(MessageLevel::Error, e);
+                        return CommandOutcome::Failed;
+                    }
+                }
+            }
+            ["read" | "r"] => {
+                self.notify(
+                    MessageLevel::Error,
+                    "Usage: :read <file> (.json, .txt or .md)",
+                );
+                return CommandOutcome::Failed;
+            }
+            ["export", _, ..] => {
+                let file = Self::command_arg(input).unwrap_or_default();
+                // `:export notes.md group` puts each priority under a heading
+                let (file, group) = match file.strip_suffix(" group") {
+                    Some(md) if md.trim_end().ends_with(".md") => (md.trim_end().to_string(), true),
+                    _ => (file, false),
+                };
+                // A search or a Visual selection narrows the export
+                let todos: Vec<Todo> = self
+                    .command_todos()
+                    .iter()
+                    .map(|&i| self.todos[i].clone())
+                    .collect();
+                let Some(format) = ExportFormat::from_file(&file, group) else {
+                    self.notify(
+                        MessageLevel::Error,
+                        format!(
+                            "Unsupported format: {} (use .txt, .md, .json, .csv or .html)",
+                            file
+                        ),
+                    );
+                    return CommandOutcome::Failed;
+                };
+                let result = fs::File::create(&file).and_then(|f| {
+                    let mut out = io::BufWriter::new(f);
+                    export(&todos, format, &mut out)?;
+                    out.flush()
+                });
+                match result {
+                    Ok(_) if todos.len() < self.todos.len() => self.notify(
+                        MessageLevel::Info,
+                        format!(
+                            "Exported {} of {} todos to {}",
+                            todos.len(),
+                            self.todos.len(),
+                            file
+                        ),
+                    ),
+                    Ok(_) => self.notify(
+                        MessageLevel::Info,
+                        format!("Exported {} todos to {}", todos.len(), file),
+                    ),
+                    Err(e) => {
+                        self.notify(MessageLevel::Error, format!("Error: {}", e));
+                        return CommandOutcome::Failed;
+                    }
+                }
+            }
+            ["export"] => {
+                self.notify(
+                    MessageLevel::Error,
+                    "Usage: :export <filename> (.txt, .md, .json, .csv or .html)",
+                );
+                return CommandOutcome::Failed;
+            }
+            ["checkhealth"] => {
+                let size = fs::metadata(&self.current_file)
+                    .map(|m| format_size(m.len()))
+                    .unwrap_or_else(|_| "not saved yet".to_string());
+                self.notify(
+                    MessageLevel::Info,
+                    format!(
+                        "Colors: detected {} (COLORTERM={}, TERM={}), using {} │ {}: {}{}",
+                        self.detected_color_support.name(),
+                        std::env::var("COLORTERM").unwrap_or_default(),
+                        std::env::var("TERM").unwrap_or_default(),
+                        self.options.color_support.name(),
+                        self.current_file.display(),
+                        size,
+                        if self.options.compact_json {
+                            ", compact JSON"
+                        } else {
+                            ""
+                        }
+                    ),
+                );
+            }
+            ["backups"] => {
+                let backups = self.list_backups();
+                if backups.is_empty() {
+                    self.notify(
+                        MessageLevel::Info,
+                        format!(
+                            "No backups of {} in {}",
+                            self.current_file.display(),
+                            self.backup_dir.display()
+                        ),
+                    );
+                } else {
+                    let mut lines =
+                        vec![format!("In {}:", self.backup_dir.display()), String::new()];
+                    for name in &backups {
+                        let size = fs::metadata(self.backup_dir.join(name))
+                            .map(|m| format_size(m.len()))
+                            .unwrap_or_default();
+                        lines.push(format!("  {}  {}", name, size));
+                    }
+                    lines.push(String::new());
+                    lines.push(":restore <name> loads one (u to undo)".to_string());
+                    self.show_popup("Backups", &lines.join("\n"));
+                    self.notify(MessageLevel::Info, format!("{} backups", backups.len()));
+                }
+            }
+            ["restore"] => {
+                self.notify(MessageLevel::Error, "Usage: :restore <name> (see :backups)");
+                return CommandOutcome::Failed;
+            }
+            ["restore", ..] => {
+                let name = Self::command_arg(input).unwrap_or_default();
+                match self.restore_backup(&name) {
+                    Ok(count) => {
+                        self.notify(
+                            MessageLevel::Info,
+                            format!("Restored {} todos from {} (u to undo)", count, name),
+                        );
+                    }
+                    Err(e) => {
+                        self.notify(MessageLevel::Error, e);
+                        return CommandOutcome::Failed;
+                    }
+                }
+            }
+            ["help"] => self.show_help(),
+            ["stats"] => self.show_stats(),
+            ["registers" | "reg"] => self.show_registers(),
+            ["messages" | "mes"] => self.show_messages(),
+            ["messages" | "mes", "clear"] => {
+                self.message_log.clear();
+                self.message.clear();
+            }
+            ["editall"] => self.edit_all_pending = true,
+            ["alias" | "alias!"] => self.show_aliases(),
+            ["alias" | "alias!", ..] => {
+                let args = Self::command_arg(input).unwrap_or_default();
+                match self.define_alias(&args, cmd.starts_with("alias!")) {
+                    Ok(message) => self.notify(MessageLevel::Info, message),
+                    Err(e) => {
+                        self.fail(e);
+                        return CommandOutcome::Failed;
+                    }
+                }
+            }
+            ["unalias", name] => match self.remove_alias(name) {
+                Ok(message) => self.notify(MessageLevel::Info, message),
+                Err(e) => {
+                    self.fail(e);
+                    return CommandOutcome::Failed;
+                }
+            },
+            ["unalias", ..] => {
+                self.fail("Usage: :unalias <name>".to_string());
+                return CommandOutcome::Failed;
+            }
+            [name, ..] => {
+                let near: Vec<&str> = self
+                    .aliases
+                    .keys()
+                    .filter(|alias| is_near_miss(name, alias))
+                    .map(String::as_str)
+                    .collect();
+                self.notify(
+                    MessageLevel::Error,
+                    if near.is_empty() {
+                        format!("Unknown command: {}", input)
+                    } else {
+                        format!("Unknown command: {} (alias :{}?)", input, near.join(", :"))
+                    },
+                );
+                return CommandOutcome::Failed;
+            }
+        }
+        CommandOutcome::Done
+    }
+
+    fn clear_completed(&mut self) {
+        self.save_snapshot();
+        let len = self.todos.len();
+        self.todos.retain(|t| !t.completed);
+        let removed = len - self.todos.len();
+        self.filter_todos();
+        self.notify(
+            MessageLevel::Info,
+            format!("Removed {} completed todos", removed),
+        );
+    }
+
+    // Works on the whole list, whatever the search shows. The cursor stays
+    // on its todo, or moves to the one it was folded into.
+    fn dedup_todos(&mut self) {
+        let selected = self
+            .list_state
+            .selected()
+            .and_then(|row| self.filtered_todos.get(row).copied());
+        self.save_snapshot();
+        let len = self.todos.len();
+        let moved = dedup::dedup(&mut self.todos);
+        self.filter_todos();
+        if let Some(row) = selected
+            .and_then(|old| moved.get(old))
+            .and_then(|&new| self.filtered_todos.iter().position(|&i| i == new))
+        {
+            self.list_state.select(Some(row));
+        }
+        self.notify(
+            MessageLevel::Info,
+            format!("Removed {} duplicates", len - self.todos.len()),
+        );
+    }
+
+    // The list as it came back from `:editall`, or why it didn't
+    pub fn finish_edit_all(&mut self, edited: Result<String, String>) {
+        let text = match edited {
+            Ok(text) => text,
+            Err(e) => {
+                self.notify(MessageLevel::Error, e);
+                return;
+            }
+        };
+        let edit = bulkedit::apply(&self.todos, &text, self.today);
+        if edit.is_unchanged() {
+            self.notify(MessageLevel::Info, "No changes");
+            return;
+        }
+        let row = self.list_state.selected().unwrap_or(0);
+        self.save_snapshot();
+        self.todos = edit.todos;
+        self.filter_todos();
+        if !self.filtered_todos.is_empty() {
+            self.list_state
+                .select(Some(row.min(self.filtered_todos.len() - 1)));
+        }
+        self.notify(
+            MessageLevel::Info,
+            format!(
+                "Edited list: {} changed, {} added, {} removed",
+                edit.edited, edit.added, edit.removed
+            ),
+        );
+    }
+
+    // Holds a destructive command until it's answered with y, n or Esc
+    fn ask_confirm(&mut self, confirm: Confirm, question: String) {
+        self.pending_confirm = Some(confirm);
+        // The question has to be seen to be answered
+        self.error_pinned = false;
+        self.notify(MessageLevel::Info, question);
+        self.mode = Mode::Confirm;
+    }
+
+    fn answer_confirm(&mut self, yes: bool) {
+        self.mode = Mode::Normal;
+        match self.pending_confirm.take() {
+            Some(Confirm::ClearCompleted) if yes => self.clear_completed(),
+            Some(Confirm::Dedup) if yes => self.dedup_todos(),
+            _ => self.notify(MessageLevel::Info, "Cancelled, nothing changed"),
+        }
+    }
+
+    // Runs commands in order and stops at the first one that fails or quits,
+    // leaving its message (plus how many were skipped) on the message line.
+    pub fn run_startup_commands(&mut self, commands: &[String]) -> CommandOutcome {
+        for (i, command) in commands.iter().enumerate() {
+            let command = command.trim().trim_start_matches(':');
+            match self.execute_command(command) {
+                CommandOutcome::Done => {}
+                CommandOutcome::Quit => return CommandOutcome::Quit,
+                CommandOutcome::Failed => {
+                    let skipped = commands.len() - i - 1;
+                    if skipped > 0 {
+                        self.notify(
+                            MessageLevel::Error,
+                            format!(
+                                "{} (-c \"{}\" failed, {} remaining skipped)",
+                                self.message, command, skipped
+                            ),
+                        );
+                    }
+                    return CommandOutcome::Failed;
+                }
+            }
+        }
+        CommandOutcome::Done
+    }
+
+    fn show_help(&mut self) {
+        self.popup = Some(TextPopup::new(" Help ", help_text()));
+        self.mode = Mode::Help;
+    }
+
+    // `gx` opens the URL in the selected todo's text or note, asking which
+    // when there are several
+    fn open_todo_url(&mut self) {
+        let Some(i) = self.selected_todo_index() else {
+            self.fail(self.no_selection_message());
+            return;
+        };
+        let todo = &self.todos[i];
+        let mut urls: Vec<String> = Vec::new();
+        let note_urls = todo.note.as_deref().map(find_urls).unwrap_or_default();
+        for url in find_urls(&todo.text).into_iter().chain(note_urls) {
+            if !urls.iter().any(|seen| seen == url) {
+                urls.push(url.to_string());
+            }
+        }
+        match urls.len() {
+            0 => self.notify(MessageLevel::Info, "No URL in this todo or its note"),
+            1 => self.open_url(&urls[0]),
+            _ => {
+                urls.truncate(9);
+                let choices: Vec<String> = urls
+                    .iter()
+                    .enumerate()
+                    .map(|(n, url)| format!("{}: {}", n + 1, truncate(url, 40)))
+                    .collect();
+                self.notify(
+                    MessageLevel::Info,
+                    format!("Open which? {} (Esc to cancel)", choices.join("  ")),
+                );
+                self.url_choices = urls;
+                self.mode = Mode::ChooseUrl;
+            }
+        }
+    }
+
+    fn choose_url(&mut self, choice: Option<usize>) {
+        self.mode = Mode::Normal;
+        let urls = std::mem::take(&mut self.url_choices);
+        match choice.and_then(|n| urls.get(n)) {
+            Some(url) => self.open_url(url),
+            None => self.message.clear(),
+        }
+    }
+
+    fn open_url(&mut self, url: &str) {
+        self.notify(
+            MessageLevel::Info,
+            match open_with_platform(url) {
+                Ok(()) => format!("Opened {}", url),
+                Err(e) => format!("Couldn't open {}: {}", url, e),
+            },
+        );
+    }
+
+    fn show_stats(&mut self) {
+        let stats = compute_stats(&self.todos, self.today);
+        self.popup = Some(TextPopup::new(
+            " Statistics ",
+            stats_lines(&stats, self.today),
+        ));
+        self.mode = Mode::Popup;
+    }
+
+    fn show_registers(&mut self) {
+        let lines = self.registers.lines();
+        if lines.is_empty() {
+            self.notify(MessageLevel::Info, "All registers are empty");
+            return;
+        }
+        self.popup = Some(TextPopup::new(" Registers ", lines));
+        self.mode = Mode::Popup;
+    }
+
+    // :alias with nothing after it
+    fn show_aliases(&mut self) {
+        if self.aliases.is_empty() {
+            self.notify(
+                MessageLevel::Info,
+                "No aliases (:alias sp sort priority adds one)",
+            );
+            return;
+        }
+        let width = self
+            .aliases
+            .keys()
+            .map(|name| name.len())
+            .max()
+            .unwrap_or(0);
+        let mut lines: Vec<String> = self
+            .aliases
+            .iter()
+            .map(|(name, command)| {
+                let shadows = if COMMAND_NAMES.contains(&name.as_str()) {
+                    "  (replaces the built-in)"
+                } else {
+                    ""
+                };
+                format!("  :{:width$}  {}{}", name, command, shadows, width = width)
+            })
+            .collect();
+        lines.push(String::new());
+        lines.push(":alias name command adds one, :unalias name removes it".to_string());
+        self.show_popup("Aliases", &lines.join("\n"));
+        self.notify(
+            MessageLevel::Info,
+            format!("{} aliases", self.aliases.len()),
+        );
+    }
+
+    // :alias sp shows one alias, :alias sp sort priority defines it and
+    // saves it to the config file
+    fn define_alias(&mut self, args: &str, force: bool) -> Result<String, String> {
+        let args = args.trim();
+        let (name, command) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+        let name = name.to_lowercase();
+        let command = command.trim();
+        if command.is_empty() {
+            return match self.aliases.get(&name) {
+                Some(command) => Ok(format!(":{} is :{}", name, command)),
+                None => Err(format!("No alias :{}", name)),
+            };
+        }
+        check_alias(&name, force)?;
+        self.aliases.insert(name.clone(), command.to_string());
+        let message = format!(":{} is now :{}", name, command);
+        Ok(match self.save_alias(&name) {
+            Ok(()) => message,
+            Err(e) => format!("{}, but it couldn't be saved: {}", message, e),
+        })
+    }
+
+    fn remove_alias(&mut self, name: &str) -> Result<String, String> {
+        let name = name.to_lowercase();
+        if self.aliases.remove(&name).is_none() {
+            return Err(format!("No alias :{}", name));
+        }
+        let message = format!("Removed :{}", name);
+        Ok(match self.save_alias(&name) {
+            Ok(()) => message,
+            Err(e) => format!("{}, but the config couldn't be saved: {}", message, e),
+        })
+    }
+
+    // Writes `name` to the config's [aliases] as it now stands (dropping it
+    // if it's gone), keeping the rest of the file as it was
+    fn save_alias(&self, name: &str) -> io::Result<()> {
+        let forced = format!("{}!", name);
+        let command = self.aliases.get(name).map(String::as_str);
+        let shadows = COMMAND_NAMES.contains(&name);
+        self.update_config(|config| {
+            let config =
+                crate::config::set_value(config, "aliases", name, command.filter(|_| !shadows));
+            crate::config::set_value(&config, "aliases", &forced, command.filter(|_| shadows))
+        })
+    }
+
+    // Rewrites the config file with `edit`, creating it if need be. Without
+    // a config file (as in tests), nothing is written.
+    fn update_config(&self, edit: impl FnOnce(&str) -> String) -> io::Result<()> {
+        let Some(path) = &self.config_path else {
+            return Ok(());
+        };
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, edit(&contents))
+    }
+
+    fn show_popup(&mut self, title: &str, text: &str) {
+        self.popup = Some(TextPopup::new(
+            title,
+            text.lines().map(str::to_string).collect(),
+        ));
+        self.mode = Mode::Popup;
+    }
+
+    fn close_popup(&mut self) {
+        self.popup = None;
+        self.mode = Mode::Normal;
+    }
+
+    fn edit_todo(&mut self) {
+        if let Some(idx) = self.list_state.selected()
+            && let Some(&todo_idx) = self.filtered_todos.get(idx)
+            && todo_idx < self.todos.len()
+        {
+            // Shown with its priority, due date and fields so saving
+            // without touching them keeps them
+            self.input.set(&self.todos[todo_idx].input_text());
+            self.mode = Mode::Insert;
+            self.list_state.select(Some(idx));
+            self.is_editing = true;
+        } else {
+            self.fail(self.no_selection_message());
+        }
+    }
+
+    fn save_edited_todo(&mut self) {
+        if self.input.text().trim().is_empty() {
+            // Empty text means delete the todo (vim-like behavior)
+            if let Some(idx) = self.list_state.selected()
+                && let Some(&todo_idx) = self.filtered_todos.get(idx)
+                && todo_idx < self.todos.len()
+            {
+                self.save_snapshot();
+                self.todos.remove(todo_idx);
+                self.notify(MessageLevel::Info, "TODO deleted (empty text)");
+                self.filter_todos();
+
+                // Adjust selection after deletion
+                if !self.filtered_todos.is_empty() {
+                    let new_idx = idx.min(self.filtered_todos.len() - 1);
+                    self.list_state.select(Some(new_idx));
+                } else {
+                    self.list_state.select(None);
+                }
+            }
+            self.mode = Mode::Normal;
+            self.input.clear();
+            self.is_editing = false;
+            return;
+        }
+
+        if let Some(idx) = self.list_state.selected()
+            && let Some(&todo_idx) = self.filtered_todos.get(idx)
+            && todo_idx < self.todos.len()
+        {
+            self.save_snapshot();
+            self.todos[todo_idx].apply_input(&self.input.text());
+            self.notify(MessageLevel::Info, "TODO updated");
+            self.last_action = Some(LastAction {
+                action: Action::Edit(self.input.text()),
+                rows: 1,
+            });
+        }
+        self.mode = Mode::Normal;
+        self.input.clear();
+        self.is_editing = false;
+    }
+
+    /// Copies the todo under the cursor (or the visual selection) to the
+    /// unnamed register and `"0`, or the register named with `"`.
+    pub fn yank_todo(&mut self) {
+        if self.register == Some('+') {
+            self.yank_to_system();
+            return;
+        }
+        let indices = self.get_selected_indices();
+        if indices.is_empty() {
+            self.fail(self.no_selection_message());
+            return;
+        }
+        if let Err(e) = Registers::check_writable(self.register) {
+            self.fail(e);
+            return;
+        }
+
+        let yanked = indices
+            .iter()
+            .filter_map(|&idx| self.filtered_todos.get(idx))
+            .filter_map(|&i| self.todos.get(i).cloned())
+            .collect();
+        self.registers.store(self.register, yanked, false);
+
+        let into = match self.register {
+            Some(name) => format!(" into \"{}", name),
+            None => String::new(),
+        };
+        self.notify(
+            MessageLevel::Info,
+            if indices.len() == 1 {
+                format!("TODO yanked{}", into)
+            } else {
+                format!("{} todos yanked{}", indices.len(), into)
+            },
+        );
+
+        if self.mode == Mode::Visual {
+            self.mode = Mode::Normal;
+            self.visual_start = None;
+        }
+    }
+
+    /// p pastes below the selected todo and P above it; 3p pastes the
+    /// clipboard three times over. gp pastes below as fresh pending todos,
+    /// e.g. to reuse a block of finished items as a template, leaving the
+    /// register itself as is. `"ap` pastes register a instead of the
+    /// unnamed one.
+    pub fn paste_todos(&mut self, above: bool, reset: bool, count: usize) {
+        let register = self.register;
+        if register == Some('+') {
+            self.paste_from_system();
+            return;
+        }
+        let todos = self.registers.get(register);
+        if todos.is_empty() {
+            self.fail(match register {
+                Some(name) => format!("Register {} is empty", name),
+                None => "Nothing to paste".to_string(),
+            });
+            return;
+        }
+
+        let pasted: Vec<Todo> = std::iter::repeat_n(todos, count)
+            .flatten()
+            .map(|todo| {
+                let mut todo = todo.clone();
+                if reset {
+                    todo.set_completed(false, self.today);
+                }
+                todo
+            })
+            .collect();
+        self.save_snapshot();
+        let insert_pos = self.paste_position(above);
+        let pasted_len = pasted.len();
+        self.todos.splice(insert_pos..insert_pos, pasted);
+
+        self.filter_todos();
+        self.select_todo(insert_pos);
+        self.flash((insert_pos..insert_pos + pasted_len).collect());
+        self.notify(
+            MessageLevel::Info,
+            if reset {
+                format!("Pasted {} todos (reset to pending)", pasted_len)
+            } else {
+                format!("Pasted {} todos", pasted_len)
+            },
+        );
+        self.last_action = Some(LastAction {
+            action: Action::Paste {
+                above,
+                reset,
+                register,
+            },
+            rows: count,
+        });
+    }
+
+    // One todo per non-empty line of pasted text, each read like typed input
+    fn parse_lines(&self, text: &str) -> Vec<Todo> {
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| self.todo_from_input(line))
+            .collect()
+    }
+
+    fn todo_from_input(&self, line: &str) -> Todo {
+        let mut todo = Todo {
+            created: Some(self.today),
+            ..Default::default()
+        };
+        todo.apply_input(line);
+        todo
+    }
+
+    // A bracketed paste from the terminal goes into whatever input is open.
+    // Several lines pasted while adding a todo become one todo each.
+    fn paste_text(&mut self, text: &str) {
+        if let Some(input) = &mut self.quick_add {
+            input.paste(text, false);
+            return;
+        }
+        match self.mode {
+            Mode::Insert
+                if !self.is_editing
+                    && text.lines().filter(|line| !line.trim().is_empty()).count() > 1 =>
+            {
+                self.add_pasted_lines(text)
+            }
+            Mode::Insert => self.input.paste(text, false),
+            Mode::Command => self.command_input.paste(text, false),
+            Mode::Search => {
+                self.search_query.paste(text, false);
+                self.request_search();
+            }
+            Mode::NoteEdit => self.note_input.paste(text, true),
+            _ => {}
+        }
+    }
+
+    // Appends the pasted lines as one undo step, leaving anything already
+    // typed in the input for Enter
+    fn add_pasted_lines(&mut self, text: &str) {
+        let todos = self.parse_lines(text);
+        self.save_snapshot();
+        let start = self.todos.len();
+        let count = todos.len();
+        self.todos.extend(todos);
+        self.filter_todos();
+        self.flash((start..start + count).collect());
+        if !self.filtered_todos.is_empty() {
+            self.list_state.select(Some(self.filtered_todos.len() - 1));
+        }
+        self.notify(MessageLevel::Info, format!("Pasted {} todos", count));
+    }
+
+    // Pasted todos go just below the selected todo, or above it, in the
+    // full list whatever the filter shows. With nothing selected they go at
+    // the end, or the top when pasting above.
+    fn paste_position(&self, above: bool) -> usize {
+        let selected = self
+            .list_state
+            .selected()
+            .and_then(|i| self.filtered_todos.get(i));
+        match (selected, above) {
+            (Some(&i), true) => i,
+            (Some(&i), false) => i + 1,
+            (None, true) => 0,
+            (None, false) => self.todos.len(),
+        }
+    }
+
+    // Moves the cursor onto the todo at `index`, if the filter shows it
+    fn select_todo(&mut self, index: usize) {
+        if let Some(row) = self.filtered_todos.iter().position(|&i| i == index) {
+            self.list_state.select(Some(row));
+        }
+    }
+
+    // On X11 and Wayland copied text is served by the process that copied
+    // it, so the clipboard stays open once used rather than being dropped
+    fn system_clipboard(&mut self) -> Result<&mut arboard::Clipboard, String> {
+        let clipboard = match self.system_clipboard.take() {
+            Some(clipboard) => clipboard,
+            None => arboard::Clipboard::new()
+                .map_err(|e| format!("System clipboard unavailable: {}", e))?,
+        };
+        Ok(self.system_clipboard.insert(clipboard))
+    }
+
+    // "+y or :yank+: copies the selected todos' text to the OS clipboard, one
+    // per line. The internal clipboard used by p is left as is.
+    fn yank_to_system(&mut self) {
+        let indices = self.get_selected_indices();
+        if indices.is_empty() {
+            self.fail(self.no_selection_message());
+            return;
+        }
+
+        let text = indices
+            .iter()
+            .filter_map(|&idx| self.filtered_todos.get(idx))
+            .filter_map(|&i| self.todos.get(i))
+            .map(|todo| todo.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let copied = self.system_clipboard().and_then(|clipboard| {
+            clipboard
+                .set_text(text)
+                .map_err(|e| format!("Couldn't copy to the system clipboard: {}", e))
+        });
+        match copied {
+            Ok(()) if indices.len() == 1 => {
+                self.notify(MessageLevel::Info, "TODO copied to the system clipboard")
+            }
+            Ok(()) => self.notify(
+                MessageLevel::Info,
+                format!("{} todos copied to the system clipboard", indices.len()),
+            ),
+            Err(e) => self.notify(MessageLevel::Error, e),
+        }
+
+        if self.mode == Mode::Visual {
+            self.mode = Mode::Normal;
+            self.visual_start = None;
+        }
+    }
+
+    // "+p or :paste+: each non-empty line on the OS clipboard becomes a todo
+    // below the selection, read the same way as typed input
+    fn paste_from_system(&mut self) {
+        let text = match self.system_clipboard().and_then(|clipboard| {
+            clipboard.get_text().map_err(|e| match e {
+                arboard::Error::ContentNotAvailable => {
+                    "No text on the system clipboard".to_string()
+                }
+                e => format!("Couldn't read the system clipboard: {}", e),
+            })
+        }) {
+            Ok(text) => text,
+            Err(e) => {
+                self.notify(MessageLevel::Error, e);
+                return;
+            }
+        };
+
+        let todos = self.parse_lines(&text);
+        if todos.is_empty() {
+            self.notify(MessageLevel::Info, "No text on the system clipboard");
+            return;
+        }
+
+        self.save_snapshot();
+        let insert_pos = self.paste_position(false);
+        let count = todos.len();
+        self.todos.splice(insert_pos..insert_pos, todos);
+        self.filter_todos();
+        self.select_todo(insert_pos);
+        self.flash((insert_pos..insert_pos + count).collect());
+        self.notify(
+            MessageLevel::Info,
+            format!("Pasted {} todos from the system clipboard", count),
+        );
+    }
+}
+
+/// What a key asked of the event loop.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AppExit {
+    Quit,
+}
+
+// Whether `key` is a digit of a count after the operator in `pending`; a
+// 0 only carries on a count already started, like vim
+fn counts_after(pending: &str, key: KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Char('1'..='9') => true,
+        KeyCode::Char('0') => pending.ends_with(|c: char| c.is_ascii_digit()),
+        _ => false,
+    }
+}
+
+// Paging (Ctrl+d/u/f/b, PageUp/PageDown), H/M/L and zz
+fn is_view_motion(key: KeyEvent, pending: &str) -> bool {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Char('d' | 'u' | 'f' | 'b') => ctrl,
+        KeyCode::PageDown | KeyCode::PageUp | KeyCode::Char('H' | 'M' | 'L') => true,
+        KeyCode::Char('z') => pending == "z",
+        _ => false,
+    }
+}
+
+/// Applies one key event to `app` in whatever mode it's in. Key sequences
+/// (`gg`, `dd`, `"+y`) carry over between calls through the app until
+/// finished, broken by another key or cancelled with Esc.
+/// Returns [`AppExit::Quit`] when the key quit, e.g. `q` or `:wq`.
+pub fn handle_key(app: &mut App, key: KeyEvent) -> Option<AppExit> {
+    // Only process key press events, not release
+    if key.kind != KeyEventKind::Press {
+        return None;
+    }
+    app.error_pinned = false;
+    // Keys typed while recording go into the macro, but not the keys a
+    // macro plays back
+    if !app.replaying
+        && let Some((_, keys)) = &mut app.recording
+    {
+        keys.push(key);
+    }
+    let typed = app
+        .pending
+        .take()
+        .map(|pending| pending.keys)
+        .unwrap_or_default();
+    let mut pending = typed.as_str();
+    // `"a` names the register for the next yank, delete or paste
+    if let Some(name) = typed
+        .strip_prefix('"')
+        .filter(|name| name.chars().count() == 1)
+    {
+        app.register = name.chars().next();
+        pending = "";
+    }
+    let count = app.repeat_count;
+
+    // Ctrl+C stops a running shell command rather than anything else
+    if key.code == KeyCode::Char('c')
+        && key.modifiers.contains(KeyModifiers::CONTROL)
+        && app.shell_job.is_some()
+    {
+        app.cancel_shell();
+        return None;
+    }
+
+    if let Some(input) = &mut app.quick_add {
+        match key.code {
+            KeyCode::Esc => app.quick_add = None,
+            KeyCode::Enter => app.submit_quick_add(),
+            _ => {
+                edit_text(input, key);
+            }
+        }
+        return None;
+    }
+    if key.code == KeyCode::Char('n')
+        && key.modifiers.contains(KeyModifiers::CONTROL)
+        && matches!(app.mode, Mode::Normal | Mode::Visual | Mode::Search)
+    {
+        app.open_quick_add();
+        return None;
+    }
+
+    // In Normal mode an operator takes the motion after it
+    let mut took_motion = false;
+    if app.mode == Mode::Normal && key.code != KeyCode::Esc {
+        match app.operator_key(pending, key) {
+            Some(rest) => pending = rest,
+            None => took_motion = true,
+        }
+    }
+
+    // Esc drops a half-typed sequence or count and nothing else
+    if key.code == KeyCode::Esc
+        && matches!(app.mode, Mode::Normal | Mode::Visual)
+        && (!pending.is_empty() || app.repeat_count > 0 || app.register.is_some())
+    {
+        app.repeat_count = 0;
+        app.register = None;
+        return None;
+    }
+
+    match app.mode {
+        Mode::Normal => match key.code {
+            _ if took_motion => {}
+            // The key after `"` names a register rather than doing anything
+            KeyCode::Char(_) if pending == "\"" => {}
+            KeyCode::Char('q') if pending.is_empty() && key.modifiers.is_empty() => {
+                if app.replaying {
+                    app.fail("Can't record while a macro plays".to_string());
+                } else if app.recording.is_some() {
+                    app.stop_recording();
+                    return None;
+                }
+                // Otherwise it waits for the register to record into
+            }
+            // `qA` appends to macro a
+            KeyCode::Char(c) if pending == "q" && c.is_ascii_alphabetic() => {
+                let name = c.to_ascii_lowercase();
+                let keys = if c.is_ascii_uppercase() {
+                    app.macros.get(&name).cloned().unwrap_or_default()
+                } else {
+                    Vec::new()
+                };
+                app.recording = Some((name, keys));
+                app.message.clear();
+            }
+            _ if pending == "q" => {
+                if app.q_quits() && app.run_key_action(KeyAction::Quit) {
+                    return Some(AppExit::Quit);
+                }
+            }
+            KeyCode::Char(c) if pending == "@" => {
+                let count = std::mem::take(&mut app.repeat_count).max(1);
+                if let Some(exit) = play_macro(app, c, count) {
+                    return Some(exit);
+                }
+            }
+            KeyCode::Esc if !app.search_query.is_empty() => {
+                app.clear_search();
+                app.notify(MessageLevel::Info, "Search cleared");
+            }
+            KeyCode::Char(c @ '1'..='9') => app.push_count_digit(c),
+            KeyCode::Char(c @ ('A' | 'B' | 'C')) if pending == "z" => {
+                app.set_priority_filter(Some(c));
+            }
+            KeyCode::Char('a') if pending == "z" => app.set_priority_filter(None),
+            KeyCode::Char('p') if pending == "g" => {
+                let count = std::mem::take(&mut app.repeat_count).max(1);
+                app.paste_todos(false, true, count);
+            }
+            KeyCode::Char('x') if pending == "g" => app.open_todo_url(),
+            _ if is_view_motion(key, pending) => {
+                let count = std::mem::take(&mut app.repeat_count);
+                app.view_motion(key, count);
+            }
+            _ => match app.keymap.action(key, pending) {
+                Some(action) => {
+                    if app.run_key_action(action) {
+                        return Some(AppExit::Quit);
+                    }
+                }
+                None => match key.code {
+                    // 5G and 5gg go to the fifth row, like vim
+                    KeyCode::Char('G') => match std::mem::take(&mut app.repeat_count) {
+                        0 => app.jump_to_row(usize::MAX),
+                        count => app.jump_to_row(count),
+                    },
+                    KeyCode::Char('g') if pending == "g" => {
+                        let count = std::mem::take(&mut app.repeat_count);
+                        app.jump_to_row(count.max(1));
+                    }
+                    // Only jump when not building a number (e.g., "10j")
+                    KeyCode::Char('0')
+                        if app.repeat_count == 0 && !app.filtered_todos.is_empty() =>
+                    {
+                        app.list_state.select(Some(0));
+                    }
+                    KeyCode::Char('$') if !app.filtered_todos.is_empty() => {
+                        app.list_state.select(Some(app.filtered_todos.len() - 1));
+                    }
+                    KeyCode::Char('V') => app.start_visual(),
+                    KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app.select_all()
+                    }
+                    KeyCode::Down => app.next(),
+                    KeyCode::Up => app.previous(),
+                    _ => {}
+                },
+            },
+        },
+        Mode::Insert => match key.code {
+            KeyCode::Esc if app.options.esc_commits => {
+                app.mode = Mode::Normal;
+                if !app.input.is_empty() {
+                    if app.is_editing {
+                        app.save_edited_todo();
+                    } else {
+                        app.add_todo();
+                    }
+                }
+                app.input.clear();
+                app.is_editing = false;
+            }
+            KeyCode::Esc => app.cancel_insert(),
+            KeyCode::Enter => {
+                if app.is_editing {
+                    app.save_edited_todo();
+                } else {
+                    app.add_todo();
+                }
+            }
+            _ => {
+                edit_text(&mut app.input, key);
+            }
+        },
+        Mode::Command => match key.code {
+            KeyCode::Esc => {
+                app.mode = Mode::Normal;
+                app.command_input.clear();
+                app.command_range = None;
+            }
+            KeyCode::Up => app.recall_command(true),
+            KeyCode::Down => app.recall_command(false),
+            KeyCode::Enter => match app.submit_command() {
+                CommandOutcome::Quit => return Some(AppExit::Quit),
+                CommandOutcome::Failed => app.key_failed = true,
+                CommandOutcome::Done => {}
+            },
+            _ => {
+                edit_text(&mut app.command_input, key);
+            }
+        },
+        Mode::Visual => match key.code {
+            KeyCode::Char(_) if pending == "\"" => {}
+            KeyCode::Char(c @ '1'..='9') => app.push_count_digit(c),
+            KeyCode::Char('0') if app.repeat_count > 0 => app.push_count_digit('0'),
+            _ => {
+                // Counts only apply to motions, and any other key drops one
+                let count = std::mem::take(&mut app.repeat_count);
+                let last_row = app.filtered_todos.len().saturating_sub(1);
+                match key.code {
+                    KeyCode::Esc => {
+                        app.mode = Mode::Normal;
+                        app.visual_start = None;
+                    }
+                    _ if app.filtered_todos.is_empty() => {}
+                    KeyCode::Char('j') | KeyCode::Down => app.extend_visual(count.max(1) as isize),
+                    KeyCode::Char('k') | KeyCode::Up => app.extend_visual(-(count.max(1) as isize)),
+                    // 5G goes to the fifth row, like vim
+                    KeyCode::Char('G') if count > 0 => app.jump_to_row(count),
+                    KeyCode::Char('G' | '$') => app.list_state.select(Some(last_row)),
+                    KeyCode::Char('g') if pending == "g" => app.jump_to_row(count.max(1)),
+                    // Keep the count for the second g of `5gg`
+                    KeyCode::Char('g') => app.repeat_count = count,
+                    KeyCode::Char('0') => app.list_state.select(Some(0)),
+                    _ if is_view_motion(key, pending) => app.view_motion(key, count),
+                    KeyCode::Char('o') => app.swap_visual_ends(),
+                    KeyCode::Char(':') => app.start_range_command(),
+                    KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app.select_all()
+                    }
+                    KeyCode::Char('x') => app.toggle_todo(),
+                    KeyCode::Char('d') => app.delete_todo(),
+                    KeyCode::Char('T') => app.postpone_todos(1),
+                    KeyCode::Char('y') => app.yank_todo(),
+                    KeyCode::Char('+') => app.change_priority(PriorityChange::Raise),
+                    KeyCode::Char('-') => app.change_priority(PriorityChange::Lower),
+                    _ => {}
+                }
+            }
+        },
+        Mode::Search => match key.code {
+            KeyCode::Esc => {
+                app.clear_search();
+                app.mode = Mode::Normal;
+            }
+            KeyCode::Enter => app.submit_search(),
+            KeyCode::Up => app.recall_search(true),
+            KeyCode::Down => app.recall_search(false),
+            _ => {
+                let before = app.search_query.text();
+                if edit_text(&mut app.search_query, key) && app.search_query.text() != before {
+                    app.request_search();
+                }
+            }
+        },
+        Mode::NoteEdit => match key.code {
+            KeyCode::Esc => app.cancel_note(),
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => app.save_note(),
+            KeyCode::Enter => app.note_input.newline(),
+            KeyCode::Up => app.note_input.up(),
+            KeyCode::Down => app.note_input.down(),
+            _ => {
+                edit_text(&mut app.note_input, key);
+            }
+        },
+        Mode::Picker => {
+            let navigated = app
+                .picker
+                .as_mut()
+                .is_some_and(|picker| picker.scroll.navigate(key, pending));
+            if !navigated {
+                match key.code {
+                    KeyCode::Enter => app.pick(),
+                    _ => app.dismiss_picker(),
+                }
+            }
+        }
+        Mode::Confirm => match key.code {
+            KeyCode::Char('y' | 'Y') => app.answer_confirm(true),
+            KeyCode::Char('n' | 'N') | KeyCode::Esc => app.answer_confirm(false),
+            _ => {}
+        },
+        Mode::ChooseUrl => match key.code {
+            KeyCode::Char(c @ '1'..='9') => {
+                app.choose_url(Some(c as usize - '1' as usize));
+            }
+            KeyCode::Esc => app.choose_url(None),
+            _ => {}
+        },
+        Mode::Merge => match key.code {
+            KeyCode::Char('k') => app.choose_merge(MergeChoice::KeepMine),
+            KeyCode::Char('t') => app.choose_merge(MergeChoice::TakeTheirs),
+            KeyCode::Char('b') => app.choose_merge(MergeChoice::KeepBoth),
+            KeyCode::Esc => app.cancel_merge(),
+            _ => {}
+        },
+        Mode::Help | Mode::Popup => {
+            if let Some(popup) = &mut app.popup {
+                if popup.scroll.navigate(key, pending) {
+                    // Handled by the shared popup navigation
+                } else {
+                    match key.code {
+                        KeyCode::Char('l') | KeyCode::Right => popup.hscroll += 4,
+                        KeyCode::Char('h') | KeyCode::Left => {
+                            popup.hscroll = popup.hscroll.saturating_sub(4)
+                        }
+                        KeyCode::Esc | KeyCode::Char('q') => app.close_popup(),
+                        _ => {}
+                    }
+                }
+            } else {
+                app.close_popup();
+            }
+        }
+    }
+
+    let pending = if took_motion { typed.as_str() } else { pending };
+    app.pending = app
+        .pending_after(pending, key, count)
+        .map(|keys| PendingKey {
+            keys,
+            since: Instant::now(),
+        });
+    // A named register lasts until the command it was named for is done
+    if app.pending.is_none() && app.repeat_count == 0 {
+        app.register = None;
+    }
+    None
+}
+
+// `@a`: feeds the keys recorded in macro a back through `handle_key`,
+// `count` times over, stopping at the first key that fails. `@@` plays the
+// last macro played.
+fn play_macro(app: &mut App, name: char, count: usize) -> Option<AppExit> {
+    if app.replaying {
+        app.fail("A macro can't play another macro".to_string());
+        return None;
+    }
+    let name = match name {
+        '@' => match app.last_macro {
+            Some(name) => name,
+            None => {
+                app.fail("No macro played yet".to_string());
+                return None;
+            }
+        },
+        name => name.to_ascii_lowercase(),
+    };
+    let Some(keys) = app.macros.get(&name).cloned() else {
+        app.fail(format!("Nothing recorded in @{}", name));
+        return None;
+    };
+    app.last_macro = Some(name);
+
+    app.replaying = true;
+    let mut exit = None;
+    'play: for _ in 0..count {
+        for &key in &keys {
+            app.key_failed = false;
+            exit = handle_key(app, key);
+            if exit.is_some() || app.key_failed {
+                break 'play;
+            }
+        }
+    }
+    app.replaying = false;
+    // A macro that ends halfway through a sequence doesn't leave it open
+    app.pending = None;
+    exit
+}
+
+// Whether `name` can be an alias: a word of letters, digits, `-` or `_`
+// that's not a built-in command, unless `force` (`:alias!`) says so
+fn check_alias(name: &str, force: bool) -> Result<(), String> {
+    let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!(
+            "'{}' can't be an alias; use letters and digits",
+            name
+        ));
+    }
+    let name = name.to_lowercase();
+    if name == "alias" || name == "unalias" {
+        return Err(format!(":{} can't be aliased", name));
+    }
+    if COMMAND_NAMES.contains(&name.as_str()) && !force {
+        return Err(format!(
+            ":{} is a built-in command; :alias! {} (or {}! in the config) replaces it",
+            name, name, name
+        ));
+    }
+    Ok(())
+}
+
+// "Unknown option", with any option names it could be a typo of
+fn unknown_option(name: &str) -> String {
+    let typed = name.strip_prefix("no").unwrap_or(name);
+    let near: Vec<&str> = OPTIONS
+        .iter()
+        .map(|&(option, _, _)| option)
+        .filter(|option| is_near_miss(name, option) || is_near_miss(typed, option))
+        .collect();
+    if near.is_empty() {
+        format!("Unknown option: {} (:set all lists them)", name)
+    } else {
+        format!("Unknown option: {} ({}?)", name, near.join(", "))
+    }
+}
+
+// An unknown command close enough to an alias to be a typo of it
+fn is_near_miss(typed: &str, alias: &str) -> bool {
+    let allowed = if alias.len() > 4 { 2 } else { 1 };
+    strsim::damerau_levenshtein(typed, alias) <= allowed
+}
+
+// One end of a `:1,5d` range
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Address {
+    // A row of the list as shown, counting from 1
+    Row(usize),
+    // `.`, the cursor row
+    Cursor,
+    // `$`
+    Last,
+    // `'<` and `'>`, the ends of the Visual selection
+    VisualStart,
+    VisualEnd,
+}
+
+// An address at the start of `input`, and what follows it
+fn split_address(input: &str) -> Option<(Address, &str)> {
+    let input = input.trim_start();
+    for (prefix, address) in [
+        (".", Address::Cursor),
+        ("$", Address::Last),
+        ("'<", Address::VisualStart),
+        ("'>", Address::VisualEnd),
+    ] {
+        if let Some(rest) = input.strip_prefix(prefix) {
+            return Some((address, rest));
+        }
+    }
+    let digits = input.bytes().take_while(u8::is_ascii_digit).count();
+    if digits == 0 {
+        return None;
+    }
+    let row = input[..digits].parse().unwrap_or(usize::MAX);
+    Some((Address::Row(row), &input[digits..]))
+}
+
+// `1,5d` as typed at the `:` prompt: the first address, the second if
+// there is one, and the command after them
+fn split_range(input: &str) -> Option<(Address, Option<Address>, &str)> {
+    let (first, rest) = split_address(input)?;
+    match rest.trim_start().strip_prefix(',') {
+        Some(rest) => {
+            let (last, rest) = split_address(rest)?;
+            Some((first, Some(last), rest))
+        }
+        None => Some((first, None, rest)),
+    }
+}
+
+// `m0` and `m$` are `m 0` and `m $`
+fn spaced_move(command: &str) -> String {
+    match command.strip_prefix('m') {
+        Some(dest) if split_address(dest).is_some() && !dest.starts_with(' ') => {
+            format!("m {}", dest)
+        }
+        _ => command.to_string(),
+    }
+}
+
+// The commands a `:1,5` range can go in front of
+fn takes_range(command: &str) -> bool {
+    let name = command.split_whitespace().next().unwrap_or_default();
+    matches!(
+        name.to_lowercase().as_str(),
+        "d" | "delete" | "x" | "toggle" | "m" | "move" | "pri" | "export"
+    ) || name.starts_with('!')
+        || ["s", "g", "g!", "v"]
+            .iter()
+            .any(|name| pattern_command(command, name).is_some())
+}
+
+// `s/a/b/` or `%s/a/b/` as typed at the `:` prompt, for `name` "s": whether
+// it's for the whole list, and what follows the name. The delimiter after
+// the name tells `:s/` and `:g/` apart from `:set` or `:goto`.
+fn pattern_command<'a>(input: &'a str, name: &str) -> Option<(bool, &'a str)> {
+    let input = input.trim_start();
+    let (whole, rest) = match input.strip_prefix('%') {
+        Some(rest) => (true, rest),
+        None => (false, input),
+    };
+    let spec = rest.strip_prefix(name)?;
+    spec.starts_with(is_delimiter).then_some((whole, spec))
+}
+
+/// Text pasted into the terminal, as one event rather than a key per char.
+pub fn handle_paste(app: &mut App, text: &str) {
+    app.paste_text(text);
+    app.pending = None;
+    app.register = None;
+}
+
+/// The mouse wheel scrolls popups, pickers and the list. In the list a
+/// click selects the todo under it, and a double-click, a middle click or
+/// a click on the checkbox toggles it. Other mouse events are ignored.
+pub fn handle_mouse(app: &mut App, mouse: MouseEvent) {
+    let scroll = match app.mode {
+        Mode::Help | Mode::Popup => app.popup.as_mut().map(|p| &mut p.scroll),
+        Mode::Picker => app.picker.as_mut().map(|p| &mut p.scroll),
+        _ => None,
+    };
+    if let Some(scroll) = scroll {
+        scroll.scroll_mouse(mouse.kind);
+        return;
+    }
+    if !matches!(app.mode, Mode::Normal | Mode::Visual) {
+        return;
+    }
+    match mouse.kind {
+        MouseEventKind::ScrollDown => app.scroll_view(MOUSE_SCROLL_LINES),
+        MouseEventKind::ScrollUp => app.scroll_view(-MOUSE_SCROLL_LINES),
+        MouseEventKind::Down(button @ (MouseButton::Left | MouseButton::Middle)) => {
+            if let Some((row, on_checkbox)) = app.list_hits.row_at(mouse.column, mouse.row) {
+                let toggle = button == MouseButton::Middle || on_checkbox;
+                app.click_row(row, toggle, Instant::now());
+            }
+        }
+        _ => {}
+    }
+}
+
+// Line-editing keys shared by every text input. Returns false for keys it
+// doesn't handle, which the caller's mode can use for itself.
+fn edit_text(buffer: &mut TextBuffer, key: KeyEvent) -> bool {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let alt = key.modifiers.contains(KeyModifiers::ALT);
+    match key.code {
+        KeyCode::Char('a') if ctrl => buffer.home(),
+        KeyCode::Char('e') if ctrl => buffer.end(),
+        KeyCode::Char('w') if ctrl => buffer.delete_word_back(),
+        KeyCode::Char('u') if ctrl => buffer.delete_to_line_start(),
+        KeyCode::Char('f') if alt => buffer.word_right(),
+        KeyCode::Char('b') if alt => buffer.word_left(),
+        KeyCode::Char('d') if alt => buffer.delete_word(),
+        KeyCode::Right if ctrl || alt => buffer.word_right(),
+        KeyCode::Left if ctrl || alt => buffer.word_left(),
+        KeyCode::Char(_) if ctrl || alt => return false,
+        KeyCode::Char(c) => buffer.insert(c),
+        KeyCode::Backspace => buffer.backspace(),
+        KeyCode::Delete => buffer.delete(),
+        KeyCode::Left => buffer.left(),
+        KeyCode::Right => buffer.right(),
+        KeyCode::Home => buffer.home(),
+        KeyCode::End => buffer.end(),
+        _ => return false,
+    }
+    true
+}
+
+// Fingerprint of a file's contents, to tell our own writes from outside edits
+fn content_hash(contents: &str) -> u64 {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Writes `contents` to a temporary file next to `path`, syncs it and renames
+// it over `path`, so a crash or full disk mid-save leaves the old file intact.
+fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    // Replace the file a symlink points at rather than the link itself
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let dir = match target.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+    let temp = dir.join(format!(".{}.tmp.{}", name, std::process::id()));
+
+    let written = (|| {
+        let mut file = fs::File::create(&temp)?;
+        if let Ok(metadata) = fs::metadata(&target) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()
+    })();
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(io::Error::new(
+            e.kind(),
+            format!("couldn't write temp file {}: {}", temp.display(), e),
+        ));
+    }
+
+    if let Err(e) = replace_file(&temp, &target) {
+        let _ = fs::remove_file(&temp);
+        return Err(io::Error::new(
+            e.kind(),
+            format!(
+                "couldn't rename {} over {}: {}",
+                temp.display(),
+                target.display(),
+                e
+            ),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn replace_file(from: &Path, to: &Path) -> io::Result<()> {
+    fs::rename(from, to)
+}
+
+// Renaming over an existing file can fail on Windows (e.g. when it's
+// read-only or briefly held open by a scanner), so retry once after
+// removing the old file.
+#[cfg(windows)]
+fn replace_file(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if to.exists() => {
+            fs::remove_file(to).map_err(|_| e)?;
+            fs::rename(from, to)
+        }
+        result => result,
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = 1024 * KB;
+    if bytes >= MB {
+        format!("{:.1} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{:.1} KB", bytes as f64 / KB as f64)
+    } else {
+        format!("{} bytes", bytes)
+    }
+}
+
+// `:!cmd`, and the forms that feed todos to a command or read its output
+fn is_shell_command(entry: &str) -> bool {
+    let entry = entry.trim_start();
+    entry.starts_with('!')
+        || entry.starts_with("%!")
+        || ["r ", "read "].iter().any(|read| {
+            entry
+                .strip_prefix(read)
+                .is_some_and(|rest| rest.trim_start().starts_with('!'))
+        })
+}
+
+// Hands `url` to the desktop's opener without waiting for the browser;
+// a thread reaps the opener when it exits
+fn open_with_platform(url: &str) -> io::Result<()> {
+    let mut opener = if cfg!(target_os = "windows") {
+        let mut opener = Command::new("cmd");
+        // The empty argument is start's window title
+        opener.args(["/C", "start", ""]);
+        opener
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    let mut child = opener
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+// Starts `command` in the platform shell, with `input` on its stdin and
+// its output piped back
+fn spawn_shell(command: &str, input: Option<String>) -> io::Result<Child> {
+    let mut shell = if cfg!(target_os = "windows") {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    };
+    let mut child = shell
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // Written from another thread, so a command that prints before it has
+    // read everything can't block on a full pipe while we block on its stdin
+    if let Some((input, mut stdin)) = input.zip(child.stdin.take()) {
+        thread::spawn(move || {
+            let _ = stdin.write_all(input.as_bytes());
+        });
+    }
+    Ok(child)
+}
+
+fn run_shell(command: &str, input: Option<String>) -> io::Result<Output> {
+    spawn_shell(command, input)?.wait_with_output()
+}
+
+// stdout followed by stderr, as a terminal would usually show them
+fn shell_output_text(output: &Output) -> String {
+    let mut text = String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stderr = stderr.trim_end();
+    if !stderr.is_empty() {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(stderr);
+    }
+    text
+}
+
+// None when the command succeeded
+fn exit_status(output: &Output) -> Option<String> {
+    match output.status.code() {
+        Some(0) => None,
+        Some(code) => Some(format!("exit {}", code)),
+        None => Some("killed".to_string()),
+    }
+}
+
+// Numbers compare numerically, anything else as text
+fn compare_meta(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(x), Ok(y)) => x.total_cmp(&y),
+        _ => a.cmp(b),
+    }
+}
+
+fn flip(order: Ordering, reverse: bool) -> Ordering {
+    if reverse { order.reverse() } else { order }
+}
+
+// Compares the sort keys of two todos, putting todos without one last
+// whichever way the rest are ordered
+fn compare_present<T>(
+    a: Option<T>,
+    b: Option<T>,
+    reverse: bool,
+    compare: impl FnOnce(&T, &T) -> Ordering,
+) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => flip(compare(&a, &b), reverse),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+// "today", "yesterday", "3 days ago"; dates after `today` are spelled out
+fn relative_day(date: NaiveDate, today: NaiveDate) -> String {
+    match (today - date).num_days() {
+        0 => "today".to_string(),
+        1 => "yesterday".to_string(),
+        days if days > 1 => format!("{} days ago", days),
+        _ => format!("on {}", date.format("%Y-%m-%d")),
+    }
+}
+
+// The :info line, e.g. "Created 3 days ago, completed yesterday"
+fn date_info(todo: &Todo, today: NaiveDate) -> String {
+    let created = todo
+        .created
+        .map(|date| format!("created {}", relative_day(date, today)));
+    let completed = match todo.completed_on {
+        Some(date) => Some(format!("completed {}", relative_day(date, today))),
+        None if todo.completed => Some("completed, date unknown".to_string()),
+        None => None,
+    };
+    let info = match (created, completed) {
+        (Some(created), Some(completed)) => format!("{}, {}", created, completed),
+        (Some(part), None) | (None, Some(part)) => part,
+        (None, None) => return "No dates recorded for this todo".to_string(),
+    };
+    let mut chars = info.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+// The :stats popup: totals first, then open/done tables by priority and by
+// project
+fn stats_lines(stats: &Stats, today: NaiveDate) -> Vec<String> {
+    const LABEL: usize = 24;
+    let row = |label: &str, value: String| {
+        let label = truncate(label, LABEL);
+        let pad = LABEL.saturating_sub(display_width(&label));
+        format!("  {}{}  {}", label, " ".repeat(pad), value)
+    };
+    let table_row = |label: &str, counts: &Counts| {
+        row(label, format!("{:>5}  {:>5}", counts.open, counts.done))
+    };
+
+    let mut lines = vec![
+        String::new(),
+        row("Todos", stats.total.to_string()),
+        row(
+            "Completed",
+            format!("{} ({}%)", stats.completed, stats.percent_done()),
+        ),
+        row(
+            &format!("Completed last {} days", RECENT_DAYS),
+            stats.completed_recently.to_string(),
+        ),
+    ];
+    if let Some((created, text)) = &stats.oldest_open {
+        lines.push(row(
+            "Oldest open",
+            format!("{} ({})", text, relative_day(*created, today)),
+        ));
+    }
+
+    lines.push(String::new());
+    lines.push(row("Priority", format!("{:>5}  {:>5}", "Open", "Done")));
+    for (priority, counts) in &stats.by_priority {
+        lines.push(table_row(&format!("({})", priority), counts));
+    }
+    lines.push(table_row("none", &stats.no_priority));
+
+    if !stats.by_project.is_empty() {
+        lines.push(String::new());
+        lines.push(row("Project", format!("{:>5}  {:>5}", "Open", "Done")));
+        for (project, counts) in &stats.by_project {
+            lines.push(table_row(project, counts));
+        }
+    }
+    lines
+}
+
+fn help_text() -> Vec<String> {
+    [
+        "",
+        "KEY BINDINGS",
+        "",
+        "Navigation:",
+        "  j / k          Move up/down",
+        "  gg             Go to first todo",
+        "  G              Go to last todo",
+        "  5G / :5        Go to the fifth todo (also 5gg; :$ for the last)",
+        "  0 / $          Jump to first/last",
+        "  3j / 5k        Repeat motion N times",
+        "  Esc            Cancel a count or half-typed dd/gg (a second passes too)",
+        "  Ctrl+d / Ctrl+u  Scroll half a screen down/up",
+        "  Ctrl+f / Ctrl+b  Scroll a screen down/up (PageDown / PageUp)",
+        "  H / M / L      Top, middle or bottom row on screen",
+        "  zz             Center the view on the cursor",
+        "",
+        "Editing:",
+        "  i              Insert new todo",
+        "  Ctrl+N         Quick add to the end (also in Visual and Search)",
+        "  A              Append new todo",
+        "  e              Edit selected todo",
+        "  x / 3x         Toggle completion (of 3 todos)",
+        "  T / 3T         Postpone todo(s) below the other pending ones",
+        "  + / -          Raise/lower priority",
+        "  dd / 3dd       Delete todo(s)",
+        "  J / K          Move todo down/up",
+        "  o              Open note editor (Enter new line, Ctrl+S save, Esc cancel)",
+        "  Tab            Show/hide the note under the todo",
+        "  gx             Open the URL in the todo or its note",
+        "  Enter          Save todo (Insert mode)",
+        "  Esc            Cancel insert/edit",
+        "",
+        "Line editing (any text input):",
+        "  Ctrl+A / Ctrl+E      Start/end of line",
+        "  Alt+F / Alt+B        Next/previous word (also Ctrl+Right/Left)",
+        "  Alt+D                Delete to the end of the next word",
+        "  Ctrl+W / Ctrl+U      Delete word back / to start of line",
+        "  Pasting lines in Insert mode adds one todo per line",
+        "",
+        "Yank/Paste:",
+        "  y / 3y         Yank (copy) todo(s)",
+        "  p / 3p         Paste below current (3 times)",
+        "  P / 3P         Paste above current",
+        "  gp             Paste as pending (uncompleted)",
+        "  \"+y / \"+p      Copy to / paste from the system clipboard",
+        "  \"ay / \"ap      Yank into / paste from register a (\"A appends)",
+        "  \"1p / \"0p      Paste the last delete / the last yank",
+        "  .              Repeat the last change: toggle, delete, postpone, +/-,",
+        "                 add, edit or paste (same number of rows)",
+        "  3.             Repeat it 3 times, undone with one u",
+        "",
+        "Operators (d delete, y yank) with a motion:",
+        "  dG / dgg       To the last / first todo",
+        "  d3j / d3k      The cursor row and the 3 below / above",
+        "  d5G / d5gg     To the fifth todo",
+        "  d3d / 3dd      3 todos from the cursor",
+        "  yG / yj / ...  The same rows, copied; the cursor goes to the first",
+        "  .              Repeats the delete with its motion (dG deletes to the end again)",
+        "",
+        "Mouse:",
+        "  Click          Select a todo",
+        "  Double-click   Toggle it (also middle click or a click on [ ])",
+        "  Wheel          Scroll the list",
+        "",
+        "Undo/Redo:",
+        "  u              Undo",
+        "  Ctrl+r         Redo",
+        "",
+        "Macros:",
+        "  qa ... q       Record keys into macro a (qA appends)",
+        "  @a / 5@a       Play macro a (5 times); stops at an error",
+        "  @@             Play the last macro again",
+        "  q              Quit, once no register follows (or q Enter)",
+        "",
+        "Visual Mode:",
+        "  v / V          Enter visual mode",
+        "  Ctrl+A         Select all (also :selectall)",
+        "  j / k / 10j    Extend selection",
+        "  gg / G / 0 / $ Extend to the first/last todo",
+        "  Ctrl+d/u/f/b, H/M/L, zz  Scroll and jump as in Normal mode",
+        "  o              Go to the other end of the selection",
+        "  x              Toggle selected todos",
+        "  d              Delete selected todos",
+        "  + / -          Raise/lower priority of selected todos",
+        "  :              Run :export, :xall, :dall, :done all... on the selection",
+        "  Esc            Exit visual mode",
+        "",
+        "Quick filter:",
+        "  zA / zB / zC   Show only open (A)/(B)/(C) todos",
+        "  za             Clear the quick filter",
+        "",
+        "Search:",
+        "  /              Start search",
+        "  Enter          Confirm search",
+        "  n / N          Next/previous match",
+        "  Up / Down      Recall earlier searches (while typing)",
+        "  / Enter        Repeat the last search",
+        "  /re:<regex>    Regex search (case-insensitive; (?-i) for exact case)",
+        "  Esc            Clear search (also from Normal mode)",
+        "",
+        "Commands:",
+        "  Up / Down      Recall earlier commands",
+        "  :q             Quit (warns if unsaved, except with --stdin)",
+        "  :q!            Force quit without saving",
+        "  :w             Save",
+        "  :w <file>      Write a copy; keep editing the active file",
+        "  :w! <file>     Same, overwriting an existing file",
+        "  :saveas <file> Save to file and make it the active file",
+        "  :w!            Save even if the file changed on disk",
+        "  :e / :e!       Reload the file from disk (:e! discards changes)",
+        "  :wq            Save and quit",
+        "  :clear         Remove completed todos (asks y/n first)",
+        "  :clear!        Remove completed todos without asking",
+        "  :dedup         Remove repeated todos, keeping notes (asks y/n; ! doesn't)",
+        "  :editall       Edit the whole list in $EDITOR, one todo per line",
+        "  :sort          Sort by completion",
+        "  :sort priority Sort by priority",
+        "  :sort alpha    Sort by text, ignoring case",
+        "  :sort due      Sort by due date, soonest first",
+        "  :sort created  Sort oldest first",
+        "  :sort completed Sort most recently completed first",
+        "  :info          Show when the todo was created and completed",
+        "  :pri A / :pri -  Set or clear priority",
+        "  :1,5d          Delete rows 1-5 (. cursor, $ last, '<,'> selection)",
+        "  :.,$x / :3m0   Toggle, or move below a row; ranges also take pri, export, s, g",
+        "  :sort meta:key Sort by a key:value field",
+        "  :sort due!     Any sort with ! reverses it (missing keys stay last)",
+        "  :filter k:v    Show todos with a field (:filter clears)",
+        "  :set           List changed options (:set all lists every one)",
+        "  :set name?     Show an option; :set! name=value also saves it to the config",
+        "  :set nometa    Hide key:value fields (:set meta shows)",
+        "  :set number    Number the rows (:set nonumber hides)",
+        "  :set rnu       Number rows by distance from the cursor (:set nornu)",
+        "  :set nomouse   Leave the mouse to the terminal (:set mouse)",
+        "  :!cmd          Execute shell command (long output or errors open a popup)",
+        "  :%!cmd         Replace the todos shown with the command's output",
+        "                 (:'<,'>!cmd from Visual filters the selection)",
+        "  :r !cmd        Add a todo per line of the command's output",
+        "  Ctrl+C         Cancel a running :! or :r ! command",
+        "  :open <file>   Open a file (.json or todo.txt .txt); :open! discards changes",
+        "  :file          Show the active file",
+        "  :export <file> Export to .txt, .md, .json, .csv or .html (search results, if any)",
+        "  :export <file>.md group",
+        "                 Export markdown with a heading per priority",
+        "  :expandall     Show every note (:collapseall hides them)",
+        "  :xall          Toggle all search results (also :invert)",
+        "  :toggleall     Complete all results, or reopen them if all are done",
+        "  :done all      Complete all search results",
+        "  :undone all    Reopen all search results",
+        "  :dall          Delete all search results",
+        "  :yank+         Copy to the system clipboard (:paste+ pastes)",
+        "  :retag +a +b   Rename a project (or @a @b context)",
+        "  :s/re/text/gi  Substitute in the todo (:%s all results, :'<,'> selection)",
+        "  :g/re/d        Delete matches (:v/re/d the rest; x toggles, pri A sets priority)",
+        "  :move <n>      Move todo to position n (0 top, $ bottom)",
+        "  :merge <file>  Merge another list, resolving conflicts",
+        "  :read <file>   Insert a file's todos below the cursor (also :r)",
+        "  :import <file> --format gtasks|reminders",
+        "                 Append Google Tasks / Reminders export",
+        "  :import <file>.txt",
+        "                 Replace the list with a todo.txt file",
+        "  :import <file>.csv",
+        "                 Append todos from a CSV file",
+        "  :import <file>.md",
+        "                 Append a markdown file's checklist items",
+        "  :set layout compact|comfortable",
+        "                 One or two rows per todo",
+        "  :set dupecheck off|warn|strict",
+        "                 Warn about or confirm near-duplicates",
+        "  :set esccommits  Esc saves instead of cancelling",
+        "  :set regexsearch",
+        "                 Every search is a regex, no re: needed",
+        "  :set nofiltersearch",
+        "                 / highlights matches instead of filtering",
+        "  :set colors 16|256|true|none|auto",
+        "                 Override detected color support",
+        "  :set truecolor / :set notruecolor",
+        "                 Force or drop 24-bit color",
+        "  :set histignoreshell",
+        "                 Keep :! commands out of history",
+        "  :set compactjson",
+        "                 Save minified JSON (smaller, faster saves)",
+        "  :backups       List backups taken before each save",
+        "  :restore <name>",
+        "                 Load a backup (u to undo)",
+        "  :set backups N Keep the newest N backups (0 turns them off)",
+        "  :set escalate 14d",
+        "                 Raise stale todos a priority every 14 days (off stops)",
+        "  :stats         Show totals, priorities, projects and recent progress",
+        "  :registers     List what each register holds",
+        "  :messages      Show recent messages (:messages clear empties them)",
+        "  :alias sp sort priority",
+        "                 Make :sp run :sort priority (:alias lists, :unalias removes)",
+        "  :checkhealth   Show detected colors and data file size",
+        "  :help          Show this help",
+        "",
+        "Popups (help, stats, shell output, pickers):",
+        "  j / k          Scroll or move (arrow keys and mouse wheel too)",
+        "  Ctrl+d / Ctrl+u",
+        "                 Half a page down / up",
+        "  Space / PageDown / PageUp",
+        "                 A page down / up",
+        "  gg / G         Top / bottom (also Home / End)",
+        "  h / l          Pan across lines too long to wrap",
+        "  Esc / q        Close",
+        "",
+        "Other:",
+        "  ?              Show help",
+        "  Esc            Exit current mode",
+        "",
+        "Colors and Normal-mode keys can be changed in ~/.config/tuido/config.toml",
+        "",
+        "Press Esc to close",
+    ]
+    .into_iter()
+    .map(str::to_string)
+    .collect()
+}
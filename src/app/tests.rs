@@ -0,0 +1,2554 @@
+use super::*;
+
+// An app holding `texts`, cursor on the first. The file is never written:
+// nothing here saves.
+fn app_with(texts: &[&str]) -> App {
+    let mut app = App::new(std::env::temp_dir().join("tuido-test-unsaved.json"));
+    app.todos = texts.iter().map(|text| Todo::new(*text)).collect();
+    app.filter_todos();
+    app.list_state
+        .select((!app.filtered_todos.is_empty()).then_some(0));
+    app
+}
+
+fn press(app: &mut App, code: KeyCode) -> Option<AppExit> {
+    handle_key(app, KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+fn ctrl(app: &mut App, c: char) -> Option<AppExit> {
+    handle_key(app, KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL))
+}
+
+fn keys(app: &mut App, keys: &str) {
+    for c in keys.chars() {
+        press(app, KeyCode::Char(c));
+    }
+}
+
+fn texts(app: &App) -> Vec<&str> {
+    app.todos.iter().map(|todo| todo.text.as_str()).collect()
+}
+
+fn selected_text(app: &App) -> Option<&str> {
+    app.selected_todo_index()
+        .map(|i| app.todos[i].text.as_str())
+}
+
+// A search narrows the list to the todos matching `query`
+fn search_for(app: &mut App, query: &str) {
+    keys(app, "/");
+    keys(app, query);
+    press(app, KeyCode::Enter);
+}
+
+#[test]
+fn add_appends_and_selects_the_new_todo() {
+    let mut app = app_with(&["buy milk"]);
+    keys(&mut app, "i(A) call bob due:2025-03-01");
+    press(&mut app, KeyCode::Enter);
+
+    assert_eq!(texts(&app), ["buy milk", "call bob"]);
+    assert_eq!(app.todos[1].priority, Some('A'));
+    assert_eq!(app.todos[1].due, NaiveDate::from_ymd_opt(2025, 3, 1));
+    assert_eq!(selected_text(&app), Some("call bob"));
+}
+
+#[test]
+fn add_to_an_empty_list() {
+    let mut app = app_with(&[]);
+    assert_eq!(app.selected_todo_index(), None);
+    keys(&mut app, "ifirst");
+    press(&mut app, KeyCode::Enter);
+
+    assert_eq!(texts(&app), ["first"]);
+    assert_eq!(selected_text(&app), Some("first"));
+}
+
+#[test]
+fn add_ignores_blank_input() {
+    let mut app = app_with(&["buy milk"]);
+    keys(&mut app, "i   ");
+    press(&mut app, KeyCode::Enter);
+
+    assert_eq!(texts(&app), ["buy milk"]);
+    assert_eq!(app.message, "Empty todo not added");
+}
+
+#[test]
+fn edit_replaces_the_selected_todo_and_keeps_its_note() {
+    let mut app = app_with(&["buy milk", "call bob"]);
+    app.todos[1].note = Some("about friday".to_string());
+    keys(&mut app, "je");
+    ctrl(&mut app, 'u');
+    keys(&mut app, "(B) call rob");
+    press(&mut app, KeyCode::Enter);
+
+    assert_eq!(texts(&app), ["buy milk", "call rob"]);
+    assert_eq!(app.todos[1].priority, Some('B'));
+    assert_eq!(app.todos[1].note.as_deref(), Some("about friday"));
+    assert_eq!(app.mode, Mode::Normal);
+}
+
+#[test]
+fn quick_add_from_visual_mode_keeps_the_range() {
+    let mut app = app_with(&["a", "b", "c", "d"]);
+    keys(&mut app, "jVj");
+    ctrl(&mut app, 'n');
+    keys(&mut app, "(B) call alice");
+    press(&mut app, KeyCode::Enter);
+
+    assert_eq!(texts(&app), ["a", "b", "c", "d", "call alice"]);
+    assert_eq!(app.todos[4].priority, Some('B'));
+    assert!(app.quick_add.is_none());
+    assert_eq!(app.mode, Mode::Visual);
+    assert_eq!(app.visual_start, Some(1));
+    assert_eq!(app.list_state.selected(), Some(2));
+}
+
+#[test]
+fn quick_add_keeps_the_search_and_the_selection() {
+    let mut app = app_with(&["buy milk", "call bob", "buy bread"]);
+    search_for(&mut app, "buy");
+    keys(&mut app, "j");
+    ctrl(&mut app, 'n');
+    keys(&mut app, "call alice");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(texts(&app)[3], "call alice");
+    assert_eq!(app.search_query.text(), "buy");
+    assert_eq!(app.filtered_todos, [0, 2]);
+    assert_eq!(selected_text(&app), Some("buy bread"));
+
+    // Also while the query is still being typed
+    keys(&mut app, "/bre");
+    ctrl(&mut app, 'n');
+    keys(&mut app, "water plants");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(texts(&app)[4], "water plants");
+    assert_eq!(app.mode, Mode::Search);
+    assert_eq!(app.search_query.text(), "bre");
+    assert_eq!(app.filtered_todos, [2]);
+    assert_eq!(selected_text(&app), Some("buy bread"));
+}
+
+#[test]
+fn edit_shows_the_priority_prefix_and_keeps_it() {
+    let mut app = app_with(&["call bob"]);
+    app.todos[0].priority = Some('A');
+    keys(&mut app, "e");
+    assert_eq!(app.input.text(), "(A) call bob");
+    keys(&mut app, " back");
+    press(&mut app, KeyCode::Enter);
+
+    assert_eq!(texts(&app), ["call bob back"]);
+    assert_eq!(app.todos[0].priority, Some('A'));
+}
+
+#[test]
+fn edit_changes_or_removes_the_priority_prefix() {
+    let mut app = app_with(&["call bob", "buy milk"]);
+    app.todos[0].priority = Some('A');
+    app.todos[1].priority = Some('C');
+    keys(&mut app, "e");
+    press(&mut app, KeyCode::Home);
+    press(&mut app, KeyCode::Right);
+    press(&mut app, KeyCode::Delete);
+    keys(&mut app, "b");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.todos[0].priority, Some('B'));
+
+    keys(&mut app, "je");
+    press(&mut app, KeyCode::Home);
+    for _ in 0..4 {
+        press(&mut app, KeyCode::Delete);
+    }
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(texts(&app), ["call bob", "buy milk"]);
+    assert_eq!(app.todos[1].priority, None);
+}
+
+#[test]
+fn edit_round_trips_a_todo_with_a_note() {
+    let mut app = app_with(&["call bob"]);
+    app.todos[0].priority = Some('A');
+    app.todos[0].due = NaiveDate::from_ymd_opt(2025, 3, 1);
+    app.todos[0].note = Some("number is in the CRM\nask about friday".to_string());
+    let before = app.todos[0].clone();
+    keys(&mut app, "e");
+    press(&mut app, KeyCode::Enter);
+
+    assert_eq!(app.todos[0], before);
+}
+
+#[test]
+fn add_with_emoji_and_cjk_backspaces_whole_graphemes() {
+    let mut app = app_with(&[]);
+    // The family is one grapheme of five chars joined by ZWJs
+    keys(&mut app, "i買い物 👨\u{200d}👩\u{200d}👧");
+    press(&mut app, KeyCode::Backspace);
+    keys(&mut app, "🎉 cafe\u{301}");
+    press(&mut app, KeyCode::Backspace);
+    press(&mut app, KeyCode::Enter);
+
+    assert_eq!(texts(&app), ["買い物 🎉 caf"]);
+}
+
+#[test]
+fn edit_moves_and_deletes_by_grapheme() {
+    let mut app = app_with(&["👍🏽 buy 牛乳"]);
+    keys(&mut app, "e");
+    press(&mut app, KeyCode::Home);
+    press(&mut app, KeyCode::Right);
+    press(&mut app, KeyCode::Backspace);
+    keys(&mut app, "🍵");
+    press(&mut app, KeyCode::End);
+    press(&mut app, KeyCode::Left);
+    press(&mut app, KeyCode::Delete);
+    keys(&mut app, "茶");
+    press(&mut app, KeyCode::Enter);
+
+    assert_eq!(texts(&app), ["🍵 buy 牛茶"]);
+}
+
+#[test]
+fn escape_cancels_an_add_without_touching_history() {
+    let mut app = app_with(&["a"]);
+    keys(&mut app, "ihalf-typed");
+    press(&mut app, KeyCode::Esc);
+
+    assert_eq!(texts(&app), ["a"]);
+    assert_eq!(app.mode, Mode::Normal);
+    assert_eq!(app.message, "Insert cancelled");
+    assert!(app.history.is_empty());
+}
+
+#[test]
+fn escape_cancels_an_edit_and_keeps_the_original() {
+    let mut app = app_with(&["buy milk", "call bob"]);
+    app.todos[0].priority = Some('A');
+    keys(&mut app, "e");
+    keys(&mut app, " and eggs");
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(texts(&app), ["buy milk", "call bob"]);
+    assert_eq!(app.message, "Edit cancelled");
+
+    // Clearing the input first mustn't delete the todo either
+    keys(&mut app, "e");
+    ctrl(&mut app, 'u');
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(texts(&app), ["buy milk", "call bob"]);
+    assert_eq!(app.todos[0].priority, Some('A'));
+    assert!(app.history.is_empty());
+}
+
+#[test]
+fn esccommits_brings_back_escape_saving_the_input() {
+    let mut app = app_with(&["buy milk"]);
+    app.execute_command("set esccommits");
+    keys(&mut app, "icall bob");
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(texts(&app), ["buy milk", "call bob"]);
+    assert_eq!(app.mode, Mode::Normal);
+
+    keys(&mut app, "gge later");
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(texts(&app), ["buy milk later", "call bob"]);
+
+    app.execute_command("set noesccommits");
+    keys(&mut app, "inever mind");
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.todos.len(), 2);
+}
+
+#[test]
+fn edit_on_an_empty_list_does_nothing() {
+    let mut app = app_with(&[]);
+    keys(&mut app, "e");
+
+    assert!(app.todos.is_empty());
+    assert_eq!(app.mode, Mode::Normal);
+}
+
+#[test]
+fn delete_removes_the_selected_todo_into_the_clipboard() {
+    let mut app = app_with(&["buy milk", "call bob", "fix bike"]);
+    keys(&mut app, "jdd");
+
+    assert_eq!(texts(&app), ["buy milk", "fix bike"]);
+    assert_eq!(selected_text(&app), Some("fix bike"));
+    assert_eq!(app.registers.unnamed.len(), 1);
+    assert_eq!(app.registers.unnamed[0].text, "call bob");
+}
+
+#[test]
+fn delete_the_last_todo_moves_the_cursor_up() {
+    let mut app = app_with(&["buy milk", "call bob"]);
+    keys(&mut app, "jdd");
+    assert_eq!(selected_text(&app), Some("buy milk"));
+
+    keys(&mut app, "dd");
+    assert!(app.todos.is_empty());
+    assert_eq!(app.list_state.selected(), None);
+}
+
+#[test]
+fn delete_on_an_empty_list_leaves_the_clipboard_alone() {
+    let mut app = app_with(&["buy milk"]);
+    keys(&mut app, "y");
+    app.todos.clear();
+    app.filter_todos();
+    app.list_state.select(None);
+    keys(&mut app, "dd");
+
+    assert!(app.todos.is_empty());
+    assert_eq!(app.registers.unnamed[0].text, "buy milk");
+}
+
+#[test]
+fn counted_delete_takes_that_many_rows() {
+    let mut app = app_with(&["a", "b", "c", "d"]);
+    keys(&mut app, "j2dd");
+
+    assert_eq!(texts(&app), ["a", "d"]);
+    assert_eq!(app.registers.unnamed.len(), 2);
+}
+
+#[test]
+fn huge_counts_are_capped_instead_of_overflowing() {
+    let mut app = app_with(&["a", "b", "c"]);
+    keys(&mut app, "9999999999999999999999999");
+    assert_eq!(app.repeat_count, MAX_COUNT);
+    keys(&mut app, "j");
+    assert_eq!(app.repeat_count, 0);
+
+    keys(&mut app, "ggv1000000000000000000000000");
+    assert_eq!(app.repeat_count, MAX_COUNT);
+    keys(&mut app, "j");
+    assert_eq!(app.mode, Mode::Visual);
+    assert_eq!(selected_text(&app), Some("c"));
+}
+
+#[test]
+fn toggle_completes_and_reopens() {
+    let mut app = app_with(&["buy milk", "call bob"]);
+    keys(&mut app, "jx");
+    assert!(app.todos[1].completed);
+    assert_eq!(app.todos[1].completed_on, Some(app.today));
+    assert!(!app.todos[0].completed);
+
+    keys(&mut app, "x");
+    assert!(!app.todos[1].completed);
+    assert_eq!(app.todos[1].completed_on, None);
+}
+
+#[test]
+fn toggle_on_an_empty_list_does_nothing() {
+    let mut app = app_with(&[]);
+    keys(&mut app, "x");
+
+    assert!(app.todos.is_empty());
+    assert!(app.history.is_empty());
+}
+
+#[test]
+fn visual_toggle_covers_the_range() {
+    let mut app = app_with(&["a", "b", "c"]);
+    keys(&mut app, "Vjx");
+
+    let done: Vec<bool> = app.todos.iter().map(|todo| todo.completed).collect();
+    assert_eq!(done, [true, true, false]);
+    assert_eq!(app.mode, Mode::Normal);
+}
+
+#[test]
+fn search_filters_and_escape_clears() {
+    let mut app = app_with(&["buy milk", "call bob", "buy bread"]);
+    search_for(&mut app, "buy");
+
+    assert_eq!(app.filtered_todos.len(), 2);
+    assert!(
+        app.filtered_todos
+            .iter()
+            .all(|&i| app.todos[i].text.starts_with("buy"))
+    );
+
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.filtered_todos, [0, 1, 2]);
+}
+
+#[test]
+fn search_ranks_matches_and_selects_the_best_as_you_type() {
+    let mut app = app_with(&["review pull requests", "Pay Rent", "prepare slides"]);
+    // Every "p" starts a word, so the tie keeps list order
+    keys(&mut app, "/p");
+    assert_eq!(app.filtered_todos, [0, 1, 2]);
+    assert_eq!(selected_text(&app), Some("review pull requests"));
+    keys(&mut app, "r");
+    assert_eq!(app.filtered_todos, [2, 1, 0]);
+    assert_eq!(selected_text(&app), Some("prepare slides"));
+    press(&mut app, KeyCode::Backspace);
+    assert_eq!(app.filtered_todos, [0, 1, 2]);
+    assert_eq!(selected_text(&app), Some("review pull requests"));
+
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.filtered_todos, [0, 1, 2]);
+}
+
+#[test]
+fn search_without_matches_leaves_nothing_selected() {
+    let mut app = app_with(&["buy milk", "call bob"]);
+    search_for(&mut app, "zebra");
+
+    assert!(app.filtered_todos.is_empty());
+    assert_eq!(app.selected_todo_index(), None);
+    // Keys that act on the selection are harmless
+    keys(&mut app, "xddy");
+    assert_eq!(texts(&app), ["buy milk", "call bob"]);
+    assert!(app.todos.iter().all(|todo| !todo.completed));
+}
+
+#[test]
+fn no_matches_and_no_todos_get_different_hints() {
+    let app = app_with(&[]);
+    assert_eq!(app.empty_list_hint(), "No todos yet — press i to add one");
+
+    let mut app = app_with(&["buy milk", "call bob"]);
+    search_for(&mut app, "zebra");
+    assert_eq!(
+        app.empty_list_hint(),
+        "No matches for 'zebra' — press Esc to clear search"
+    );
+}
+
+#[test]
+fn changes_with_nothing_selected_say_why() {
+    let mut app = app_with(&[]);
+    for key in ["x", "dd", "e", "o"] {
+        app.message.clear();
+        keys(&mut app, key);
+        assert_eq!(app.message, "No todos yet — press i to add one", "{}", key);
+    }
+
+    let mut app = app_with(&["buy milk", "call bob"]);
+    search_for(&mut app, "zebra");
+    for key in ["x", "dd", "e", "o"] {
+        app.message.clear();
+        keys(&mut app, key);
+        assert_eq!(
+            app.message, "No matches for 'zebra' — press Esc to clear search",
+            "{}",
+            key
+        );
+    }
+    assert_eq!(app.mode, Mode::Normal);
+}
+
+#[test]
+fn clearing_a_search_without_matches_selects_again() {
+    let mut app = app_with(&["buy milk", "call bob", "fix bike"]);
+    keys(&mut app, "j");
+    search_for(&mut app, "zebra");
+    assert_eq!(app.selected_todo_index(), None);
+    // With nothing to go back to, the top of the list
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(selected_text(&app), Some("buy milk"));
+
+    // Backspacing the query back to something that matches, too
+    keys(&mut app, "/zz");
+    assert_eq!(app.selected_todo_index(), None);
+    press(&mut app, KeyCode::Backspace);
+    press(&mut app, KeyCode::Backspace);
+    keys(&mut app, "fix");
+    assert_eq!(selected_text(&app), Some("fix bike"));
+    press(&mut app, KeyCode::Esc);
+    assert!(app.selected_todo_index().is_some());
+}
+
+#[test]
+fn actions_under_a_search_reach_the_right_todo() {
+    let mut app = app_with(&["buy milk", "call bob", "buy bread", "fix bike"]);
+    search_for(&mut app, "bike");
+    assert_eq!(selected_text(&app), Some("fix bike"));
+
+    keys(&mut app, "x");
+    assert!(app.todos[3].completed);
+    assert!(app.todos[..3].iter().all(|todo| !todo.completed));
+
+    keys(&mut app, "dd");
+    assert_eq!(texts(&app), ["buy milk", "call bob", "buy bread"]);
+}
+
+#[test]
+fn priority_filter_hides_other_todos() {
+    let mut app = app_with(&["(A) ship it", "call bob", "(A) fix bug"]);
+    for todo in &mut app.todos {
+        let input = todo.text.clone();
+        todo.apply_input(&input);
+    }
+    keys(&mut app, "zA");
+    assert_eq!(app.filtered_todos, [0, 2]);
+
+    // The second row of the filtered list is the third todo
+    keys(&mut app, "jx");
+    assert!(app.todos[2].completed);
+    assert!(!app.todos[1].completed);
+
+    keys(&mut app, "za");
+    assert_eq!(app.filtered_todos, [0, 1, 2]);
+}
+
+#[test]
+fn undo_reverts_a_change() {
+    let mut app = app_with(&["buy milk", "call bob"]);
+    keys(&mut app, "dd");
+    assert_eq!(texts(&app), ["call bob"]);
+
+    keys(&mut app, "u");
+    assert_eq!(texts(&app), ["buy milk", "call bob"]);
+    assert_eq!(app.message, "Undo: reverted to previous state");
+}
+
+#[test]
+fn undo_with_no_history() {
+    let mut app = app_with(&["buy milk"]);
+    keys(&mut app, "u");
+
+    assert_eq!(texts(&app), ["buy milk"]);
+    assert_eq!(app.message, "Nothing to undo");
+}
+
+#[test]
+fn a_toggle_and_its_reversal_leave_no_undo_steps() {
+    let mut app = app_with(&["a", "b"]);
+    keys(&mut app, "xx");
+    assert!(!app.todos[0].completed);
+    keys(&mut app, "u");
+    assert_eq!(app.message, "Nothing to undo");
+}
+
+#[test]
+fn an_edit_and_its_reversal_leave_no_undo_steps() {
+    let mut app = app_with(&["buy milk"]);
+    for text in ["buy oat milk", "buy milk"] {
+        keys(&mut app, "e");
+        ctrl(&mut app, 'u');
+        keys(&mut app, text);
+        press(&mut app, KeyCode::Enter);
+    }
+    assert_eq!(texts(&app), ["buy milk"]);
+    keys(&mut app, "u");
+    assert_eq!(app.message, "Nothing to undo");
+}
+
+#[test]
+fn distinct_changes_each_keep_an_undo_step() {
+    let mut app = app_with(&["a", "b"]);
+    // Toggles of two different todos, then a delete
+    keys(&mut app, "xjx");
+    keys(&mut app, "kdd");
+    assert_eq!(texts(&app), ["b"]);
+    keys(&mut app, "u");
+    assert_eq!(texts(&app), ["a", "b"]);
+    keys(&mut app, "u");
+    assert!(app.todos[0].completed && !app.todos[1].completed);
+    keys(&mut app, "u");
+    assert!(!app.todos[0].completed);
+    keys(&mut app, "u");
+    assert_eq!(app.message, "Nothing to undo");
+}
+
+#[test]
+fn a_change_that_changes_nothing_leaves_no_undo_step() {
+    let mut app = app_with(&["a", "b"]);
+    keys(&mut app, "x");
+    // Already in order, so the sort is a no-op
+    app.execute_command("sort");
+    let history = app.history.len();
+    app.execute_command("sort");
+    assert_eq!(app.history.len(), history);
+
+    keys(&mut app, "u");
+    assert!(!app.todos[0].completed);
+    keys(&mut app, "u");
+    assert_eq!(app.message, "Nothing to undo");
+}
+
+// Three different changes from ["a", "b", "c"], returning the list before
+// and after each: [start, after 1, after 2, after 3]
+fn make_three_changes(app: &mut App) -> Vec<Vec<Todo>> {
+    let mut states = vec![app.todos.clone()];
+    keys(app, "dd");
+    states.push(app.todos.clone());
+    keys(app, "x");
+    states.push(app.todos.clone());
+    keys(app, "inew");
+    press(app, KeyCode::Enter);
+    press(app, KeyCode::Esc);
+    states.push(app.todos.clone());
+    states
+}
+
+#[test]
+fn undo_and_redo_walk_every_state() {
+    let mut app = app_with(&["a", "b", "c"]);
+    let states = make_three_changes(&mut app);
+    assert_eq!(texts(&app), ["b", "c", "new"]);
+
+    for expected in states[..3].iter().rev() {
+        keys(&mut app, "u");
+        assert_eq!(&app.todos, expected);
+    }
+    keys(&mut app, "u");
+    assert_eq!(app.message, "Nothing to undo");
+    assert_eq!(app.todos, states[0]);
+
+    for expected in &states[1..] {
+        ctrl(&mut app, 'r');
+        assert_eq!(&app.todos, expected);
+    }
+    ctrl(&mut app, 'r');
+    assert_eq!(app.message, "Nothing to redo");
+    assert_eq!(app.todos, states[3]);
+
+    // And back down again from the top of the redo stack
+    for expected in states[..3].iter().rev() {
+        keys(&mut app, "u");
+        assert_eq!(&app.todos, expected);
+    }
+}
+
+#[test]
+fn redo_right_after_one_undo() {
+    let mut app = app_with(&["a", "b"]);
+    keys(&mut app, "dd");
+    keys(&mut app, "u");
+    assert_eq!(texts(&app), ["a", "b"]);
+
+    ctrl(&mut app, 'r');
+    assert_eq!(texts(&app), ["b"]);
+    assert_eq!(app.message, "Redo: reapplied change");
+    ctrl(&mut app, 'r');
+    assert_eq!(app.message, "Nothing to redo");
+}
+
+#[test]
+fn undo_and_redo_alternating() {
+    let mut app = app_with(&["a", "b", "c"]);
+    let states = make_three_changes(&mut app);
+    for _ in 0..3 {
+        keys(&mut app, "u");
+        assert_eq!(app.todos, states[2]);
+        ctrl(&mut app, 'r');
+        assert_eq!(app.todos, states[3]);
+    }
+}
+
+#[test]
+fn a_change_after_undo_drops_the_redo_branch() {
+    let mut app = app_with(&["a", "b", "c"]);
+    let states = make_three_changes(&mut app);
+    keys(&mut app, "uu");
+    assert_eq!(app.todos, states[1]);
+
+    keys(&mut app, "Gdd");
+    let branched = app.todos.clone();
+    assert_eq!(texts(&app), ["b"]);
+    ctrl(&mut app, 'r');
+    assert_eq!(app.message, "Nothing to redo");
+    assert_eq!(app.todos, branched);
+
+    keys(&mut app, "u");
+    assert_eq!(app.todos, states[1]);
+    keys(&mut app, "u");
+    assert_eq!(app.todos, states[0]);
+    keys(&mut app, "u");
+    assert_eq!(app.message, "Nothing to undo");
+
+    ctrl(&mut app, 'r');
+    assert_eq!(app.todos, states[1]);
+    ctrl(&mut app, 'r');
+    assert_eq!(app.todos, branched);
+    ctrl(&mut app, 'r');
+    assert_eq!(app.message, "Nothing to redo");
+}
+
+#[test]
+fn undo_and_redo_restore_the_cursor() {
+    let mut app = app_with(&["a", "b", "c"]);
+    keys(&mut app, "jdd");
+    assert_eq!(selected_text(&app), Some("c"));
+
+    keys(&mut app, "u");
+    assert_eq!(selected_text(&app), Some("b"));
+    ctrl(&mut app, 'r');
+    assert_eq!(selected_text(&app), Some("c"));
+}
+
+#[test]
+fn undo_restores_a_deletion_made_under_a_search() {
+    let mut app = app_with(&["buy milk", "call bob", "buy bread"]);
+    search_for(&mut app, "bread");
+    keys(&mut app, "dd");
+    assert_eq!(texts(&app), ["buy milk", "call bob"]);
+
+    keys(&mut app, "u");
+    assert_eq!(texts(&app), ["buy milk", "call bob", "buy bread"]);
+    // Still searching, so only the restored match is listed
+    assert_eq!(app.filtered_todos, [2]);
+}
+
+#[test]
+fn yank_and_paste_below_and_above() {
+    let mut app = app_with(&["a", "b"]);
+    // Esc drops the pending `y`, so `j` moves rather than yanking both
+    keys(&mut app, "y");
+    press(&mut app, KeyCode::Esc);
+    keys(&mut app, "jp");
+    assert_eq!(texts(&app), ["a", "b", "a"]);
+    assert_eq!(app.list_state.selected(), Some(2));
+
+    keys(&mut app, "kP");
+    assert_eq!(texts(&app), ["a", "a", "b", "a"]);
+    assert_eq!(app.list_state.selected(), Some(1));
+}
+
+#[test]
+fn counted_paste_repeats_the_clipboard() {
+    let mut app = app_with(&["a", "b"]);
+    keys(&mut app, "y3p");
+
+    assert_eq!(texts(&app), ["a", "a", "a", "a", "b"]);
+}
+
+#[test]
+fn named_registers_keep_their_own_todos() {
+    let mut app = app_with(&["a", "b", "c"]);
+    keys(&mut app, "\"ayyjdd");
+    assert_eq!(texts(&app), ["a", "c"]);
+    assert_eq!(app.message, "TODO deleted");
+
+    keys(&mut app, "\"ap");
+    assert_eq!(texts(&app), ["a", "c", "a"]);
+    keys(&mut app, "p");
+    assert_eq!(texts(&app), ["a", "c", "a", "b"]);
+
+    // `"A` appends, and a yank goes to `"0` too
+    keys(&mut app, "gg\"Ayy");
+    assert_eq!(app.message, "TODO yanked into \"A");
+    assert_eq!(app.registers.get(Some('a')).len(), 2);
+    keys(&mut app, "\"b2p");
+    assert_eq!(app.message, "Register b is empty");
+    assert_eq!(app.pending_keys(), "");
+}
+
+#[test]
+fn deletes_shift_through_the_numbered_registers() {
+    let mut app = app_with(&["a", "b", "c"]);
+    keys(&mut app, "dddd");
+    assert_eq!(app.registers.get(Some('1'))[0].text, "b");
+    assert_eq!(app.registers.get(Some('2'))[0].text, "a");
+    keys(&mut app, "\"2P");
+    assert_eq!(texts(&app), ["a", "c"]);
+
+    keys(&mut app, "\"1yy");
+    assert_eq!(app.message, "Register 1 is filled by yanks and deletes");
+    assert_eq!(app.pending_keys(), "");
+}
+
+#[test]
+fn registers_lists_what_each_register_holds() {
+    let mut app = app_with(&["a", "b", "c"]);
+    press(&mut app, KeyCode::Char(':'));
+    keys(&mut app, "registers");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.message, "All registers are empty");
+
+    keys(&mut app, "yyj\"kyjdd");
+    assert_eq!(app.pending_keys(), "");
+    press(&mut app, KeyCode::Char(':'));
+    keys(&mut app, "registers");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.mode, Mode::Popup);
+    assert_eq!(
+        app.popup.as_ref().unwrap().lines,
+        [r#"""  b"#, r#""0  a"#, r#""1  b"#, r#""k  b"#, "    c"]
+    );
+}
+
+#[test]
+fn paste_with_an_empty_clipboard() {
+    let mut app = app_with(&["a"]);
+    keys(&mut app, "p");
+
+    assert_eq!(texts(&app), ["a"]);
+    assert_eq!(app.message, "Nothing to paste");
+}
+
+#[test]
+fn paste_into_an_empty_list() {
+    let mut app = app_with(&["a"]);
+    keys(&mut app, "dd");
+    assert!(app.todos.is_empty());
+
+    keys(&mut app, "p");
+    assert_eq!(texts(&app), ["a"]);
+    assert_eq!(selected_text(&app), Some("a"));
+}
+
+#[test]
+fn paste_under_a_search_lands_after_the_selected_todo() {
+    let mut app = app_with(&["buy milk", "call bob", "buy bread", "fix bike"]);
+    keys(&mut app, "y");
+    search_for(&mut app, "bread");
+    keys(&mut app, "p");
+
+    assert_eq!(
+        texts(&app),
+        ["buy milk", "call bob", "buy bread", "buy milk", "fix bike"]
+    );
+}
+
+#[test]
+fn gp_pastes_completed_todos_as_pending() {
+    let mut app = app_with(&["a"]);
+    keys(&mut app, "xygp");
+
+    assert!(app.todos[0].completed);
+    assert_eq!(app.todos[0].completed_on, Some(app.today));
+    assert!(!app.todos[1].completed);
+    assert_eq!(app.todos[1].completed_on, None);
+    let yanked = &app.registers.get(None)[0];
+    assert!(yanked.completed);
+    assert_eq!(yanked.completed_on, Some(app.today));
+}
+
+#[test]
+fn near_duplicates_are_flagged_above_the_threshold() {
+    let mut app = app_with(&[
+        "email bob about the contract",
+        "renew car insurance",
+        "buy tea",
+        "call mom",
+    ]);
+    app.todos[3].completed = true;
+    let similar = |app: &App, text: &str| app.find_similar(text).map(|i| app.todos[i].text.clone());
+
+    // Typos, case and small rewordings of a pending todo
+    for text in [
+        "email bob about the contract",
+        "Email Bob about the contract",
+        "email bob about teh contract",
+        "email bob about the contracts",
+        "renew car insurence",
+    ] {
+        assert!(similar(&app, text).is_some(), "{}", text);
+    }
+    // Different todos that share words, texts too short to judge, and
+    // completed todos
+    for text in [
+        "email alice about the lease",
+        "renew passport",
+        "buy tea",
+        "call mom",
+        "call mom tonight",
+    ] {
+        assert_eq!(similar(&app, text), None, "{}", text);
+    }
+    assert_eq!(
+        similar(&app, "renew car insurence").as_deref(),
+        Some("renew car insurance")
+    );
+}
+
+#[test]
+fn near_duplicates_warn_or_wait_for_a_second_enter() {
+    let mut app = app_with(&["email bob about the contract"]);
+    keys(&mut app, "iemail bob about teh contract");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.todos.len(), 2);
+    assert_eq!(
+        app.message,
+        "Similar to existing: 'email bob about the contract' (#1)"
+    );
+    press(&mut app, KeyCode::Esc);
+
+    app.execute_command("set dupecheck strict");
+    keys(&mut app, "iemail bob about the contract!");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.todos.len(), 2);
+    assert!(app.message.ends_with("press Enter again to add"));
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.todos.len(), 3);
+
+    press(&mut app, KeyCode::Esc);
+    app.execute_command("set dupecheck off");
+    keys(&mut app, "iemail bob about the contract");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.message, "TODO added");
+}
+
+#[test]
+fn startup_commands_run_in_order() {
+    let mut app = app_with(&["a", "b", "c"]);
+    let commands = ["1d", ":1x"].map(String::from);
+    assert_eq!(app.run_startup_commands(&commands), CommandOutcome::Done);
+    assert_eq!(texts(&app), ["b", "c"]);
+    assert!(app.todos[0].completed);
+}
+
+#[test]
+fn a_failed_startup_command_skips_the_rest() {
+    let mut app = app_with(&["a", "b", "c"]);
+    let commands = ["1x", "bogus", "1d", "wq"].map(String::from);
+    assert_eq!(app.run_startup_commands(&commands), CommandOutcome::Failed);
+    assert_eq!(texts(&app), ["a", "b", "c"]);
+    assert!(app.todos[0].completed);
+    assert_eq!(
+        app.message,
+        "Unknown command: bogus (-c \"bogus\" failed, 2 remaining skipped)"
+    );
+    assert_eq!(app.message_level, MessageLevel::Error);
+}
+
+#[test]
+fn a_startup_command_can_quit() {
+    let mut app = app_with(&["a", "b"]);
+    let commands = ["1d", "q!", "1d"].map(String::from);
+    assert_eq!(app.run_startup_commands(&commands), CommandOutcome::Quit);
+    assert_eq!(texts(&app), ["b"]);
+}
+
+#[test]
+fn quit_needs_a_saved_list() {
+    let mut app = app_with(&["a"]);
+    app.saved_snapshot = Some(app.todos.clone());
+    // `q` waits to see whether a register follows for a macro
+    assert_eq!(press(&mut app, KeyCode::Char('q')), None);
+    assert_eq!(press(&mut app, KeyCode::Enter), Some(AppExit::Quit));
+
+    let mut app = app_with(&["a"]);
+    app.saved_snapshot = Some(app.todos.clone());
+    keys(&mut app, "x");
+    // The event loop refreshes this before every draw
+    app.update_dirty_status();
+    keys(&mut app, "q");
+    assert_eq!(press(&mut app, KeyCode::Enter), None);
+    keys(&mut app, ":q!");
+    assert_eq!(press(&mut app, KeyCode::Enter), Some(AppExit::Quit));
+}
+
+#[test]
+fn a_lone_q_quits_once_it_times_out() {
+    let mut app = app_with(&["a"]);
+    app.saved_snapshot = Some(app.todos.clone());
+    keys(&mut app, "q");
+    assert!(app.expire_pending(Instant::now() + PENDING_TIMEOUT));
+    assert!(app.quit_requested);
+
+    let mut app = app_with(&["a"]);
+    keys(&mut app, "x");
+    app.update_dirty_status();
+    keys(&mut app, "q");
+    assert!(app.expire_pending(Instant::now() + PENDING_TIMEOUT));
+    assert!(!app.quit_requested);
+    assert!(app.message.contains("unsaved changes"));
+}
+
+#[test]
+fn macros_record_and_replay_keys() {
+    let mut app = app_with(&["a", "b", "c", "d", "e", "f"]);
+    keys(&mut app, "qa");
+    assert_eq!(app.recording(), Some('a'));
+    keys(&mut app, "x+jq");
+    assert_eq!(app.recording(), None);
+    assert_eq!(app.message, "Recorded @a (3 keys)");
+
+    keys(&mut app, "@a");
+    assert!(app.todos[1].completed);
+    assert_eq!(app.todos[1].priority, Some('C'));
+    keys(&mut app, "2@a");
+    assert!(app.todos[..4].iter().all(|todo| todo.completed));
+    keys(&mut app, "@@");
+    assert!(app.todos[4].completed);
+    assert_eq!(selected_text(&app), Some("f"));
+}
+
+#[test]
+fn a_macro_stops_at_the_first_key_that_fails() {
+    let mut app = app_with(&["a", "b", "c"]);
+    keys(&mut app, "qbxjq");
+    // Stops at the end of the list instead of wrapping to the top
+    keys(&mut app, "50@b");
+    assert!(app.todos.iter().all(|todo| todo.completed));
+    assert_eq!(selected_text(&app), Some("c"));
+    assert_eq!(app.message, "End of the list");
+
+    keys(&mut app, "@z");
+    assert_eq!(app.message, "Nothing recorded in @z");
+}
+
+#[test]
+fn macros_cant_nest() {
+    let mut app = app_with(&["a", "b", "c"]);
+    keys(&mut app, "qaxq");
+    // Playing a macro while recording is fine; playing one from inside a
+    // macro isn't
+    keys(&mut app, "qbj@aq");
+    assert!(app.todos[1].completed);
+    keys(&mut app, "qaj@bq");
+    keys(&mut app, "gg@a");
+    assert_eq!(app.message, "A macro can't play another macro");
+    assert_eq!(selected_text(&app), Some("b"));
+}
+
+#[test]
+fn two_key_sequences_reset_between_keys() {
+    let mut app = app_with(&["a", "b", "c"]);
+    keys(&mut app, "G");
+    assert_eq!(app.list_state.selected(), Some(2));
+    keys(&mut app, "gg");
+    assert_eq!(app.list_state.selected(), Some(0));
+
+    // A `d` on its own, then another key and `d`, isn't a `dd`
+    keys(&mut app, "d");
+    press(&mut app, KeyCode::Right);
+    keys(&mut app, "d");
+    assert_eq!(app.todos.len(), 3);
+    // Nor is the last `d` of `ddd`
+    press(&mut app, KeyCode::Esc);
+    keys(&mut app, "ddd");
+    assert_eq!(app.todos.len(), 2);
+    keys(&mut app, "d");
+    assert_eq!(app.todos.len(), 1);
+}
+
+#[test]
+fn delete_with_a_motion() {
+    let mut app = app_with(&["a", "b", "c", "d", "e", "f"]);
+    keys(&mut app, "jjdG");
+    assert_eq!(texts(&app), ["a", "b"]);
+    assert_eq!(app.registers.unnamed.len(), 4);
+    assert_eq!(selected_text(&app), Some("b"));
+
+    // The whole range is one undo step
+    keys(&mut app, "u");
+    assert_eq!(texts(&app).len(), 6);
+    keys(&mut app, "Gkdgg");
+    assert_eq!(texts(&app), ["f"]);
+    keys(&mut app, "u");
+
+    keys(&mut app, "ggjd2j");
+    assert_eq!(texts(&app), ["a", "e", "f"]);
+    keys(&mut app, "u");
+    keys(&mut app, "Gd2k");
+    assert_eq!(texts(&app), ["a", "b", "c"]);
+    keys(&mut app, "u");
+
+    // Counts before and after multiply; G and gg take theirs as a row
+    keys(&mut app, "gg2d2j");
+    assert_eq!(texts(&app), ["f"]);
+    keys(&mut app, "u");
+    keys(&mut app, "ggd3G");
+    assert_eq!(texts(&app), ["d", "e", "f"]);
+    keys(&mut app, "u");
+    keys(&mut app, "Gd5gg");
+    assert_eq!(texts(&app), ["a", "b", "c", "d"]);
+    keys(&mut app, "u");
+    keys(&mut app, "ggd3d");
+    assert_eq!(texts(&app), ["d", "e", "f"]);
+}
+
+#[test]
+fn dot_repeats_an_operator_with_its_motion() {
+    let mut app = app_with(&["a", "b", "c", "d", "e", "f", "g"]);
+    keys(&mut app, "dj");
+    keys(&mut app, ".");
+    assert_eq!(texts(&app), ["e", "f", "g"]);
+    keys(&mut app, "jdG");
+    assert_eq!(texts(&app), ["e"]);
+    keys(&mut app, "uu");
+    assert_eq!(texts(&app), ["c", "d", "e", "f", "g"]);
+
+    // dG again reaches the end of the list from wherever the cursor is
+    let mut app = app_with(&["a", "b", "c", "d", "e"]);
+    keys(&mut app, "jjjdG");
+    assert_eq!(texts(&app), ["a", "b", "c"]);
+    keys(&mut app, "k.");
+    assert_eq!(texts(&app), ["a"]);
+}
+
+#[test]
+fn dot_repeats_adds_edits_and_pastes() {
+    let mut app = app_with(&["a"]);
+    keys(&mut app, "ib");
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Esc);
+    keys(&mut app, ".");
+    assert_eq!(texts(&app), ["a", "b", "b"]);
+    assert_eq!(app.message, "repeated: add");
+
+    let mut app = app_with(&["a", "b", "c"]);
+    keys(&mut app, "e");
+    ctrl(&mut app, 'u');
+    keys(&mut app, "(A) x");
+    press(&mut app, KeyCode::Enter);
+    keys(&mut app, "j.");
+    assert_eq!(texts(&app), ["x", "x", "c"]);
+    assert_eq!(app.todos[1].priority, Some('A'));
+
+    let mut app = app_with(&["a", "b"]);
+    keys(&mut app, "yyp.");
+    assert_eq!(texts(&app), ["a", "a", "a", "b"]);
+    assert_eq!(app.message, "repeated: paste");
+}
+
+#[test]
+fn dot_takes_a_count_and_undoes_in_one_step() {
+    let mut app = app_with(&["a", "b", "c", "d"]);
+    keys(&mut app, "+j3.");
+    assert_eq!(app.todos[0].priority, Some('C'));
+    assert!(app.todos[1..].iter().all(|todo| todo.priority == Some('C')));
+    assert_eq!(app.message, "repeated: priority ×3");
+    keys(&mut app, "u");
+    assert!(app.todos[1..].iter().all(|todo| todo.priority.is_none()));
+    assert_eq!(app.todos[0].priority, Some('C'));
+
+    let mut app = app_with(&["a"]);
+    keys(&mut app, "ib");
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Esc);
+    keys(&mut app, "3.");
+    assert_eq!(texts(&app), ["a", "b", "b", "b", "b"]);
+    keys(&mut app, "u");
+    assert_eq!(texts(&app), ["a", "b"]);
+}
+
+#[test]
+fn repeated_deletes_stop_when_the_list_runs_out() {
+    let mut app = app_with(&["a", "b", "c", "d", "e"]);
+    keys(&mut app, "dj5.");
+    assert!(app.todos.is_empty());
+    assert_eq!(app.list_state.selected(), None);
+    assert_eq!(app.message, "repeated: delete ×5");
+    keys(&mut app, "u");
+    assert_eq!(texts(&app), ["c", "d", "e"]);
+}
+
+#[test]
+fn operators_work_on_the_list_as_shown() {
+    let mut app = app_with(&["buy milk", "call bob", "buy bread", "fix bike", "buy eggs"]);
+    search_for(&mut app, "buy");
+    keys(&mut app, "dj");
+    assert_eq!(texts(&app), ["call bob", "fix bike", "buy eggs"]);
+    assert_eq!(
+        app.registers
+            .unnamed
+            .iter()
+            .map(|todo| todo.text.as_str())
+            .collect::<Vec<_>>(),
+        ["buy milk", "buy bread"]
+    );
+}
+
+#[test]
+fn yank_with_a_motion() {
+    let mut app = app_with(&["a", "b", "c", "d"]);
+    keys(&mut app, "jyj");
+    assert_eq!(app.registers.unnamed.len(), 2);
+    assert_eq!(selected_text(&app), Some("b"));
+    keys(&mut app, "Gyk");
+    assert_eq!(app.registers.unnamed.len(), 2);
+    assert_eq!(selected_text(&app), Some("c"));
+    keys(&mut app, "ygg");
+    assert_eq!(app.registers.unnamed.len(), 3);
+    assert_eq!(selected_text(&app), Some("a"));
+    assert_eq!(texts(&app), ["a", "b", "c", "d"]);
+
+    // `3y` yanks three rows at once, and the count carries to a motion
+    keys(&mut app, "3y");
+    assert_eq!(app.registers.unnamed.len(), 3);
+    assert_eq!(app.pending_keys(), "3y");
+    keys(&mut app, "j");
+    assert_eq!(app.registers.unnamed.len(), 4);
+    keys(&mut app, "3yy");
+    assert_eq!(app.registers.unnamed.len(), 3);
+}
+
+#[test]
+fn keys_after_an_operator_that_arent_motions() {
+    let mut app = app_with(&["a", "b"]);
+    keys(&mut app, "y3p");
+    assert_eq!(texts(&app), ["a", "a", "a", "a", "b"]);
+
+    let mut app = app_with(&["a", "b"]);
+    keys(&mut app, "xygp");
+    assert_eq!(texts(&app), ["a", "a", "b"]);
+    assert!(!app.todos[1].completed);
+
+    // `d` then `x` toggles, as it always has
+    keys(&mut app, "dx");
+    assert_eq!(texts(&app).len(), 3);
+    assert!(app.todos[1].completed);
+}
+
+#[test]
+fn a_pending_key_times_out() {
+    let mut app = app_with(&["a", "b", "c"]);
+    keys(&mut app, "d");
+    assert!(!app.expire_pending(Instant::now()));
+    assert_eq!(app.pending_keys(), "d");
+    assert!(app.expire_pending(Instant::now() + PENDING_TIMEOUT));
+    assert_eq!(app.pending_keys(), "");
+    keys(&mut app, "d");
+    assert_eq!(app.todos.len(), 3);
+    keys(&mut app, "d");
+    assert_eq!(app.todos.len(), 2);
+}
+
+#[test]
+fn escape_cancels_a_pending_key_and_count() {
+    let mut app = app_with(&["a", "b", "c", "d"]);
+    search_for(&mut app, "a");
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.filtered_todos.len(), 4);
+
+    keys(&mut app, "3d");
+    assert_eq!(app.pending_keys(), "3d");
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.pending_keys(), "");
+    keys(&mut app, "d");
+    assert_eq!(app.todos.len(), 4);
+
+    // Only the pending key goes; the search stays until the next Esc
+    search_for(&mut app, "b");
+    keys(&mut app, "g");
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.filtered_todos.len(), 1);
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.filtered_todos.len(), 4);
+
+    // In Visual mode too, which the next Esc leaves
+    keys(&mut app, "v2");
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.mode, Mode::Visual);
+    keys(&mut app, "j");
+    assert_eq!(app.list_state.selected(), Some(1));
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.mode, Mode::Normal);
+}
+
+#[test]
+fn pending_keys_show_the_sequence_so_far() {
+    let mut app = app_with(&["a", "b", "c"]);
+    for (typed, shown) in [
+        ("1", "1"),
+        ("2", "12"),
+        ("g", "12g"),
+        ("g", ""),
+        ("\"", "\""),
+        ("+", "\"+"),
+        ("j", ""),
+    ] {
+        keys(&mut app, typed);
+        assert_eq!(app.pending_keys(), shown, "after {}", typed);
+    }
+    // Keys that start nothing, and sequence keys typed as text
+    for typed in ["x", "j", "i", "d", "g", "z"] {
+        keys(&mut app, typed);
+        assert_eq!(app.pending_keys(), "", "after {}", typed);
+    }
+    assert_eq!(app.mode, Mode::Insert);
+}
+
+#[test]
+fn export_format_from_the_file_name() {
+    assert_eq!(
+        ExportFormat::from_file("list.txt", false),
+        Some(ExportFormat::TodoTxt)
+    );
+    assert_eq!(
+        ExportFormat::from_file("notes.md", true),
+        Some(ExportFormat::MarkdownGrouped)
+    );
+    assert_eq!(
+        ExportFormat::from_file("dir.v2/list.json", false),
+        Some(ExportFormat::Json)
+    );
+    assert_eq!(ExportFormat::from_file("list.xml", false), None);
+    assert_eq!(ExportFormat::from_file("list", false), None);
+}
+
+#[test]
+fn export_writes_to_any_writer() {
+    let mut app = app_with(&["ship it", "call bob"]);
+    app.todos[0].priority = Some('A');
+    app.todos[1].note = Some("about \"friday\"".to_string());
+
+    let mut out = Vec::new();
+    export(&app.todos, ExportFormat::TodoTxt, &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "(A) ship it\ncall bob note:\"about \\\"friday\\\"\"\n"
+    );
+
+    let mut out = Vec::new();
+    export(&app.todos, ExportFormat::Json, &mut out).unwrap();
+    let read: Vec<Todo> = serde_json::from_slice(&out).unwrap();
+    assert_eq!(read, app.todos);
+}
+
+#[test]
+fn scratch_list_needs_a_file_name_to_save() {
+    let mut app = App::new(PathBuf::new());
+    app.load_scratch(vec![Todo::new("from a pipe")]);
+    keys(&mut app, "x");
+    assert_eq!(app.execute_command("w"), CommandOutcome::Failed);
+    assert_eq!(app.execute_command("wq"), CommandOutcome::Failed);
+    assert!(app.message.contains("no file name"));
+    // Quitting doesn't lose anything; the caller writes the list out
+    assert_eq!(app.execute_command("q"), CommandOutcome::Quit);
+    keys(&mut app, "q");
+    assert_eq!(press(&mut app, KeyCode::Enter), Some(AppExit::Quit));
+}
+
+#[test]
+fn saveas_gives_a_scratch_list_its_file() {
+    let path = std::env::temp_dir().join(format!("tuido-test-scratch-{}.json", std::process::id()));
+    let mut app = App::new(PathBuf::new());
+    app.load_scratch(vec![Todo::new("from a pipe")]);
+    let outcome = app.execute_command(&format!("saveas {}", path.display()));
+    let saved = App::read_todo_file(&path);
+    let _ = fs::remove_file(&path);
+
+    assert_eq!(outcome, CommandOutcome::Done);
+    assert_eq!(saved.unwrap(), app.todos);
+    assert!(!app.scratch);
+    keys(&mut app, "x");
+    app.update_dirty_status();
+    assert_eq!(app.execute_command("q"), CommandOutcome::Failed);
+}
+
+// An empty directory of its own for a test that writes files
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tuido-test-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn open_bang_discards_changes_that_undo_brings_back() {
+    let dir = scratch_dir("open");
+    let other = dir.join("other.json");
+    fs::write(
+        &other,
+        r#"[{"text": "from the other file", "completed": false}]"#,
+    )
+    .unwrap();
+    let mut app = app_with(&["buy milk", "call bob"]);
+    app.saved_snapshot = Some(app.todos.clone());
+    keys(&mut app, "x");
+
+    let open = format!("open {}", other.display());
+    assert_eq!(app.execute_command(&open), CommandOutcome::Failed);
+    assert!(app.message.starts_with("Unsaved changes in"));
+    assert_eq!(texts(&app), ["buy milk", "call bob"]);
+
+    assert_eq!(
+        app.execute_command(&format!("open! {}", other.display())),
+        CommandOutcome::Done
+    );
+    assert_eq!(texts(&app), ["from the other file"]);
+    assert_eq!(app.current_file, other);
+    assert!(!app.is_dirty);
+
+    // The edit made before :open! is one undo away, now unsaved in the
+    // newly opened file
+    keys(&mut app, "u");
+    assert_eq!(texts(&app), ["buy milk", "call bob"]);
+    assert!(app.todos[0].completed);
+    assert!(app.is_dirty);
+    keys(&mut app, "u");
+    assert!(!app.todos[0].completed);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn write_copies_while_saveas_retargets() {
+    let dir = scratch_dir("saveas");
+    let main = dir.join("main.json");
+    let mut app = App::new(main.clone());
+    app.options.backup_count = 0;
+    app.todos = vec![Todo::new("a")];
+    app.filter_todos();
+    app.list_state.select(Some(0));
+    assert_eq!(app.execute_command("w"), CommandOutcome::Done);
+    assert!(!app.is_dirty);
+
+    keys(&mut app, "x");
+    app.update_dirty_status();
+    let copy = dir.join("copy.json");
+    assert_eq!(
+        app.execute_command(&format!("w {}", copy.display())),
+        CommandOutcome::Done
+    );
+    assert!(app.message.starts_with("Wrote copy to"));
+    assert_eq!(app.current_file, main);
+    assert!(app.is_dirty);
+    assert!(App::read_todo_file(&copy).unwrap()[0].completed);
+    assert!(!App::read_todo_file(&main).unwrap()[0].completed);
+
+    // Only a bang on the command forces, not one in the file name
+    let bang = dir.join("notes!.json");
+    fs::write(&bang, "[]").unwrap();
+    assert_eq!(
+        app.execute_command(&format!("w {}", bang.display())),
+        CommandOutcome::Failed
+    );
+    assert!(app.message.ends_with("exists (add ! to overwrite)"));
+    assert_eq!(fs::read_to_string(&bang).unwrap(), "[]");
+    assert_eq!(
+        app.execute_command(&format!("w! {}", bang.display())),
+        CommandOutcome::Done
+    );
+    assert_eq!(App::read_todo_file(&bang).unwrap(), app.todos);
+
+    let other = dir.join("other.json");
+    assert_eq!(
+        app.execute_command(&format!("saveas {}", other.display())),
+        CommandOutcome::Done
+    );
+    assert_eq!(app.current_file, other);
+    assert!(!app.is_dirty);
+
+    // A plain :w now goes to the new file
+    keys(&mut app, "x");
+    assert_eq!(app.execute_command("w"), CommandOutcome::Done);
+    assert!(!App::read_todo_file(&other).unwrap()[0].completed);
+    assert!(!App::read_todo_file(&main).unwrap()[0].completed);
+    assert!(App::read_todo_file(&copy).unwrap()[0].completed);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+fn todo_with(text: &str, completed: bool, priority: Option<char>, note: Option<&str>) -> Todo {
+    Todo {
+        completed,
+        priority,
+        note: note.map(str::to_string),
+        ..Todo::new(text)
+    }
+}
+
+#[test]
+fn plan_merge_sorts_incoming_todos() {
+    let existing = [
+        todo_with("same", false, None, None),
+        todo_with("done there", false, None, None),
+        todo_with("reprioritized", false, Some('A'), None),
+        todo_with("noted", false, None, Some("mine")),
+        todo_with("twice", false, None, None),
+    ];
+    let incoming = vec![
+        todo_with("  same ", false, None, None),
+        todo_with("done there", true, None, None),
+        todo_with("reprioritized", false, Some('B'), None),
+        todo_with("noted", false, None, Some("theirs")),
+        todo_with("twice", false, None, None),
+        todo_with("twice", false, None, None),
+        todo_with("brand new", false, None, None),
+        // Case counts: this is a different todo
+        todo_with("Same", false, None, None),
+    ];
+    let plan = App::plan_merge(&existing, incoming);
+
+    assert_eq!(plan.unchanged, 2);
+    let conflicts: Vec<_> = plan
+        .conflicts
+        .iter()
+        .map(|(i, theirs)| (*i, theirs.text.as_str()))
+        .collect();
+    assert_eq!(
+        conflicts,
+        [(1, "done there"), (2, "reprioritized"), (3, "noted")]
+    );
+    // A second copy has nothing left to pair with, so it's new
+    let new: Vec<_> = plan.new.iter().map(|todo| todo.text.as_str()).collect();
+    assert_eq!(new, ["twice", "brand new", "Same"]);
+}
+
+#[test]
+fn merge_conflicts_are_decided_one_by_one_and_undone_together() {
+    let dir = scratch_dir("merge");
+    let theirs = dir.join("theirs.json");
+    let incoming = [
+        todo_with("a", true, None, None),
+        todo_with("b", false, Some('A'), None),
+        todo_with("c", false, None, Some("their note")),
+        todo_with("d", false, None, None),
+    ];
+    fs::write(&theirs, serde_json::to_string(&incoming).unwrap()).unwrap();
+    let mut app = app_with(&["a", "b", "c"]);
+    let before = app.todos.clone();
+
+    app.execute_command(&format!("merge {}", theirs.display()));
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(app.mode, Mode::Merge);
+    keys(&mut app, "kt");
+    assert_eq!(app.todos, before);
+    keys(&mut app, "b");
+    assert_eq!(app.mode, Mode::Normal);
+
+    assert_eq!(texts(&app), ["a", "b", "c", "c", "d"]);
+    assert!(!app.todos[0].completed);
+    assert_eq!(app.todos[1].priority, Some('A'));
+    assert_eq!(app.todos[2].note, None);
+    assert_eq!(app.todos[3].note.as_deref(), Some("their note"));
+    assert!(app.message.ends_with("3 conflicts (1 taken, 1 kept both)"));
+
+    keys(&mut app, "u");
+    assert_eq!(app.todos, before);
+}
+
+#[test]
+fn a_failed_save_leaves_the_file_intact() {
+    let dir = scratch_dir("atomic");
+    let file = dir.join("todos.json");
+    let mut app = App::new(file.clone());
+    app.options.backup_count = 0;
+    app.todos = vec![Todo::new("a")];
+    app.filter_todos();
+    app.list_state.select(Some(0));
+    assert_eq!(app.execute_command("w"), CommandOutcome::Done);
+    let saved = fs::read_to_string(&file).unwrap();
+
+    // Something already sitting where the temporary file goes
+    let temp = dir.join(format!(".todos.json.tmp.{}", std::process::id()));
+    fs::create_dir(&temp).unwrap();
+    keys(&mut app, "x");
+    assert_eq!(app.execute_command("w"), CommandOutcome::Failed);
+    assert!(
+        app.message.contains("couldn't write temp file"),
+        "{}",
+        app.message
+    );
+    assert_eq!(fs::read_to_string(&file).unwrap(), saved);
+    assert!(app.is_dirty);
+
+    fs::remove_dir(&temp).unwrap();
+    assert_eq!(app.execute_command("w"), CommandOutcome::Done);
+    assert!(App::read_todo_file(&file).unwrap()[0].completed);
+    // Nothing is left behind next to the file
+    let names: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(names, ["todos.json"]);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn write_atomic_says_which_step_failed() {
+    let dir = scratch_dir("atomic-rename");
+    // A directory with something in it can't be renamed over
+    let target = dir.join("busy");
+    fs::create_dir(&target).unwrap();
+    fs::write(target.join("inside"), "kept").unwrap();
+    let error = write_atomic(&target, "[]").unwrap_err().to_string();
+    assert!(error.starts_with("couldn't rename"), "{}", error);
+    assert_eq!(fs::read_to_string(target.join("inside")).unwrap(), "kept");
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn rollover_escalates_stale_todos_as_one_undo_step() {
+    let mut app = app_with(&["old", "older", "new"]);
+    let today = app.today;
+    app.todos[0].created = Some(today - chrono::Days::new(14));
+    app.todos[1].created = Some(today - chrono::Days::new(30));
+    app.todos[2].created = Some(today);
+    app.execute_command("set escalate 14d");
+    assert_eq!(
+        app.message,
+        "Escalation: every 14 days — escalated 2 stale todos (u to undo)"
+    );
+
+    // The next day nothing is a full period past its last escalation
+    assert!(app.check_rollover(today + chrono::Days::new(1)));
+    let priorities: Vec<_> = app.todos.iter().map(|todo| todo.priority).collect();
+    assert_eq!(priorities, [Some('C'), Some('C'), None]);
+
+    assert!(app.check_rollover(today + chrono::Days::new(14)));
+    let priorities: Vec<_> = app.todos.iter().map(|todo| todo.priority).collect();
+    assert_eq!(priorities, [Some('B'), Some('B'), Some('C')]);
+    assert_eq!(app.message, "New day — escalated 3 stale todos (u to undo)");
+    keys(&mut app, "u");
+    let priorities: Vec<_> = app.todos.iter().map(|todo| todo.priority).collect();
+    assert_eq!(priorities, [Some('C'), Some('C'), None]);
+}
+
+#[test]
+fn retag_rewrites_whole_tags_wherever_they_sit() {
+    let mut app = app_with(&[
+        "+web launch page",
+        "fix +web header",
+        "deploy +web",
+        "update +website copy",
+        "email about +web-team",
+        "call @web",
+    ]);
+    assert_eq!(
+        app.execute_command("retag +web +site"),
+        CommandOutcome::Done
+    );
+    assert_eq!(
+        texts(&app),
+        [
+            "+site launch page",
+            "fix +site header",
+            "deploy +site",
+            "update +website copy",
+            "email about +web-team",
+            "call @web",
+        ]
+    );
+    assert_eq!(app.message, "Retagged +web -> +site in 3 todos");
+    assert_eq!(app.recently_changed, [0, 1, 2]);
+
+    // Contexts work the same way, and one undo puts back the whole rewrite
+    app.execute_command("retag @web @phone");
+    assert_eq!(app.todos[5].text, "call @phone");
+    keys(&mut app, "uu");
+    assert_eq!(app.todos[0].text, "+web launch page");
+    assert_eq!(app.todos[5].text, "call @web");
+}
+
+#[test]
+fn retag_refuses_mismatched_or_plain_words() {
+    let mut app = app_with(&["fix +web header"]);
+    for command in ["retag +web @web", "retag web site", "retag +web"] {
+        assert_eq!(app.execute_command(command), CommandOutcome::Failed);
+        assert_eq!(app.message, "Usage: :retag +old +new or :retag @old @new");
+    }
+    assert_eq!(texts(&app), ["fix +web header"]);
+}
+
+#[test]
+fn retag_over_the_threshold_needs_a_bang() {
+    let texts_in: Vec<String> = (0..=BULK_CONFIRM_THRESHOLD)
+        .map(|i| format!("task {} +old", i))
+        .collect();
+    let mut app = app_with(&texts_in.iter().map(String::as_str).collect::<Vec<_>>());
+    assert_eq!(
+        app.execute_command("retag +old +new"),
+        CommandOutcome::Failed
+    );
+    assert_eq!(
+        app.message,
+        "21 todos would change; use :retag! +old +new to confirm"
+    );
+    assert!(app.todos.iter().all(|todo| todo.text.ends_with("+old")));
+
+    assert_eq!(
+        app.execute_command("retag! +old +new"),
+        CommandOutcome::Done
+    );
+    assert!(app.todos.iter().all(|todo| todo.text.ends_with("+new")));
+    keys(&mut app, "u");
+    assert!(app.todos.iter().all(|todo| todo.text.ends_with("+old")));
+}
+
+#[test]
+fn retag_refreshes_the_search() {
+    let mut app = app_with(&["fix +web header", "buy milk"]);
+    search_for(&mut app, "+web");
+    assert_eq!(app.filtered_todos, [0]);
+    app.execute_command("retag +web +site");
+    assert!(app.filtered_todos.is_empty());
+    assert_eq!(app.selected_todo_index(), None);
+}
+
+#[test]
+fn bulk_changes_flash_the_todos_they_touched() {
+    let mut app = app_with(&["buy milk", "call bob", "buy bread", "fix bike"]);
+    search_for(&mut app, "buy");
+    app.execute_command("done all");
+    assert_eq!(app.recently_changed, [0, 2]);
+
+    app.execute_command("g/bread/x");
+    assert_eq!(app.recently_changed, [2]);
+
+    keys(&mut app, "yp");
+    assert_eq!(app.recently_changed, [1]);
+    assert_eq!(app.todos[1].text, "buy milk");
+
+    let today = app.today;
+    app.todos[4].created = Some(today - chrono::Days::new(30));
+    app.execute_command("set escalate 14d");
+    assert_eq!(app.recently_changed, [4]);
+
+    // A change to one todo ends the flash early
+    keys(&mut app, "x");
+    assert!(app.recently_changed.is_empty());
+}
+
+#[test]
+fn the_flash_clears_once_it_times_out() {
+    let mut app = app_with(&["a", "b", "c"]);
+    app.execute_command("xall");
+    assert_eq!(app.recently_changed, [0, 1, 2]);
+
+    assert!(!app.expire_flash(Instant::now()));
+    assert_eq!(app.recently_changed, [0, 1, 2]);
+    assert!(app.expire_flash(Instant::now() + FLASH_DURATION));
+    assert!(app.recently_changed.is_empty());
+    assert!(!app.expire_flash(Instant::now() + FLASH_DURATION));
+}
+
+// Big enough to be searched on the worker thread, with "needle" in every
+// hundredth todo
+fn big_list() -> App {
+    let texts: Vec<String> = (0..ASYNC_SEARCH_THRESHOLD + 100)
+        .map(|i| {
+            if i % 100 == 0 {
+                format!("needle {}", i)
+            } else {
+                format!("hay {}", i)
+            }
+        })
+        .collect();
+    app_with(&texts.iter().map(String::as_str).collect::<Vec<_>>())
+}
+
+// Polls like the tick loop until the search in flight lands
+fn wait_for_search(app: &mut App) {
+    let start = Instant::now();
+    while app.search_pending {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "search never landed"
+        );
+        app.poll_search();
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn big_lists_are_searched_in_the_background() {
+    let mut app = big_list();
+    keys(&mut app, "/needle");
+    assert!(app.search_pending);
+    assert_eq!(app.filtered_todos.len(), app.todos.len());
+
+    wait_for_search(&mut app);
+    assert_eq!(app.filtered_todos.len(), 51);
+    assert!(
+        app.filtered_todos
+            .iter()
+            .all(|&i| app.todos[i].text.starts_with("needle"))
+    );
+}
+
+#[test]
+fn only_the_newest_query_is_applied() {
+    let mut app = big_list();
+    keys(&mut app, "/hay");
+    // Each keystroke supersedes the query before it
+    for _ in 0..3 {
+        press(&mut app, KeyCode::Backspace);
+    }
+    keys(&mut app, "needle 5000");
+    wait_for_search(&mut app);
+    let texts: Vec<_> = app
+        .filtered_todos
+        .iter()
+        .map(|&i| app.todos[i].text.as_str())
+        .collect();
+    assert_eq!(texts[0], "needle 5000");
+}
+
+#[test]
+fn a_change_drops_the_search_in_flight() {
+    let mut app = big_list();
+    keys(&mut app, "/needle");
+    assert!(app.search_pending);
+    // Deleting shifts every index the worker is about to send back
+    app.delete_todos(vec![0]);
+    assert!(!app.search_pending);
+    let after_delete = app.filtered_todos.clone();
+    assert!(
+        after_delete
+            .iter()
+            .all(|&i| app.todos[i].text.starts_with("needle"))
+    );
+
+    // A later search queues behind the stale one, so once it lands the
+    // stale result has been seen and thrown away
+    press(&mut app, KeyCode::Backspace);
+    keys(&mut app, "e");
+    wait_for_search(&mut app);
+    assert_eq!(app.filtered_todos, after_delete);
+}
+
+#[test]
+fn undo_history_keeps_the_last_hundred_states() {
+    let mut app = app_with(&[]);
+    for i in 0..105 {
+        keys(&mut app, &format!("i{}", i));
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Esc);
+    }
+    let last = app.todos.clone();
+    assert_eq!(app.history.len(), MAX_UNDO);
+
+    // The first undo keeps the live list for redo, which takes a slot
+    for _ in 0..MAX_UNDO - 1 {
+        keys(&mut app, "u");
+    }
+    assert_eq!(app.history.len(), MAX_UNDO);
+    assert_eq!(app.todos.len(), 6);
+    keys(&mut app, "u");
+    assert_eq!(app.message, "Nothing to undo");
+
+    for _ in 0..MAX_UNDO - 1 {
+        ctrl(&mut app, 'r');
+    }
+    assert_eq!(app.todos, last);
+    ctrl(&mut app, 'r');
+    assert_eq!(app.message, "Nothing to redo");
+}
+
+#[test]
+fn counted_g_jumps_to_a_row_of_the_list_as_shown() {
+    let mut app = app_with(&["a", "b", "c", "d", "e", "f"]);
+    keys(&mut app, "3G");
+    assert_eq!(selected_text(&app), Some("c"));
+    keys(&mut app, "5gg");
+    assert_eq!(selected_text(&app), Some("e"));
+    // The count was used up, so plain G and gg go to the ends
+    keys(&mut app, "gg");
+    assert_eq!(selected_text(&app), Some("a"));
+    keys(&mut app, "G");
+    assert_eq!(selected_text(&app), Some("f"));
+    keys(&mut app, "99G");
+    assert_eq!(selected_text(&app), Some("f"));
+
+    // Rows count in the filtered view
+    search_for(&mut app, "e");
+    keys(&mut app, "1G");
+    assert_eq!(selected_text(&app), Some("e"));
+}
+
+#[test]
+fn colon_number_jumps_to_a_row() {
+    let mut app = app_with(&["a", "b", "c"]);
+    assert_eq!(app.execute_command("2"), CommandOutcome::Done);
+    assert_eq!(selected_text(&app), Some("b"));
+    app.execute_command("$");
+    assert_eq!(selected_text(&app), Some("c"));
+    app.execute_command("0");
+    assert_eq!(selected_text(&app), Some("a"));
+}
+
+#[test]
+fn counted_gg_in_visual_mode() {
+    let mut app = app_with(&["a", "b", "c", "d"]);
+    keys(&mut app, "v3gg");
+    assert_eq!(app.visual_start, Some(0));
+    assert_eq!(selected_text(&app), Some("c"));
+}
+
+#[test]
+fn substitute_on_the_cursor_todo_or_the_whole_list() {
+    let mut app = app_with(&["fix +web bug", "call bob", "ship +web +web site"]);
+    assert_eq!(app.execute_command(r"s/\+web/+site/"), CommandOutcome::Done);
+    assert_eq!(
+        texts(&app),
+        ["fix +site bug", "call bob", "ship +web +web site"]
+    );
+
+    assert_eq!(
+        app.execute_command(r"%s/\+WEB/+site/gi"),
+        CommandOutcome::Done
+    );
+    assert_eq!(texts(&app)[2], "ship +site +site site");
+    assert_eq!(app.message, "2 substitutions on 1 todo");
+    // One undo step for the whole command
+    keys(&mut app, "u");
+    assert_eq!(texts(&app)[2], "ship +web +web site");
+
+    // Groups reorder, and the priority prefix can be reached too
+    app.execute_command(r"%s/^(\w+) (\w+)$/(B) \2 \1/");
+    assert_eq!(texts(&app)[1], "bob call");
+    assert_eq!(app.todos[1].priority, Some('B'));
+}
+
+#[test]
+fn substitute_keeps_to_the_search_and_the_selection() {
+    let mut app = app_with(&["a one", "b one", "a two", "a three"]);
+    search_for(&mut app, "a ");
+    app.execute_command("%s/a/x/");
+    assert_eq!(app.message, "3 substitutions on 3 todos");
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(texts(&app), ["x one", "b one", "x two", "x three"]);
+
+    keys(&mut app, "ggjjVj:s/x/y/");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(texts(&app), ["x one", "b one", "y two", "y three"]);
+    assert_eq!(app.message, "2 substitutions on 2 todos");
+}
+
+#[test]
+fn a_bad_substitution_changes_nothing() {
+    let mut app = app_with(&["a", "b"]);
+    assert_eq!(app.execute_command("%s/(/x/"), CommandOutcome::Failed);
+    assert_eq!(app.message, "invalid regex: unclosed group");
+    assert_eq!(app.execute_command("%s/z/x/"), CommandOutcome::Failed);
+    assert_eq!(app.message, "Pattern not found: z");
+    // Emptying a todo isn't a substitution
+    assert_eq!(app.execute_command("s/a//"), CommandOutcome::Failed);
+    assert_eq!(texts(&app), ["a", "b"]);
+    assert!(app.history.is_empty());
+    // Still commands of their own
+    assert_eq!(app.execute_command("sort"), CommandOutcome::Done);
+}
+
+#[test]
+fn global_deletes_toggles_or_prioritizes_matches() {
+    let mut app = app_with(&["fix +web bug", "call bob", "ship +web site", "(B) pay rent"]);
+    assert_eq!(app.execute_command(r"g/\+web/x"), CommandOutcome::Done);
+    assert_eq!(app.message, "2 todos toggled");
+    assert!(app.todos[0].completed && app.todos[2].completed);
+    assert!(!app.todos[1].completed);
+
+    // Matched as `e` shows the todo, priority included
+    app.execute_command(r"g/^\(B\)/pri -");
+    assert_eq!(app.todos[3].priority, None);
+    assert_eq!(app.message, "Cleared priority of 1 todo");
+    app.execute_command("g/bob/pri a");
+    assert_eq!(app.todos[1].priority, Some('A'));
+
+    assert_eq!(app.execute_command(r"v/\+web/d"), CommandOutcome::Done);
+    assert_eq!(texts(&app), ["fix +web bug", "ship +web site"]);
+    assert_eq!(app.message, "2 todos deleted");
+    assert_eq!(app.registers.unnamed.len(), 2);
+    // One undo step for the whole command
+    keys(&mut app, "u");
+    assert_eq!(app.todos.len(), 4);
+}
+
+#[test]
+fn global_keeps_to_the_search_and_the_selection() {
+    let mut app = app_with(&["a one", "b one", "a two", "a three"]);
+    search_for(&mut app, "a ");
+    app.execute_command("g!/two/d");
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(texts(&app), ["b one", "a two"]);
+
+    let mut app = app_with(&["a one", "b one", "a two", "a three"]);
+    keys(&mut app, "jVj:g/a/x");
+    press(&mut app, KeyCode::Enter);
+    let completed: Vec<bool> = app.todos.iter().map(|t| t.completed).collect();
+    assert_eq!(completed, [false, false, true, false]);
+}
+
+#[test]
+fn a_bad_global_changes_nothing() {
+    let many: Vec<String> = (0..25).map(|i| format!("todo {}", i)).collect();
+    let many: Vec<&str> = many.iter().map(String::as_str).collect();
+    let mut app = app_with(&many);
+    assert_eq!(app.execute_command("g/todo/d"), CommandOutcome::Failed);
+    assert_eq!(
+        app.message,
+        "25 todos would be deleted; add ! to confirm (d!)"
+    );
+    assert_eq!(app.execute_command("g/(/d"), CommandOutcome::Failed);
+    assert_eq!(app.message, "invalid regex: unclosed group");
+    assert_eq!(app.execute_command("g/zzz/d"), CommandOutcome::Failed);
+    assert_eq!(app.message, "Pattern not found: zzz");
+    assert_eq!(app.execute_command("v/todo/x"), CommandOutcome::Failed);
+    assert_eq!(app.execute_command("g/todo/frob"), CommandOutcome::Failed);
+    assert!(app.message.starts_with("Usage: :g/pattern/d"));
+    assert_eq!(app.todos.len(), 25);
+    assert!(app.history.is_empty());
+
+    assert_eq!(app.execute_command("g/todo/d!"), CommandOutcome::Done);
+    assert!(app.todos.is_empty());
+}
+
+#[test]
+fn ranges_delete_toggle_and_prioritize_rows() {
+    let mut app = app_with(&["a", "b", "c", "d", "e"]);
+    assert_eq!(app.execute_command("2,3d"), CommandOutcome::Done);
+    assert_eq!(texts(&app), ["a", "d", "e"]);
+    assert_eq!(app.message, "2 todos deleted");
+    assert_eq!(app.registers.unnamed.len(), 2);
+
+    keys(&mut app, "j");
+    app.execute_command(".,$x");
+    let completed: Vec<bool> = app.todos.iter().map(|t| t.completed).collect();
+    assert_eq!(completed, [false, true, true]);
+
+    // Backwards ranges are turned around
+    app.execute_command("$,1pri b");
+    assert!(app.todos.iter().all(|t| t.priority == Some('B')));
+    // Each ranged command is one undo step
+    keys(&mut app, "u");
+    assert!(app.todos.iter().all(|t| t.priority.is_none()));
+    keys(&mut app, "u");
+    assert!(app.todos.iter().all(|t| !t.completed));
+}
+
+#[test]
+fn ranges_count_rows_of_the_list_as_shown() {
+    let mut app = app_with(&["a one", "b one", "a two", "a three"]);
+    search_for(&mut app, "a ");
+    assert_eq!(app.execute_command("2,3d"), CommandOutcome::Done);
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(texts(&app), ["a one", "b one"]);
+
+    let mut app = app_with(&["a", "b", "c", "d"]);
+    keys(&mut app, "jVj:'<,'>d");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(texts(&app), ["a", "d"]);
+}
+
+#[test]
+fn ranged_moves_go_below_a_row() {
+    let mut app = app_with(&["a", "b", "c", "d", "e"]);
+    assert_eq!(app.execute_command("3m0"), CommandOutcome::Done);
+    assert_eq!(texts(&app), ["c", "a", "b", "d", "e"]);
+    app.execute_command("1,2m$");
+    assert_eq!(texts(&app), ["b", "d", "e", "c", "a"]);
+    assert_eq!(app.message, "Moved 2 todos to rows 4-5");
+    assert_eq!(selected_text(&app), Some("a"));
+    app.execute_command("4m 1");
+    assert_eq!(texts(&app), ["b", "c", "d", "e", "a"]);
+    keys(&mut app, "u");
+    assert_eq!(texts(&app), ["b", "d", "e", "c", "a"]);
+
+    assert_eq!(app.execute_command("2,4m3"), CommandOutcome::Failed);
+    assert_eq!(app.execute_command("1m up"), CommandOutcome::Failed);
+}
+
+#[test]
+fn out_of_range_addresses_clamp() {
+    let mut app = app_with(&["a", "b", "c"]);
+    assert_eq!(app.execute_command("2,9x"), CommandOutcome::Done);
+    assert_eq!(app.message, "2 todos toggled (range clamped to 2,3)");
+    assert_eq!(app.execute_command("0d"), CommandOutcome::Done);
+    assert_eq!(texts(&app), ["b", "c"]);
+
+    // Only for commands that take one, and '<,'> only from Visual mode
+    assert_eq!(app.execute_command("1,2w"), CommandOutcome::Failed);
+    assert_eq!(app.message, "No range allowed: :w");
+    assert_eq!(app.execute_command("'<,'>d"), CommandOutcome::Failed);
+    assert_eq!(app.todos.len(), 2);
+    // A bare :x isn't a toggle, and a bare row still jumps
+    assert_eq!(app.execute_command("x"), CommandOutcome::Failed);
+    assert!(app.message.starts_with("Unknown"));
+    app.execute_command("2");
+    assert_eq!(selected_text(&app), Some("c"));
+}
+
+#[test]
+fn aliases_expand_once_before_dispatch() {
+    let mut app = app_with(&["b", "a", "c"]);
+    app.todos[1].priority = Some('A');
+    assert_eq!(
+        app.execute_command("alias sp sort priority"),
+        CommandOutcome::Done
+    );
+    assert_eq!(app.message, ":sp is now :sort priority");
+    app.execute_command("SP");
+    assert_eq!(texts(&app), ["a", "b", "c"]);
+
+    // `%s` takes what follows the name; without it, that's appended
+    app.execute_command("alias so sort %s!");
+    app.execute_command("so alpha");
+    assert_eq!(texts(&app), ["c", "b", "a"]);
+    app.execute_command("alias s2 sort");
+    app.execute_command("s2 alpha");
+    assert_eq!(texts(&app), ["a", "b", "c"]);
+
+    // An alias naming an alias isn't expanded again
+    app.execute_command("alias loop loop");
+    assert_eq!(app.execute_command("loop"), CommandOutcome::Failed);
+    assert!(app.message.starts_with("Unknown command: loop"));
+
+    assert_eq!(app.execute_command("spp"), CommandOutcome::Failed);
+    assert_eq!(app.message, "Unknown command: spp (alias :sp?)");
+}
+
+#[test]
+fn aliases_only_replace_built_ins_with_a_bang() {
+    let mut app = app_with(&["b", "a"]);
+    assert_eq!(
+        app.execute_command("alias sort sort alpha"),
+        CommandOutcome::Failed
+    );
+    assert!(app.message.starts_with(":sort is a built-in command"));
+    assert_eq!(
+        app.execute_command("alias! sort sort alpha"),
+        CommandOutcome::Done
+    );
+    app.execute_command("sort");
+    assert_eq!(texts(&app), ["a", "b"]);
+
+    assert_eq!(app.execute_command("alias 9x d"), CommandOutcome::Failed);
+    assert_eq!(
+        app.execute_command("alias! alias w"),
+        CommandOutcome::Failed
+    );
+    assert_eq!(app.execute_command("unalias sort"), CommandOutcome::Done);
+    assert_eq!(app.execute_command("unalias sort"), CommandOutcome::Failed);
+}
+
+#[test]
+fn aliases_come_from_and_go_back_to_the_config() {
+    let path = std::env::temp_dir().join(format!("tuido-test-config-{}.toml", std::process::id()));
+    let config = "[keys]\ndelete = \"D\"\n\n[aliases]\nsp = \"sort priority\" # daily\nw = \"write\"\n\"q!\" = \"wq\"\n";
+    fs::write(&path, config).unwrap();
+    let mut app = app_with(&["a"]);
+    let errors = app.apply_config(config);
+    assert_eq!(
+        errors,
+        ["line 6: :w is a built-in command; :alias! w (or w! in the config) replaces it"]
+    );
+    app.use_config_file(path.clone());
+    assert_eq!(app.expand_alias("q").as_deref(), Some("wq"));
+
+    app.execute_command("alias sd sort due");
+    app.execute_command("alias! e edit!");
+    app.execute_command("unalias sp");
+    let saved = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(
+        saved,
+        "[keys]\ndelete = \"D\"\n\n[aliases]\nw = \"write\"\n\"q!\" = \"wq\"\nsd = \"sort due\"\n\"e!\" = \"edit!\"\n"
+    );
+
+    app.execute_command("alias");
+    assert_eq!(app.mode, Mode::Popup);
+    assert_eq!(app.message, "3 aliases");
+}
+
+#[test]
+fn set_turns_options_on_and_off_and_shows_them() {
+    let mut app = app_with(&["a"]);
+    assert_eq!(app.execute_command("set nu"), CommandOutcome::Done);
+    assert!(app.options.number);
+    app.execute_command("set number?");
+    assert_eq!(app.message, "number");
+    app.execute_command("set nonumber");
+    app.execute_command("set nu?");
+    assert_eq!(app.message, "nonumber");
+    app.execute_command("set number!");
+    assert!(app.options.number);
+
+    // A value after `=` or a space
+    app.execute_command("set layout=comfortable");
+    assert!(app.options.density == Density::Comfortable);
+    app.execute_command("set backups 3");
+    app.execute_command("set backups");
+    assert_eq!(app.message, "backups=3");
+
+    assert_eq!(
+        app.execute_command("set layout=roomy"),
+        CommandOutcome::Failed
+    );
+    assert_eq!(app.message, "Usage: :set layout compact|comfortable");
+    assert_eq!(app.execute_command("set number=3"), CommandOutcome::Failed);
+    assert_eq!(app.execute_command("set nolayout"), CommandOutcome::Failed);
+    assert_eq!(app.message, "layout takes a value: :set layout=...");
+    assert_eq!(app.execute_command("set numbr"), CommandOutcome::Failed);
+    assert_eq!(app.message, "Unknown option: numbr (number?)");
+    assert_eq!(app.execute_command("set nomous"), CommandOutcome::Failed);
+    assert_eq!(app.message, "Unknown option: nomous (mouse?)");
+}
+
+#[test]
+fn set_alone_lists_what_changed() {
+    let mut app = app_with(&["a"]);
+    app.execute_command("set");
+    assert_eq!(
+        app.message,
+        "Every option is at its default (:set all lists them)"
+    );
+    app.execute_command("set nomouse");
+    app.execute_command("set layout comfortable");
+    app.execute_command("set");
+    assert_eq!(app.mode, Mode::Popup);
+    assert_eq!(app.message, "2 options changed");
+    let popup = app.popup.as_ref().unwrap();
+    assert_eq!(popup.lines[..2], ["  layout=comfortable", "  nomouse"]);
+
+    press(&mut app, KeyCode::Esc);
+    app.execute_command("set all");
+    assert_eq!(app.message, "15 options");
+}
+
+#[test]
+fn options_load_from_and_save_to_the_config() {
+    let path = std::env::temp_dir().join(format!("tuido-test-options-{}.toml", std::process::id()));
+    let config =
+        "[options]\nnumber = true\nlayout = \"comfortable\"\nmouse = maybe\nnumbr = true\n";
+    fs::write(&path, config).unwrap();
+    let mut app = app_with(&["a"]);
+    app.notify(MessageLevel::Info, "Loaded");
+    let errors = app.apply_config(config);
+    assert_eq!(
+        errors,
+        [
+            "line 4: mouse is true or false",
+            "line 5: Unknown option: numbr (number?)"
+        ]
+    );
+    // What the options would say when set by hand isn't news at startup
+    assert_eq!(app.message, "Loaded");
+    assert_eq!(app.message_log.len(), 1);
+    assert!(app.options.number);
+    assert!(app.options.density == Density::Comfortable);
+
+    app.use_config_file(path.clone());
+    app.execute_command("set nomouse");
+    app.execute_command("set! nonumber");
+    assert_eq!(app.message, "Hiding row numbers (saved)");
+    app.execute_command("set! escalate=2w");
+    let saved = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(
+        saved,
+        "[options]\nnumber = false\nlayout = \"comfortable\"\nmouse = maybe\nnumbr = true\nescalate = \"14d\"\n"
+    );
+}
+
+#[test]
+fn messages_keeps_a_bounded_log_with_errors_marked() {
+    let mut app = app_with(&["a"]);
+    press(&mut app, KeyCode::Char(':'));
+    keys(&mut app, "messages");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.message, "No messages yet");
+
+    press(&mut app, KeyCode::Char(':'));
+    keys(&mut app, "bogus");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.message_level, MessageLevel::Error);
+    press(&mut app, KeyCode::Char(':'));
+    keys(&mut app, "messages");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.mode, Mode::Popup);
+    let popup = app.popup.as_ref().unwrap();
+    assert_eq!(popup.lines.len(), 2);
+    assert!(popup.lines[1].ends_with("  Unknown command: bogus"));
+    assert_eq!(popup.error_lines, [1]);
+    press(&mut app, KeyCode::Esc);
+
+    app.execute_command("export");
+    let logged = app.message_log.back().unwrap();
+    assert_eq!(logged.level, MessageLevel::Error);
+    assert!(logged.text.starts_with("Usage: :export"));
+
+    for i in 0..MAX_MESSAGES + 5 {
+        app.notify(MessageLevel::Info, format!("note {}", i));
+    }
+    assert_eq!(app.message_log.len(), MAX_MESSAGES);
+    assert_eq!(app.message_log.back().unwrap().text, "note 204");
+
+    press(&mut app, KeyCode::Char(':'));
+    keys(&mut app, "mes clear");
+    press(&mut app, KeyCode::Enter);
+    assert!(app.message_log.is_empty());
+    assert_eq!(app.message, "");
+}
+
+#[test]
+fn an_error_stays_up_over_later_messages_until_the_next_key() {
+    let mut app = app_with(&["a"]);
+    press(&mut app, KeyCode::Char(':'));
+    keys(&mut app, "bogus");
+    press(&mut app, KeyCode::Enter);
+    app.notify(MessageLevel::Info, "routine");
+    assert_eq!(app.message, "Unknown command: bogus");
+    assert_eq!(app.message_log.back().unwrap().text, "routine");
+
+    keys(&mut app, "j");
+    app.notify(MessageLevel::Info, "routine");
+    assert_eq!(app.message, "routine");
+    assert_eq!(app.message_level, MessageLevel::Info);
+}
+
+// Popup navigation over positions 0..=max with `page` rows on screen
+fn scroll_state(max: usize, page: usize) -> ScrollState {
+    let mut scroll = ScrollState::default();
+    scroll.set_bounds(max, page);
+    scroll
+}
+
+fn navigate(scroll: &mut ScrollState, code: KeyCode, modifiers: KeyModifiers) -> bool {
+    scroll.navigate(KeyEvent::new(code, modifiers), "")
+}
+
+#[test]
+fn scrolling_clamps_at_both_ends() {
+    let mut scroll = scroll_state(30, 10);
+    assert!(navigate(
+        &mut scroll,
+        KeyCode::Char('k'),
+        KeyModifiers::NONE
+    ));
+    assert_eq!(scroll.position, 0);
+    navigate(&mut scroll, KeyCode::Char('j'), KeyModifiers::NONE);
+    navigate(&mut scroll, KeyCode::Down, KeyModifiers::NONE);
+    assert_eq!(scroll.position, 2);
+    navigate(&mut scroll, KeyCode::Up, KeyModifiers::NONE);
+    assert_eq!(scroll.position, 1);
+
+    navigate(&mut scroll, KeyCode::Char('G'), KeyModifiers::SHIFT);
+    assert_eq!(scroll.position, 30);
+    navigate(&mut scroll, KeyCode::Char('j'), KeyModifiers::NONE);
+    assert_eq!(scroll.position, 30);
+    assert!(!navigate(
+        &mut scroll,
+        KeyCode::Char('x'),
+        KeyModifiers::NONE
+    ));
+
+    // Shrinking the popup pulls the position back in
+    scroll.set_bounds(12, 10);
+    assert_eq!(scroll.position, 12);
+}
+
+#[test]
+fn scrolling_by_pages_and_half_pages() {
+    let mut scroll = scroll_state(30, 10);
+    navigate(&mut scroll, KeyCode::Char('d'), KeyModifiers::CONTROL);
+    assert_eq!(scroll.position, 5);
+    navigate(&mut scroll, KeyCode::PageDown, KeyModifiers::NONE);
+    assert_eq!(scroll.position, 15);
+    navigate(&mut scroll, KeyCode::Char(' '), KeyModifiers::NONE);
+    assert_eq!(scroll.position, 25);
+    navigate(&mut scroll, KeyCode::PageDown, KeyModifiers::NONE);
+    assert_eq!(scroll.position, 30);
+    navigate(&mut scroll, KeyCode::Char('u'), KeyModifiers::CONTROL);
+    assert_eq!(scroll.position, 25);
+    navigate(&mut scroll, KeyCode::PageUp, KeyModifiers::NONE);
+    navigate(&mut scroll, KeyCode::PageUp, KeyModifiers::NONE);
+    assert_eq!(scroll.position, 5);
+    navigate(&mut scroll, KeyCode::PageUp, KeyModifiers::NONE);
+    assert_eq!(scroll.position, 0);
+
+    // A one-line page still moves half a page by at least one
+    let mut tiny = scroll_state(30, 1);
+    navigate(&mut tiny, KeyCode::Char('d'), KeyModifiers::CONTROL);
+    assert_eq!(tiny.position, 1);
+}
+
+#[test]
+fn gg_needs_both_keys() {
+    let mut scroll = scroll_state(30, 10);
+    navigate(&mut scroll, KeyCode::End, KeyModifiers::NONE);
+    assert_eq!(scroll.position, 30);
+    // The first g waits for the second
+    assert!(navigate(
+        &mut scroll,
+        KeyCode::Char('g'),
+        KeyModifiers::NONE
+    ));
+    assert_eq!(scroll.position, 30);
+    assert!(scroll.navigate(KeyEvent::new(KeyCode::Char('g'), KeyModifiers::NONE), "g"));
+    assert_eq!(scroll.position, 0);
+    navigate(&mut scroll, KeyCode::Char('j'), KeyModifiers::NONE);
+    navigate(&mut scroll, KeyCode::Home, KeyModifiers::NONE);
+    assert_eq!(scroll.position, 0);
+}
+
+#[test]
+fn a_list_shorter_than_the_popup_stays_in_range() {
+    let mut scroll = scroll_state(2, 10);
+    for (code, modifiers) in [
+        (KeyCode::PageDown, KeyModifiers::NONE),
+        (KeyCode::Char('d'), KeyModifiers::CONTROL),
+        (KeyCode::Char('G'), KeyModifiers::SHIFT),
+        (KeyCode::Char('j'), KeyModifiers::NONE),
+    ] {
+        navigate(&mut scroll, code, modifiers);
+        assert_eq!(scroll.position, 2);
+    }
+    navigate(&mut scroll, KeyCode::PageUp, KeyModifiers::NONE);
+    assert_eq!(scroll.position, 0);
+
+    // Text that fits entirely has nowhere to scroll
+    let mut fits = scroll_state(0, 10);
+    navigate(&mut fits, KeyCode::Char('j'), KeyModifiers::NONE);
+    navigate(&mut fits, KeyCode::End, KeyModifiers::NONE);
+    assert_eq!(fits.position, 0);
+}
+
+// Edits `text` with the cursor at the end, returning the buffer
+fn line_editing(text: &str, edits: &[(KeyCode, KeyModifiers)]) -> TextBuffer {
+    let mut buffer = TextBuffer::new(text);
+    for &(code, modifiers) in edits {
+        edit_text(&mut buffer, KeyEvent::new(code, modifiers));
+    }
+    buffer
+}
+
+const ALT: KeyModifiers = KeyModifiers::ALT;
+const CTRL: KeyModifiers = KeyModifiers::CONTROL;
+
+#[test]
+fn word_motions_skip_punctuation_and_spaces() {
+    let home = (KeyCode::Char('a'), CTRL);
+    let forward = (KeyCode::Char('f'), ALT);
+    let back = (KeyCode::Char('b'), ALT);
+    assert_eq!(line_editing("foo,  bar", &[home, forward]).col, 3);
+    assert_eq!(line_editing("foo,  bar", &[home, forward, forward]).col, 9);
+    // Nowhere further to go on the last line
+    assert_eq!(line_editing("foo,  bar", &[forward]).col, 9);
+    assert_eq!(line_editing("foo,  bar", &[back]).col, 6);
+    assert_eq!(line_editing("foo,  bar", &[back, back]).col, 0);
+    assert_eq!(
+        line_editing("foo,  bar", &[back, (KeyCode::Char('e'), CTRL)]).col,
+        9
+    );
+
+    // Runs of spaces, leading and trailing
+    assert_eq!(line_editing("  a    b   ", &[back]).col, 7);
+    assert_eq!(line_editing("  a    b   ", &[back, back]).col, 2);
+    assert_eq!(line_editing("  a    b   ", &[back, back, back]).col, 0);
+    assert_eq!(line_editing("  a    b   ", &[home, forward]).col, 3);
+}
+
+#[test]
+fn word_deletion_takes_the_gap_with_the_word() {
+    let home = (KeyCode::Char('a'), CTRL);
+    let delete = (KeyCode::Char('d'), ALT);
+    let buffer = line_editing("foo,  bar", &[home, delete]);
+    assert_eq!((buffer.text().as_str(), buffer.col), (",  bar", 0));
+    let buffer = line_editing("foo,  bar", &[home, delete, delete]);
+    assert_eq!((buffer.text().as_str(), buffer.col), ("", 0));
+
+    let buffer = line_editing("a    b   ", &[(KeyCode::Char('w'), CTRL)]);
+    assert_eq!((buffer.text().as_str(), buffer.col), ("a    ", 5));
+}
+
+#[test]
+fn word_motions_over_accented_and_cjk_words() {
+    let forward = (KeyCode::Char('f'), ALT);
+    let back = (KeyCode::Char('b'), ALT);
+    let home = (KeyCode::Char('a'), CTRL);
+    // An accent written as a combining mark stays with its letter
+    let text = "cafe\u{301} naïve";
+    assert_eq!(line_editing(text, &[home, forward]).col, 5);
+    assert_eq!(line_editing(text, &[back]).col, 6);
+    let buffer = line_editing(text, &[home, (KeyCode::Char('d'), ALT)]);
+    assert_eq!(buffer.text(), " naïve");
+
+    // Unicode word boundaries make each kanji and kana a word of its own,
+    // but a run of katakana is one word
+    let text = "買い物 東京タワー";
+    assert_eq!(line_editing(text, &[home, forward]).col, 1);
+    assert_eq!(
+        line_editing(text, &[home, forward, forward, forward]).col,
+        3
+    );
+    assert_eq!(line_editing(text, &[back]).col, 6);
+    assert_eq!(line_editing(text, &[back, back]).col, 5);
+    let buffer = line_editing(text, &[(KeyCode::Char('w'), CTRL)]);
+    assert_eq!(buffer.text(), "買い物 東京");
+}
+
+// Runs `:command` as typed on the command line
+fn command_line(app: &mut App, command: &str) {
+    keys(app, ":");
+    keys(app, command);
+    press(app, KeyCode::Enter);
+}
+
+// Stops the shell command a test started, as Ctrl+C does
+fn stop_shell(app: &mut App) {
+    assert!(app.shell_job.is_some());
+    ctrl(app, 'c');
+    assert!(app.shell_job.is_none());
+}
+
+#[test]
+fn a_freshly_typed_shell_command_runs_on_the_first_enter() {
+    let mut app = app_with(&["a"]);
+    command_line(&mut app, "!true");
+    assert_eq!(app.mode, Mode::Normal);
+    stop_shell(&mut app);
+    assert_eq!(app.command_history.entries, ["!true"]);
+}
+
+#[test]
+fn a_recalled_shell_command_waits_for_a_second_enter() {
+    let mut app = app_with(&["a"]);
+    command_line(&mut app, "!true");
+    stop_shell(&mut app);
+
+    keys(&mut app, ":");
+    press(&mut app, KeyCode::Up);
+    assert!(app.recalled_shell_command());
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.mode, Mode::Command);
+    assert!(app.shell_job.is_none());
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.mode, Mode::Normal);
+    stop_shell(&mut app);
+
+    // Recalled commands that aren't shell commands run straight away
+    command_line(&mut app, "sort");
+    keys(&mut app, ":");
+    press(&mut app, KeyCode::Up);
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.mode, Mode::Normal);
+}
+
+#[test]
+fn histignoreshell_keeps_shell_commands_out_of_the_history() {
+    let mut app = app_with(&["a"]);
+    command_line(&mut app, "set histignoreshell");
+    command_line(&mut app, "!true");
+    stop_shell(&mut app);
+    assert_eq!(app.command_history.entries, ["set histignoreshell"]);
+
+    // So Up recalls the command before it, which needs no confirmation
+    keys(&mut app, ":");
+    press(&mut app, KeyCode::Up);
+    assert_eq!(app.command_input.text(), "set histignoreshell");
+    assert!(!app.recalled_shell_command());
+}
+
+#[test]
+fn migration_candidates_rank_newest_then_longest() {
+    let at = |secs| std::time::UNIX_EPOCH + Duration::from_secs(secs);
+    let candidate = |name: &str, count, modified| LegacyCandidate {
+        path: PathBuf::from(name),
+        count,
+        modified,
+    };
+    let mut candidates = vec![
+        candidate("old-big.json", 500, at(1_000)),
+        candidate("new-small.json", 2, at(9_000)),
+        candidate("mid-a.json", 10, at(5_000)),
+        candidate("mid-b.json", 40, at(5_000)),
+        candidate("mid-c.json", 10, at(5_000)),
+    ];
+    rank_candidates(&mut candidates);
+    let order: Vec<_> = candidates
+        .iter()
+        .map(|c| c.path.to_str().unwrap())
+        .collect();
+    assert_eq!(
+        order,
+        [
+            "new-small.json",
+            "mid-b.json",
+            "mid-a.json",
+            "mid-c.json",
+            "old-big.json"
+        ]
+    );
+}
@@ -0,0 +1,214 @@
+//! Editing the whole list as text, for `:editall`. Each todo is one line
+//! as it's typed (`(A) text due:YYYY-MM-DD key:value`), with `x ` in front
+//! when it's completed. Lines that come back unchanged keep their todo
+//! exactly, notes and dates included.
+
+use chrono::NaiveDate;
+
+use crate::Todo;
+use crate::matching::similarity;
+
+/// What [`apply`] made of the edited text.
+#[derive(Debug)]
+pub struct BulkEdit {
+    pub todos: Vec<Todo>,
+    pub added: usize,
+    pub removed: usize,
+    pub edited: usize,
+}
+
+impl BulkEdit {
+    /// True when the text came back as it was written.
+    pub fn is_unchanged(&self) -> bool {
+        self.added == 0 && self.removed == 0 && self.edited == 0
+    }
+}
+
+/// One todo as a line of text.
+///
+/// ```
+/// let mut todo = tuido::Todo::new("file taxes");
+/// todo.priority = Some('A');
+/// todo.completed = true;
+/// assert_eq!(tuido::bulkedit::line(&todo), "x (A) file taxes");
+/// ```
+pub fn line(todo: &Todo) -> String {
+    if todo.completed {
+        format!("x {}", todo.input_text())
+    } else {
+        todo.input_text()
+    }
+}
+
+/// The whole list as text, one [`line()`] per todo.
+pub fn to_text(todos: &[Todo]) -> String {
+    todos.iter().map(|todo| line(todo) + "\n").collect()
+}
+
+/// Reads edited text back against the `todos` it was written from. Lines
+/// are lined up with the originals the way a diff would: unchanged lines
+/// keep their todo, a changed line takes the place of the line it replaced
+/// (keeping that todo's note and dates), removed lines delete their todo,
+/// and new lines become new todos created `today`. Where lines were both
+/// changed and added or removed, changed lines go to the most alike of the
+/// lines they could have replaced. Blank lines are ignored.
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use tuido::Todo;
+///
+/// let today = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+/// let mut todos = vec![Todo::new("buy milk"), Todo::new("call bob"), Todo::new("fix bike")];
+/// todos[1].note = Some("about friday".to_string());
+/// todos[2].note = Some("back tire".to_string());
+///
+/// let edit = tuido::bulkedit::apply(&todos, "call bob\nx fix bike today\nwater plants\n", today);
+/// let lines: Vec<String> = edit.todos.iter().map(tuido::bulkedit::line).collect();
+/// assert_eq!(lines, ["call bob", "x fix bike today", "water plants"]);
+/// assert_eq!((edit.added, edit.removed, edit.edited), (1, 1, 1));
+/// // Unchanged and edited lines keep their notes
+/// assert_eq!(edit.todos[0].note.as_deref(), Some("about friday"));
+/// assert_eq!(edit.todos[1].note.as_deref(), Some("back tire"));
+/// assert_eq!(edit.todos[1].completed_on, Some(today));
+///
+/// // Deleting one line and editing the next keeps the edited todo's note
+/// let edit = tuido::bulkedit::apply(&todos, "(A) call bob\nfix bike\n", today);
+/// assert_eq!((edit.added, edit.removed, edit.edited), (0, 1, 1));
+/// assert_eq!(edit.todos[0].priority, Some('A'));
+/// assert_eq!(edit.todos[0].note.as_deref(), Some("about friday"));
+/// ```
+pub fn apply(todos: &[Todo], text: &str, today: NaiveDate) -> BulkEdit {
+    let old: Vec<String> = todos.iter().map(line).collect();
+    let new: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    // Longest common subsequence of lines, filled from the end so the walk
+    // below can go forwards
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut edit = BulkEdit {
+        todos: Vec::with_capacity(new.len()),
+        added: 0,
+        removed: 0,
+        edited: 0,
+    };
+    let (mut i, mut j) = (0, 0);
+    // Lines dropped and added since the last unchanged one
+    let mut dropped: Vec<usize> = Vec::new();
+    let mut inserted: Vec<&str> = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            edit.flush(todos, &mut dropped, &mut inserted, today);
+            edit.todos.push(todos[i].clone());
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            inserted.push(new[j]);
+            j += 1;
+        } else {
+            dropped.push(i);
+            i += 1;
+        }
+    }
+    edit.flush(todos, &mut dropped, &mut inserted, today);
+    edit
+}
+
+impl BulkEdit {
+    // Settles the lines dropped and added between two unchanged ones: each
+    // added line paired with a dropped one is an edit of it, the rest are
+    // additions or removals
+    fn flush(
+        &mut self,
+        todos: &[Todo],
+        dropped: &mut Vec<usize>,
+        inserted: &mut Vec<&str>,
+        today: NaiveDate,
+    ) {
+        let originals: Vec<String> = dropped.iter().map(|&i| line(&todos[i])).collect();
+        let pairs = pair(inserted, &originals);
+        self.removed += dropped.len() - pairs.iter().flatten().count();
+        for (text, original) in inserted.drain(..).zip(pairs) {
+            let (completed, input) = match text.strip_prefix("x ") {
+                Some(rest) => (true, rest),
+                None => (false, text),
+            };
+            let mut todo = match original {
+                Some(k) => {
+                    self.edited += 1;
+                    todos[dropped[k]].clone()
+                }
+                None => {
+                    self.added += 1;
+                    Todo {
+                        created: Some(today),
+                        ..Default::default()
+                    }
+                }
+            };
+            todo.apply_input(input);
+            todo.set_completed(completed, today);
+            self.todos.push(todo);
+        }
+        dropped.clear();
+    }
+}
+
+// For each added line, the dropped line it's an edit of, if any. With as
+// many of each they pair up in order, so a rewritten line stays the same
+// todo however much it changed. Otherwise every line on the shorter side
+// is paired with one on the longer, in order, choosing the pairs whose
+// text is most alike.
+fn pair(inserted: &[&str], dropped: &[String]) -> Vec<Option<usize>> {
+    let (n, m) = (inserted.len(), dropped.len());
+    if n == m {
+        return (0..n).map(Some).collect();
+    }
+    // best[a][b]: the most total likeness pairing up inserted[a..] with
+    // dropped[b..], or -inf when the shorter side can't all be paired
+    let mut best = vec![vec![f64::NEG_INFINITY; m + 1]; n + 1];
+    for a in (0..=n).rev() {
+        for b in (0..=m).rev() {
+            best[a][b] = if (n < m && a == n) || (n > m && b == m) {
+                0.0
+            } else if a == n || b == m {
+                f64::NEG_INFINITY
+            } else {
+                let paired = similarity(inserted[a], &dropped[b]) + best[a + 1][b + 1];
+                let skipped = if n < m {
+                    best[a][b + 1]
+                } else {
+                    best[a + 1][b]
+                };
+                paired.max(skipped)
+            };
+        }
+    }
+
+    let mut pairs = vec![None; n];
+    let (mut a, mut b) = (0, 0);
+    while a < n && b < m {
+        if best[a][b] == similarity(inserted[a], &dropped[b]) + best[a + 1][b + 1] {
+            pairs[a] = Some(b);
+            a += 1;
+            b += 1;
+        } else if n < m {
+            b += 1;
+        } else {
+            a += 1;
+        }
+    }
+    pairs
+}
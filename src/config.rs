@@ -0,0 +1,190 @@
+/// One `key = value` line from a config file and the `[section]` it's in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub section: String,
+    pub key: String,
+    pub value: String,
+    /// 1-based, for error messages.
+    pub line: usize,
+}
+
+/// Reads the small subset of TOML that tuido's config uses: `[section]`
+/// headers, `key = value` pairs whose value is a quoted string or a bare
+/// word, and `#` comments. Lines that don't fit are described in the
+/// returned errors and skipped.
+///
+/// ```
+/// let (entries, errors) = tuido::config::parse(
+///     "[colors]\npriority_a = \"#ff5f5f\" # salmon\n\n[keys]\ndelete = 'D'\noops\n",
+/// );
+/// assert_eq!(entries[0].section, "colors");
+/// assert_eq!(entries[0].value, "#ff5f5f");
+/// assert_eq!((entries[1].key.as_str(), entries[1].value.as_str()), ("delete", "D"));
+/// assert_eq!(errors, ["line 6: expected key = value"]);
+/// ```
+pub fn parse(contents: &str) -> (Vec<Entry>, Vec<String>) {
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    let mut section = String::new();
+
+    for (i, raw) in contents.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            match header.split_once(']') {
+                Some((name, rest)) if is_comment_or_empty(rest) => {
+                    section = name.trim().to_string();
+                }
+                _ => errors.push(format!("line {}: malformed section header", i + 1)),
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            errors.push(format!("line {}: expected key = value", i + 1));
+            continue;
+        };
+        let key = key.trim().trim_matches('"');
+        match parse_value(value.trim()) {
+            Some(value) if !key.is_empty() => entries.push(Entry {
+                section: section.clone(),
+                key: key.to_string(),
+                value,
+                line: i + 1,
+            }),
+            _ => errors.push(format!("line {}: can't read the value of '{}'", i + 1, key)),
+        }
+    }
+    (entries, errors)
+}
+
+/// Sets `key` in `[section]` of a config file's text, or removes it when
+/// `value` is None, leaving every other line (comments included) as it was.
+/// A new key goes at the end of its section, and a new section at the end
+/// of the file.
+///
+/// ```
+/// use tuido::config::set_value;
+///
+/// let config = "[aliases]\nsp = \"sort priority\" # daily\n\n[keys]\ndelete = 'D'\n";
+/// let config = set_value(config, "aliases", "sd", Some("sort due"));
+/// assert_eq!(
+///     config,
+///     "[aliases]\nsp = \"sort priority\" # daily\nsd = \"sort due\"\n\n[keys]\ndelete = 'D'\n",
+/// );
+/// let config = set_value(&config, "aliases", "sp", None);
+/// assert!(!config.contains("sp ="));
+/// assert_eq!(set_value("", "aliases", "w!", Some("wq")), "[aliases]\n\"w!\" = \"wq\"\n");
+/// assert_eq!(set_value("", "options", "number", Some("true")), "[options]\nnumber = true\n");
+/// ```
+pub fn set_value(contents: &str, section: &str, key: &str, value: Option<&str>) -> String {
+    let line = value.map(|value| {
+        // Booleans and numbers stay bare, as TOML has them
+        let escaped = if matches!(value, "true" | "false")
+            || (!value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()))
+        {
+            value.to_string()
+        } else {
+            format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+        };
+        // TOML's bare keys are letters, digits, `-` and `_`
+        let bare = key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if bare {
+            format!("{} = {}", key, escaped)
+        } else {
+            format!("\"{}\" = {}", key, escaped)
+        }
+    });
+    let mut lines: Vec<String> = contents.lines().map(str::to_string).collect();
+    let mut current = String::new();
+    // Where the section's last key = value line is, to add after it
+    let mut end = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let trimmed = lines[i].trim();
+        if let Some(header) = trimmed.strip_prefix('[') {
+            current = header.split(']').next().unwrap_or("").trim().to_string();
+            if current == section {
+                end = Some(i);
+            }
+        } else if current == section && !trimmed.is_empty() && !trimmed.starts_with('#') {
+            end = Some(i);
+            let existing = trimmed
+                .split_once('=')
+                .map(|(k, _)| k.trim().trim_matches('"'));
+            if existing == Some(key) {
+                match &line {
+                    Some(line) => {
+                        lines[i] = line.clone();
+                        return join_lines(&lines);
+                    }
+                    None => {
+                        lines.remove(i);
+                        continue;
+                    }
+                }
+            }
+        }
+        i += 1;
+    }
+    if let Some(line) = line {
+        match end {
+            Some(end) => lines.insert(end + 1, line),
+            None => {
+                if lines.last().is_some_and(|last| !last.trim().is_empty()) {
+                    lines.push(String::new());
+                }
+                lines.push(format!("[{}]", section));
+                lines.push(line);
+            }
+        }
+    }
+    join_lines(&lines)
+}
+
+fn join_lines(lines: &[String]) -> String {
+    let mut text = lines.join("\n");
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    text
+}
+
+fn is_comment_or_empty(text: &str) -> bool {
+    let text = text.trim();
+    text.is_empty() || text.starts_with('#')
+}
+
+/// A `"basic"` string (with `\"` and `\\` escapes), a `'literal'` string,
+/// or a bare word, any of them optionally followed by a comment.
+fn parse_value(text: &str) -> Option<String> {
+    let mut chars = text.chars();
+    match chars.next()? {
+        '"' => {
+            let mut value = String::new();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => return is_comment_or_empty(chars.as_str()).then_some(value),
+                    '\\' => match chars.next()? {
+                        'n' => value.push('\n'),
+                        't' => value.push('\t'),
+                        escaped => value.push(escaped),
+                    },
+                    c => value.push(c),
+                }
+            }
+            None
+        }
+        '\'' => {
+            let (value, rest) = chars.as_str().split_once('\'')?;
+            is_comment_or_empty(rest).then(|| value.to_string())
+        }
+        _ => {
+            let value = text.split('#').next().unwrap_or("").trim();
+            (!value.is_empty()).then(|| value.to_string())
+        }
+    }
+}
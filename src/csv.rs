@@ -0,0 +1,181 @@
+use chrono::NaiveDate;
+
+use crate::Todo;
+
+/// Column order used by [`to_string`] and expected by [`parse_todos`] when the
+/// file has no header row.
+pub const COLUMNS: [&str; 5] = ["text", "completed", "priority", "note", "due"];
+
+/// Splits RFC 4180 style text into rows of fields. Quoted fields may contain
+/// the delimiter, doubled quotes and newlines; blank rows are dropped.
+///
+/// ```
+/// let rows = tuido::csv::parse("a,\"b, \"\"c\"\"\"\n", ',');
+/// assert_eq!(rows, vec![vec!["a".to_string(), "b, \"c\"".to_string()]]);
+/// ```
+pub fn parse(contents: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(c);
+            }
+        } else if c == '"' && field.is_empty() {
+            in_quotes = true;
+        } else if c == delimiter {
+            row.push(std::mem::take(&mut field));
+        } else if c == '\n' || c == '\r' {
+            if c == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            row.push(std::mem::take(&mut field));
+            if row.iter().any(|f| !f.is_empty()) {
+                rows.push(std::mem::take(&mut row));
+            } else {
+                row.clear();
+            }
+        } else {
+            field.push(c);
+        }
+    }
+    row.push(field);
+    if row.iter().any(|f| !f.is_empty()) {
+        rows.push(row);
+    }
+    rows
+}
+
+/// Quotes a field when it contains a comma, quote or line break.
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Writes todos as CSV with a header row (see [`COLUMNS`]).
+///
+/// ```
+/// let mut todo = tuido::Todo::new("call, then \"email\"");
+/// todo.note = Some("line 1\nline 2".to_string());
+/// let csv = tuido::csv::to_string(&[todo.clone()]);
+/// let (todos, errors) = tuido::csv::parse_todos(&csv);
+/// assert_eq!(todos, vec![todo]);
+/// assert!(errors.is_empty());
+/// ```
+pub fn to_string(todos: &[Todo]) -> String {
+    let mut output = COLUMNS.join(",") + "\r\n";
+    for todo in todos {
+        let fields = [
+            quote(&todo.text),
+            todo.completed.to_string(),
+            todo.priority.map(String::from).unwrap_or_default(),
+            quote(todo.note.as_deref().unwrap_or("")),
+            todo.due
+                .map(|d| d.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+        ];
+        output.push_str(&fields.join(","));
+        output.push_str("\r\n");
+    }
+    output
+}
+
+/// Reads CSV in the [`to_string`] layout. A header row is used to find the
+/// columns when present; otherwise they're taken in [`COLUMNS`] order. Rows
+/// that can't be read are left out and described in the returned errors.
+///
+/// ```
+/// let (todos, errors) = tuido::csv::parse_todos("milk,false,A\nbread,maybe\n");
+/// assert_eq!(todos.len(), 1);
+/// assert_eq!(errors, ["row 2: completed should be true or false, not 'maybe'"]);
+/// ```
+pub fn parse_todos(contents: &str) -> (Vec<Todo>, Vec<String>) {
+    let mut rows = parse(contents, ',').into_iter().peekable();
+    let mut columns: Vec<String> = COLUMNS.iter().map(|c| c.to_string()).collect();
+    let has_header = rows
+        .peek()
+        .and_then(|row| row.first())
+        .is_some_and(|first| first.trim().eq_ignore_ascii_case("text"));
+    let mut row_number = 0;
+    if has_header {
+        columns = rows
+            .next()
+            .unwrap_or_default()
+            .iter()
+            .map(|c| c.trim().to_lowercase())
+            .collect();
+        row_number += 1;
+    }
+
+    let mut todos = Vec::new();
+    let mut errors = Vec::new();
+    for row in rows {
+        row_number += 1;
+        let field = |name: &str| -> &str {
+            columns
+                .iter()
+                .position(|c| c == name)
+                .and_then(|i| row.get(i))
+                .map_or("", |f| f.as_str())
+        };
+        match read_row(field) {
+            Ok(todo) => todos.push(todo),
+            Err(e) => errors.push(format!("row {}: {}", row_number, e)),
+        }
+    }
+    (todos, errors)
+}
+
+fn read_row<'a>(field: impl Fn(&str) -> &'a str) -> Result<Todo, String> {
+    let text = field("text").trim();
+    if text.is_empty() {
+        return Err("empty text".to_string());
+    }
+    let completed = match field("completed").trim().to_lowercase().as_str() {
+        "true" | "yes" | "1" | "x" => true,
+        "false" | "no" | "0" | "" => false,
+        other => {
+            return Err(format!(
+                "completed should be true or false, not '{}'",
+                other
+            ));
+        }
+    };
+    let priority = match field("priority").trim() {
+        "" => None,
+        p if p.len() == 1 && p.as_bytes()[0].is_ascii_alphabetic() => {
+            p.chars().next().map(|c| c.to_ascii_uppercase())
+        }
+        p => return Err(format!("priority should be a letter, not '{}'", p)),
+    };
+    let note = Some(field("note").to_string()).filter(|n| !n.trim().is_empty());
+    let due = match field("due").trim() {
+        "" => None,
+        d => Some(
+            NaiveDate::parse_from_str(d, "%Y-%m-%d")
+                .map_err(|_| format!("due should be YYYY-MM-DD, not '{}'", d))?,
+        ),
+    };
+    Ok(Todo {
+        text: text.to_string(),
+        completed,
+        priority,
+        note,
+        due,
+        ..Default::default()
+    })
+}
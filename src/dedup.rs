@@ -0,0 +1,95 @@
+//! Finding and folding together repeated todos, as left behind by importing
+//! the same file twice.
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+use crate::Todo;
+
+/// What two todos must share to count as duplicates: their text, ignoring
+/// case and surrounding or repeated whitespace.
+///
+/// ```
+/// use tuido::dedup::dedup_key;
+///
+/// assert_eq!(dedup_key("  Call  Bob "), dedup_key("call bob"));
+/// assert_ne!(dedup_key("call bob"), dedup_key("call rob"));
+/// ```
+pub fn dedup_key(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Removes every todo whose [`dedup_key`] matches an earlier one. Of each
+/// set of duplicates, the one carrying the most (a note, a priority) is
+/// kept, the first of them on a tie, in the place of the first duplicate.
+/// Notes from the removed todos that the kept one doesn't already have are
+/// added to its note, so nothing written down is lost.
+///
+/// Returns where each todo ended up: for every old index, the new index of
+/// the todo it was kept as or folded into.
+///
+/// ```
+/// use tuido::Todo;
+///
+/// let mut todos = vec![
+///     Todo::new("call bob"),
+///     Todo::new("buy milk"),
+///     Todo::new("Call Bob "),
+///     Todo::new("call bob"),
+/// ];
+/// todos[2].priority = Some('A');
+/// todos[3].note = Some("ask about friday".to_string());
+///
+/// let moved = tuido::dedup::dedup(&mut todos);
+/// assert_eq!(todos.len(), 2);
+/// assert_eq!(moved, [0, 1, 0, 0]);
+/// // The prioritized copy is kept first, and picks up the other's note
+/// assert_eq!(todos[0].text, "Call Bob ");
+/// assert_eq!(todos[0].priority, Some('A'));
+/// assert_eq!(todos[0].note.as_deref(), Some("ask about friday"));
+/// ```
+pub fn dedup(todos: &mut Vec<Todo>) -> Vec<usize> {
+    let mut kept: Vec<Todo> = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut moved = Vec::with_capacity(todos.len());
+    for todo in todos.drain(..) {
+        match seen.entry(dedup_key(&todo.text)) {
+            Entry::Occupied(entry) => {
+                absorb(&mut kept[*entry.get()], todo);
+                moved.push(*entry.get());
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(kept.len());
+                moved.push(kept.len());
+                kept.push(todo);
+            }
+        }
+    }
+    *todos = kept;
+    moved
+}
+
+// How much a todo carries beyond its text
+fn richness(todo: &Todo) -> usize {
+    usize::from(todo.note.is_some()) + usize::from(todo.priority.is_some())
+}
+
+fn absorb(kept: &mut Todo, mut other: Todo) {
+    if richness(&other) > richness(kept) {
+        std::mem::swap(kept, &mut other);
+    }
+    let Some(note) = other.note.filter(|note| !note.trim().is_empty()) else {
+        return;
+    };
+    match &mut kept.note {
+        Some(existing) if existing.contains(note.as_str()) => {}
+        Some(existing) => {
+            existing.push_str("\n\n");
+            existing.push_str(&note);
+        }
+        None => kept.note = Some(note),
+    }
+}
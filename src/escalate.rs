@@ -0,0 +1,80 @@
+use chrono::NaiveDate;
+
+use crate::Todo;
+
+#[cfg(test)]
+mod tests;
+
+/// Todos with this `key:value` field (any value) are never escalated.
+pub const OPT_OUT_KEY: &str = "noescalate";
+
+/// Reads a period such as `14d`, `2w` or a plain number of days.
+///
+/// ```
+/// assert_eq!(tuido::escalate::parse_period("14d"), Some(14));
+/// assert_eq!(tuido::escalate::parse_period("2w"), Some(14));
+/// assert_eq!(tuido::escalate::parse_period("0d"), None);
+/// ```
+pub fn parse_period(value: &str) -> Option<i64> {
+    let value = value.trim().to_lowercase();
+    let (number, unit) = match value.strip_suffix('w') {
+        Some(weeks) => (weeks, 7),
+        None => (value.strip_suffix('d').unwrap_or(&value), 1),
+    };
+    number
+        .parse::<i64>()
+        .ok()
+        .filter(|&n| n > 0)
+        .map(|n| n * unit)
+}
+
+/// The priority one step above: none becomes C, C becomes B and so on.
+/// A is already the top.
+fn raised(priority: Option<char>) -> Option<char> {
+    match priority {
+        None => Some('C'),
+        Some('A') => None,
+        Some(p) => Some((p as u8 - 1) as char),
+    }
+}
+
+/// True when `todo` is pending, at least `period_days` old and hasn't been
+/// escalated within the last `period_days`.
+pub fn is_stale(todo: &Todo, today: NaiveDate, period_days: i64) -> bool {
+    let old_enough = |date: NaiveDate| (today - date).num_days() >= period_days;
+    !todo.completed
+        && raised(todo.priority).is_some()
+        && !todo.meta.contains_key(OPT_OUT_KEY)
+        && todo.created.is_some_and(old_enough)
+        && todo.escalated.is_none_or(old_enough)
+}
+
+/// Raises every stale todo (see [`is_stale`]) one priority step, records
+/// `today` as when it happened and returns how many changed.
+///
+/// ```
+/// use chrono::NaiveDate;
+///
+/// let day = |d| NaiveDate::from_ymd_opt(2025, 3, d).unwrap();
+/// let mut todos = vec![tuido::Todo::new("file taxes")];
+/// todos[0].created = Some(day(1));
+///
+/// assert_eq!(tuido::escalate::escalate(&mut todos, day(14), 14), 0);
+/// assert_eq!(tuido::escalate::escalate(&mut todos, day(15), 14), 1);
+/// assert_eq!(todos[0].priority, Some('C'));
+/// // Once per period
+/// assert_eq!(tuido::escalate::escalate(&mut todos, day(28), 14), 0);
+/// assert_eq!(tuido::escalate::escalate(&mut todos, day(29), 14), 1);
+/// assert_eq!(todos[0].priority, Some('B'));
+/// ```
+pub fn escalate(todos: &mut [Todo], today: NaiveDate, period_days: i64) -> usize {
+    let mut count = 0;
+    for todo in todos.iter_mut() {
+        if is_stale(todo, today, period_days) {
+            todo.priority = raised(todo.priority);
+            todo.escalated = Some(today);
+            count += 1;
+        }
+    }
+    count
+}
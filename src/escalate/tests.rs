@@ -0,0 +1,61 @@
+use super::*;
+
+fn day(d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 3, d).unwrap()
+}
+
+fn created_on(d: u32) -> Todo {
+    let mut todo = Todo::new("file taxes");
+    todo.created = Some(day(d));
+    todo
+}
+
+#[test]
+fn escalation_waits_a_full_period_between_steps() {
+    let mut todos = vec![created_on(1)];
+    let priorities: Vec<_> = (1..=31)
+        .map(|d| {
+            escalate(&mut todos, day(d), 7);
+            todos[0].priority
+        })
+        .collect();
+    // Raised on days 8, 15, 22; A is the top, so day 29 changes nothing
+    assert_eq!(priorities[6], None);
+    assert_eq!(priorities[7], Some('C'));
+    assert_eq!(priorities[13], Some('C'));
+    assert_eq!(priorities[14], Some('B'));
+    assert_eq!(priorities[21], Some('A'));
+    assert_eq!(priorities[30], Some('A'));
+    assert_eq!(todos[0].escalated, Some(day(22)));
+}
+
+#[test]
+fn the_last_escalation_is_remembered_per_todo() {
+    let mut todos = vec![created_on(1), created_on(1)];
+    todos[1].escalated = Some(day(10));
+    assert_eq!(escalate(&mut todos, day(15), 14), 1);
+    assert_eq!(todos[0].escalated, Some(day(15)));
+    assert_eq!(todos[1].escalated, Some(day(10)));
+    assert_eq!(todos[1].priority, None);
+    assert_eq!(escalate(&mut todos, day(24), 14), 1);
+    assert_eq!(todos[1].priority, Some('C'));
+}
+
+#[test]
+fn some_todos_are_never_escalated() {
+    let mut done = created_on(1);
+    done.completed = true;
+    let mut opted_out = created_on(1);
+    opted_out
+        .meta
+        .insert(OPT_OUT_KEY.to_string(), "yes".to_string());
+    let mut top = created_on(1);
+    top.priority = Some('A');
+    let undated = Todo::new("no creation date");
+    let young = created_on(20);
+
+    let mut todos = vec![done, opted_out, top, undated, young];
+    let before = todos.clone();
+    assert_eq!(escalate(&mut todos, day(30), 14), 0);
+    assert_eq!(todos, before);
+}
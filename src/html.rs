@@ -0,0 +1,136 @@
+//! A standalone HTML page of the list, for sharing with people who don't
+//! use tuido. Everything the page needs is inline, so the one file can be
+//! attached to an email or opened straight from disk.
+
+use chrono::NaiveDateTime;
+
+use crate::Todo;
+
+const STYLE: &str = "\
+body { font-family: system-ui, sans-serif; max-width: 48rem; margin: 2rem auto; padding: 0 1rem; color: #222; }
+header p { color: #666; margin-top: 0; }
+ol { padding-left: 2rem; }
+li { margin: 0.4rem 0; }
+li.done .text { text-decoration: line-through; color: #888; }
+.priority { display: inline-block; min-width: 1.2em; padding: 0 0.3em; border-radius: 0.3em; color: #fff; font-size: 0.8em; font-weight: bold; text-align: center; }
+.priority-a { background: #c0392b; }
+.priority-b { background: #b7950b; }
+.priority-c { background: #2e6fd8; }
+.priority-other { background: #888; }
+.due, .meta { color: #666; font-size: 0.9em; }
+details { margin: 0.2rem 0 0 1.6rem; }
+.note { white-space: pre-wrap; color: #444; }
+";
+
+/// Escapes the characters HTML gives a meaning to, so todo text can't add
+/// markup or scripts to the page.
+///
+/// ```
+/// assert_eq!(
+///     tuido::html::escape("<script>alert(\"hi\")</script> & co"),
+///     "&lt;script&gt;alert(&quot;hi&quot;)&lt;/script&gt; &amp; co"
+/// );
+/// ```
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Renders todos as an HTML page: a header with the export time and
+/// counts, then a numbered checklist. Priorities get a badge in the same
+/// colors as the list in tuido, completed todos are struck through and
+/// notes fold out of a `<details>` block.
+///
+/// ```
+/// use chrono::NaiveDate;
+///
+/// let exported = NaiveDate::from_ymd_opt(2025, 3, 1)
+///     .unwrap()
+///     .and_hms_opt(9, 30, 0)
+///     .unwrap();
+/// let mut todo = tuido::Todo::new("fix <b>bold</b> bug");
+/// todo.priority = Some('A');
+/// todo.completed = true;
+/// todo.note = Some("seen in 1.2".to_string());
+///
+/// let page = tuido::html::to_string(&[todo], exported);
+/// assert!(page.contains("Exported 2025-03-01 09:30 · 1 todo, 1 completed"));
+/// assert!(page.contains(r#"<li class="done"><input type="checkbox" checked disabled>"#));
+/// assert!(page.contains(r#"<span class="priority priority-a">A</span>"#));
+/// assert!(page.contains("fix &lt;b&gt;bold&lt;/b&gt; bug"));
+/// assert!(page.contains(r#"<details><summary>Note</summary><div class="note">seen in 1.2</div></details>"#));
+/// ```
+pub fn to_string(todos: &[Todo], exported: NaiveDateTime) -> String {
+    let completed = todos.iter().filter(|t| t.completed).count();
+    let mut page = String::new();
+    page.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    page.push_str("<title>TODOs</title>\n");
+    page.push_str(&format!("<style>\n{}</style>\n</head>\n<body>\n", STYLE));
+    page.push_str(&format!(
+        "<header>\n<h1>TODOs</h1>\n<p>Exported {} · {} {}, {} completed</p>\n</header>\n",
+        exported.format("%Y-%m-%d %H:%M"),
+        todos.len(),
+        if todos.len() == 1 { "todo" } else { "todos" },
+        completed
+    ));
+    page.push_str("<ol>\n");
+    for todo in todos {
+        page.push_str(&list_item(todo));
+    }
+    page.push_str("</ol>\n</body>\n</html>\n");
+    page
+}
+
+fn list_item(todo: &Todo) -> String {
+    let mut item = if todo.completed {
+        String::from("<li class=\"done\"><input type=\"checkbox\" checked disabled>")
+    } else {
+        String::from("<li><input type=\"checkbox\" disabled>")
+    };
+    if let Some(priority) = todo.priority {
+        let class = match priority {
+            'A' | 'B' | 'C' => format!("priority-{}", priority.to_ascii_lowercase()),
+            _ => "priority-other".to_string(),
+        };
+        item.push_str(&format!(
+            " <span class=\"priority {}\">{}</span>",
+            class,
+            escape(&priority.to_string())
+        ));
+    }
+    item.push_str(&format!(
+        " <span class=\"text\">{}</span>",
+        escape(&todo.text)
+    ));
+    if let Some(due) = todo.due {
+        item.push_str(&format!(
+            " <span class=\"due\">due {}</span>",
+            due.format("%Y-%m-%d")
+        ));
+    }
+    for (key, value) in &todo.meta {
+        item.push_str(&format!(
+            " <span class=\"meta\">{}:{}</span>",
+            escape(key),
+            escape(value)
+        ));
+    }
+    if let Some(note) = todo.note.as_deref().filter(|n| !n.trim().is_empty()) {
+        item.push_str(&format!(
+            "\n<details><summary>Note</summary><div class=\"note\">{}</div></details>",
+            escape(note)
+        ));
+    }
+    item.push_str("</li>\n");
+    item
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use crate::{Todo, csv, todotxt};
+
+fn imported(title: &str, parent: Option<&str>, completed: bool, notes: &str, due: &str) -> Todo {
+    let text = match parent {
+        Some(parent) => format!("{}: {}", parent, title),
+        None => title.to_string(),
+    };
+    Todo {
+        text,
+        completed,
+        note: if notes.trim().is_empty() {
+            None
+        } else {
+            Some(notes.trim().to_string())
+        },
+        due: due.get(..10).and_then(todotxt::parse_date),
+        ..Default::default()
+    }
+}
+
+/// Reads a Google Takeout `Tasks.json`: `{"items": [task lists]}` with each
+/// list holding its own `items`. Sub-tasks become `parent: child` text.
+/// Returns the todos and how many entries were skipped.
+pub fn google_tasks(contents: &str) -> Result<(Vec<Todo>, usize), String> {
+    let root: serde_json::Value =
+        serde_json::from_str(contents).map_err(|e| format!("Invalid Google Tasks JSON: {}", e))?;
+    let lists: Vec<&serde_json::Value> = match root.get("items").and_then(|v| v.as_array()) {
+        Some(items) => items.iter().collect(),
+        None => vec![&root],
+    };
+
+    let mut todos = Vec::new();
+    let mut skipped = 0;
+    for list in lists {
+        let Some(tasks) = list.get("items").and_then(|v| v.as_array()) else {
+            skipped += 1;
+            continue;
+        };
+        let field = |task: &serde_json::Value, key: &str| {
+            task.get(key)
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string()
+        };
+        let titles: HashMap<String, String> = tasks
+            .iter()
+            .map(|t| (field(t, "id"), field(t, "title")))
+            .collect();
+
+        for task in tasks {
+            let title = field(task, "title");
+            let is_task = task
+                .get("kind")
+                .and_then(|v| v.as_str())
+                .is_none_or(|k| k == "tasks#task");
+            let deleted = task
+                .get("deleted")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            if !is_task || deleted || title.trim().is_empty() {
+                skipped += 1;
+                continue;
+            }
+            let parent = titles
+                .get(&field(task, "parent"))
+                .filter(|p| !p.trim().is_empty());
+            todos.push(imported(
+                title.trim(),
+                parent.map(|p| p.trim()),
+                field(task, "status") == "completed",
+                &field(task, "notes"),
+                &field(task, "due"),
+            ));
+        }
+    }
+    Ok((todos, skipped))
+}
+
+/// Reads an Apple Reminders export: CSV (or tab-separated) with a header row.
+/// Column names are matched loosely and missing columns are treated as
+/// empty. Returns the todos and how many rows were skipped.
+///
+/// ```
+/// let (todos, skipped) = tuido::import::reminders("Title,Completed\nmilk,yes\n,\n");
+/// assert_eq!(todos[0].text, "milk");
+/// assert!(todos[0].completed);
+/// assert_eq!(skipped, 0);
+/// ```
+pub fn reminders(contents: &str) -> (Vec<Todo>, usize) {
+    let delimiter = match contents.lines().next() {
+        Some(header) if header.contains('\t') && !header.contains(',') => '\t',
+        _ => ',',
+    };
+    let mut rows = csv::parse(contents, delimiter).into_iter();
+    let Some(header) = rows.next() else {
+        return (Vec::new(), 0);
+    };
+    let header: Vec<String> = header.iter().map(|h| h.trim().to_lowercase()).collect();
+    let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+    let title_col = column(&["title", "name", "reminder", "task"]);
+    let notes_col = column(&["notes", "note", "body"]);
+    let completed_col = column(&["completed", "done", "is completed", "status"]);
+    let due_col = column(&["due", "due date", "duedate"]);
+    let parent_col = column(&["parent", "parent title"]);
+    let type_col = column(&["type", "kind"]);
+
+    let mut todos = Vec::new();
+    let mut skipped = 0;
+    for row in rows {
+        let cell =
+            |col: Option<usize>| col.and_then(|c| row.get(c)).map(|v| v.trim()).unwrap_or("");
+        let title = cell(title_col);
+        let is_list = matches!(
+            cell(type_col).to_lowercase().as_str(),
+            "list" | "group" | "section"
+        );
+        if title.is_empty() || is_list {
+            skipped += 1;
+            continue;
+        }
+        let completed = matches!(
+            cell(completed_col).to_lowercase().as_str(),
+            "true" | "yes" | "1" | "x" | "completed" | "done"
+        );
+        let parent = Some(cell(parent_col)).filter(|p| !p.is_empty());
+        todos.push(imported(
+            title,
+            parent,
+            completed,
+            cell(notes_col),
+            cell(due_col),
+        ));
+    }
+    (todos, skipped)
+}
@@ -0,0 +1,40 @@
+//! Everything in tuido but the terminal setup and event loop: the [`Todo`]
+//! type, the text conventions layered on it (priorities, due dates, tags),
+//! search matching and substitution, terminal text widths, list statistics,
+//! editing the list as text, and the file formats tuido reads and writes or
+//! exports to, plus the application state in [`app`] and its rendering in
+//! [`ui`].
+//!
+//! ```
+//! use tuido::{Todo, parse_priority};
+//!
+//! let (priority, text) = parse_priority("(A) ship release");
+//! let mut todo = Todo::new(text);
+//! todo.priority = priority;
+//!
+//! let today = chrono::NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+//! assert_eq!(tuido::todotxt::format_line(&todo, today), "(A) ship release");
+//! ```
+
+pub mod app;
+pub mod bulkedit;
+pub mod config;
+pub mod csv;
+pub mod dedup;
+pub mod escalate;
+pub mod html;
+pub mod import;
+pub mod markdown;
+pub mod matching;
+pub mod stats;
+pub mod substitute;
+mod todo;
+pub mod todotxt;
+pub mod ui;
+pub mod validate;
+pub mod width;
+
+pub use todo::{
+    Todo, find_urls, lower_priority, parse_due, parse_meta, parse_priority, raise_priority,
+    split_meta, tag_kind, url_in,
+};
@@ -0,0 +1,197 @@
+use crate::{Todo, parse_due, parse_meta, parse_priority};
+
+/// Renders todos as a markdown checklist under a `# TODOs` heading and a
+/// count of the completed ones. Priorities are a bold `**(A)**` prefix, due
+/// dates and fields follow the text as typed, and notes are quoted under
+/// their todo. [`parse`] reads all of it back.
+///
+/// ```
+/// let mut done = tuido::Todo::new("ship");
+/// done.completed = true;
+/// done.priority = Some('A');
+/// done.note = Some("tag the release\nthen announce".to_string());
+/// let open = tuido::Todo::new("write changelog");
+///
+/// let doc = tuido::markdown::to_string(&[done.clone(), open.clone()]);
+/// assert_eq!(
+///     doc,
+///     "# TODOs\n\n1/2 completed\n\n\
+///      - [x] **(A)** ship\n  > tag the release\n  > then announce\n\
+///      - [ ] write changelog\n"
+/// );
+/// assert_eq!(tuido::markdown::parse(&doc), [done, open]);
+/// ```
+pub fn to_string(todos: &[Todo]) -> String {
+    let mut output = header(todos);
+    for todo in todos {
+        output.push_str(&item(todo));
+    }
+    output
+}
+
+/// Like [`to_string`], but with the todos under a `## (A)`, `## (B)`, ...
+/// heading per priority, and `## No priority` last. Each section keeps the
+/// list order.
+///
+/// ```
+/// let mut urgent = tuido::Todo::new("fix login");
+/// urgent.priority = Some('A');
+/// let todos = [tuido::Todo::new("tidy desk"), urgent];
+///
+/// let doc = tuido::markdown::to_string_grouped(&todos);
+/// assert!(doc.ends_with("## (A)\n\n- [ ] **(A)** fix login\n\n## No priority\n\n- [ ] tidy desk\n"));
+/// ```
+pub fn to_string_grouped(todos: &[Todo]) -> String {
+    let mut priorities: Vec<Option<char>> = todos.iter().map(|t| t.priority).collect();
+    // A to Z, then None
+    priorities.sort_by_key(|p| (p.is_none(), *p));
+    priorities.dedup();
+
+    let mut output = header(todos);
+    for (i, priority) in priorities.into_iter().enumerate() {
+        if i > 0 {
+            output.push('\n');
+        }
+        match priority {
+            Some(p) => output.push_str(&format!("## ({})\n\n", p)),
+            None => output.push_str("## No priority\n\n"),
+        }
+        for todo in todos.iter().filter(|t| t.priority == priority) {
+            output.push_str(&item(todo));
+        }
+    }
+    output
+}
+
+fn header(todos: &[Todo]) -> String {
+    let completed = todos.iter().filter(|t| t.completed).count();
+    format!("# TODOs\n\n{}/{} completed\n\n", completed, todos.len())
+}
+
+// One checklist item, with its note quoted on the lines after it
+fn item(todo: &Todo) -> String {
+    let checkbox = if todo.completed { "[x]" } else { "[ ]" };
+    let input = todo.input_text();
+    let text = match todo.priority {
+        // input_text starts with "(A) "
+        Some(priority) => format!("**({})** {}", priority, &input[4..]),
+        None => input,
+    };
+    let mut output = format!("- {} {}\n", checkbox, text);
+    if let Some(note) = todo.note.as_deref().filter(|n| !n.trim().is_empty()) {
+        for line in note.lines() {
+            if line.is_empty() {
+                output.push_str("  >\n");
+            } else {
+                output.push_str(&format!("  > {}\n", line));
+            }
+        }
+    }
+    output
+}
+
+/// Reads every checklist item (`- [ ]`, `- [x]`, `* [X]`, `1. [ ]`, ...) out
+/// of a markdown document and ignores everything else, including fenced
+/// code blocks. Each item's text is read like a typed todo (priority, also
+/// in bold as [`to_string`] writes it, `due:` and `key:value` fields), and
+/// indented items are flattened into `parent: child` text so their context
+/// survives without nesting. A `>` quote indented under an item becomes its
+/// note.
+///
+/// ```
+/// let doc = "# Launch\n\nSome prose.\n\n- [ ] Website\n  - [x] copy\n  - [ ] images\n* [X] Email list\n";
+/// let todos = tuido::markdown::parse(doc);
+/// let texts: Vec<&str> = todos.iter().map(|t| t.text.as_str()).collect();
+/// assert_eq!(texts, ["Website", "Website: copy", "Website: images", "Email list"]);
+/// assert!(todos[1].completed && todos[3].completed);
+/// ```
+pub fn parse(contents: &str) -> Vec<Todo> {
+    let mut todos: Vec<Todo> = Vec::new();
+    // (indent, text) of the checklist items enclosing the current line
+    let mut parents: Vec<(usize, String)> = Vec::new();
+    let mut in_code = false;
+    for line in contents.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        if let Some(quoted) = trimmed.strip_prefix('>')
+            && parents.last().is_some_and(|(i, _)| indent > *i)
+            && let Some(todo) = todos.last_mut()
+        {
+            let quoted = quoted.strip_prefix(' ').unwrap_or(quoted);
+            match &mut todo.note {
+                Some(note) => {
+                    note.push('\n');
+                    note.push_str(quoted);
+                }
+                None => todo.note = Some(quoted.to_string()),
+            }
+            continue;
+        }
+        let Some((completed, text)) = checklist_item(trimmed) else {
+            continue;
+        };
+        while parents.last().is_some_and(|(i, _)| *i >= indent) {
+            parents.pop();
+        }
+        let (priority, text) = parse_priority(&unbold_priority(text));
+        let (due, text) = parse_due(&text);
+        let (meta, text) = parse_meta(&text);
+        let full = match parents.last() {
+            Some((_, parent)) => format!("{}: {}", parent, text),
+            None => text,
+        };
+        parents.push((indent, full.clone()));
+        todos.push(Todo {
+            text: full,
+            completed,
+            priority,
+            due,
+            meta,
+            ..Default::default()
+        });
+    }
+    todos
+}
+
+// "**(A)** text" -> "(A) text"
+fn unbold_priority(text: &str) -> String {
+    match text.as_bytes() {
+        [b'*', b'*', b'(', _, b')', b'*', b'*', ..] if text.is_char_boundary(7) => {
+            format!("{}{}", &text[2..5], &text[7..])
+        }
+        _ => text.to_string(),
+    }
+}
+
+// "- [x] text" (or `*`, `+`, `1.`) -> (completed, text)
+fn checklist_item(line: &str) -> Option<(bool, &str)> {
+    let rest = match line.split_once(' ') {
+        Some((marker, rest))
+            if marker == "-"
+                || marker == "*"
+                || marker == "+"
+                || (marker.len() > 1
+                    && marker.ends_with(['.', ')'])
+                    && marker[..marker.len() - 1]
+                        .bytes()
+                        .all(|b| b.is_ascii_digit())) =>
+        {
+            rest.trim_start()
+        }
+        _ => return None,
+    };
+    let completed = match rest.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    let text = rest[3..].trim();
+    (!text.is_empty()).then_some((completed, text))
+}
@@ -0,0 +1,515 @@
+use regex::RegexBuilder;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::Todo;
+
+#[cfg(test)]
+mod tests;
+
+/// A search query starting with this is a regular expression.
+pub const REGEX_PREFIX: &str = "re:";
+
+/// How a query matched a todo's text, from strongest to weakest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchKind {
+    /// The query appears in the text (case-insensitive).
+    Substring,
+    /// The query's characters appear in order, e.g. "proj" in "project".
+    Subsequence,
+}
+
+/// A scored match of a query against one text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Match {
+    pub kind: MatchKind,
+    /// Higher is better. Only comparable between matches of the same kind.
+    pub score: i64,
+    /// Sorted char offsets to highlight: every occurrence of a substring
+    /// match, or the chars a subsequence match picked out.
+    pub positions: Vec<usize>,
+}
+
+// fzf-style scoring: every matched char earns SCORE_MATCH plus a bonus for
+// starting a word or continuing a run, and gaps between matched chars cost
+// GAP_START for the first skipped char and GAP_EXTENSION for each after it.
+const SCORE_MATCH: i64 = 16;
+const GAP_START: i64 = 3;
+const GAP_EXTENSION: i64 = 1;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CAMEL: i64 = 7;
+const BONUS_CONSECUTIVE: i64 = 4;
+// The query's first char counts its bonus twice, so where the match starts
+// matters most
+const FIRST_CHAR_MULTIPLIER: i64 = 2;
+
+/// Matches one text against a query the way tuido's search does.
+///
+/// ```
+/// use tuido::matching::{MatchKind, match_kind};
+///
+/// assert_eq!(match_kind("Write report", "report"), Some(MatchKind::Substring));
+/// assert_eq!(match_kind("project", "pjt"), Some(MatchKind::Subsequence));
+/// assert_eq!(match_kind("milk", "bread"), None);
+/// ```
+pub fn match_kind(text: &str, query: &str) -> Option<MatchKind> {
+    fuzzy_match(text, query).map(|m| m.kind)
+}
+
+/// Scores `text` against `query`, ignoring case. Matches at word starts and
+/// runs of consecutive chars score higher, gaps score lower.
+///
+/// ```
+/// use tuido::matching::fuzzy_match;
+///
+/// // Word starts beat the same letters buried inside words
+/// let starts = fuzzy_match("Fix Bug", "fb").unwrap();
+/// let buried = fuzzy_match("surfboard", "fb").unwrap();
+/// assert!(starts.score > buried.score);
+/// assert_eq!(starts.positions, [0, 4]);
+/// assert!(fuzzy_match("milk", "bread").is_none());
+/// ```
+pub fn fuzzy_match(text: &str, query: &str) -> Option<Match> {
+    let original: Vec<char> = text.chars().collect();
+    let chars = lowercase(&original);
+    let query: Vec<char> = query.chars().collect();
+    match_chars(&original, &chars, &lowercase(&query))
+}
+
+// One lowercase char per original char, so offsets line up with the text
+fn lowercase(chars: &[char]) -> Vec<char> {
+    chars
+        .iter()
+        .map(|&c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
+// fuzzy_match on a text already split into chars and lowercased
+fn match_chars(original: &[char], chars: &[char], query: &[char]) -> Option<Match> {
+    if query.is_empty() {
+        return Some(Match {
+            kind: MatchKind::Substring,
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+
+    let (score, aligned) = best_alignment(original, chars, query)?;
+    let occurrences = substring_positions(chars, query);
+    Some(if occurrences.is_empty() {
+        Match {
+            kind: MatchKind::Subsequence,
+            score,
+            positions: aligned,
+        }
+    } else {
+        Match {
+            kind: MatchKind::Substring,
+            score,
+            positions: occurrences,
+        }
+    })
+}
+
+/// The chars of `text` to highlight for `query` (see [`Match::positions`]).
+/// Empty when there's no match.
+///
+/// ```
+/// use tuido::matching::match_positions;
+///
+/// assert_eq!(match_positions("Pay rent, pay bills", "pay"), [0, 1, 2, 10, 11, 12]);
+/// assert_eq!(match_positions("project", "pjt"), [0, 3, 6]);
+/// assert!(match_positions("milk", "bread").is_empty());
+/// ```
+pub fn match_positions(text: &str, query: &str) -> Vec<usize> {
+    fuzzy_match(text, query)
+        .map(|m| m.positions)
+        .unwrap_or_default()
+}
+
+/// Indices of the todos matching `query`, in list order. An empty query
+/// matches everything.
+///
+/// ```
+/// use tuido::Todo;
+///
+/// let todos = vec![Todo::new("buy milk"), Todo::new("call bob")];
+/// assert_eq!(tuido::matching::match_todos(&todos, "milk"), vec![0]);
+/// assert_eq!(tuido::matching::match_todos(&todos, ""), vec![0, 1]);
+/// ```
+pub fn match_todos(todos: &[Todo], query: &str) -> Vec<usize> {
+    if query.is_empty() {
+        return (0..todos.len()).collect();
+    }
+    todos
+        .iter()
+        .enumerate()
+        .filter(|(_, todo)| match_kind(&todo.text, query).is_some())
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// The todos matching `query`, best first, each with its
+/// [`Match::positions`]: substring matches rank above subsequence ones,
+/// then higher scores first, then list order. An empty query matches
+/// everything in list order, with nothing to highlight.
+///
+/// ```
+/// use tuido::Todo;
+///
+/// let todos = vec![Todo::new("buy milk"), Todo::new("call bob"), Todo::new("café")];
+/// assert_eq!(tuido::matching::search_todos(&todos, "milk"), [(0, vec![4, 5, 6, 7])]);
+/// // Offsets count chars, not bytes
+/// assert_eq!(tuido::matching::search_todos(&todos, "É"), [(2, vec![3])]);
+/// assert_eq!(tuido::matching::search_todos(&todos, "").len(), 3);
+/// ```
+///
+/// Ranking on a longer list:
+///
+/// ```
+/// use tuido::Todo;
+///
+/// let todos: Vec<Todo> = [
+///     "review pull requests",   // 0: p and r start words far apart
+///     "call the bank",          // 1: no match
+///     "prepare slides",         // 2: "pr" at a word start
+///     "Pay Rent",               // 3: p and r start neighbouring words
+///     "update project roadmap", // 4: "pr" at a later word start
+///     "shopping trip",          // 5: p and r inside words
+/// ]
+/// .into_iter()
+/// .map(Todo::new)
+/// .collect();
+/// let order: Vec<usize> = tuido::matching::search_todos(&todos, "pr")
+///     .into_iter()
+///     .map(|(i, _)| i)
+///     .collect();
+/// // Substrings first (equal scores keep list order), then subsequences by
+/// // how well the chars line up with word starts and how close they are
+/// assert_eq!(order, [2, 4, 3, 0, 5]);
+/// ```
+pub fn search_todos(todos: &[Todo], query: &str) -> Vec<(usize, Vec<usize>)> {
+    if query.is_empty() {
+        return (0..todos.len()).map(|i| (i, Vec::new())).collect();
+    }
+    rank(
+        todos
+            .iter()
+            .enumerate()
+            .filter_map(|(i, todo)| fuzzy_match(&todo.text, query).map(|m| (i, m)))
+            .collect(),
+    )
+}
+
+// Best first: substrings, then higher scores, then list order
+fn rank(mut matches: Vec<(usize, Match)>) -> Vec<(usize, Vec<usize>)> {
+    matches.sort_by(|(a_index, a), (b_index, b)| {
+        a.kind
+            .cmp(&b.kind)
+            .then(b.score.cmp(&a.score))
+            .then(a_index.cmp(b_index))
+    });
+    matches.into_iter().map(|(i, m)| (i, m.positions)).collect()
+}
+
+// Every non-overlapping occurrence of `query` in `chars`
+fn substring_positions(chars: &[char], query: &[char]) -> Vec<usize> {
+    let mut positions = Vec::new();
+    let mut start = 0;
+    while start + query.len() <= chars.len() {
+        if chars[start..start + query.len()] == *query {
+            positions.extend(start..start + query.len());
+            start += query.len();
+        } else {
+            start += 1;
+        }
+    }
+    positions
+}
+
+// How much matching at `i` is worth beyond SCORE_MATCH: the start of a
+// word, or an uppercase letter after a lowercase one (camelCase)
+fn bonus(original: &[char], i: usize) -> i64 {
+    let current = original[i];
+    match i.checked_sub(1).map(|prev| original[prev]) {
+        None => BONUS_BOUNDARY,
+        Some(prev) if !prev.is_alphanumeric() && current.is_alphanumeric() => BONUS_BOUNDARY,
+        Some(prev) if prev.is_lowercase() && current.is_uppercase() => BONUS_CAMEL,
+        _ => 0,
+    }
+}
+
+// The highest-scoring way to pick `query`'s chars out of `chars` in order,
+// as its score and the picked offsets. Dynamic programming over (text char,
+// query char) with affine gap costs, so it stays O(text × query).
+fn best_alignment(original: &[char], chars: &[char], query: &[char]) -> Option<(i64, Vec<usize>)> {
+    let (n, m) = (chars.len(), query.len());
+    if m > n {
+        return None;
+    }
+    // score[j][i]: best score with query[..=j] matched and query[j] at i;
+    // from[j][i]: where query[j - 1] sits in that alignment
+    let mut score = vec![vec![None::<i64>; n]; m];
+    let mut from = vec![vec![0usize; n]; m];
+
+    for i in 0..n {
+        if chars[i] == query[0] {
+            score[0][i] = Some(SCORE_MATCH + bonus(original, i) * FIRST_CHAR_MULTIPLIER);
+        }
+    }
+    for j in 1..m {
+        // Best alignment of query[..j] ending at least two chars back, with
+        // the gap up to the current char already charged
+        let mut gapped: Option<(i64, usize)> = None;
+        for i in 1..n {
+            if i >= 2 {
+                gapped = gapped.map(|(s, k)| (s - GAP_EXTENSION, k));
+                if let Some(s) = score[j - 1][i - 2] {
+                    let candidate = s - GAP_START;
+                    if gapped.is_none_or(|(best, _)| candidate > best) {
+                        gapped = Some((candidate, i - 2));
+                    }
+                }
+            }
+            if chars[i] != query[j] {
+                continue;
+            }
+            let consecutive = score[j - 1][i - 1].map(|s| {
+                (
+                    s + SCORE_MATCH + bonus(original, i).max(BONUS_CONSECUTIVE),
+                    i - 1,
+                )
+            });
+            let skipped = gapped.map(|(s, k)| (s + SCORE_MATCH + bonus(original, i), k));
+            let best = match (consecutive, skipped) {
+                (Some(c), Some(g)) => Some(if g.0 > c.0 { g } else { c }),
+                (c, g) => c.or(g),
+            };
+            if let Some((s, k)) = best {
+                score[j][i] = Some(s);
+                from[j][i] = k;
+            }
+        }
+    }
+
+    let (best, mut i) = (0..n)
+        .filter_map(|i| score[m - 1][i].map(|s| (s, i)))
+        .max_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)))?;
+    let mut positions = vec![i; m];
+    for j in (1..m).rev() {
+        i = from[j][i];
+        positions[j - 1] = i;
+    }
+    Some((best, positions))
+}
+
+/// Runs a search the way the `/` prompt does: a query starting with
+/// [`REGEX_PREFIX`], or any query when `regex` is set, is a regular
+/// expression (see [`regex_search_todos`]); anything else goes to
+/// [`search_todos`].
+///
+/// ```
+/// use tuido::Todo;
+///
+/// let todos = vec![Todo::new("fix login #12"), Todo::new("fix typo")];
+/// assert_eq!(tuido::matching::search(&todos, "re:#\\d+", false).unwrap().len(), 1);
+/// assert_eq!(tuido::matching::search(&todos, "fix", false).unwrap().len(), 2);
+/// assert!(tuido::matching::search(&todos, "re:fix(", false).is_err());
+/// ```
+pub fn search(
+    todos: &[Todo],
+    query: &str,
+    regex: bool,
+) -> Result<Vec<(usize, Vec<usize>)>, String> {
+    match query.strip_prefix(REGEX_PREFIX) {
+        Some(pattern) => regex_search_todos(todos, pattern),
+        None if regex => regex_search_todos(todos, query),
+        None => Ok(search_todos(todos, query)),
+    }
+}
+
+/// Todo texts split into chars and lowercased once, for searching the same
+/// list over and over as a query is typed. Each search first brings the
+/// index in line with the todos, redoing only the texts that changed; a
+/// search repeated with nothing changed returns the last result as is.
+///
+/// ```
+/// use tuido::Todo;
+/// use tuido::matching::{SearchIndex, search};
+///
+/// let mut todos = vec![Todo::new("buy milk"), Todo::new("call bob")];
+/// let mut index = SearchIndex::default();
+/// assert_eq!(index.search(&todos, "bo", false), search(&todos, "bo", false));
+///
+/// todos[0].text = "call bo".to_string();
+/// assert_eq!(index.search(&todos, "bo", false).unwrap().len(), 2);
+/// assert_eq!(index.search(&todos, "re:^call", false).unwrap().len(), 2);
+/// ```
+#[derive(Default)]
+pub struct SearchIndex {
+    entries: Vec<IndexedText>,
+    last: Option<LastSearch>,
+}
+
+struct IndexedText {
+    text: String,
+    chars: Vec<char>,
+    lower: Vec<char>,
+}
+
+struct LastSearch {
+    query: String,
+    regex: bool,
+    result: Result<Vec<(usize, Vec<usize>)>, String>,
+}
+
+impl SearchIndex {
+    /// Same as [`search`], reusing what's already been worked out.
+    pub fn search(
+        &mut self,
+        todos: &[Todo],
+        query: &str,
+        regex: bool,
+    ) -> Result<Vec<(usize, Vec<usize>)>, String> {
+        self.update(todos);
+        if let Some(last) = &self.last
+            && last.query == query
+            && last.regex == regex
+        {
+            return last.result.clone();
+        }
+        let result = match query.strip_prefix(REGEX_PREFIX) {
+            Some(pattern) => regex_search_todos(todos, pattern),
+            None if regex => regex_search_todos(todos, query),
+            None => Ok(self.search_todos(query)),
+        };
+        self.last = Some(LastSearch {
+            query: query.to_string(),
+            regex,
+            result: result.clone(),
+        });
+        result
+    }
+
+    fn search_todos(&self, query: &str) -> Vec<(usize, Vec<usize>)> {
+        if query.is_empty() {
+            return (0..self.entries.len()).map(|i| (i, Vec::new())).collect();
+        }
+        let query: Vec<char> = query.chars().collect();
+        let query = lowercase(&query);
+        rank(
+            self.entries
+                .iter()
+                .enumerate()
+                .filter_map(|(i, entry)| {
+                    match_chars(&entry.chars, &entry.lower, &query).map(|m| (i, m))
+                })
+                .collect(),
+        )
+    }
+
+    // Rebuilds the entries whose todo's text changed, dropping the last
+    // result if any did
+    fn update(&mut self, todos: &[Todo]) {
+        let mut changed = self.entries.len() != todos.len();
+        self.entries.truncate(todos.len());
+        for (i, todo) in todos.iter().enumerate() {
+            if self
+                .entries
+                .get(i)
+                .is_some_and(|entry| entry.text == todo.text)
+            {
+                continue;
+            }
+            let chars: Vec<char> = todo.text.chars().collect();
+            let entry = IndexedText {
+                text: todo.text.clone(),
+                lower: lowercase(&chars),
+                chars,
+            };
+            match self.entries.get_mut(i) {
+                Some(slot) => *slot = entry,
+                None => self.entries.push(entry),
+            }
+            changed = true;
+        }
+        if changed {
+            self.last = None;
+        }
+    }
+}
+
+/// The todos whose text matches `pattern`, in list order, each with the
+/// char offsets of every match to highlight. Case-insensitive unless the
+/// pattern turns it off with `(?-i)`. A pattern that doesn't compile comes
+/// back as a one-line `invalid regex: ...` message.
+///
+/// ```
+/// use tuido::Todo;
+///
+/// let todos = vec![Todo::new("Fix bug #12"), Todo::new("fix docs"), Todo::new("café 7")];
+/// let hits = tuido::matching::regex_search_todos(&todos, "^fix.*#\\d+").unwrap();
+/// assert_eq!(hits, [(0, (0..11).collect::<Vec<_>>())]);
+/// assert_eq!(tuido::matching::regex_search_todos(&todos, "(?-i)^fix").unwrap().len(), 1);
+/// // Offsets count chars, not bytes
+/// assert_eq!(tuido::matching::regex_search_todos(&todos, "\\d").unwrap()[1], (2, vec![5]));
+/// assert_eq!(
+///     tuido::matching::regex_search_todos(&todos, "fix(").unwrap_err(),
+///     "invalid regex: unclosed group"
+/// );
+/// ```
+pub fn regex_search_todos(
+    todos: &[Todo],
+    pattern: &str,
+) -> Result<Vec<(usize, Vec<usize>)>, String> {
+    let regex = RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(regex_error)?;
+    Ok(todos
+        .iter()
+        .enumerate()
+        .filter(|(_, todo)| regex.is_match(&todo.text))
+        .map(|(i, todo)| {
+            let char_offset = |byte: usize| todo.text[..byte].chars().count();
+            let positions = regex
+                .find_iter(&todo.text)
+                .flat_map(|m| char_offset(m.start())..char_offset(m.end()))
+                .collect();
+            (i, positions)
+        })
+        .collect())
+}
+
+// The full message draws a caret under the pattern over several lines; the
+// status line only has room for the last one
+pub(crate) fn regex_error(e: regex::Error) -> String {
+    let message = e.to_string();
+    let last = message.lines().last().unwrap_or_default();
+    format!("invalid regex: {}", last.trim_start_matches("error: "))
+}
+
+/// Normalized Levenshtein similarity of two texts, from 0.0 to 1.0,
+/// ignoring case and surrounding whitespace. Edits are counted in grapheme
+/// clusters, so swapping one emoji for another is a single edit however
+/// many chars each is made of.
+///
+/// ```
+/// use tuido::matching::similarity;
+///
+/// assert_eq!(similarity("Buy milk", "buy milk "), 1.0);
+/// assert_eq!(similarity("🍕 party", "🍔 party"), 1.0 - 1.0 / 7.0);
+/// // A skin-tone modifier makes a different grapheme, not an extra one
+/// assert_eq!(similarity("👍 ok", "👍🏽 ok"), 0.75);
+/// assert_eq!(similarity("", ""), 1.0);
+/// ```
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a = a.trim().to_lowercase();
+    let b = b.trim().to_lowercase();
+    let a: Vec<&str> = a.graphemes(true).collect();
+    let b: Vec<&str> = b.graphemes(true).collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - strsim::generic_levenshtein(&a, &b) as f64 / longest as f64
+}
@@ -0,0 +1,78 @@
+use super::*;
+
+const FIXTURE: [&str; 8] = [
+    "review pull requests",
+    "call the bank",
+    "prepare slides",
+    "Pay Rent",
+    "update project roadmap",
+    "shopping trip",
+    "email Priya re: budget",
+    "print boarding passes",
+];
+
+fn todos() -> Vec<Todo> {
+    FIXTURE.into_iter().map(Todo::new).collect()
+}
+
+fn order(query: &str) -> Vec<&'static str> {
+    search_todos(&todos(), query)
+        .into_iter()
+        .map(|(i, _)| FIXTURE[i])
+        .collect()
+}
+
+#[test]
+fn ranking_on_a_fixture_list() {
+    assert_eq!(
+        order("pr"),
+        [
+            "prepare slides",
+            "update project roadmap",
+            "email Priya re: budget",
+            "print boarding passes",
+            "Pay Rent",
+            "review pull requests",
+            "shopping trip",
+        ]
+    );
+    assert_eq!(
+        order("road"),
+        ["update project roadmap", "print boarding passes"]
+    );
+    assert_eq!(order("zzz"), Vec::<&str>::new());
+}
+
+#[test]
+fn an_empty_query_keeps_list_order() {
+    assert_eq!(order(""), FIXTURE);
+}
+
+#[test]
+fn substrings_outrank_better_scoring_subsequences() {
+    // "rent" buried in a word scores below "r…e…n…t" on word starts, yet
+    // still comes first
+    let buried = fuzzy_match("parents", "rent").unwrap();
+    let starts = fuzzy_match("Read Every New Ticket", "rent").unwrap();
+    assert_eq!(buried.kind, MatchKind::Substring);
+    assert_eq!(starts.kind, MatchKind::Subsequence);
+    assert!(starts.score > buried.score);
+
+    let todos = vec![Todo::new("Read Every New Ticket"), Todo::new("parents")];
+    let order: Vec<usize> = search_todos(&todos, "rent")
+        .into_iter()
+        .map(|(i, _)| i)
+        .collect();
+    assert_eq!(order, [1, 0]);
+}
+
+#[test]
+fn matching_ignores_case_both_ways() {
+    assert_eq!(order("PAY"), ["Pay Rent"]);
+    assert_eq!(match_positions("Pay Rent", "rent"), [4, 5, 6, 7]);
+}
+
+#[test]
+fn match_todos_keeps_list_order() {
+    assert_eq!(match_todos(&todos(), "pr"), [0, 2, 3, 4, 5, 6, 7]);
+}
@@ -0,0 +1,134 @@
+//! The numbers behind `:stats`: how much is done, where the open work sits
+//! by priority and project, and how much got finished lately.
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+
+use crate::{Todo, tag_kind};
+
+#[cfg(test)]
+mod tests;
+
+/// How many days back "completed recently" reaches, today included.
+pub const RECENT_DAYS: i64 = 7;
+
+/// Open and completed todos in one group.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Counts {
+    pub open: usize,
+    pub done: usize,
+}
+
+impl Counts {
+    fn add(&mut self, completed: bool) {
+        if completed {
+            self.done += 1;
+        } else {
+            self.open += 1;
+        }
+    }
+}
+
+/// A summary of a todo list, see [`compute_stats`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub total: usize,
+    pub completed: usize,
+    /// Todos per priority letter, A first.
+    pub by_priority: BTreeMap<char, Counts>,
+    /// Todos without a priority.
+    pub no_priority: Counts,
+    /// Todos per `+project` tag. A todo naming the same project twice is
+    /// counted once.
+    pub by_project: BTreeMap<String, Counts>,
+    /// Todos completed within the last [`RECENT_DAYS`] days.
+    pub completed_recently: usize,
+    /// The creation date and text of the oldest pending todo, among those
+    /// with a known creation date.
+    pub oldest_open: Option<(NaiveDate, String)>,
+}
+
+impl Stats {
+    /// Completed todos as a whole percentage of all of them; 0 for an empty
+    /// list.
+    pub fn percent_done(&self) -> usize {
+        (self.completed * 100).checked_div(self.total).unwrap_or(0)
+    }
+}
+
+/// Counts up `todos` as of `today`.
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use tuido::stats::{Counts, compute_stats};
+///
+/// let day = |d| NaiveDate::from_ymd_opt(2025, 3, d).unwrap();
+/// let mut todos = vec![
+///     tuido::Todo::new("draft post +blog"),
+///     tuido::Todo::new("fix header +site +blog"),
+///     tuido::Todo::new("renew domain +site"),
+/// ];
+/// todos[0].created = Some(day(2));
+/// todos[1].created = Some(day(1));
+/// todos[1].priority = Some('A');
+/// todos[1].set_completed(true, day(10));
+///
+/// let stats = compute_stats(&todos, day(12));
+/// assert_eq!((stats.total, stats.completed, stats.percent_done()), (3, 1, 33));
+/// assert_eq!(stats.by_priority[&'A'], Counts { open: 0, done: 1 });
+/// assert_eq!(stats.no_priority, Counts { open: 2, done: 0 });
+/// assert_eq!(stats.by_project["+blog"], Counts { open: 1, done: 1 });
+/// assert_eq!(stats.completed_recently, 1);
+/// assert_eq!(stats.oldest_open, Some((day(2), "draft post +blog".to_string())));
+///
+/// assert_eq!(compute_stats(&[], day(12)).percent_done(), 0);
+/// ```
+pub fn compute_stats(todos: &[Todo], today: NaiveDate) -> Stats {
+    let mut stats = Stats {
+        total: todos.len(),
+        ..Default::default()
+    };
+    for todo in todos {
+        if todo.completed {
+            stats.completed += 1;
+        }
+        match todo.priority {
+            Some(priority) => stats.by_priority.entry(priority).or_default(),
+            None => &mut stats.no_priority,
+        }
+        .add(todo.completed);
+
+        let mut projects: Vec<&str> = todo
+            .text
+            .split_whitespace()
+            .filter(|word| tag_kind(word) == Some('+'))
+            .collect();
+        projects.sort_unstable();
+        projects.dedup();
+        for project in projects {
+            stats
+                .by_project
+                .entry(project.to_string())
+                .or_default()
+                .add(todo.completed);
+        }
+
+        if todo
+            .completed_on
+            .is_some_and(|date| (0..RECENT_DAYS).contains(&(today - date).num_days()))
+        {
+            stats.completed_recently += 1;
+        }
+        if !todo.completed
+            && let Some(created) = todo.created
+            && stats
+                .oldest_open
+                .as_ref()
+                .is_none_or(|(oldest, _)| created < *oldest)
+        {
+            stats.oldest_open = Some((created, todo.text.clone()));
+        }
+    }
+    stats
+}
@@ -0,0 +1,95 @@
+use super::*;
+
+fn day(d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 3, d).unwrap()
+}
+
+// Text, priority, and the days of March it was created and completed on
+type Row<'a> = (&'a str, Option<char>, Option<u32>, Option<u32>);
+
+fn fixture(rows: &[Row]) -> Vec<Todo> {
+    rows.iter()
+        .map(|&(text, priority, created, completed)| {
+            let mut todo = Todo::new(text);
+            todo.priority = priority;
+            todo.created = created.map(day);
+            if let Some(completed) = completed {
+                todo.set_completed(true, day(completed));
+            }
+            todo
+        })
+        .collect()
+}
+
+#[test]
+fn an_empty_list_is_all_zeros() {
+    let stats = compute_stats(&[], day(20));
+    assert_eq!(stats, Stats::default());
+    assert_eq!(stats.percent_done(), 0);
+}
+
+#[test]
+fn counts_by_priority_and_project() {
+    let todos = fixture(&[
+        ("draft post +blog", Some('B'), None, None),
+        ("fix header +site +blog +site", Some('A'), None, Some(10)),
+        ("renew domain +site", Some('A'), None, None),
+        ("water plants", None, None, Some(12)),
+        ("call bob", None, None, None),
+        ("plan trip +travel", Some('C'), None, None),
+    ]);
+    let stats = compute_stats(&todos, day(20));
+
+    assert_eq!((stats.total, stats.completed), (6, 2));
+    assert_eq!(stats.percent_done(), 33);
+    assert_eq!(
+        stats.by_priority.into_iter().collect::<Vec<_>>(),
+        [
+            ('A', Counts { open: 1, done: 1 }),
+            ('B', Counts { open: 1, done: 0 }),
+            ('C', Counts { open: 1, done: 0 }),
+        ]
+    );
+    assert_eq!(stats.no_priority, Counts { open: 1, done: 1 });
+    // The header fix names +site twice but counts once
+    assert_eq!(
+        stats.by_project.into_iter().collect::<Vec<_>>(),
+        [
+            ("+blog".to_string(), Counts { open: 1, done: 1 }),
+            ("+site".to_string(), Counts { open: 1, done: 1 }),
+            ("+travel".to_string(), Counts { open: 1, done: 0 }),
+        ]
+    );
+}
+
+#[test]
+fn recent_completions_reach_back_a_week_including_today() {
+    let todos = fixture(&[
+        ("today", None, None, Some(20)),
+        ("six days ago", None, None, Some(14)),
+        ("a week ago", None, None, Some(13)),
+        ("long ago", None, None, Some(1)),
+        ("pending", None, None, None),
+    ]);
+    let stats = compute_stats(&todos, day(20));
+    assert_eq!(stats.completed_recently, 2);
+    assert_eq!(stats.percent_done(), 80);
+}
+
+#[test]
+fn the_oldest_open_todo_skips_done_and_undated_ones() {
+    let todos = fixture(&[
+        ("undated", None, None, None),
+        ("done long ago", None, Some(1), Some(2)),
+        ("newer", None, Some(9), None),
+        ("oldest open", None, Some(4), None),
+        ("same day, later in the list", None, Some(4), None),
+    ]);
+    let stats = compute_stats(&todos, day(20));
+    assert_eq!(stats.oldest_open, Some((day(4), "oldest open".to_string())));
+
+    let all_done = fixture(&[("a", None, Some(1), Some(3)), ("b", None, None, Some(4))]);
+    let stats = compute_stats(&all_done, day(20));
+    assert_eq!(stats.oldest_open, None);
+    assert_eq!(stats.percent_done(), 100);
+}
@@ -0,0 +1,212 @@
+//! Vim's substitute and global commands, `:s/pattern/replacement/flags`
+//! and `:g/pattern/command`, on todos as they're typed (`(A) text
+//! due:YYYY-MM-DD key:value`), so a pattern can reach the priority, due
+//! date and fields as well as the text and its tags.
+
+use regex::{Regex, RegexBuilder};
+
+use crate::matching::regex_error;
+
+/// A parsed substitution, ready to run over any number of todos.
+#[derive(Debug)]
+pub struct Substitute {
+    regex: Regex,
+    replacement: String,
+    global: bool,
+}
+
+impl Substitute {
+    /// Reads what follows the `s` of `:s/foo/bar/gi`. The first char is the
+    /// delimiter, usually `/`; a backslash before it makes it literal. The
+    /// closing delimiter and the replacement can be left off, which
+    /// replaces matches with nothing. Flags: `g` replaces every match in a
+    /// todo rather than the first, `i` ignores case. In the replacement
+    /// `\1` or `$1` is the first group, and `\0` or `$0` the whole match.
+    ///
+    /// ```
+    /// use tuido::substitute::Substitute;
+    ///
+    /// let swap = Substitute::parse(r"/(\w+) (\w+)/\2 $1/").unwrap();
+    /// assert_eq!(swap.apply("call bob"), Some(("bob call".to_string(), 1)));
+    ///
+    /// let all = Substitute::parse("#a#o#gi").unwrap();
+    /// assert_eq!(all.apply("Banana"), Some(("Bonono".to_string(), 3)));
+    /// assert_eq!(all.apply("kiwi"), None);
+    ///
+    /// let path = Substitute::parse(r"/\/tmp/~").unwrap();
+    /// assert_eq!(path.apply("clear /tmp"), Some(("clear ~".to_string(), 1)));
+    ///
+    /// assert_eq!(Substitute::parse("/x(/y/").unwrap_err(), "invalid regex: unclosed group");
+    /// assert_eq!(Substitute::parse("/x/y/q").unwrap_err(), "Unknown flag: q");
+    /// ```
+    pub fn parse(spec: &str) -> Result<Substitute, String> {
+        let mut chars = spec.chars();
+        let delimiter = chars
+            .next()
+            .filter(|&c| is_delimiter(c))
+            .ok_or("Usage: :s/pattern/replacement/flags")?;
+        let mut parts = split_unescaped(chars.as_str(), delimiter).into_iter();
+        let pattern = parts.next().unwrap_or_default();
+        let replacement = parts.next().unwrap_or_default();
+        let flags = parts.next().unwrap_or_default();
+        if parts.next().is_some() {
+            return Err(format!("Unexpected {} after the flags", delimiter));
+        }
+        if pattern.is_empty() {
+            return Err("Empty pattern".to_string());
+        }
+
+        let mut global = false;
+        let mut ignore_case = false;
+        for flag in flags.trim_end().chars() {
+            match flag {
+                'g' => global = true,
+                'i' => ignore_case = true,
+                flag => return Err(format!("Unknown flag: {}", flag)),
+            }
+        }
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .map_err(regex_error)?;
+        Ok(Substitute {
+            regex,
+            replacement: expand_groups(&replacement),
+            global,
+        })
+    }
+
+    /// The pattern as typed, delimiters unescaped.
+    pub fn pattern(&self) -> &str {
+        self.regex.as_str()
+    }
+
+    /// `text` with the substitution made, and how many matches it
+    /// replaced; None when nothing matches.
+    pub fn apply(&self, text: &str) -> Option<(String, usize)> {
+        let matches = self.regex.find_iter(text).count();
+        if matches == 0 {
+            return None;
+        }
+        let limit = if self.global { 0 } else { 1 };
+        let replaced = self.regex.replacen(text, limit, self.replacement.as_str());
+        Some((replaced.into_owned(), if self.global { matches } else { 1 }))
+    }
+}
+
+/// The pattern of `:g/pattern/command`, and the command to run on every
+/// todo it matches.
+#[derive(Debug)]
+pub struct Global {
+    regex: Regex,
+    command: String,
+}
+
+impl Global {
+    /// Reads what follows the `g` of `:g/\+work/d`: a delimiter, the
+    /// pattern, the delimiter again and the command. Matching is
+    /// case-sensitive, as in vim; `(?i)` in the pattern ignores case.
+    ///
+    /// ```
+    /// use tuido::substitute::Global;
+    ///
+    /// let global = Global::parse(r"/\+work\b/pri A").unwrap();
+    /// assert_eq!(global.command(), "pri A");
+    /// assert!(global.is_match("ship it +work"));
+    /// assert!(!global.is_match("ship it +workshop"));
+    ///
+    /// assert_eq!(Global::parse("/x/").unwrap().command(), "");
+    /// assert_eq!(Global::parse("/(/d").unwrap_err(), "invalid regex: unclosed group");
+    /// ```
+    pub fn parse(spec: &str) -> Result<Global, String> {
+        let mut chars = spec.chars();
+        let delimiter = chars
+            .next()
+            .filter(|&c| is_delimiter(c))
+            .ok_or("Usage: :g/pattern/command")?;
+        let (pattern, command) = split_pattern(chars.as_str(), delimiter);
+        if pattern.is_empty() {
+            return Err("Empty pattern".to_string());
+        }
+        let regex = Regex::new(&pattern).map_err(regex_error)?;
+        Ok(Global {
+            regex,
+            command: command.unwrap_or_default().trim().to_string(),
+        })
+    }
+
+    /// The pattern as typed, delimiters unescaped.
+    pub fn pattern(&self) -> &str {
+        self.regex.as_str()
+    }
+
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+}
+
+/// Whether `c` can separate the parts of a substitution, as the `/` in
+/// `s/a/b/` does. Letters, digits, spaces, backslashes and quotes can't.
+pub fn is_delimiter(c: char) -> bool {
+    c.is_ascii_punctuation() && !matches!(c, '\\' | '"')
+}
+
+// Splits on `delimiter`, dropping the backslash from `\/` but keeping any
+// other escape for the regex or the replacement to read
+fn split_unescaped(text: &str, delimiter: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut rest = Some(text);
+    while let Some(text) = rest {
+        let (part, next) = split_pattern(text, delimiter);
+        parts.push(part);
+        rest = next;
+    }
+    parts
+}
+
+// The text up to the first unescaped `delimiter`, and what follows it if
+// there is one
+fn split_pattern(text: &str, delimiter: char) -> (String, Option<&str>) {
+    let mut part = String::new();
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, next)) if next == delimiter => part.push(next),
+                Some((_, next)) => {
+                    part.push('\\');
+                    part.push(next);
+                }
+                None => part.push('\\'),
+            },
+            c if c == delimiter => return (part, Some(&text[i + c.len_utf8()..])),
+            c => part.push(c),
+        }
+    }
+    (part, None)
+}
+
+// Vim's `\1` becomes the regex crate's `${1}`, and `\\` a single backslash;
+// `$1` and `$$` are the regex crate's own
+fn expand_groups(replacement: &str) -> String {
+    let mut expanded = String::new();
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some(digit @ '0'..='9')) => {
+                chars.next();
+                expanded.push_str(&format!("${{{}}}", digit));
+            }
+            ('\\', Some('\\')) => {
+                chars.next();
+                expanded.push('\\');
+            }
+            (c, _) => expanded.push(c),
+        }
+    }
+    expanded
+}
@@ -0,0 +1,321 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// A single todo item, as stored in tuido's JSON file.
+/// Equality ignores `note_expanded`, so expanding a note doesn't count as an
+/// edit.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Todo {
+    pub text: String,
+    pub completed: bool,
+    #[serde(default)]
+    pub priority: Option<char>,
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub due: Option<NaiveDate>,
+    /// Free-form `key:value` fields such as `effort:3` or `client:acme`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub meta: BTreeMap<String, String>,
+    /// When the todo was added, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<NaiveDate>,
+    /// When the todo was completed, if known; cleared when it's reopened.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_on: Option<NaiveDate>,
+    /// When [`crate::escalate`] last raised the priority.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escalated: Option<NaiveDate>,
+    /// Display state for the note; never serialized.
+    #[serde(default, skip_serializing, skip_deserializing)]
+    pub note_expanded: bool,
+}
+
+impl PartialEq for Todo {
+    fn eq(&self, other: &Todo) -> bool {
+        self.text == other.text
+            && self.completed == other.completed
+            && self.priority == other.priority
+            && self.note == other.note
+            && self.due == other.due
+            && self.meta == other.meta
+            && self.created == other.created
+            && self.completed_on == other.completed_on
+            && self.escalated == other.escalated
+    }
+}
+
+impl Todo {
+    /// A pending todo with the given text and no other metadata.
+    pub fn new(text: impl Into<String>) -> Todo {
+        Todo {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Completes or reopens the todo, recording `today` as the completion
+    /// date. Setting the state it's already in changes nothing, so a todo
+    /// completed last week keeps its date.
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    ///
+    /// let monday = NaiveDate::from_ymd_opt(2025, 3, 3).unwrap();
+    /// let tuesday = monday.succ_opt().unwrap();
+    /// let mut todo = tuido::Todo::new("file taxes");
+    ///
+    /// todo.set_completed(true, monday);
+    /// todo.set_completed(true, tuesday);
+    /// assert_eq!(todo.completed_on, Some(monday));
+    ///
+    /// todo.toggle(tuesday);
+    /// assert!(!todo.completed);
+    /// assert_eq!(todo.completed_on, None);
+    /// ```
+    pub fn set_completed(&mut self, completed: bool, today: NaiveDate) {
+        if self.completed != completed {
+            self.completed = completed;
+            self.completed_on = completed.then_some(today);
+        }
+    }
+
+    /// Flips the todo between done and pending; see [`Todo::set_completed`].
+    pub fn toggle(&mut self, today: NaiveDate) {
+        self.set_completed(!self.completed, today);
+    }
+
+    /// The todo as it's typed into the input line, which is how `e` shows it
+    /// for editing: `(A) text due:YYYY-MM-DD key:value`.
+    ///
+    /// ```
+    /// let mut todo = tuido::Todo::new("ship release");
+    /// todo.apply_input("(b) ship release due:2025-03-01 effort:3");
+    /// assert_eq!(todo.input_text(), "(B) ship release due:2025-03-01 effort:3");
+    /// ```
+    pub fn input_text(&self) -> String {
+        let mut input = match self.priority {
+            Some(priority) => format!("({}) {}", priority, self.text),
+            None => self.text.clone(),
+        };
+        if let Some(due) = self.due {
+            input.push_str(&format!(" due:{}", due.format("%Y-%m-%d")));
+        }
+        for (key, value) in &self.meta {
+            input.push_str(&format!(" {}:{}", key, value));
+        }
+        input
+    }
+
+    /// Takes the text, priority, due date and fields from a typed input line,
+    /// as written by [`Todo::input_text`]. Everything else, the note
+    /// included, is kept.
+    ///
+    /// ```
+    /// let mut todo = tuido::Todo::new("call bob");
+    /// todo.priority = Some('A');
+    /// todo.note = Some("number is in the CRM".to_string());
+    ///
+    /// // Changing a word keeps the priority and the note
+    /// todo.apply_input(&todo.input_text().replace("bob", "bob back"));
+    /// assert_eq!(todo.text, "call bob back");
+    /// assert_eq!(todo.priority, Some('A'));
+    /// assert_eq!(todo.note.as_deref(), Some("number is in the CRM"));
+    ///
+    /// // Deleting the prefix clears the priority
+    /// todo.apply_input("call bob back");
+    /// assert_eq!(todo.priority, None);
+    /// assert!(todo.note.is_some());
+    /// ```
+    pub fn apply_input(&mut self, input: &str) {
+        let (priority, text) = parse_priority(input);
+        let (due, text) = parse_due(&text);
+        let (meta, text) = parse_meta(&text);
+        self.text = text;
+        self.priority = priority;
+        self.due = due;
+        self.meta = meta;
+    }
+}
+
+/// Splits a leading priority such as `(A)` off the text. Any letter from
+/// A to Z works, in either case, and comes back uppercase.
+///
+/// ```
+/// assert_eq!(
+///     tuido::parse_priority("(a) call bob"),
+///     (Some('A'), "call bob".to_string())
+/// );
+/// assert_eq!(tuido::parse_priority("(E) someday"), (Some('E'), "someday".to_string()));
+/// assert_eq!(tuido::parse_priority("call bob"), (None, "call bob".to_string()));
+/// assert_eq!(tuido::parse_priority("(1) first"), (None, "(1) first".to_string()));
+/// ```
+pub fn parse_priority(text: &str) -> (Option<char>, String) {
+    match text.as_bytes() {
+        [b'(', letter, b')', ..] if letter.is_ascii_alphabetic() => (
+            Some(letter.to_ascii_uppercase() as char),
+            text[3..].trim().to_string(),
+        ),
+        _ => (None, text.to_string()),
+    }
+}
+
+/// The priority one step up: none becomes C, C becomes B and B becomes A,
+/// which stays at the top.
+///
+/// ```
+/// assert_eq!(tuido::raise_priority(None), Some('C'));
+/// assert_eq!(tuido::raise_priority(Some('B')), Some('A'));
+/// assert_eq!(tuido::raise_priority(Some('A')), Some('A'));
+/// ```
+pub fn raise_priority(priority: Option<char>) -> Option<char> {
+    match priority {
+        None => Some('C'),
+        Some('A') => Some('A'),
+        Some(p) => Some((p as u8 - 1) as char),
+    }
+}
+
+/// The priority one step down: A becomes B, B becomes C, and C or anything
+/// lower is cleared.
+///
+/// ```
+/// assert_eq!(tuido::lower_priority(Some('A')), Some('B'));
+/// assert_eq!(tuido::lower_priority(Some('C')), None);
+/// assert_eq!(tuido::lower_priority(None), None);
+/// ```
+pub fn lower_priority(priority: Option<char>) -> Option<char> {
+    match priority {
+        Some(p @ ('A' | 'B')) => Some((p as u8 + 1) as char),
+        _ => None,
+    }
+}
+
+/// Pulls a `due:YYYY-MM-DD` token out from anywhere in the text. Tokens
+/// with an invalid date are left in place as ordinary words.
+///
+/// ```
+/// let (due, text) = tuido::parse_due("ship due:2025-03-01 release");
+/// assert_eq!(due.map(|d| d.to_string()), Some("2025-03-01".to_string()));
+/// assert_eq!(text, "ship release");
+/// ```
+pub fn parse_due(text: &str) -> (Option<NaiveDate>, String) {
+    let mut due = None;
+    let words: Vec<&str> = text
+        .split(' ')
+        .filter(|word| {
+            match word
+                .strip_prefix("due:")
+                .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+            {
+                Some(date) => {
+                    due = Some(date);
+                    false
+                }
+                None => true,
+            }
+        })
+        .collect();
+    (due, words.join(" ").trim().to_string())
+}
+
+/// Pulls trailing `key:value` tokens off the text. Keys are `[a-z_]+` so
+/// times like "10:30" and URLs stay part of the text, `due` is left to
+/// [`parse_due`], and at least one word of text is always kept. When a key
+/// repeats, the last value wins.
+///
+/// ```
+/// let (meta, text) = tuido::parse_meta("meet at 10:30 client:acme effort:3");
+/// assert_eq!(text, "meet at 10:30");
+/// assert_eq!(meta["client"], "acme");
+/// assert_eq!(meta["effort"], "3");
+/// ```
+pub fn parse_meta(text: &str) -> (BTreeMap<String, String>, String) {
+    let mut meta = BTreeMap::new();
+    let mut rest = text.trim_end();
+    while let Some((head, token)) = rest.rsplit_once(' ') {
+        match split_meta(token) {
+            Some((key, value)) => {
+                meta.entry(key.to_string())
+                    .or_insert_with(|| value.to_string());
+                rest = head.trim_end();
+            }
+            None => break,
+        }
+    }
+    (meta, rest.to_string())
+}
+
+/// Splits a single `key:value` token, following the rules of [`parse_meta`].
+pub fn split_meta(token: &str) -> Option<(&str, &str)> {
+    let (key, value) = token.split_once(':')?;
+    let valid_key =
+        !key.is_empty() && key != "due" && key.chars().all(|c| c.is_ascii_lowercase() || c == '_');
+    if valid_key && !value.is_empty() && !value.starts_with("//") {
+        Some((key, value))
+    } else {
+        None
+    }
+}
+
+/// Returns the sigil if `word` is a project (`+name`) or context (`@name`)
+/// tag. Tags are whole whitespace-separated words.
+///
+/// ```
+/// assert_eq!(tuido::tag_kind("+website"), Some('+'));
+/// assert_eq!(tuido::tag_kind("@home"), Some('@'));
+/// assert_eq!(tuido::tag_kind("+"), None);
+/// ```
+pub fn tag_kind(word: &str) -> Option<char> {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(sigil @ ('+' | '@')) if chars.next().is_some() => Some(sigil),
+        _ => None,
+    }
+}
+
+/// Returns the web address in `word`, one whitespace-separated word of a
+/// todo or note, without punctuation around it such as the brackets of
+/// `(see https://example.com)` or a sentence's full stop.
+///
+/// ```
+/// assert_eq!(tuido::url_in("https://example.com/a?b=1"), Some("https://example.com/a?b=1"));
+/// assert_eq!(tuido::url_in("<http://example.com>."), Some("http://example.com"));
+/// assert_eq!(
+///     tuido::url_in("(https://en.wikipedia.org/wiki/Rust_(language))"),
+///     Some("https://en.wikipedia.org/wiki/Rust_(language)")
+/// );
+/// assert_eq!(tuido::url_in("example.com"), None);
+/// assert_eq!(tuido::url_in("https://"), None);
+/// ```
+pub fn url_in(word: &str) -> Option<&str> {
+    let start = word.find("https://").or_else(|| word.find("http://"))?;
+    let mut url = &word[start..];
+    // A closing bracket the address opened, as in wiki links, stays
+    while let Some(last) = url.chars().last().filter(|c| ".,;:!?)]}>'\"".contains(*c)) {
+        if last == ')' && url.matches('(').count() >= url.matches(')').count() {
+            break;
+        }
+        url = &url[..url.len() - 1];
+    }
+    let host = url.split_once("://").map_or("", |(_, rest)| rest);
+    (!host.is_empty()).then_some(url)
+}
+
+/// Every web address in `text`, in order, each once.
+///
+/// ```
+/// let urls = tuido::find_urls("review https://git.io/pr/1 (and http://ci.example/1), then https://git.io/pr/1");
+/// assert_eq!(urls, ["https://git.io/pr/1", "http://ci.example/1"]);
+/// ```
+pub fn find_urls(text: &str) -> Vec<&str> {
+    let mut urls: Vec<&str> = Vec::new();
+    for url in text.split_whitespace().filter_map(url_in) {
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+    urls
+}
@@ -0,0 +1,220 @@
+use chrono::NaiveDate;
+
+use crate::{Todo, parse_due, parse_meta};
+
+#[cfg(test)]
+mod tests;
+
+/// The tag a note is written as, after everything else on the line:
+/// `note:"..."` with backslash escapes for `\`, `"` and line breaks.
+pub const NOTE_TAG: &str = "note:";
+
+/// Parses a whole todo.txt file, one todo per non-blank line.
+///
+/// ```
+/// let todos = tuido::todotxt::parse("x 2025-01-02 (A) done\nx ray appointment\n");
+/// assert!(todos[0].completed);
+/// assert_eq!(todos[0].priority, Some('A'));
+/// assert!(!todos[1].completed);
+/// assert_eq!(todos[1].text, "x ray appointment");
+/// ```
+pub fn parse(contents: &str) -> Vec<Todo> {
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(parse_line)
+        .collect()
+}
+
+/// Serializes todos as todo.txt, using `today` as the completion date of
+/// completed todos that don't have one.
+pub fn to_string(todos: &[Todo], today: NaiveDate) -> String {
+    todos
+        .iter()
+        .map(|todo| format_line(todo, today) + "\n")
+        .collect()
+}
+
+pub(crate) fn parse_date(token: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(token, "%Y-%m-%d").ok()
+}
+
+fn split_priority(text: &str) -> Option<(char, &str)> {
+    let bytes = text.as_bytes();
+    if bytes.len() >= 4
+        && bytes[0] == b'('
+        && bytes[1].is_ascii_uppercase()
+        && bytes[2] == b')'
+        && bytes[3] == b' '
+    {
+        Some((bytes[1] as char, &text[4..]))
+    } else {
+        None
+    }
+}
+
+fn split_date(text: &str) -> Option<&str> {
+    let (date, tail) = text.split_once(' ')?;
+    parse_date(date).map(|_| tail)
+}
+
+/// Formats one todo as a todo.txt line. Completed todos need a completion
+/// date, `today` if they don't have one, since per the spec a line is only
+/// completed when it starts with a lowercase "x " followed by a date; that
+/// way "x ray appointment" and "X marks the spot" stay open.
+///
+/// ```
+/// use chrono::NaiveDate;
+///
+/// let date = |day| NaiveDate::from_ymd_opt(2025, 3, day).unwrap();
+/// let mut todo = tuido::Todo::new("file taxes");
+/// todo.priority = Some('A');
+/// todo.created = Some(date(1));
+/// todo.set_completed(true, date(4));
+/// assert_eq!(
+///     tuido::todotxt::format_line(&todo, date(9)),
+///     "x 2025-03-04 (A) 2025-03-01 file taxes"
+/// );
+/// ```
+pub fn format_line(todo: &Todo, today: NaiveDate) -> String {
+    let date = today.format("%Y-%m-%d").to_string();
+    let mut line = String::new();
+    if todo.completed {
+        let completed_on = todo.completed_on.unwrap_or(today);
+        line.push_str(&format!("x {} ", completed_on.format("%Y-%m-%d")));
+    }
+    if let Some(priority) = todo.priority {
+        line.push_str(&format!("({}) ", priority));
+    }
+
+    // Text that the importer would read as a completion marker, priority or
+    // date gets a creation date in front so it's taken literally.
+    let text = &todo.text;
+    let ambiguous = split_date(text).is_some()
+        || (todo.priority.is_none() && split_priority(text).is_some())
+        || (!todo.completed
+            && todo.priority.is_none()
+            && text.strip_prefix("x ").and_then(split_date).is_some());
+    if let Some(created) = todo.created {
+        line.push_str(&format!("{} ", created.format("%Y-%m-%d")));
+    } else if ambiguous {
+        line.push_str(&format!("{} ", date));
+    }
+
+    line.push_str(text);
+    if let Some(due) = todo.due {
+        line.push_str(&format!(" due:{}", due.format("%Y-%m-%d")));
+    }
+    for (key, value) in &todo.meta {
+        line.push_str(&format!(" {}:{}", key, value));
+    }
+    if let Some(note) = todo.note.as_deref().filter(|n| !n.is_empty()) {
+        line.push_str(&format!(" {}\"{}\"", NOTE_TAG, escape_note(note)));
+    }
+    line
+}
+
+fn escape_note(note: &str) -> String {
+    let mut escaped = String::with_capacity(note.len());
+    for c in note.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Reads a `note:"..."` tag back off the end of a line. Quotes inside the
+// note are escaped, so the last unescaped ` note:"` starts the tag.
+fn split_note(line: &str) -> Option<(&str, String)> {
+    let start = line.rfind(&format!(" {}\"", NOTE_TAG))?;
+    let quoted = line[start + NOTE_TAG.len() + 2..].strip_suffix('"')?;
+    let mut note = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                'n' => note.push('\n'),
+                escaped => note.push(escaped),
+            },
+            '"' => return None,
+            _ => note.push(c),
+        }
+    }
+    Some((&line[..start], note))
+}
+
+/// Parses one todo.txt line; see [`format_line`] for the completion rule.
+/// Everything [`format_line`] writes comes back:
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use tuido::todotxt::{format_line, parse_line};
+///
+/// let date = |day| NaiveDate::from_ymd_opt(2025, 3, day).unwrap();
+/// let mut todo = tuido::Todo::new("call +acme about @phone invoice");
+/// todo.priority = Some('B');
+/// todo.created = Some(date(1));
+/// todo.set_completed(true, date(4));
+/// todo.due = Some(date(7));
+/// todo.meta.insert("effort".to_string(), "3".to_string());
+/// todo.note = Some("ask for \"Dana\"\nnot C:\\billing".to_string());
+///
+/// let line = format_line(&todo, date(9));
+/// assert_eq!(
+///     line,
+///     r#"x 2025-03-04 (B) 2025-03-01 call +acme about @phone invoice due:2025-03-07 effort:3 note:"ask for \"Dana\"\nnot C:\\billing""#
+/// );
+/// assert_eq!(parse_line(&line), todo);
+/// ```
+pub fn parse_line(line: &str) -> Todo {
+    let mut rest = line.trim_end();
+    let note = split_note(rest).map(|(line, note)| {
+        rest = line;
+        note
+    });
+
+    let completed_on = rest
+        .strip_prefix("x ")
+        .and_then(|tail| tail.split_once(' '))
+        .and_then(|(date, _)| parse_date(date));
+    let completed = match rest.strip_prefix("x ").and_then(split_date) {
+        Some(tail) => {
+            rest = tail;
+            true
+        }
+        None => false,
+    };
+
+    let priority = match split_priority(rest) {
+        Some((priority, tail)) => {
+            rest = tail;
+            Some(priority)
+        }
+        None => None,
+    };
+
+    // Optional creation date
+    let created = rest.split_once(' ').and_then(|(date, _)| parse_date(date));
+    if let Some(tail) = split_date(rest) {
+        rest = tail;
+    }
+
+    let (due, text) = parse_due(rest);
+    let (meta, text) = parse_meta(&text);
+    Todo {
+        text,
+        completed,
+        priority,
+        due,
+        meta,
+        created,
+        completed_on,
+        note,
+        ..Default::default()
+    }
+}
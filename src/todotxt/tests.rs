@@ -0,0 +1,125 @@
+use super::*;
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 3, day).unwrap()
+}
+
+// Writes `todo` out and reads it back
+fn round_trip(todo: &Todo) -> Todo {
+    parse_line(&format_line(todo, date(9)))
+}
+
+#[test]
+fn x_ray_appointment_stays_open() {
+    let todo = parse_line("x ray appointment");
+    assert!(!todo.completed);
+    assert_eq!(todo.completed_on, None);
+    assert_eq!(todo.text, "x ray appointment");
+
+    let todo = Todo::new("x ray appointment");
+    assert_eq!(format_line(&todo, date(9)), "x ray appointment");
+    assert_eq!(round_trip(&todo), todo);
+}
+
+#[test]
+fn capital_x_is_not_a_completion_marker() {
+    let todo = parse_line("X marks the spot");
+    assert!(!todo.completed);
+    assert_eq!(todo.text, "X marks the spot");
+
+    let todo = Todo::new("X marks the spot");
+    assert_eq!(format_line(&todo, date(9)), "X marks the spot");
+    assert_eq!(round_trip(&todo), todo);
+
+    let todo = parse_line("X 2025-03-04 not done either");
+    assert!(!todo.completed);
+}
+
+#[test]
+fn completed_todos_keep_their_date() {
+    let todo = parse_line("x 2025-03-04 file taxes");
+    assert!(todo.completed);
+    assert_eq!(todo.completed_on, Some(date(4)));
+    assert_eq!(todo.text, "file taxes");
+    assert_eq!(format_line(&todo, date(9)), "x 2025-03-04 file taxes");
+
+    let mut todo = Todo::new("file taxes");
+    todo.set_completed(true, date(4));
+    assert_eq!(round_trip(&todo), todo);
+}
+
+#[test]
+fn completed_todos_without_a_date_get_today() {
+    let mut todo = Todo::new("file taxes");
+    todo.completed = true;
+    assert_eq!(format_line(&todo, date(9)), "x 2025-03-09 file taxes");
+    assert_eq!(round_trip(&todo).completed_on, Some(date(9)));
+}
+
+#[test]
+fn text_that_looks_like_a_marker_is_kept_literally() {
+    for text in [
+        "x 2025-03-04 looks done",
+        "(A) looks urgent",
+        "2025-03-04 looks dated",
+    ] {
+        let todo = Todo::new(text);
+        let back = round_trip(&todo);
+        assert_eq!(back.text, text);
+        assert!(!back.completed);
+        assert_eq!(back.priority, None);
+    }
+}
+
+// Every field todo.txt can hold, in every combination the format allows
+const FIXTURE: &str = include_str!("../../tests/fixtures/todo.txt");
+
+#[test]
+fn a_file_with_every_field_round_trips() {
+    let todos = parse(FIXTURE);
+    assert_eq!(todos.len(), 7);
+    assert_eq!(to_string(&todos, date(9)), FIXTURE);
+    assert_eq!(parse(&to_string(&todos, date(9))), todos);
+
+    let call = &todos[0];
+    assert_eq!(call.text, "call +acme about @phone invoice");
+    assert_eq!(call.priority, Some('A'));
+    assert_eq!(call.created, Some(date(1)));
+    assert_eq!(call.due, Some(date(7)));
+    assert_eq!(call.meta["effort"], "3");
+    assert_eq!(
+        call.note.as_deref(),
+        Some("ask for \"Dana\"\nnot C:\\billing")
+    );
+
+    let taxes = &todos[1];
+    assert!(taxes.completed);
+    assert_eq!(taxes.completed_on, Some(date(4)));
+    assert_eq!(taxes.priority, Some('B'));
+    assert_eq!(taxes.created, NaiveDate::from_ymd_opt(2025, 2, 20));
+
+    // A date after the creation date is part of the text
+    assert_eq!(todos[3].created, Some(date(2)));
+    assert_eq!(todos[3].text, "2025-03-04 looks dated");
+    assert_eq!(todos[4].meta.len(), 2);
+    assert_eq!(todos[6].note.as_deref(), Some("kept \\ after done"));
+}
+
+#[test]
+fn todos_with_every_field_round_trip() {
+    let mut full = Todo::new("call +acme about @phone invoice");
+    full.priority = Some('A');
+    full.created = Some(date(1));
+    full.set_completed(true, date(4));
+    full.due = Some(date(7));
+    full.meta.insert("effort".to_string(), "3".to_string());
+    full.meta.insert("client".to_string(), "acme".to_string());
+    full.note = Some("line one\n\"quoted\" \\ slash\nnote:\"fake tag\"".to_string());
+
+    let mut open = full.clone();
+    open.set_completed(false, date(9));
+
+    for todo in [full, open, Todo::new("bare")] {
+        assert_eq!(round_trip(&todo), todo);
+    }
+}
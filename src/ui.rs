@@ -0,0 +1,1070 @@
+//! Rendering [`App`] with ratatui: the list, the status and command lines,
+//! and the popups drawn over them.
+
+use crate::app::{
+    App, ColorSupport, Density, ListHits, MessageLevel, Mode, Picker, STDIN_NAME, TextBuffer,
+    TextPopup, find_match,
+};
+use crate::width::{ELLIPSIS, truncate, wrap};
+use crate::{Todo, tag_kind, url_in};
+use chrono::NaiveDate;
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, List, ListItem, ListState, Paragraph, Scrollbar, ScrollbarOrientation,
+        ScrollbarState,
+    },
+};
+use std::ops::Range;
+
+#[cfg(test)]
+mod tests;
+
+// Below this many list rows the two-line layout shows too few items to be
+// useful, so rendering falls back to compact.
+const MIN_COMFORTABLE_HEIGHT: u16 = 12;
+
+// Smaller terminals get a note asking for more room instead of a screen
+// too cramped to use
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 10;
+
+// Popup size limits; see centered_rect
+const POPUP_MIN_WIDTH: u16 = 72;
+const POPUP_MAX_WIDTH: u16 = 110;
+const POPUP_MIN_HEIGHT: u16 = 18;
+const POPUP_MAX_HEIGHT: u16 = 40;
+
+/// Draws the whole screen for the app's current mode.
+pub fn draw(f: &mut Frame, app: &mut App) {
+    let area = f.area();
+    if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+        // Nothing on screen to click
+        app.list_hits = ListHits::default();
+        let note = format!("Terminal too small (need {}x{})", MIN_WIDTH, MIN_HEIGHT);
+        let lines: Vec<Line> = wrap(&note, area.width.max(1) as usize)
+            .into_iter()
+            .map(Line::from)
+            .collect();
+        let height = (lines.len() as u16).min(area.height);
+        f.render_widget(
+            Paragraph::new(lines).alignment(Alignment::Center),
+            Rect {
+                y: area.y + (area.height - height) / 2,
+                height,
+                ..area
+            },
+        );
+        return;
+    }
+    match app.mode {
+        Mode::Help => {
+            if let Some(popup) = &mut app.popup {
+                render_text_popup(f, popup);
+            }
+        }
+        Mode::Popup => {
+            render_main_ui(f, app);
+            if let Some(popup) = &mut app.popup {
+                render_text_popup(f, popup);
+            }
+        }
+        Mode::Merge => {
+            render_main_ui(f, app);
+            render_merge_popup(f, app);
+        }
+        Mode::NoteEdit => {
+            render_main_ui(f, app);
+            render_note_editor(f, app);
+        }
+        Mode::Picker => {
+            render_main_ui(f, app);
+            if let Some(picker) = &mut app.picker {
+                render_picker(f, picker);
+            }
+        }
+        _ => {
+            render_main_ui(f, app);
+        }
+    }
+    if let Some(input) = &app.quick_add {
+        render_quick_add(f, input);
+    }
+    if app.options.color_support == ColorSupport::NoColor {
+        strip_colors(f.buffer_mut());
+    }
+}
+
+// Modifiers-only styling for NO_COLOR: anything drawn on a background
+// (cursor row, visual selection, status bar) is shown reversed and dark
+// gray text is dimmed
+// The filtered rows to draw in `room` lines, as ratatui's List would pick
+// them: from `offset` when the selected row fits below it, otherwise
+// scrolled just far enough to show the selection at the top or bottom.
+// Only rows near the screen are measured, however long the list.
+fn visible_rows(
+    len: usize,
+    selected: Option<usize>,
+    offset: usize,
+    room: usize,
+    height: impl Fn(usize) -> usize,
+) -> Range<usize> {
+    if len == 0 {
+        return 0..0;
+    }
+    let mut first = offset.min(len - 1);
+    if let Some(selected) = selected.map(|row| row.min(len - 1)) {
+        if selected < first {
+            first = selected;
+        } else {
+            let mut used = 0;
+            for row in first..=selected {
+                used += height(row);
+                if used > room {
+                    break;
+                }
+            }
+            if used > room {
+                // Selection at the bottom, with as many rows above as fit
+                first = selected;
+                used = height(selected);
+                while first > 0 && used + height(first - 1) <= room {
+                    first -= 1;
+                    used += height(first);
+                }
+            }
+        }
+    }
+    let (mut end, mut used) = (first, 0);
+    while end < len && (end == first || used + height(end) <= room) {
+        used += height(end);
+        end += 1;
+    }
+    first..end
+}
+
+fn strip_colors(buffer: &mut ratatui::buffer::Buffer) {
+    for cell in &mut buffer.content {
+        if cell.bg != Color::Reset {
+            cell.modifier |= Modifier::REVERSED;
+        }
+        if cell.fg == Color::DarkGray {
+            cell.modifier |= Modifier::DIM;
+        }
+        cell.fg = Color::Reset;
+        cell.bg = Color::Reset;
+    }
+}
+
+fn render_main_ui(f: &mut Frame, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .split(f.area());
+
+    // Determine visual selection range if in visual mode
+    let visual_range = if app.mode == Mode::Visual
+        && app.visual_start.is_some()
+        && let Some(start) = app.visual_start
+        && let Some(end) = app.list_state.selected()
+    {
+        Some((start.min(end), start.max(end)))
+    } else {
+        None
+    };
+
+    // Comfortable layout gives every todo a second, dimmed row for its details
+    let two_line =
+        app.options.density == Density::Comfortable && chunks[0].height >= MIN_COMFORTABLE_HEIGHT;
+
+    // Due dates are right-aligned inside the borders and highlight symbol
+    let today = app.today;
+    let row_width = chunks[0].width.saturating_sub(4) as usize;
+
+    // `:set number` and `:set relativenumber` put a gutter of numbers in
+    // front of each todo, as wide as the biggest number needs; the lines
+    // under a todo are indented past it
+    let gutter_width = if app.options.number || app.options.relative_number {
+        app.filtered_todos.len().max(1).to_string().len() + 1
+    } else {
+        0
+    };
+    let gutter_pad = " ".repeat(gutter_width);
+    let cursor = app.list_state.selected();
+    let dimmed = Style::default()
+        .fg(Color::DarkGray)
+        .remove_modifier(Modifier::BOLD | Modifier::CROSSED_OUT);
+
+    // Only the rows on screen become ListItems, so a long list draws as
+    // fast as a short one. Each row is its todo plus the details line and
+    // the expanded note's wrapped lines.
+    let note_width = row_width.saturating_sub(gutter_width + NOTE_INDENT.len());
+    let row_height = |row: usize| {
+        let todo = &app.todos[app.filtered_todos[row]];
+        let note_lines = match &todo.note {
+            Some(note) if todo.note_expanded => wrap(note, note_width).len(),
+            _ => 0,
+        };
+        1 + usize::from(two_line) + note_lines
+    };
+    let rows = visible_rows(
+        app.filtered_todos.len(),
+        app.list_state.selected(),
+        app.list_state.offset(),
+        chunks[0].height.saturating_sub(2) as usize,
+        row_height,
+    );
+
+    // Main todo list
+    let items: Vec<ListItem> = app.filtered_todos[rows.clone()]
+        .iter()
+        .zip(rows.clone())
+        .map(|(&todo_idx, idx)| {
+            let todo = &app.todos[todo_idx];
+            let checkbox = if todo.completed { "[✓]" } else { "[ ]" };
+
+            // Determine style based on completion and priority
+            let theme = &app.theme;
+            let mut style = if todo.completed {
+                Style::default()
+                    .fg(app.options.color_support.adapt(theme.completed))
+                    .add_modifier(Modifier::CROSSED_OUT)
+            } else {
+                Style::default().fg(Color::White)
+            };
+
+            // Apply priority color if not completed
+            if !todo.completed
+                && let Some(priority) = todo.priority
+            {
+                style = style.add_modifier(Modifier::BOLD);
+                let color = match priority {
+                    'A' => theme.priority_a,
+                    'B' => theme.priority_b,
+                    'C' => theme.priority_c,
+                    _ => theme.priority_other,
+                };
+                style = style.fg(app.options.color_support.adapt(color));
+            }
+
+            // Overdue and due-today override the priority color
+            if !todo.completed
+                && let Some(due) = todo.due
+            {
+                if due < today {
+                    style = style.fg(Color::Red);
+                } else if due == today {
+                    style = style.fg(Color::Yellow);
+                }
+            }
+
+            // Apply visual mode highlighting for selected items
+            if let Some((start, end)) = visual_range
+                && idx >= start
+                && idx <= end
+            {
+                style = style.bg(app.options.color_support.visual_bg(theme));
+            } else if app.recently_changed.contains(&todo_idx) {
+                style = style.patch(app.options.color_support.flash_style());
+            }
+
+            let note_indicator = if todo.note.is_some() && !todo.note_expanded {
+                " ›"
+            } else {
+                ""
+            };
+
+            // Project (+name) and context (@name) tags stand out on open todos,
+            // and the chars matching the search are highlighted
+            let matched = find_match(&app.search_matches, todo_idx).unwrap_or_default();
+            let mut spans = Vec::new();
+            if gutter_width > 0 {
+                // Relative numbers are distances, except on the cursor row
+                let (number, style) = match cursor {
+                    Some(cursor) if app.options.relative_number && cursor != idx => {
+                        (cursor.abs_diff(idx), dimmed)
+                    }
+                    _ if app.options.relative_number => (idx + 1, dimmed.fg(Color::Yellow)),
+                    _ => (idx + 1, dimmed),
+                };
+                spans.push(Span::styled(
+                    format!("{:>1$} ", number, gutter_width - 1),
+                    style,
+                ));
+            }
+            spans.push(Span::raw(format!(" {} ", checkbox)));
+            let mut offset = 0;
+            for (i, word) in todo.text.split(' ').enumerate() {
+                if i > 0 {
+                    spans.push(Span::raw(" "));
+                    offset += 1;
+                }
+                let style = match tag_kind(word) {
+                    Some('+') if !todo.completed => Style::default().fg(Color::Cyan),
+                    Some('@') if !todo.completed => Style::default().fg(Color::Magenta),
+                    // Links are underlined so `gx` has something to open
+                    _ if url_in(word).is_some() => Style::default()
+                        .fg(Color::Blue)
+                        .add_modifier(Modifier::UNDERLINED),
+                    _ => Style::default(),
+                };
+                spans.extend(highlight_matches(word, offset, matched, style));
+                offset += word.chars().count();
+            }
+            spans.push(Span::raw(note_indicator));
+            if app.options.show_meta {
+                for (key, value) in &todo.meta {
+                    spans.push(Span::styled(
+                        format!(" {}:{}", key, value),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+            }
+            // Long text is cut short so a due date stays right-aligned
+            // inside the border
+            if let Some(due) = todo.due {
+                let date = format!(" {} ", due.format("%Y-%m-%d"));
+                spans = truncate_spans(spans, row_width.saturating_sub(date.len()));
+                let used = Line::from(spans.clone()).width() + date.len();
+                spans.push(Span::raw(" ".repeat(row_width.saturating_sub(used))));
+                spans.push(Span::raw(date));
+            } else {
+                spans = truncate_spans(spans, row_width);
+            }
+            let mut lines = vec![Line::from(spans)];
+            if two_line {
+                lines.push(Line::styled(
+                    format!("{}     {}", gutter_pad, todo_details(todo, today)),
+                    dimmed,
+                ));
+            }
+            // Expanded notes are wrapped and indented under the todo
+            if todo.note_expanded
+                && let Some(note) = &todo.note
+            {
+                for row in wrap(note, note_width) {
+                    lines.push(Line::styled(
+                        format!("{}{}{}", gutter_pad, NOTE_INDENT, row),
+                        dimmed,
+                    ));
+                }
+            }
+            ListItem::new(lines).style(style)
+        })
+        .collect();
+
+    let list_block = Block::default().borders(Borders::ALL).title(" TODOs ");
+    let inner = list_block.inner(chunks[0]);
+
+    // Update highlight style based on visual mode
+    let highlight_style = if app.mode == Mode::Visual {
+        Style::default()
+            .bg(app.options.color_support.visual_bg(&app.theme))
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default()
+            .bg(app.options.color_support.adapt(app.theme.highlight_bg))
+            .add_modifier(Modifier::BOLD)
+    };
+
+    let list = List::new(items)
+        .block(list_block)
+        .highlight_style(highlight_style)
+        .highlight_symbol("❯ ");
+
+    // The list only has the visible rows, so it gets a state relative to
+    // them and the real offset is kept for the next draw
+    let mut state =
+        ListState::default().with_selected(app.list_state.selected().map(|row| row - rows.start));
+    f.render_stateful_widget(list, chunks[0], &mut state);
+    *app.list_state.offset_mut() = rows.start;
+    app.list_view = rows.clone();
+
+    // Where each row landed, for mouse clicks: inside the border, past the
+    // highlight symbol (only drawn with a selection) and the gutter
+    let symbol = if state.selected().is_some() { 2 } else { 0 };
+    let checkbox = inner.x + symbol + gutter_width as u16 + 1;
+    let mut top = inner.y;
+    let mut hits = Vec::new();
+    for row in rows.clone() {
+        if top >= inner.bottom() {
+            break;
+        }
+        let bottom = (top + row_height(row) as u16).min(inner.bottom());
+        hits.push((top..bottom, row));
+        top = bottom;
+    }
+    app.list_hits = ListHits {
+        columns: inner.left()..inner.right(),
+        checkbox: checkbox..checkbox + 3,
+        rows: hits,
+    };
+
+    // Flashed todos the list didn't draw: filtered out or scrolled away
+    let shown = &app.filtered_todos[rows];
+    let off_screen = app
+        .recently_changed
+        .iter()
+        .filter(|i| !shown.contains(i))
+        .count();
+
+    if app.filtered_todos.is_empty() {
+        let inner = chunks[0].inner(ratatui::layout::Margin::new(1, 1));
+        if inner.height > 0 {
+            let hint_area = Rect {
+                y: inner.y + inner.height / 2,
+                height: 1,
+                ..inner
+            };
+            let hint = Paragraph::new(app.empty_list_hint())
+                .alignment(ratatui::layout::Alignment::Center)
+                .style(Style::default().fg(Color::DarkGray));
+            f.render_widget(hint, hint_area);
+        }
+    }
+
+    // Status line (like vim's statusline)
+    let mode_str = match app.mode {
+        Mode::Normal => "-- NORMAL --",
+        Mode::Insert => "-- INSERT --",
+        Mode::Command => "-- COMMAND --",
+        Mode::Visual => "-- VISUAL --",
+        Mode::Search => "-- SEARCH --",
+        Mode::NoteEdit => "-- NOTE EDIT --",
+        Mode::Help => "-- HELP --",
+        Mode::Popup => "-- VIEW --",
+        Mode::Merge => "-- MERGE --",
+        Mode::Picker => "-- PICK --",
+        Mode::Confirm => "-- CONFIRM --",
+        Mode::ChooseUrl => "-- OPEN URL --",
+    };
+
+    let mode_color = match app.mode {
+        Mode::Normal => Color::Cyan,
+        Mode::Insert => Color::Green,
+        Mode::Command => Color::Yellow,
+        Mode::Visual => Color::Magenta,
+        Mode::Search => Color::Blue,
+        Mode::NoteEdit => Color::Cyan,
+        Mode::Help | Mode::Popup => Color::White,
+        Mode::Merge => Color::LightRed,
+        Mode::Picker => Color::LightBlue,
+        Mode::Confirm => Color::LightRed,
+        Mode::ChooseUrl => Color::LightBlue,
+    };
+
+    // Calculate stats
+    let total = app.todos.len();
+    let completed = app.todos.iter().filter(|t| t.completed).count();
+    let percent = (completed * 100).checked_div(total).unwrap_or(0);
+    let selected_idx = app.list_state.selected().map(|i| i + 1).unwrap_or(0);
+
+    // Open todos per priority, A first
+    let mut priority_counts = std::collections::BTreeMap::new();
+    for priority in app
+        .todos
+        .iter()
+        .filter(|t| !t.completed)
+        .filter_map(|t| t.priority)
+    {
+        *priority_counts.entry(priority).or_insert(0) += 1;
+    }
+
+    let mut status_parts = vec![
+        Span::styled(
+            format!(" {} ", mode_str),
+            Style::default()
+                .fg(Color::Black)
+                .bg(mode_color)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!(
+            " {}{} ",
+            if app.scratch {
+                STDIN_NAME.into()
+            } else {
+                app.current_file
+                    .file_name()
+                    .map(|name| name.to_string_lossy())
+                    .unwrap_or_else(|| app.current_file.to_string_lossy())
+            },
+            if app.is_dirty { " [+]" } else { "" }
+        )),
+        Span::styled(
+            if app.changed_on_disk {
+                "changed on disk "
+            } else {
+                ""
+            },
+            Style::default().fg(Color::Red),
+        ),
+        Span::raw(format!("│ [{}/{}] {}% ", selected_idx, total, percent)),
+        Span::raw(format!("│ {} completed ", completed)),
+    ];
+
+    // Up front, so a narrow terminal doesn't cut it off
+    if let Some(job) = &app.shell_job {
+        status_parts.insert(
+            3,
+            Span::styled(
+                format!("│ running: {}… (Ctrl+C to cancel) ", job.command),
+                Style::default().fg(Color::Yellow),
+            ),
+        );
+    }
+
+    if off_screen > 0 {
+        status_parts.push(Span::styled(
+            format!("│ +{} changed off-screen ", off_screen),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+
+    // The three highest priorities in use, and how many todos have a lower one
+    if !priority_counts.is_empty() {
+        let mut counts: Vec<String> = priority_counts
+            .iter()
+            .take(3)
+            .map(|(priority, count)| format!("{}:{}", priority, count))
+            .collect();
+        let rest: usize = priority_counts.values().skip(3).sum();
+        if rest > 0 {
+            counts.push(format!("+{}", rest));
+        }
+        status_parts.push(Span::raw(format!("│ {} ", counts.join(" "))));
+    }
+
+    if let Some(priority) = app.priority_filter {
+        status_parts.push(Span::styled(
+            format!("│ ({}) only ", priority),
+            Style::default().fg(Color::Magenta),
+        ));
+    }
+
+    if let Some((key, value)) = &app.meta_filter {
+        status_parts.push(Span::styled(
+            format!("│ {}:{} ", key, value),
+            Style::default().fg(Color::Magenta),
+        ));
+    }
+
+    // Add search results if in search mode or filtered
+    if let Some(error) = &app.search_error {
+        status_parts.push(Span::styled(
+            format!("│ {} ", error),
+            Style::default().fg(Color::Red),
+        ));
+    } else if !app.search_query.is_empty() {
+        status_parts.push(Span::styled(
+            format!(
+                "│ {} results for '{}'{}",
+                app.search_matches.len(),
+                app.search_query,
+                if app.search_pending { " …" } else { "" }
+            ),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+
+    // A macro being recorded and a count or half-typed sequence (`3`, `d`,
+    // `"+`) go at the right end, where the rest of the status line can't
+    // push them off
+    let mut right = Vec::new();
+    if let Some(name) = app.recording() {
+        right.push(Span::styled(
+            format!(" recording @{} ", name),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    let keys = app.pending_keys();
+    if !keys.is_empty() {
+        right.push(Span::styled(
+            format!(" {} ", keys),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+    }
+    let right_width = Line::from(right.clone()).width();
+    let room = (chunks[1].width as usize).saturating_sub(right_width);
+    let mut status_parts = truncate_spans(status_parts, room);
+    if !right.is_empty() {
+        let used = Line::from(status_parts.clone()).width();
+        status_parts.push(Span::raw(" ".repeat(room.saturating_sub(used))));
+        status_parts.extend(right);
+    }
+    let status_line = Paragraph::new(Line::from(status_parts)).style(
+        Style::default()
+            .bg(app.options.color_support.rgb(30, 30, 30))
+            .fg(Color::White),
+    );
+
+    f.render_widget(status_line, chunks[1]);
+
+    // Command line
+    let cmd_line = match app.mode {
+        Mode::Insert => Paragraph::new(format!("New TODO: {}", app.input))
+            .style(Style::default().fg(Color::White)),
+        Mode::Command if app.confirmed_shell.is_some() => Paragraph::new(format!(
+            "⚠ :{}  — press Enter again to run this recalled shell command",
+            app.command_input
+        ))
+        .style(Style::default().fg(Color::Red)),
+        Mode::Command if app.recalled_shell_command() => {
+            Paragraph::new(format!("⚠ :{}", app.command_input))
+                .style(Style::default().fg(Color::Yellow))
+        }
+        Mode::Command => Paragraph::new(format!("{}{}", app.command_prompt(), app.command_input))
+            .style(Style::default().fg(Color::White)),
+        Mode::Search => {
+            Paragraph::new(format!("/{}", app.search_query)).style(Style::default().fg(Color::Cyan))
+        }
+        Mode::Confirm => {
+            Paragraph::new(app.message.clone()).style(Style::default().fg(Color::LightRed))
+        }
+        _ if app.message_level == MessageLevel::Error => {
+            Paragraph::new(app.message.clone()).style(Style::default().fg(Color::LightRed))
+        }
+        _ => Paragraph::new(app.message.clone()).style(Style::default().fg(Color::Yellow)),
+    };
+
+    f.render_widget(cmd_line, chunks[2]);
+
+    // Put the terminal cursor where typing lands; other modes leave it
+    // hidden and rely on the list highlight
+    let before_cursor = match app.mode {
+        Mode::Insert => Some(format!("New TODO: {}", app.input.before_cursor())),
+        Mode::Command if app.confirmed_shell.is_some() || app.recalled_shell_command() => {
+            Some(format!("⚠ :{}", app.command_input.before_cursor()))
+        }
+        Mode::Command => Some(format!(
+            "{}{}",
+            app.command_prompt(),
+            app.command_input.before_cursor()
+        )),
+        Mode::Search => Some(format!("/{}", app.search_query.before_cursor())),
+        _ => None,
+    };
+    if let Some(text) = before_cursor {
+        let col = (Line::from(text).width() as u16).min(chunks[2].width.saturating_sub(1));
+        f.set_cursor_position((chunks[2].x + col, chunks[2].y));
+    }
+}
+
+// Cuts `spans` to fit in `max` columns, ending them with an ellipsis in the
+// style of the last span kept. Cuts fall between graphemes of the whole line,
+// so a wide character is dropped rather than split across the border.
+fn truncate_spans(spans: Vec<Span<'_>>, max: usize) -> Vec<Span<'_>> {
+    let text: String = spans.iter().map(|span| span.content.as_ref()).collect();
+    let cut = truncate(&text, max);
+    let Some(kept) = cut.strip_suffix(ELLIPSIS).map(str::len) else {
+        return spans;
+    };
+    let mut result = Vec::new();
+    let mut start = 0;
+    for span in spans {
+        if start >= kept {
+            break;
+        }
+        let len = span.content.len();
+        let style = span.style;
+        if start + len <= kept {
+            result.push(span);
+        } else {
+            result.push(Span::styled(
+                span.content[..kept - start].to_string(),
+                style,
+            ));
+        }
+        start += len;
+    }
+    let style = result.last().map(|span| span.style).unwrap_or_default();
+    result.push(Span::styled(ELLIPSIS, style));
+    result
+}
+
+// Splits `word`, which starts at char `offset` of its todo's text, into
+// spans with the chars at `matched` offsets highlighted
+fn highlight_matches<'a>(
+    word: &'a str,
+    offset: usize,
+    matched: &[usize],
+    style: Style,
+) -> Vec<Span<'a>> {
+    // Underlined too, so matches stay visible on the cursor row's background
+    let highlight = style
+        .fg(Color::Black)
+        .bg(Color::Yellow)
+        .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    let mut spans = Vec::new();
+    let mut run_start = 0;
+    let mut run_matched = false;
+    for (i, (byte, _)) in word.char_indices().enumerate() {
+        let is_match = matched.binary_search(&(offset + i)).is_ok();
+        if is_match != run_matched {
+            if byte > run_start {
+                let run_style = if run_matched { highlight } else { style };
+                spans.push(Span::styled(&word[run_start..byte], run_style));
+            }
+            (run_start, run_matched) = (byte, is_match);
+        }
+    }
+    let run_style = if run_matched { highlight } else { style };
+    spans.push(Span::styled(&word[run_start..], run_style));
+    spans
+}
+
+const NOTE_INDENT: &str = "      ";
+
+// The dimmed second row of the comfortable layout: the note's first line,
+// due date, tags and fields, and how long ago the todo was added
+fn todo_details(todo: &Todo, today: NaiveDate) -> String {
+    let mut details = Vec::new();
+    if let Some(first_line) = todo.note.as_deref().and_then(|n| n.lines().next()) {
+        details.push(first_line.to_string());
+    }
+    if let Some(due) = todo.due {
+        details.push(format!("due {}", due.format("%Y-%m-%d")));
+    }
+    let labels: Vec<String> = todo
+        .text
+        .split_whitespace()
+        .filter(|word| tag_kind(word).is_some())
+        .map(str::to_string)
+        .chain(
+            todo.meta
+                .iter()
+                .map(|(key, value)| format!("{}:{}", key, value)),
+        )
+        .collect();
+    if !labels.is_empty() {
+        details.push(labels.join(" "));
+    }
+    if let Some(created) = todo.created {
+        details.push(match (today - created).num_days() {
+            ..=0 => "added today".to_string(),
+            1 => "1 day old".to_string(),
+            days => format!("{} days old", days),
+        });
+    }
+    if details.is_empty() {
+        "no details".to_string()
+    } else {
+        details.join(" · ")
+    }
+}
+
+// Hard-wraps one popup line at spaces, keeping its indentation on the
+// continuation rows. A word that can't fit is left whole for h/l to pan to.
+fn wrap_popup_line(line: &str, width: usize) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    if chars.len() <= width || width == 0 {
+        return vec![line.to_string()];
+    }
+    let indent = chars
+        .iter()
+        .take_while(|c| **c == ' ')
+        .count()
+        .min(width / 2);
+    let mut rows = Vec::new();
+    let mut start = 0;
+    let mut prefix = 0;
+    while start < chars.len() {
+        let room = width - prefix;
+        let rest = &chars[start..];
+        let take = if rest.len() <= room {
+            rest.len()
+        } else {
+            // Break at the last space that fits, else after the long word
+            match rest[..=room].iter().rposition(|c| *c == ' ') {
+                Some(pos) if pos > 0 => pos,
+                _ => rest.iter().position(|c| *c == ' ').unwrap_or(rest.len()),
+            }
+        };
+        let row: String = rest[..take].iter().collect();
+        rows.push(format!("{}{}", " ".repeat(prefix), row.trim_end()));
+        start += take;
+        while start < chars.len() && chars[start] == ' ' {
+            start += 1;
+        }
+        prefix = indent;
+    }
+    rows
+}
+
+fn render_text_popup(f: &mut Frame, popup: &mut TextPopup) {
+    let area = centered_rect(60, 50, f.area());
+    f.render_widget(ratatui::widgets::Clear, area);
+
+    // Leave the rightmost column for the scrollbar
+    let inner = Block::default().borders(Borders::ALL).inner(area);
+    let text_width = inner.width.saturating_sub(1) as usize;
+    // Each wrapped row keeps whether its line is an error
+    let rows: Vec<(String, bool)> = popup
+        .lines
+        .iter()
+        .enumerate()
+        .flat_map(|(i, line)| {
+            let error = popup.error_lines.contains(&i);
+            wrap_popup_line(line, text_width)
+                .into_iter()
+                .map(move |row| (row, error))
+        })
+        .collect();
+    let height = inner.height as usize;
+    let max_scroll = rows.len().saturating_sub(height);
+    popup.scroll.set_bounds(max_scroll, height);
+    let widest = rows
+        .iter()
+        .map(|(r, _)| r.chars().count())
+        .max()
+        .unwrap_or(0);
+    popup.hscroll = popup.hscroll.min(widest.saturating_sub(text_width));
+
+    let mut hints = vec![];
+    if max_scroll > 0 {
+        hints.push("j/k scroll".to_string());
+    }
+    if widest > text_width {
+        hints.push("h/l pan".to_string());
+    }
+    hints.push("Esc close".to_string());
+    if max_scroll > 0 {
+        hints.push(format!(
+            "{}-{}/{}",
+            popup.scroll.position + 1,
+            (popup.scroll.position + height).min(rows.len()),
+            rows.len()
+        ));
+    }
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(popup.title.as_str())
+        .title_bottom(format!(" {} ", hints.join("  ")));
+
+    let visible: Vec<Line> = rows
+        .iter()
+        .skip(popup.scroll.position)
+        .take(height)
+        .map(|(row, error)| {
+            let line = Line::from(row.chars().skip(popup.hscroll).collect::<String>());
+            if *error {
+                line.style(Style::default().fg(Color::LightRed))
+            } else {
+                line
+            }
+        })
+        .collect();
+    f.render_widget(
+        Paragraph::new(visible)
+            .block(block)
+            .style(Style::default().fg(Color::White)),
+        area,
+    );
+
+    if max_scroll > 0 {
+        let mut state = ScrollbarState::new(max_scroll + 1).position(popup.scroll.position);
+        f.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight),
+            inner,
+            &mut state,
+        );
+    }
+}
+
+fn render_note_editor(f: &mut Frame, app: &App) {
+    let area = centered_rect(70, 60, f.area());
+    f.render_widget(ratatui::widgets::Clear, area);
+
+    let title = app
+        .current_note_index
+        .and_then(|i| app.todos.get(i))
+        .map(|todo| format!(" Note: {} ", todo.text))
+        .unwrap_or_else(|| " Note ".to_string());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(title)
+        .title_bottom(" Enter new line  Ctrl+S save  Esc cancel ");
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let (rows, (cursor_row, cursor_col)) = app.note_input.wrapped(inner.width as usize);
+    // Scroll just enough to keep the cursor row in view
+    let height = inner.height as usize;
+    let scroll = (cursor_row + 1).saturating_sub(height);
+    let lines: Vec<Line> = rows
+        .into_iter()
+        .skip(scroll)
+        .take(height)
+        .map(Line::from)
+        .collect();
+    f.render_widget(Paragraph::new(lines), inner);
+    if height > 0 {
+        f.set_cursor_position((
+            inner.x + cursor_col as u16,
+            inner.y + (cursor_row - scroll) as u16,
+        ));
+    }
+}
+
+fn render_quick_add(f: &mut Frame, input: &TextBuffer) {
+    let screen = f.area();
+    let width = screen.width.saturating_sub(4).clamp(1, 70);
+    let area = Rect {
+        x: screen.x + screen.width.saturating_sub(width) / 2,
+        y: screen.y + screen.height.saturating_sub(3) / 2,
+        width,
+        height: screen.height.min(3),
+    };
+    f.render_widget(ratatui::widgets::Clear, area);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Quick add ")
+        .title_bottom(" Enter add to end  Esc cancel ");
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    // Keep the cursor of a long input in view, measuring in terminal
+    // columns so wide (CJK, emoji) chars count double
+    let chars: Vec<char> = input.text().chars().collect();
+    let columns =
+        |from: usize| Line::from(chars[from..input.col].iter().collect::<String>()).width();
+    let mut start = 0;
+    while start < input.col && columns(start) >= inner.width as usize {
+        start += 1;
+    }
+    let visible: String = chars[start..].iter().collect();
+    f.render_widget(Paragraph::new(visible), inner);
+    if inner.height > 0 {
+        f.set_cursor_position((inner.x + columns(start) as u16, inner.y));
+    }
+}
+
+fn render_picker(f: &mut Frame, picker: &mut Picker) {
+    let area = centered_rect(80, 50, f.area());
+    f.render_widget(ratatui::widgets::Clear, area);
+    let height = area.height.saturating_sub(2) as usize;
+    picker
+        .scroll
+        .set_bounds(picker.items.len().saturating_sub(1), height);
+
+    let items: Vec<ListItem> = picker
+        .items
+        .iter()
+        .map(|item| ListItem::new(item.as_str()))
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(picker.title.as_str())
+                .title_bottom(format!(
+                    " j/k move  Enter choose  any other key skip  {}/{} ",
+                    picker.scroll.position + 1,
+                    picker.items.len()
+                )),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("❯ ");
+    let mut state = ListState::default();
+    state.select(Some(picker.scroll.position));
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn render_merge_popup(f: &mut Frame, app: &App) {
+    let Some(merge) = &app.merge else {
+        return;
+    };
+    let current = merge.choices.len();
+    let Some((mine_idx, theirs)) = merge.plan.conflicts.get(current) else {
+        return;
+    };
+    let mine = &app.todos[*mine_idx];
+
+    let describe = |todo: &Todo| -> Vec<Line> {
+        vec![
+            Line::from(todo.text.clone()),
+            Line::from(""),
+            Line::from(format!(
+                "Completed: {}",
+                if todo.completed { "yes" } else { "no" }
+            )),
+            Line::from(format!(
+                "Priority:  {}",
+                todo.priority
+                    .map(|p| format!("({})", p))
+                    .unwrap_or_else(|| "none".to_string())
+            )),
+            Line::from(format!(
+                "Note:      {}",
+                todo.note.as_deref().unwrap_or("none")
+            )),
+        ]
+    };
+
+    let area = centered_rect(80, 50, f.area());
+    f.render_widget(ratatui::widgets::Clear, area);
+    let block = Block::default().borders(Borders::ALL).title(format!(
+        " Merge conflict {}/{} from {} ",
+        current + 1,
+        merge.plan.conflicts.len(),
+        merge.source
+    ));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(rows[0]);
+
+    let wrap = ratatui::widgets::Wrap { trim: false };
+    f.render_widget(
+        Paragraph::new(describe(mine))
+            .wrap(wrap)
+            .block(Block::default().borders(Borders::ALL).title(" Mine ")),
+        columns[0],
+    );
+    f.render_widget(
+        Paragraph::new(describe(theirs))
+            .wrap(wrap)
+            .block(Block::default().borders(Borders::ALL).title(" Theirs ")),
+        columns[1],
+    );
+    f.render_widget(
+        Paragraph::new("k keep mine   t take theirs   b keep both   Esc cancel")
+            .style(Style::default().fg(Color::Yellow)),
+        rows[1],
+    );
+}
+
+// Popups take the given share of the screen, but never less than a
+// readable minimum (up to 90% of a small terminal, but always room for
+// the borders and a line) and never more than a comfortable reading width
+// on a large one.
+fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let fit = |total: u16, percent: u16, min: u16, max: u16| -> u16 {
+        let share = (u32::from(total) * u32::from(percent) / 100) as u16;
+        share
+            .clamp(min, max)
+            .min((u32::from(total) * 9 / 10) as u16)
+            .max(total.min(3))
+    };
+    let width = fit(r.width, percent_x, POPUP_MIN_WIDTH, POPUP_MAX_WIDTH);
+    let height = fit(r.height, percent_y, POPUP_MIN_HEIGHT, POPUP_MAX_HEIGHT);
+    Rect {
+        x: r.x + (r.width - width) / 2,
+        y: r.y + (r.height - height) / 2,
+        width,
+        height,
+    }
+}
@@ -0,0 +1,517 @@
+use super::*;
+use crate::app::{handle_key, handle_mouse};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::{Terminal, backend::TestBackend};
+use std::alloc::{GlobalAlloc, Layout as AllocLayout, System};
+use std::cell::Cell;
+use std::path::PathBuf;
+
+// Counts the allocations made on each thread, so tests running alongside
+// don't disturb a count
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: AllocLayout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: AllocLayout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+fn list_of(len: usize) -> App {
+    let mut app = App::new(PathBuf::from("tuido-test-unsaved.json"));
+    app.load_scratch(
+        (0..len)
+            .map(|i| Todo::new(format!("todo number {} +project @home", i)))
+            .collect(),
+    );
+    app
+}
+
+// Allocations made by one draw with the cursor halfway down the list
+fn allocations_scrolling(app: &mut App) -> usize {
+    let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    terminal.draw(|f| draw(f, app)).unwrap();
+    app.list_state.select(Some(app.todos.len() / 2));
+    let before = ALLOCATIONS.with(Cell::get);
+    terminal.draw(|f| draw(f, app)).unwrap();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn drawing_allocates_for_the_screen_not_the_list() {
+    let short = allocations_scrolling(&mut list_of(100));
+    let long = allocations_scrolling(&mut list_of(20_000));
+    assert!(
+        long < short + short / 4,
+        "{} allocations for 20000 todos, {} for 100",
+        long,
+        short
+    );
+}
+
+#[test]
+fn scrolling_keeps_the_selection_on_screen() {
+    let mut app = list_of(1_000);
+    let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    // 20 rows inside the borders, above the two status lines
+    for (selected, offset) in [(0, 0), (19, 0), (20, 1), (500, 481), (490, 481), (3, 3)] {
+        app.list_state.select(Some(selected));
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+        assert_eq!(app.list_state.offset(), offset, "selected {}", selected);
+    }
+}
+
+#[test]
+fn visible_rows_measures_rows_of_different_heights() {
+    // Every third row is three lines tall
+    let height = |row: usize| if row.is_multiple_of(3) { 3 } else { 1 };
+    assert_eq!(visible_rows(0, None, 0, 10, height), 0..0);
+    // From the offset while the selection fits
+    assert_eq!(visible_rows(100, Some(2), 0, 10, height), 0..6);
+    // Scrolled so the selection is the last row that fits
+    assert_eq!(visible_rows(100, Some(9), 0, 10, height), 4..10);
+    // Back up to a selection above the offset
+    assert_eq!(visible_rows(100, Some(1), 4, 10, height), 1..7);
+    // A row taller than the screen is still drawn
+    assert_eq!(visible_rows(100, Some(3), 0, 2, height), 3..4);
+    // An offset past the end, after the list shrank
+    assert_eq!(visible_rows(5, None, 40, 10, height), 4..5);
+}
+
+// The list's rows as text, without the borders
+fn list_rows(app: &mut App, rows: u16) -> Vec<String> {
+    let mut terminal = Terminal::new(TestBackend::new(40, rows + 4)).unwrap();
+    terminal.draw(|f| draw(f, app)).unwrap();
+    let buffer = terminal.backend().buffer();
+    (1..=rows)
+        .map(|y| {
+            (1..39)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn number_gutters() {
+    let mut app = list_of(12);
+    app.todos.iter_mut().for_each(|todo| todo.text.truncate(14));
+    app.list_state.select(Some(9));
+    app.execute_command("set number");
+    let rows = list_rows(&mut app, 12);
+    assert_eq!(rows[0], "   1  [ ] todo number 0");
+    assert_eq!(rows[9], "❯ 10  [ ] todo number 9");
+
+    app.execute_command("set relativenumber");
+    let rows = list_rows(&mut app, 12);
+    assert_eq!(rows[0], "   9  [ ] todo number 0");
+    assert_eq!(rows[9], "❯ 10  [ ] todo number 9");
+    assert_eq!(rows[11], "   2  [ ] todo number 11");
+
+    app.execute_command("set nonumber");
+    app.execute_command("set norelativenumber");
+    assert_eq!(list_rows(&mut app, 12)[0], "   [ ] todo number 0");
+}
+
+#[test]
+fn wide_text_is_cut_inside_the_border() {
+    let mut app = list_of(0);
+    app.load_scratch(vec![
+        Todo::new("日本語のテキストがとても長いのでボーダーを越えてしまう"),
+        Todo::new("🎉🎉🎉🎉🎉🎉🎉🎉🎉🎉🎉🎉🎉🎉🎉🎉🎉🎉🎉🎉 party"),
+        Todo::new("cafe\u{301} cafe\u{301} cafe\u{301} cafe\u{301} cafe\u{301} cafe\u{301}"),
+    ]);
+    let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+    terminal.draw(|f| draw(f, &mut app)).unwrap();
+    let buffer = terminal.backend().buffer();
+    let rows: Vec<String> = (1..=3)
+        .map(|y| (0..40).map(|x| buffer[(x, y)].symbol()).collect())
+        .collect();
+    // The cell after a wide char reads as a space
+    assert_eq!(
+        rows[0],
+        "│❯  [ ] 日 本 語 の テ キ ス ト が と て も 長 い の …│"
+    );
+    assert_eq!(
+        rows[1],
+        "│   [ ] 🎉 🎉 🎉 🎉 🎉 🎉 🎉 🎉 🎉 🎉 🎉 🎉 🎉 🎉 🎉 …│"
+    );
+    // Combining accents take no column, so all six fit
+    assert_eq!(
+        rows[2],
+        "│   [ ] cafe\u{301} cafe\u{301} cafe\u{301} cafe\u{301} cafe\u{301} cafe\u{301}  │"
+    );
+}
+
+#[test]
+fn empty_lists_draw_a_hint_saying_why() {
+    let mut terminal = Terminal::new(TestBackend::new(70, 10)).unwrap();
+    let mut app = list_of(0);
+    terminal.draw(|f| draw(f, &mut app)).unwrap();
+    let screen = format!("{:?}", terminal.backend().buffer());
+    assert!(screen.contains("No todos yet — press i to add one"));
+
+    let mut app = list_of(3);
+    type_keys(&mut app, &mut terminal, "/zebra");
+    press(&mut app, &mut terminal, KeyCode::Enter, false);
+    let screen = format!("{:?}", terminal.backend().buffer());
+    assert!(screen.contains("No matches for 'zebra' — press Esc to clear search"));
+}
+
+#[test]
+fn visual_selection_covers_the_gutter() {
+    let mut app = list_of(5);
+    app.execute_command("set number");
+    app.visual_start = Some(1);
+    app.mode = Mode::Visual;
+    app.list_state.select(Some(2));
+    let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+    terminal.draw(|f| draw(f, &mut app)).unwrap();
+    let buffer = terminal.backend().buffer();
+    let visual = app.options.color_support.visual_bg(&app.theme);
+    // The gutter cell of the first selected row, and not of the row above
+    assert_eq!(buffer[(4, 2)].bg, visual);
+    assert_ne!(buffer[(4, 1)].bg, visual);
+}
+
+fn press(app: &mut App, terminal: &mut Terminal<TestBackend>, code: KeyCode, ctrl: bool) {
+    let modifiers = if ctrl {
+        KeyModifiers::CONTROL
+    } else {
+        KeyModifiers::NONE
+    };
+    handle_key(app, KeyEvent::new(code, modifiers));
+    terminal.draw(|f| draw(f, app)).unwrap();
+}
+
+fn type_keys(app: &mut App, terminal: &mut Terminal<TestBackend>, keys: &str) {
+    for c in keys.chars() {
+        press(app, terminal, KeyCode::Char(c), false);
+    }
+}
+
+// (selected row, first row on screen)
+fn view(app: &App) -> (usize, usize) {
+    (app.list_state.selected().unwrap(), app.list_state.offset())
+}
+
+#[test]
+fn paging_moves_the_view_and_the_cursor_together() {
+    let mut app = list_of(100);
+    // 20 rows inside the borders
+    let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    terminal.draw(|f| draw(f, &mut app)).unwrap();
+    let mut ctrl = |app: &mut App, c| press(app, &mut terminal, KeyCode::Char(c), true);
+
+    ctrl(&mut app, 'd');
+    assert_eq!(view(&app), (10, 10));
+    ctrl(&mut app, 'f');
+    assert_eq!(view(&app), (30, 30));
+    ctrl(&mut app, 'b');
+    assert_eq!(view(&app), (10, 10));
+    ctrl(&mut app, 'u');
+    assert_eq!(view(&app), (0, 0));
+    ctrl(&mut app, 'u');
+    assert_eq!(view(&app), (0, 0));
+
+    // The view stops at the end of the list, the cursor at the last row
+    for _ in 0..6 {
+        ctrl(&mut app, 'f');
+    }
+    assert_eq!(view(&app), (99, 80));
+}
+
+#[test]
+fn h_m_l_pick_rows_on_screen() {
+    let mut app = list_of(100);
+    let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    terminal.draw(|f| draw(f, &mut app)).unwrap();
+    press(&mut app, &mut terminal, KeyCode::Char('d'), true);
+
+    type_keys(&mut app, &mut terminal, "L");
+    assert_eq!(view(&app), (29, 10));
+    type_keys(&mut app, &mut terminal, "M");
+    assert_eq!(view(&app), (19, 10));
+    type_keys(&mut app, &mut terminal, "H");
+    assert_eq!(view(&app), (10, 10));
+    type_keys(&mut app, &mut terminal, "3H");
+    assert_eq!(view(&app), (12, 10));
+    type_keys(&mut app, &mut terminal, "3L");
+    assert_eq!(view(&app), (27, 10));
+
+    // zz puts the cursor row in the middle
+    type_keys(&mut app, &mut terminal, "zz");
+    assert_eq!(view(&app), (27, 17));
+    type_keys(&mut app, &mut terminal, "ggzz");
+    assert_eq!(view(&app), (0, 0));
+
+    // On a list shorter than the screen, L is the last row
+    let mut app = list_of(5);
+    terminal.draw(|f| draw(f, &mut app)).unwrap();
+    type_keys(&mut app, &mut terminal, "L");
+    assert_eq!(view(&app), (4, 0));
+}
+
+#[test]
+fn paging_in_visual_mode_and_under_a_search() {
+    let mut app = list_of(100);
+    let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    terminal.draw(|f| draw(f, &mut app)).unwrap();
+    press(&mut app, &mut terminal, KeyCode::Char('j'), false);
+    press(&mut app, &mut terminal, KeyCode::Char('v'), false);
+    press(&mut app, &mut terminal, KeyCode::Char('d'), true);
+    assert_eq!(app.mode, Mode::Visual);
+    assert_eq!(app.visual_start, Some(1));
+    assert_eq!(view(&app), (11, 10));
+    press(&mut app, &mut terminal, KeyCode::Char('L'), false);
+    assert_eq!(view(&app), (29, 10));
+    press(&mut app, &mut terminal, KeyCode::Esc, false);
+
+    // Rows are the todos the search shows: numbers 1, 10-19, 21, 31, ...
+    type_keys(&mut app, &mut terminal, "/number 1");
+    press(&mut app, &mut terminal, KeyCode::Enter, false);
+    assert_eq!(app.filtered_todos.len(), 19);
+    type_keys(&mut app, &mut terminal, "gg");
+    press(&mut app, &mut terminal, KeyCode::Char('f'), true);
+    assert_eq!(view(&app), (18, 0));
+}
+
+fn mouse(
+    app: &mut App,
+    terminal: &mut Terminal<TestBackend>,
+    kind: MouseEventKind,
+    at: (u16, u16),
+) {
+    let (column, row) = at;
+    handle_mouse(
+        app,
+        MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        },
+    );
+    terminal.draw(|f| draw(f, app)).unwrap();
+}
+
+fn done(app: &App) -> Vec<usize> {
+    (0..app.todos.len())
+        .filter(|&i| app.todos[i].completed)
+        .collect()
+}
+
+#[test]
+fn clicks_select_and_the_wheel_scrolls() {
+    let mut app = list_of(100);
+    let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    terminal.draw(|f| draw(f, &mut app)).unwrap();
+    let left = MouseEventKind::Down(MouseButton::Left);
+
+    // The first row is on the line under the top border
+    mouse(&mut app, &mut terminal, left, (20, 5));
+    assert_eq!(view(&app), (4, 0));
+
+    // The wheel moves the view, dragging the cursor along only once it
+    // would leave the screen
+    mouse(&mut app, &mut terminal, MouseEventKind::ScrollDown, (20, 5));
+    assert_eq!(view(&app), (4, 3));
+    mouse(&mut app, &mut terminal, MouseEventKind::ScrollDown, (20, 5));
+    assert_eq!(view(&app), (6, 6));
+    mouse(&mut app, &mut terminal, left, (20, 1));
+    assert_eq!(view(&app), (6, 6));
+    mouse(&mut app, &mut terminal, left, (20, 20));
+    assert_eq!(view(&app), (25, 6));
+    mouse(&mut app, &mut terminal, MouseEventKind::ScrollUp, (20, 5));
+    assert_eq!(view(&app), (22, 3));
+
+    // Borders and the status lines aren't rows
+    for at in [(0, 5), (79, 5), (20, 0), (20, 21), (20, 23)] {
+        mouse(&mut app, &mut terminal, left, at);
+        assert_eq!(view(&app), (22, 3), "click at {:?}", at);
+    }
+    assert!(done(&app).is_empty());
+}
+
+#[test]
+fn clicks_that_toggle() {
+    let mut app = list_of(100);
+    let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    terminal.draw(|f| draw(f, &mut app)).unwrap();
+    let left = MouseEventKind::Down(MouseButton::Left);
+
+    // A double-click, then a third click that only selects
+    mouse(&mut app, &mut terminal, left, (20, 3));
+    mouse(&mut app, &mut terminal, left, (20, 3));
+    assert_eq!(done(&app), [2]);
+    mouse(&mut app, &mut terminal, left, (20, 3));
+    assert_eq!(done(&app), [2]);
+
+    // Two clicks on different rows are not a double-click
+    mouse(&mut app, &mut terminal, left, (20, 5));
+    mouse(&mut app, &mut terminal, left, (20, 6));
+    assert_eq!(done(&app), [2]);
+
+    // A middle click, or a click on the checkbox
+    mouse(
+        &mut app,
+        &mut terminal,
+        MouseEventKind::Down(MouseButton::Middle),
+        (20, 8),
+    );
+    assert_eq!(done(&app), [2, 7]);
+    mouse(&mut app, &mut terminal, left, (5, 3));
+    assert_eq!(done(&app), [7]);
+    assert_eq!(view(&app), (2, 0));
+
+    // The checkbox moves over with the gutter
+    app.options.number = true;
+    terminal.draw(|f| draw(f, &mut app)).unwrap();
+    mouse(&mut app, &mut terminal, left, (5, 10));
+    assert_eq!(done(&app), [7]);
+    mouse(&mut app, &mut terminal, left, (9, 11));
+    assert_eq!(done(&app), [7, 10]);
+
+    // In Visual mode only the clicked todo toggles
+    type_keys(&mut app, &mut terminal, "ggvj");
+    mouse(&mut app, &mut terminal, left, (9, 5));
+    assert_eq!(app.mode, Mode::Normal);
+    assert_eq!(done(&app), [4, 7, 10]);
+
+    type_keys(&mut app, &mut terminal, ":set nomouse");
+    press(&mut app, &mut terminal, KeyCode::Enter, false);
+    assert!(!app.options.mouse);
+}
+
+// The main screen and each popup, drawn at the given size
+fn draw_every_screen(width: u16, height: u16) -> Vec<(&'static str, String)> {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    let mut screens = Vec::new();
+    for keys in ["", "?", "o", "v"] {
+        let mut app = list_of(30);
+        app.todos[0].note = Some("a note long enough to wrap on a narrow screen".to_string());
+        app.todos[0].note_expanded = true;
+        type_keys(&mut app, &mut terminal, keys);
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+        press(&mut app, &mut terminal, KeyCode::Char('n'), true);
+        screens.push((keys, format!("{:?}", terminal.backend().buffer())));
+    }
+    screens
+}
+
+#[test]
+fn tiny_terminals_get_a_placeholder() {
+    for (width, height) in [(0, 0), (1, 1), (5, 3), (9, 24), (80, 4), (39, 10), (40, 9)] {
+        for (_, screen) in draw_every_screen(width, height) {
+            if width >= 20 && height >= 3 {
+                assert!(screen.contains("too small"), "{}x{}", width, height);
+            }
+        }
+    }
+}
+
+#[test]
+fn small_terminals_still_draw_the_list() {
+    for (width, height) in [(40, 10), (41, 11), (60, 12), (200, 10), (40, 60)] {
+        for (keys, screen) in draw_every_screen(width, height) {
+            assert!(!screen.contains("too small"), "{}x{}", width, height);
+            // Popups may cover the list's title
+            if matches!(keys, "" | "v") {
+                assert!(screen.contains("TODOs"), "{}x{}", width, height);
+            }
+        }
+    }
+}
+
+#[test]
+fn popups_keep_room_for_their_borders() {
+    for (height, expected) in [(1, 1), (2, 2), (3, 3), (10, 9), (30, 18)] {
+        let area = centered_rect(60, 50, Rect::new(0, 0, 100, height));
+        assert_eq!(area.height, expected, "{} lines", height);
+        assert!(area.bottom() <= height);
+    }
+}
+
+#[test]
+fn pending_keys_sit_at_the_end_of_the_status_line() {
+    let mut app = list_of(5);
+    let mut terminal = Terminal::new(TestBackend::new(60, 10)).unwrap();
+    type_keys(&mut app, &mut terminal, "3d");
+    let buffer = terminal.backend().buffer();
+    let end: String = (55..60).map(|x| buffer[(x, 8)].symbol()).collect();
+    assert_eq!(end, "  3d ");
+    type_keys(&mut app, &mut terminal, "d");
+    let buffer = terminal.backend().buffer();
+    let end: String = (55..60).map(|x| buffer[(x, 8)].symbol()).collect();
+    assert_eq!(end.trim(), "");
+}
+
+#[test]
+fn recording_shows_in_the_status_line() {
+    let mut app = list_of(5);
+    let mut terminal = Terminal::new(TestBackend::new(60, 10)).unwrap();
+    type_keys(&mut app, &mut terminal, "qa2");
+    let buffer = terminal.backend().buffer();
+    let end: String = (43..60).map(|x| buffer[(x, 8)].symbol()).collect();
+    assert_eq!(end, " recording @a  2 ");
+    type_keys(&mut app, &mut terminal, "jq");
+    let buffer = terminal.backend().buffer();
+    let line: String = (0..60).map(|x| buffer[(x, 8)].symbol()).collect();
+    assert!(!line.contains("recording"));
+}
+
+#[test]
+fn bulk_changes_count_the_todos_scrolled_away() {
+    let mut app = list_of(40);
+    let mut terminal = Terminal::new(TestBackend::new(80, 10)).unwrap();
+    terminal.draw(|f| draw(f, &mut app)).unwrap();
+    app.execute_command("xall");
+    terminal.draw(|f| draw(f, &mut app)).unwrap();
+    let buffer = terminal.backend().buffer();
+    let line: String = (0..80).map(|x| buffer[(x, 8)].symbol()).collect();
+    let shown = (0..10)
+        .filter(|&y| {
+            let row: String = (0..80).map(|x| buffer[(x, y)].symbol()).collect();
+            row.contains("todo number")
+        })
+        .count();
+    let off_screen = 40 - shown;
+    assert!(
+        line.contains(&format!("+{} changed off-screen", off_screen)),
+        "{}",
+        line
+    );
+}
+
+#[test]
+fn details_row_lists_note_due_labels_and_age() {
+    let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+    let mut todo = Todo::new("call bob +acme @phone");
+    assert_eq!(todo_details(&todo, today), "+acme @phone");
+
+    todo.note = Some("ask about the invoice\nand the contract".to_string());
+    todo.due = NaiveDate::from_ymd_opt(2025, 3, 14);
+    todo.meta.insert("effort".to_string(), "3".to_string());
+    todo.created = NaiveDate::from_ymd_opt(2025, 3, 1);
+    assert_eq!(
+        todo_details(&todo, today),
+        "ask about the invoice · due 2025-03-14 · +acme @phone effort:3 · 9 days old"
+    );
+
+    todo.created = Some(today);
+    assert!(todo_details(&todo, today).ends_with("· added today"));
+    assert_eq!(todo_details(&Todo::new("plain"), today), "no details");
+}
@@ -0,0 +1,96 @@
+use crate::Todo;
+
+#[cfg(test)]
+mod tests;
+
+/// Notes longer than this many characters are cut on load.
+pub const MAX_NOTE_LEN: usize = 10_000;
+
+/// What [`validate`] had to change in a freshly loaded list.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoadReport {
+    /// Todos dropped for having no text.
+    pub skipped: usize,
+    /// Todos whose text, priority or note was normalized.
+    pub repaired: usize,
+    /// Notes cut to [`MAX_NOTE_LEN`].
+    pub notes_truncated: usize,
+}
+
+impl LoadReport {
+    /// A status message such as "Loaded 130 todos (2 skipped, 1 repaired)".
+    pub fn summary(&self, loaded: usize) -> String {
+        let mut issues = Vec::new();
+        if self.skipped > 0 {
+            issues.push(format!("{} skipped", self.skipped));
+        }
+        if self.repaired > 0 {
+            issues.push(format!("{} repaired", self.repaired));
+        }
+        if self.notes_truncated > 0 {
+            issues.push(format!(
+                "{} notes cut to {} chars",
+                self.notes_truncated, MAX_NOTE_LEN
+            ));
+        }
+        if issues.is_empty() {
+            format!("Loaded {} todos", loaded)
+        } else {
+            format!("Loaded {} todos ({})", loaded, issues.join(", "))
+        }
+    }
+}
+
+/// Cleans up todos from a file that may have been edited by hand: text is
+/// trimmed and empty todos dropped, priorities outside A-Z cleared, blank
+/// notes removed and oversized notes cut.
+///
+/// ```
+/// use tuido::Todo;
+///
+/// let mut odd = Todo::new("  padded ");
+/// odd.priority = Some('!');
+/// let (todos, report) = tuido::validate::validate(vec![odd, Todo::new(" ")]);
+/// assert_eq!(todos[0].text, "padded");
+/// assert_eq!(todos[0].priority, None);
+/// assert_eq!((report.skipped, report.repaired), (1, 1));
+/// ```
+pub fn validate(todos: Vec<Todo>) -> (Vec<Todo>, LoadReport) {
+    let mut report = LoadReport::default();
+    let mut valid = Vec::with_capacity(todos.len());
+
+    for mut todo in todos {
+        let text = todo.text.trim();
+        if text.is_empty() {
+            report.skipped += 1;
+            continue;
+        }
+        let mut repaired = text.len() != todo.text.len();
+        todo.text = text.to_string();
+
+        if let Some(priority) = todo.priority {
+            let normalized = Some(priority.to_ascii_uppercase()).filter(char::is_ascii_uppercase);
+            if normalized != todo.priority {
+                todo.priority = normalized;
+                repaired = true;
+            }
+        }
+
+        if todo.note.as_deref().is_some_and(|n| n.trim().is_empty()) {
+            todo.note = None;
+            repaired = true;
+        }
+        if let Some(note) = &mut todo.note
+            && note.chars().count() > MAX_NOTE_LEN
+        {
+            *note = note.chars().take(MAX_NOTE_LEN).collect();
+            report.notes_truncated += 1;
+        }
+
+        if repaired {
+            report.repaired += 1;
+        }
+        valid.push(todo);
+    }
+    (valid, report)
+}
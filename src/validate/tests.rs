@@ -0,0 +1,79 @@
+use super::*;
+
+#[test]
+fn text_is_trimmed_and_counted_as_a_repair() {
+    let (todos, report) = validate(vec![Todo::new("\t buy milk  \n"), Todo::new("clean")]);
+    assert_eq!(todos[0].text, "buy milk");
+    assert_eq!(todos[1].text, "clean");
+    assert_eq!(
+        report,
+        LoadReport {
+            repaired: 1,
+            ..LoadReport::default()
+        }
+    );
+}
+
+#[test]
+fn empty_rows_are_skipped() {
+    let (todos, report) = validate(vec![
+        Todo::new(""),
+        Todo::new("kept"),
+        Todo::new("   "),
+        Todo::new("\n"),
+    ]);
+    assert_eq!(todos, [Todo::new("kept")]);
+    assert_eq!(report.skipped, 3);
+    assert_eq!(report.repaired, 0);
+    assert_eq!(report.summary(todos.len()), "Loaded 1 todos (3 skipped)");
+}
+
+#[test]
+fn priorities_are_coerced_into_a_to_z() {
+    let todos = ['A', 'b', '!', '7', 'é']
+        .into_iter()
+        .map(|priority| {
+            let mut todo = Todo::new("x");
+            todo.priority = Some(priority);
+            todo
+        })
+        .collect();
+    let (todos, report) = validate(todos);
+    let priorities: Vec<_> = todos.iter().map(|todo| todo.priority).collect();
+    assert_eq!(priorities, [Some('A'), Some('B'), None, None, None]);
+    assert_eq!(report.repaired, 4);
+}
+
+#[test]
+fn notes_are_cut_at_the_limit() {
+    let note = |len| {
+        let mut todo = Todo::new("x");
+        todo.note = Some("é".repeat(len));
+        todo
+    };
+    let (todos, report) = validate(vec![
+        note(MAX_NOTE_LEN),
+        note(MAX_NOTE_LEN + 1),
+        note(MAX_NOTE_LEN * 3),
+    ]);
+    let lengths: Vec<_> = todos
+        .iter()
+        .map(|todo| todo.note.as_deref().unwrap().chars().count())
+        .collect();
+    assert_eq!(lengths, [MAX_NOTE_LEN; 3]);
+    assert_eq!(report.notes_truncated, 2);
+    assert_eq!(report.repaired, 0);
+    assert_eq!(
+        report.summary(3),
+        format!("Loaded 3 todos (2 notes cut to {} chars)", MAX_NOTE_LEN)
+    );
+}
+
+#[test]
+fn blank_notes_are_dropped() {
+    let mut todo = Todo::new("x");
+    todo.note = Some(" \n ".to_string());
+    let (todos, report) = validate(vec![todo]);
+    assert_eq!(todos[0].note, None);
+    assert_eq!(report.repaired, 1);
+}
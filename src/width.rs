@@ -0,0 +1,116 @@
+//! Terminal column widths for todo text. Wide characters (CJK, most emoji)
+//! take two columns and combining marks take none, so lengths measured in
+//! `char`s put checkboxes, due dates and borders in the wrong place. Cuts
+//! are made between grapheme clusters so an emoji sequence or an accented
+//! letter is never split.
+
+use std::borrow::Cow;
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Marks text that [`truncate`] cut short.
+pub const ELLIPSIS: &str = "…";
+
+/// Columns `text` takes in a terminal.
+///
+/// ```
+/// use tuido::width::display_width;
+///
+/// assert_eq!(display_width("buy milk"), 8);
+/// assert_eq!(display_width("買い物"), 6);
+/// assert_eq!(display_width("cafe\u{301}"), 4);
+/// ```
+pub fn display_width(text: &str) -> usize {
+    text.width()
+}
+
+/// Shortens `text` to at most `max` columns, ending it with [`ELLIPSIS`]
+/// when anything was cut. A wide character that would straddle the limit
+/// is dropped whole.
+///
+/// ```
+/// use tuido::width::truncate;
+///
+/// assert_eq!(truncate("short", 10), "short");
+/// assert_eq!(truncate("water the plants", 10), "water the…");
+/// assert_eq!(truncate("日本語のテキスト", 7), "日本語…");
+/// // The family emoji is one grapheme of several chars joined by ZWJs
+/// assert_eq!(truncate("👨‍👩‍👧 picnic", 3), "👨‍👩‍👧…");
+/// assert_eq!(truncate("👨‍👩‍👧 picnic", 2), "…");
+/// ```
+pub fn truncate(text: &str, max: usize) -> Cow<'_, str> {
+    if display_width(text) <= max {
+        return Cow::Borrowed(text);
+    }
+    if max == 0 {
+        return Cow::Borrowed("");
+    }
+    let room = max.saturating_sub(display_width(ELLIPSIS));
+    let mut used = 0;
+    let mut end = 0;
+    for (start, grapheme) in text.grapheme_indices(true) {
+        used += display_width(grapheme);
+        if used > room {
+            break;
+        }
+        end = start + grapheme.len();
+    }
+    Cow::Owned(format!("{}{}", &text[..end], ELLIPSIS))
+}
+
+/// Wraps `text` into rows of at most `width` columns, breaking at spaces.
+/// A word wider than a whole row is split between graphemes.
+///
+/// ```
+/// use tuido::width::wrap;
+///
+/// assert_eq!(wrap("call the plumber", 8), ["call the", "plumber"]);
+/// assert_eq!(wrap("東京 大阪 京都", 9), ["東京 大阪", "京都"]);
+/// assert_eq!(wrap("🎉🎉🎉", 4), ["🎉🎉", "🎉"]);
+/// ```
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut rows = Vec::new();
+    for line in text.lines() {
+        let mut row = String::new();
+        let mut row_width = 0;
+        for word in line.split_whitespace() {
+            let mut word_width = display_width(word);
+            if row_width > 0 && row_width + 1 + word_width > width {
+                rows.push(std::mem::take(&mut row));
+                row_width = 0;
+            }
+            let mut word = word;
+            while word_width > width {
+                let (head, rest) = split_at_width(word, width);
+                rows.push(head.to_string());
+                word = rest;
+                word_width = display_width(word);
+            }
+            if row_width > 0 {
+                row.push(' ');
+                row_width += 1;
+            }
+            row.push_str(word);
+            row_width += word_width;
+        }
+        rows.push(row);
+    }
+    rows
+}
+
+// Splits off the graphemes that fit in `width` columns, always taking at
+// least one so a grapheme wider than the row still makes progress.
+fn split_at_width(text: &str, width: usize) -> (&str, &str) {
+    let mut used = 0;
+    let mut end = 0;
+    for (start, grapheme) in text.grapheme_indices(true) {
+        used += display_width(grapheme);
+        if used > width && end > 0 {
+            break;
+        }
+        end = start + grapheme.len();
+    }
+    text.split_at(end)
+}
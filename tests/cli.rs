@@ -0,0 +1,69 @@
+//! Running the tuido binary headless with `-c` commands.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+// A directory of its own holding a list of `texts`, returning the
+// directory and the list's path
+fn list_in(name: &str, texts: &[&str]) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("tuido-cli-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("todos.json");
+    let todos: Vec<_> = texts.iter().map(|text| tuido::Todo::new(*text)).collect();
+    std::fs::write(&file, serde_json::to_string(&todos).unwrap()).unwrap();
+    (dir, file)
+}
+
+// Runs tuido on `file` with stdout piped, keeping the user's config, data
+// and history out of it
+fn tuido(dir: &Path, file: &Path, commands: &[&str]) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_tuido"));
+    command
+        .env("HOME", dir)
+        .env("XDG_CONFIG_HOME", dir.join("config"))
+        .env("XDG_DATA_HOME", dir.join("data"))
+        .env_remove("TUIDO_FILE")
+        .arg("--file")
+        .arg(file);
+    for cmd in commands {
+        command.args(["-c", cmd]);
+    }
+    command.output().unwrap()
+}
+
+fn texts_in(file: &Path) -> Vec<(String, bool)> {
+    let todos: Vec<tuido::Todo> =
+        serde_json::from_str(&std::fs::read_to_string(file).unwrap()).unwrap();
+    todos
+        .into_iter()
+        .map(|todo| (todo.text, todo.completed))
+        .collect()
+}
+
+#[test]
+fn commands_run_in_order_and_can_save_and_quit() {
+    let (dir, file) = list_in("order", &["a", "b", "c"]);
+    let output = tuido(&dir, &file, &["1d", "1x", "wq"]);
+    let saved = texts_in(&file);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(saved, [("b".to_string(), true), ("c".to_string(), false)]);
+}
+
+#[test]
+fn a_failing_command_stops_the_rest_and_exits_non_zero() {
+    let (dir, file) = list_in("failure", &["a", "b"]);
+    let output = tuido(&dir, &file, &["1x", "bogus", "wq"]);
+    let saved = texts_in(&file);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        "tuido: Unknown command: bogus (-c \"bogus\" failed, 1 remaining skipped)"
+    );
+    // The wq after the failure never ran
+    assert_eq!(saved, [("a".to_string(), false), ("b".to_string(), false)]);
+}
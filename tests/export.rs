@@ -0,0 +1,35 @@
+//! The HTML export of the list in `tests/fixtures/export.json`, compared
+//! with `tests/fixtures/export.html` byte for byte. After a deliberate
+//! change to the page, regenerate the golden file with
+//! `UPDATE_GOLDEN=1 cargo test --test export`.
+
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use tuido::Todo;
+
+fn fixture(name: &str) -> PathBuf {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+#[test]
+fn html_export_matches_the_golden_file() {
+    let todos: Vec<Todo> =
+        serde_json::from_str(&std::fs::read_to_string(fixture("export.json")).unwrap()).unwrap();
+    let exported = NaiveDate::from_ymd_opt(2025, 3, 10)
+        .unwrap()
+        .and_hms_opt(18, 45, 0)
+        .unwrap();
+    let page = tuido::html::to_string(&todos, exported);
+
+    let golden = fixture("export.html");
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&golden, &page).unwrap();
+    }
+    assert_eq!(page, std::fs::read_to_string(golden).unwrap());
+    // Nothing from the todos made it in as markup
+    assert!(!page.contains("<script"));
+    assert!(!page.contains("<img"));
+}
@@ -0,0 +1,96 @@
+//! The Google Tasks and Apple Reminders importers against sample exports in
+//! `tests/fixtures`, so the field mappings don't drift from the real formats.
+
+use chrono::NaiveDate;
+use tuido::import::{google_tasks, reminders};
+
+fn fixture(name: &str) -> String {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    std::fs::read_to_string(path).unwrap()
+}
+
+fn date(month: u32, day: u32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(2025, month, day)
+}
+
+#[test]
+fn google_tasks_maps_status_due_notes_and_subtasks() {
+    let (todos, skipped) = google_tasks(&fixture("google_tasks.json")).unwrap();
+    let texts: Vec<_> = todos.iter().map(|t| t.text.as_str()).collect();
+    assert_eq!(
+        texts,
+        [
+            "Plan the garden",
+            "Plan the garden: Order seeds",
+            "File expenses"
+        ]
+    );
+    // The deleted task, the untitled one and the list without tasks
+    assert_eq!(skipped, 3);
+
+    let garden = &todos[0];
+    assert!(!garden.completed);
+    assert_eq!(garden.due, date(3, 14));
+    assert_eq!(
+        garden.note.as_deref(),
+        Some("tomatoes, basil\nask about the shed")
+    );
+
+    let seeds = &todos[1];
+    assert!(seeds.completed);
+    assert_eq!(seeds.due, None);
+    assert_eq!(seeds.note, None);
+
+    // Tasks without a kind are still tasks
+    assert!(!todos[2].completed);
+}
+
+#[test]
+fn google_tasks_rejects_what_isnt_json() {
+    let error = google_tasks("Title,Notes\n").unwrap_err();
+    assert!(error.starts_with("Invalid Google Tasks JSON"));
+}
+
+#[test]
+fn reminders_csv_maps_completed_due_notes_and_parents() {
+    let (todos, skipped) = reminders(&fixture("reminders.csv"));
+    let texts: Vec<_> = todos.iter().map(|t| t.text.as_str()).collect();
+    assert_eq!(
+        texts,
+        [
+            "Buy milk",
+            "Call the bank",
+            "Buy milk: Bring bags",
+            "Renew passport"
+        ]
+    );
+    // The list row and the row without a title
+    assert_eq!(skipped, 2);
+
+    let completed: Vec<_> = todos.iter().map(|t| t.completed).collect();
+    assert_eq!(completed, [false, true, true, false]);
+
+    assert_eq!(todos[0].due, date(3, 14));
+    assert_eq!(todos[0].note.as_deref(), Some("2%, not skim"));
+    assert_eq!(
+        todos[1].note.as_deref(),
+        Some("ask about the fee\nand the new card")
+    );
+    // An unreadable date is dropped, not the reminder
+    assert_eq!(todos[3].due, None);
+}
+
+#[test]
+fn reminders_reads_tab_separated_exports_too() {
+    let (todos, skipped) = reminders(&fixture("reminders.tsv"));
+    assert_eq!(skipped, 0);
+    assert_eq!(todos.len(), 2);
+    assert_eq!(todos[0].text, "Water plants");
+    assert!(todos[0].completed);
+    assert_eq!(todos[0].due, date(4, 1));
+    assert_eq!(todos[0].note.as_deref(), Some("the fern too"));
+    assert_eq!(todos[1].text, "Tax return");
+    assert!(!todos[1].completed);
+}